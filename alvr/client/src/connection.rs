@@ -164,6 +164,9 @@ async fn connection_pipeline(
         } => pair
     };
 
+    // connection encryption is not supported by this client.
+    proto_socket.accept_encryption(None, false, None).await?;
+
     trace_err!(proto_socket.send(&(headset_info, server_ip)).await)?;
     let config_packet = trace_err!(proto_socket.recv::<ClientConfigPacket>().await)?;
 
@@ -229,6 +232,7 @@ async fn connection_pipeline(
         res = stream_socket_builder.accept_from_server(
             server_ip,
             settings.connection.stream_port,
+            None,
         ) => res?,
         _ = time::sleep(Duration::from_secs(5)) => {
             return fmt_e!("Timeout while setting up streams");
@@ -441,6 +441,7 @@ pub unsafe extern "system" fn Java_com_polygraphene_alvr_OvrActivity_onResumeNat
             available_refresh_rates,
             preferred_refresh_rate,
             reserved: format!("{}", *ALVR_VERSION),
+            resume: None,
         };
 
         let private_identity = PrivateIdentity {
@@ -355,6 +355,21 @@ define([
                     case "GraphStatistics":
                         addToTable = false;
                         break;
+                    case "ClientLatencyBreakdown":
+                        addToTable = false;
+                        break;
+                    case "ClientAudioSync":
+                        addToTable = false;
+                        break;
+                    case "BandwidthStatistics":
+                        addToTable = false;
+                        break;
+                    case "ClientFecStatistics":
+                        addToTable = false;
+                        break;
+                    case "ClientWifiStatistics":
+                        addToTable = false;
+                        break;
                     default:
                         line = idObject.id;
                         break;
@@ -427,14 +442,40 @@ define([
                 case "GraphStatistics":
                     updateGraphStatistics(json.data);
                     break;
+                case "ClientLatencyBreakdown":
+                case "ClientAudioSync":
+                case "BandwidthStatistics":
+                case "ClientFecStatistics":
+                    updateClientLatencyBreakdown(json.data);
+                    break;
+                case "ClientWifiStatistics":
+                    updateClientWifiStatistics(json.data);
+                    break;
                 case "SessionUpdated":
                     updateSession();
                     break;
+                case "PairingRequested":
+                    requestPairingCode(json.data);
+                    break;
                 default:
                     break;
             }
         }
 
+        // The client refuses to stream until the code shown in the headset is echoed back.
+        function requestPairingCode(ip) {
+            const code = prompt(i18n["pairingPrompt"].replace("{ip}", ip));
+            if (code === null || code.trim() === "") {
+                return;
+            }
+            $.ajax({
+                type: "POST",
+                url: "api/client/pair",
+                contentType: "application/json;charset=UTF-8",
+                data: JSON.stringify(code.trim()),
+            });
+        }
+
         function legendAsTooltipPlugin({
             className,
             style = {
@@ -891,6 +932,18 @@ define([
             }
         }
 
+        function updateClientLatencyBreakdown(breakdown) {
+            for (const stat in breakdown) {
+                $("#statistic_" + stat).text(breakdown[stat].toFixed(2));
+            }
+        }
+
+        function updateClientWifiStatistics(wifi) {
+            for (const stat in wifi) {
+                $("#statistic_" + stat).text(wifi[stat]);
+            }
+        }
+
         function updateStatistics(statistics) {
             clearTimeout(timeoutHandler);
             // $("#connectionCard").hide();
@@ -12,6 +12,9 @@ define({
         "toggleAdvanced": "Show advanced options",
         "settingsGuide": "Settings guide",
         "restartSteamVR": "Restart SteamVR",
+        "recenterClient": "Recenter headset",
+        "pushUrl": "Open URL on headset",
+        "pushText": "Copy text to headset",
         // Installation page
         "runSetupWizard": "Run setup wizard",
         "addFirewallRules": "Add firewall rules",
@@ -25,6 +25,8 @@ define({
         configureClientButton: "Configure",
         configureClientAddIp: "Add new IP",
         configureClientRemoveIp: "Remove",
+        // Pairing
+        pairingPrompt: "Enter the pairing code shown in the headset ({ip}):",
         // Statistics container
         statistics: "Statistics",
         streamingStatistics: "Streaming Statistics",
@@ -52,6 +54,26 @@ define({
         headset: "Headset",
         left: "Left",
         right: "Right",
+        clientFrameReceive: "Client frame receive",
+        clientQueueWait: "Client reorder wait",
+        clientDecoderSubmit: "Client decoder submit",
+        clientDecode: "Client decode",
+        clientPacketLoss: "Client packet loss",
+        clientFecRecoveredFrames: "Frames recovered by FEC",
+        clientUnrecoverableFrames: "Frames lost",
+        clientWifiRssi: "Client wifi signal",
+        clientWifiLinkSpeed: "Client wifi link speed",
+        clientWifiChannel: "Client wifi channel",
+        audioVideoOffset: "Audio ahead of video",
+        audioBuffer: "Audio buffer",
+        videoBandwidth: "Video bandwidth",
+        videoFecBandwidth: "Video FEC bandwidth",
+        audioBandwidth: "Audio bandwidth",
+        trackingBandwidth: "Tracking bandwidth",
+        controlBandwidth: "Control bandwidth",
+        otherBandwidth: "Other bandwidth",
+        totalBandwidth: "Total bandwidth",
+        latencyMax: "max",
         // Logging tab
         logging: "Logging",
         // validation errors
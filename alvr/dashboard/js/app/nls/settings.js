@@ -302,6 +302,7 @@ define({
         "_root_connection_streamProtocol_udp-choice-.name": "UDP",
         "_root_connection_streamProtocol_throttledUdp-choice-.name": "Throttled UDP",
         "_root_connection_streamProtocol_tcp-choice-.name": "TCP",
+        "_root_connection_streamProtocol_quic-choice-.name": "QUIC", // adv
         "_root_connection_streamPort.name": "Server streaming port", // adv
         "_root_connection_streamPort.description": "Port used by the server to receive packets.", // adv
         "_root_connection_aggressiveKeyframeResend.name": "Aggressive keyframe resend",
@@ -313,6 +314,12 @@ define({
         "_root_connection_onDisconnectScript.name": "On disconnect script",
         "_root_connection_onDisconnectScript.description":
             "This script/executable will be run asynchronously when headset disconnects and on SteamVR shutdown.\nEnvironment variable ACTION will be set to &#34;disconnect&#34; (without quotes).",
+        "_root_connection_encryption.name": "Encryption", // adv
+        "_root_connection_encryption.description":
+            "Encrypts the connection with the headset. Clients that don't support it can't connect.", // adv
+        "_root_connection_encryption_content_preSharedKey.name": "Pre-shared key", // adv
+        "_root_connection_encryption_content_preSharedKey.description":
+            "Start the client with the same key (--encryption-key). When empty, keys are exchanged without authentication, which stops eavesdropping but not impersonation.", // adv
         // Extra tab
         "_root_extra_tab.name": "Extra",
         "_root_extra_theme-choice-.name": "Theme",
@@ -349,6 +356,7 @@ define({
             "This is the cause of jitter on Linux. It should always be disabled on Nvidia GPUs. AMD users should keep it on.",
         // Others
         steamVRRestartSuccess: "SteamVR successfully restarted",
+        clientNotStreaming: "No headset is streaming",
         audioDeviceError: "No audio devices found. Cannot stream audio or microphone",
     },
     it: true,
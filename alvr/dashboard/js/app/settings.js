@@ -296,6 +296,18 @@ define([
                 restartSteamVR();
             });
 
+            $("#recenterClientButton").click(() => {
+                $.post("api/client/recenter");
+            });
+
+            $("#pushUrlButton").click(() => {
+                pushToClient("api/client/open-url", $("#pushUrlButton").text().trim());
+            });
+
+            $("#pushTextButton").click(() => {
+                pushToClient("api/client/push-text", $("#pushTextButton").text().trim());
+            });
+
             $(".paramReset").click((evt) => {
                 const el = $(evt.target);
 
@@ -350,6 +362,28 @@ define([
             }
         }
 
+        function pushToClient(url, title) {
+            const content = prompt(title);
+            if (!content) {
+                return;
+            }
+            $.ajax({
+                type: "POST",
+                url: url,
+                contentType: "application/json;charset=UTF-8",
+                data: JSON.stringify(content),
+                error: () => {
+                    Lobibox.notify("error", {
+                        size: "mini",
+                        rounded: true,
+                        delayIndicator: false,
+                        sound: false,
+                        msg: i18n.clientNotStreaming,
+                    });
+                },
+            });
+        }
+
         function toggleAdvanced() {
             $("#configContainer .advanced").each((index, el) => {
                 if (!advanced) {
@@ -118,8 +118,12 @@ async fn connection_pipeline(
         available_refresh_rates: vec![90.0], // this can't be known. the server must be reworked.
         preferred_refresh_rate: 90.0,
         reserved: "".into(),
+        resume: None,
     };
 
+    // connection encryption is not supported by this client.
+    proto_socket.accept_encryption(None, false, None).await?;
+
     trace_err!(proto_socket.send(&(headset_info, server_ip)).await)?;
     let config_packet = trace_err!(proto_socket.recv::<ClientConfigPacket>().await)?;
 
@@ -170,6 +174,7 @@ async fn connection_pipeline(
         res = stream_socket_builder.accept_from_server(
             server_ip,
             settings.connection.stream_port,
+            None,
         ) => res?,
         _ = time::sleep(Duration::from_secs(5)) => {
             return fmt_e!("Timeout while setting up streams");
@@ -123,9 +123,15 @@ impl Dashboard {
                 ServerEvent::ClientFoundWrongVersion(_) => todo!(),
                 ServerEvent::ClientConnected => todo!(),
                 ServerEvent::ClientDisconnected => todo!(),
+                ServerEvent::PairingRequested(_) => todo!(),
                 ServerEvent::UpdateDownloadedBytesCount(_) => todo!(),
                 ServerEvent::UpdateDownloadError => todo!(),
                 ServerEvent::Statistics(_) => todo!(),
+                ServerEvent::ClientLatencyBreakdown(_) => todo!(),
+                ServerEvent::ClientAudioSync(_) => todo!(),
+                ServerEvent::BandwidthStatistics(_) => todo!(),
+                ServerEvent::ClientFecStatistics(_) => todo!(),
+                ServerEvent::ClientWifiStatistics(_) => todo!(),
                 ServerEvent::ServerQuitting => unreachable!(),
                 ServerEvent::Raw(_) => (),
                 ServerEvent::EchoQuery(_) => todo!(),
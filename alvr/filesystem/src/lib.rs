@@ -225,6 +225,12 @@ impl Layout {
         self.config_dir.join("session.json")
     }
 
+    // Private key of the server for encrypted connections, kept out of the session which is
+    // shared with clients.
+    pub fn server_key(&self) -> PathBuf {
+        self.config_dir.join("server_key")
+    }
+
     pub fn session_log(&self) -> PathBuf {
         if cfg!(windows) {
             self.log_dir.join("session_log.txt")
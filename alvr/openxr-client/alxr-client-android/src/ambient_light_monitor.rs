@@ -0,0 +1,67 @@
+#![cfg(target_os = "android")]
+// Forwards the ambient light level to the server as the "ambient_light" aux sensor, in lux, for
+// mixed reality apps that adapt to the room lighting. Only runs with --aux-sensors.
+use crate::ndk_sensors::{self, SensorError, ASENSOR_TYPE_LIGHT};
+use alxr_common::push_aux_sensor_reading;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+// Light sensors report on every change, the room lighting doesn't need more.
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+struct AmbientLightMonitor {
+    stop_requested: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+lazy_static! {
+    static ref AMBIENT_LIGHT_MONITOR: Mutex<Option<AmbientLightMonitor>> = Mutex::new(None);
+}
+
+fn monitor_loop(stop_requested: Arc<AtomicBool>) {
+    let mut last_report: Option<Instant> = None;
+    let result = ndk_sensors::poll_sensor(ASENSOR_TYPE_LIGHT, None, &stop_requested, |event| {
+        if last_report.map_or(true, |time| time.elapsed() >= REPORT_INTERVAL) {
+            push_aux_sensor_reading("ambient_light", &[event.data[0]]);
+            last_report = Some(Instant::now());
+        }
+    });
+    match result {
+        Ok(()) => (),
+        Err(SensorError::NoSensor) => log::info!("ALXR: No ambient light sensor"),
+        Err(SensorError::EnableFailed) => {
+            log::warn!("ALXR: Failed to enable the ambient light sensor")
+        }
+    }
+}
+
+pub fn start_ambient_light_monitor() {
+    let mut maybe_monitor = AMBIENT_LIGHT_MONITOR.lock();
+    if maybe_monitor.is_some() {
+        return;
+    }
+    let stop_requested = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let stop_requested = Arc::clone(&stop_requested);
+        thread::spawn(move || monitor_loop(stop_requested))
+    };
+    *maybe_monitor = Some(AmbientLightMonitor {
+        stop_requested,
+        thread,
+    });
+}
+
+pub fn stop_ambient_light_monitor() {
+    if let Some(monitor) = AMBIENT_LIGHT_MONITOR.lock().take() {
+        monitor.stop_requested.store(true, Ordering::Relaxed);
+        monitor.thread.join().ok();
+    }
+}
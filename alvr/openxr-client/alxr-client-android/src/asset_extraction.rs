@@ -0,0 +1,94 @@
+#![cfg(target_os = "android")]
+// Copies the precompiled shaders and other assets bundled in the apk to the internal data dir,
+// which is passed to the engine as `internalDataPath`. Assets are extracted again only when the
+// apk changes, the extraction runs in the background while the window initializes.
+use crate::jni_utils;
+use android_activity::AndroidApp;
+use jni::objects::JString;
+use std::{
+    ffi::CString,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+    time::UNIX_EPOCH,
+};
+
+// AAssetDir only lists files, so subdirectories have to be named.
+const ASSET_DIRS: &[&str] = &["shaders"];
+const VERSION_FILENAME: &str = "assets_version";
+
+// The package version alone misses reinstalls of development builds.
+fn apk_version() -> String {
+    let apk_path = jni_utils::with_env(|env| {
+        let path = jni_utils::call_method_cached(
+            env,
+            &jni_utils::android_context(),
+            "android/content/Context",
+            "getPackageCodePath",
+            "()Ljava/lang/String;",
+            &[],
+        )?
+        .l()?;
+        Ok(String::from(env.get_string(&JString::from(path))?))
+    });
+    let modified = apk_path
+        .ok()
+        .and_then(|path| fs::metadata(path).ok()?.modified().ok())
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |time| time.as_secs());
+    format!("{} {modified}", env!("CARGO_PKG_VERSION"))
+}
+
+fn extract_assets(android_app: &AndroidApp, data_dir: &Path) -> io::Result<usize> {
+    let asset_manager = android_app.asset_manager();
+    let mut extracted = 0;
+    for dir_name in ASSET_DIRS {
+        let dir_cstr = CString::new(*dir_name)?;
+        let asset_dir = match asset_manager.open_dir(&dir_cstr) {
+            Some(asset_dir) => asset_dir,
+            None => continue,
+        };
+        let out_dir = data_dir.join(dir_name);
+        fs::create_dir_all(&out_dir)?;
+        for file_name in asset_dir {
+            let asset_path = CString::new(format!("{dir_name}/{}", file_name.to_string_lossy()))?;
+            let mut asset = match asset_manager.open(&asset_path) {
+                Some(asset) => asset,
+                None => continue,
+            };
+            let mut contents = vec![];
+            asset.read_to_end(&mut contents)?;
+            fs::write(out_dir.join(file_name.to_string_lossy().as_ref()), contents)?;
+            extracted += 1;
+        }
+    }
+    Ok(extracted)
+}
+
+fn extract_if_outdated(android_app: &AndroidApp, data_dir: &Path) {
+    let version = apk_version();
+    let version_path = data_dir.join(VERSION_FILENAME);
+    if fs::read_to_string(&version_path).ok().as_deref() == Some(version.as_str()) {
+        log::debug!("alxr-client: bundled assets are up to date");
+        return;
+    }
+    // the version is written last, an interrupted extraction is redone on the next launch.
+    match extract_assets(android_app, data_dir)
+        .and_then(|extracted| fs::write(&version_path, &version).map(|_| extracted))
+    {
+        Ok(extracted) => log::info!("alxr-client: extracted {extracted} bundled assets"),
+        Err(e) => log::warn!("alxr-client: failed to extract the bundled assets: {e}"),
+    }
+}
+
+// The join handle returns the dir to pass to the engine, None when the app has no internal
+// data dir.
+pub fn start_asset_extraction(android_app: &AndroidApp) -> JoinHandle<Option<PathBuf>> {
+    let android_app = android_app.clone();
+    thread::spawn(move || {
+        let data_dir = android_app.internal_data_path()?;
+        extract_if_outdated(&android_app, &data_dir);
+        Some(data_dir)
+    })
+}
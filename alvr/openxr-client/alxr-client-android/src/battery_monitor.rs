@@ -0,0 +1,124 @@
+#![cfg(target_os = "android")]
+use crate::jni_utils::{android_context, call_method_cached, with_env};
+use alxr_common::{battery_send, subscribe_connection_state, ConnectionState, HEAD_ID};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+const BATTERY_MANAGER_CLASS: &str = "android/os/BatteryManager";
+const BATTERY_PROPERTY_CAPACITY: i32 = 4;
+
+// The stop flag is checked at this granularity so pausing the app doesn't block on a long poll.
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+struct BatteryMonitor {
+    stop_requested: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+lazy_static! {
+    static ref BATTERY_MONITOR: Mutex<Option<BatteryMonitor>> = Mutex::new(None);
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct BatteryState {
+    gauge_value: f32,
+    is_plugged: bool,
+}
+
+fn query_battery_state() -> jni::errors::Result<BatteryState> {
+    with_env(|env| {
+        let battery_service_str = env.new_string("batterymanager")?;
+        let battery_manager = call_method_cached(
+            env,
+            &android_context(),
+            "android/content/Context",
+            "getSystemService",
+            "(Ljava/lang/String;)Ljava/lang/Object;",
+            &[(&battery_service_str).into()],
+        )?
+        .l()?;
+        let capacity = call_method_cached(
+            env,
+            &battery_manager,
+            BATTERY_MANAGER_CLASS,
+            "getIntProperty",
+            "(I)I",
+            &[BATTERY_PROPERTY_CAPACITY.into()],
+        )?
+        .i()?;
+        let is_charging = call_method_cached(
+            env,
+            &battery_manager,
+            BATTERY_MANAGER_CLASS,
+            "isCharging",
+            "()Z",
+            &[],
+        )?
+        .z()?;
+        Ok(BatteryState {
+            gauge_value: (capacity.clamp(0, 100) as f32) / 100_f32,
+            is_plugged: is_charging,
+        })
+    })
+}
+
+fn monitor_loop(poll_interval: Duration, stop_requested: Arc<AtomicBool>) {
+    let mut connection_states = subscribe_connection_state();
+    let mut last_sent: Option<BatteryState> = None;
+    let mut waited = poll_interval;
+    while !stop_requested.load(Ordering::Relaxed) {
+        // the server forgets the battery state between connections.
+        while let Ok(state) = connection_states.try_recv() {
+            if state == ConnectionState::Streaming {
+                last_sent = None;
+                waited = poll_interval;
+            }
+        }
+
+        if waited >= poll_interval {
+            waited = Duration::ZERO;
+            match query_battery_state() {
+                Ok(state) if last_sent != Some(state) => {
+                    battery_send(*HEAD_ID, state.gauge_value, state.is_plugged);
+                    last_sent = Some(state);
+                }
+                Ok(_) => (),
+                Err(e) => log::warn!("ALXR: Failed to query battery state: {e}"),
+            }
+        }
+
+        thread::sleep(STOP_CHECK_INTERVAL);
+        waited += STOP_CHECK_INTERVAL;
+    }
+}
+
+pub fn start_battery_monitor(poll_interval: Duration) {
+    let mut maybe_monitor = BATTERY_MONITOR.lock();
+    if maybe_monitor.is_some() || poll_interval.is_zero() {
+        return;
+    }
+    let stop_requested = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let stop_requested = Arc::clone(&stop_requested);
+        thread::spawn(move || monitor_loop(poll_interval, stop_requested))
+    };
+    *maybe_monitor = Some(BatteryMonitor {
+        stop_requested,
+        thread,
+    });
+}
+
+pub fn stop_battery_monitor() {
+    if let Some(monitor) = BATTERY_MONITOR.lock().take() {
+        monitor.stop_requested.store(true, Ordering::Relaxed);
+        monitor.thread.join().ok();
+    }
+}
@@ -0,0 +1,218 @@
+#![cfg(target_os = "android")]
+// Writes a report to <external files>/crashes when the process panics or dies on a fatal signal,
+// with a backtrace, the options in use and the last log lines. Backtraces are in the tombstone
+// format so reports can be symbolized with `ndk-stack -sym <so dir> -i <report>`.
+use crate::kiosk;
+use android_logger::AndroidLogger;
+use lazy_static::lazy_static;
+use libc::c_int;
+use log::{Log, Metadata, Record};
+use parking_lot::Mutex;
+use std::{
+    collections::VecDeque,
+    ffi::{c_void, CStr},
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const LOG_HISTORY_LEN: usize = 200;
+const FATAL_SIGNALS: [c_int; 6] = [
+    libc::SIGSEGV,
+    libc::SIGABRT,
+    libc::SIGBUS,
+    libc::SIGFPE,
+    libc::SIGILL,
+    libc::SIGTRAP,
+];
+const REPORT_EXTENSION: &str = "txt";
+
+struct RecordingLogger {
+    inner: AndroidLogger,
+    lines: Mutex<VecDeque<String>>,
+}
+
+impl Log for RecordingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.inner.log(record);
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        alxr_common::forward_log(record);
+        let mut lines = self.lines.lock();
+        if lines.len() == LOG_HISTORY_LEN {
+            lines.pop_front();
+        }
+        lines.push_back(format!(
+            "{} {}: {}",
+            record.level(),
+            record.target(),
+            record.args()
+        ));
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
+
+lazy_static! {
+    static ref CRASH_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+    static ref CONFIG_SNAPSHOT: Mutex<String> = Mutex::new(String::new());
+}
+
+static LOGGER: OnceLock<RecordingLogger> = OnceLock::new();
+static PREVIOUS_ACTIONS: OnceLock<Vec<(c_int, libc::sigaction)>> = OnceLock::new();
+static CRASHING: AtomicBool = AtomicBool::new(false);
+
+// Replaces android_logger::init_once, log lines are still sent to logcat and are forwarded to
+// the server while connected.
+pub fn init_logging(config: android_logger::Config, max_level: log::LevelFilter) {
+    let logger = LOGGER.get_or_init(|| RecordingLogger {
+        inner: AndroidLogger::new(config),
+        lines: Mutex::new(VecDeque::with_capacity(LOG_HISTORY_LEN)),
+    });
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+fn native_backtrace() -> String {
+    let mut frames = String::new();
+    let mut index = 0;
+    // the synchronized version takes a lock the crashing thread may be holding.
+    unsafe {
+        backtrace::trace_unsynchronized(|frame| {
+            let pc = frame.ip() as usize;
+            let mut info: libc::Dl_info = std::mem::zeroed();
+            let found = libc::dladdr(pc as *const c_void, &mut info) != 0;
+            if found && !info.dli_fname.is_null() {
+                let module = CStr::from_ptr(info.dli_fname).to_string_lossy();
+                let offset = pc - info.dli_fbase as usize;
+                writeln!(frames, "      #{index:02} pc {offset:016x}  {module}").ok();
+            } else {
+                writeln!(frames, "      #{index:02} pc {pc:016x}  <unknown>").ok();
+            }
+            index += 1;
+            true
+        })
+    };
+    frames
+}
+
+// Not async-signal-safe, but the process is going down and a missing report is the worst case.
+fn write_report(reason: &str) {
+    if CRASHING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let crash_dir = match CRASH_DIR.try_lock().and_then(|dir| dir.clone()) {
+        Some(crash_dir) => crash_dir,
+        None => return,
+    };
+    let mut report = String::new();
+    writeln!(
+        report,
+        "*** *** *** *** *** *** *** *** *** *** *** *** *** *** *** ***"
+    )
+    .ok();
+    writeln!(report, "{reason}").ok();
+    writeln!(report, "backtrace:\n{}", native_backtrace()).ok();
+    if let Some(config) = CONFIG_SNAPSHOT.try_lock() {
+        writeln!(report, "options:\n{}\n", *config).ok();
+    }
+    if let Some(lines) = LOGGER.get().and_then(|logger| logger.lines.try_lock()) {
+        writeln!(report, "last log lines:").ok();
+        for line in lines.iter() {
+            writeln!(report, "{line}").ok();
+        }
+    }
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let path = crash_dir.join(format!("crash_{timestamp}.{REPORT_EXTENSION}"));
+    if fs::create_dir_all(&crash_dir).is_ok() && fs::write(&path, report).is_ok() {
+        log::error!("alxr-client: crash report written to {}", path.display());
+    }
+}
+
+extern "C" fn on_fatal_signal(signal: c_int, info: *mut libc::siginfo_t, context: *mut c_void) {
+    write_report(&format!("Fatal signal {signal}"));
+    kiosk::on_fatal_crash();
+
+    // hand over to the previous handler (debuggerd) so a tombstone is still created.
+    let previous = PREVIOUS_ACTIONS
+        .get()
+        .and_then(|actions| actions.iter().find(|(s, _)| *s == signal))
+        .map(|(_, action)| *action);
+    unsafe {
+        match previous {
+            Some(action) if action.sa_flags & libc::SA_SIGINFO != 0 => {
+                let handler: extern "C" fn(c_int, *mut libc::siginfo_t, *mut c_void) =
+                    std::mem::transmute(action.sa_sigaction);
+                handler(signal, info, context);
+            }
+            Some(action)
+                if action.sa_sigaction != libc::SIG_DFL && action.sa_sigaction != libc::SIG_IGN =>
+            {
+                let handler: extern "C" fn(c_int) = std::mem::transmute(action.sa_sigaction);
+                handler(signal);
+            }
+            _ => {
+                libc::signal(signal, libc::SIG_DFL);
+                libc::raise(signal);
+            }
+        }
+    }
+}
+
+fn install_signal_handlers() {
+    let mut previous_actions = vec![];
+    for signal in FATAL_SIGNALS {
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_fatal_signal as usize;
+            action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK;
+            libc::sigemptyset(&mut action.sa_mask);
+            let mut previous: libc::sigaction = std::mem::zeroed();
+            if libc::sigaction(signal, &action, &mut previous) == 0 {
+                previous_actions.push((signal, previous));
+            }
+        }
+    }
+    PREVIOUS_ACTIONS.set(previous_actions).ok();
+}
+
+// Must be called after the panic hook is set, the previous hook still runs.
+pub fn install(crash_dir: &Path, config_snapshot: String) {
+    *CRASH_DIR.lock() = Some(crash_dir.to_owned());
+    *CONFIG_SNAPSHOT.lock() = config_snapshot;
+
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |panic_info| {
+        write_report(&format!("Panic: {panic_info}"));
+        CRASHING.store(false, Ordering::SeqCst);
+        previous_hook(panic_info);
+    }));
+    install_signal_handlers();
+}
+
+// Reports that were not uploaded yet.
+pub fn pending_reports(crash_dir: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(crash_dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == REPORT_EXTENSION))
+        .collect()
+}
@@ -0,0 +1,84 @@
+#![cfg(target_os = "android")]
+// Samples the accelerometer and gyroscope for --imu-stream-hz, each on its own thread. The
+// samples are only sent while streaming.
+use crate::ndk_sensors::{self, SensorError, ASENSOR_TYPE_ACCELEROMETER, ASENSOR_TYPE_GYROSCOPE};
+use alxr_common::{imu_stream_hz, push_imu_sample, ImuSample, ImuSensor};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    ffi::c_int,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+struct ImuMonitor {
+    stop_requested: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+lazy_static! {
+    static ref IMU_MONITOR: Mutex<Option<ImuMonitor>> = Mutex::new(None);
+}
+
+fn monitor_loop(
+    sensor: ImuSensor,
+    sensor_type: c_int,
+    sampling_period: Duration,
+    stop_requested: Arc<AtomicBool>,
+) {
+    let result = ndk_sensors::poll_sensor(
+        sensor_type,
+        Some(sampling_period),
+        &stop_requested,
+        |event| {
+            push_imu_sample(ImuSample {
+                sensor,
+                timestamp_ns: event.timestamp,
+                values: [event.data[0], event.data[1], event.data[2]],
+            })
+        },
+    );
+    match result {
+        Ok(()) => (),
+        Err(SensorError::NoSensor) => log::info!("ALXR: No {sensor:?} to stream"),
+        Err(SensorError::EnableFailed) => log::warn!("ALXR: Failed to enable the {sensor:?}"),
+    }
+}
+
+pub fn start_imu_monitor() {
+    let rate_hz = imu_stream_hz();
+    let mut maybe_monitor = IMU_MONITOR.lock();
+    if rate_hz == 0 || maybe_monitor.is_some() {
+        return;
+    }
+    let sampling_period = Duration::from_secs(1) / rate_hz;
+    let stop_requested = Arc::new(AtomicBool::new(false));
+    let threads = [
+        (ImuSensor::Accelerometer, ASENSOR_TYPE_ACCELEROMETER),
+        (ImuSensor::Gyroscope, ASENSOR_TYPE_GYROSCOPE),
+    ]
+    .into_iter()
+    .map(|(sensor, sensor_type)| {
+        let stop_requested = Arc::clone(&stop_requested);
+        thread::spawn(move || monitor_loop(sensor, sensor_type, sampling_period, stop_requested))
+    })
+    .collect();
+    log::info!("ALXR: Streaming the IMU at {rate_hz}Hz");
+    *maybe_monitor = Some(ImuMonitor {
+        stop_requested,
+        threads,
+    });
+}
+
+pub fn stop_imu_monitor() {
+    if let Some(monitor) = IMU_MONITOR.lock().take() {
+        monitor.stop_requested.store(true, Ordering::Relaxed);
+        for thread in monitor.threads {
+            thread.join().ok();
+        }
+    }
+}
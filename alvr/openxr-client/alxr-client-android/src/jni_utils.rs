@@ -0,0 +1,128 @@
+#![cfg(target_os = "android")]
+use jni::{
+    errors::{Error, Result},
+    objects::{GlobalRef, JMethodID, JObject, JValue, JValueOwned},
+    signature::TypeSignature,
+    JNIEnv, JavaVM,
+};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+
+type MethodKey = (String, String, String);
+
+lazy_static! {
+    static ref JAVA_VM: Option<JavaVM> =
+        unsafe { JavaVM::from_raw(ndk_context::android_context().vm().cast()).ok() };
+    static ref CLASS_CACHE: Mutex<HashMap<String, GlobalRef>> = Mutex::new(HashMap::new());
+    static ref METHOD_CACHE: Mutex<HashMap<MethodKey, (JMethodID, TypeSignature)>> =
+        Mutex::new(HashMap::new());
+}
+
+pub fn java_vm() -> Result<&'static JavaVM> {
+    JAVA_VM
+        .as_ref()
+        .ok_or(Error::NullPtr("android_context().vm()"))
+}
+
+pub fn android_context<'a>() -> JObject<'a> {
+    let ctx = ndk_context::android_context().context();
+    unsafe { JObject::from_raw(ctx as jni::sys::jobject) }
+}
+
+// A failed JNI call may leave a pending java exception which must be cleared before
+// making any further JNI calls on the same thread.
+pub fn clear_pending_exception(env: &mut JNIEnv) {
+    if env.exception_check().unwrap_or(false) {
+        env.exception_describe().ok();
+        env.exception_clear().ok();
+    }
+}
+
+// Attaches the current thread to the JavaVM (if not already attached) for the duration of `f`.
+pub fn with_env<R>(f: impl FnOnce(&mut JNIEnv) -> Result<R>) -> Result<R> {
+    let mut env = java_vm()?.attach_current_thread()?;
+    let result = f(&mut env);
+    if result.is_err() {
+        clear_pending_exception(&mut env);
+    }
+    result
+}
+
+pub fn find_class_cached(env: &mut JNIEnv, class_name: &str) -> Result<GlobalRef> {
+    if let Some(class) = CLASS_CACHE.lock().get(class_name) {
+        return Ok(class.clone());
+    }
+    let class = env.find_class(class_name)?;
+    let class = env.new_global_ref(class)?;
+    CLASS_CACHE
+        .lock()
+        .insert(class_name.to_owned(), class.clone());
+    Ok(class)
+}
+
+fn method_id_cached(
+    env: &mut JNIEnv,
+    class_name: &str,
+    method_name: &str,
+    sig: &str,
+) -> Result<(JMethodID, TypeSignature)> {
+    let key = (
+        class_name.to_owned(),
+        method_name.to_owned(),
+        sig.to_owned(),
+    );
+    if let Some(entry) = METHOD_CACHE.lock().get(&key) {
+        return Ok(entry.clone());
+    }
+    let class = find_class_cached(env, class_name)?;
+    let method_id = env.get_method_id(&class, method_name, sig)?;
+    let entry = (method_id, TypeSignature::from_str(sig)?);
+    METHOD_CACHE.lock().insert(key, entry.clone());
+    Ok(entry)
+}
+
+// Calls an instance method through a cached method id, `class_name` must be the class (or super-class)
+// declaring the method.
+pub fn call_method_cached<'local>(
+    env: &mut JNIEnv<'local>,
+    obj: &JObject,
+    class_name: &str,
+    method_name: &str,
+    sig: &str,
+    args: &[JValue],
+) -> Result<JValueOwned<'local>> {
+    let (method_id, type_sig) = method_id_cached(env, class_name, method_name, sig)?;
+    if type_sig.args.len() != args.len() {
+        return Err(Error::InvalidArgList(type_sig));
+    }
+    let args: Vec<_> = args.iter().map(|arg| arg.as_jni()).collect();
+    unsafe { env.call_method_unchecked(obj, method_id, type_sig.ret, &args) }
+}
+
+pub fn get_static_field_cached<'local>(
+    env: &mut JNIEnv<'local>,
+    class_name: &str,
+    field_name: &str,
+    sig: &str,
+) -> Result<JValueOwned<'local>> {
+    let class = find_class_cached(env, class_name)?;
+    env.get_static_field(&class, field_name, sig)
+}
+
+pub fn get_static_string_field(
+    env: &mut JNIEnv,
+    class_name: &str,
+    field_name: &str,
+) -> Result<String> {
+    let value = get_static_field_cached(env, class_name, field_name, "Ljava/lang/String;")?.l()?;
+    if value.is_null() {
+        return Err(Error::NullDeref("static string field"));
+    }
+    let value = env.get_string((&value).into())?;
+    Ok(value.to_string_lossy().into_owned())
+}
+
+pub fn get_api_level() -> Result<i32> {
+    with_env(|env| get_static_field_cached(env, "android/os/Build$VERSION", "SDK_INT", "I")?.i())
+}
@@ -0,0 +1,133 @@
+#![cfg(target_os = "android")]
+// Platform side of --kiosk: lock task mode, and relaunching the app after a crash. Both only
+// fully work for apps allowed by the device owner (the MDM of the fleet), otherwise the user is
+// asked to pin the app and background relaunches may be blocked.
+use crate::jni_utils;
+use jni::objects::{JObject, JValue};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const RESTART_DELAY_MS: i64 = 1000;
+// AlarmManager.RTC, Intent.FLAG_ACTIVITY_NEW_TASK | FLAG_ACTIVITY_CLEAR_TASK and
+// PendingIntent.FLAG_IMMUTABLE | FLAG_CANCEL_CURRENT.
+const ALARM_TYPE_RTC: i32 = 1;
+const RESTART_INTENT_FLAGS: i32 = 0x10000000 | 0x00008000;
+const PENDING_INTENT_FLAGS: i32 = 0x04000000 | 0x10000000;
+
+static RESTART_ON_CRASH: AtomicBool = AtomicBool::new(false);
+
+pub fn start_lock_task() {
+    let result = jni_utils::with_env(|env| {
+        jni_utils::call_method_cached(
+            env,
+            &jni_utils::android_context(),
+            "android/app/Activity",
+            "startLockTask",
+            "()V",
+            &[],
+        )
+        .map(|_| ())
+    });
+    match result {
+        Ok(()) => log::info!("alxr-client: kiosk lock task mode started"),
+        Err(e) => log::warn!("alxr-client: failed to start lock task mode: {e}"),
+    }
+}
+
+pub fn set_restart_on_crash(enabled: bool) {
+    RESTART_ON_CRASH.store(enabled, Ordering::Relaxed);
+}
+
+fn schedule_restart() -> jni::errors::Result<()> {
+    jni_utils::with_env(|env| {
+        let context = jni_utils::android_context();
+        let package_manager = jni_utils::call_method_cached(
+            env,
+            &context,
+            "android/content/Context",
+            "getPackageManager",
+            "()Landroid/content/pm/PackageManager;",
+            &[],
+        )?
+        .l()?;
+        let package_name = jni_utils::call_method_cached(
+            env,
+            &context,
+            "android/content/Context",
+            "getPackageName",
+            "()Ljava/lang/String;",
+            &[],
+        )?
+        .l()?;
+        let intent = jni_utils::call_method_cached(
+            env,
+            &package_manager,
+            "android/content/pm/PackageManager",
+            "getLaunchIntentForPackage",
+            "(Ljava/lang/String;)Landroid/content/Intent;",
+            &[JValue::Object(&package_name)],
+        )?
+        .l()?;
+        if intent.is_null() {
+            return Err(jni::errors::Error::NullPtr("getLaunchIntentForPackage"));
+        }
+        jni_utils::call_method_cached(
+            env,
+            &intent,
+            "android/content/Intent",
+            "addFlags",
+            "(I)Landroid/content/Intent;",
+            &[JValue::Int(RESTART_INTENT_FLAGS)],
+        )?;
+        let pending_intent = env
+            .call_static_method(
+                "android/app/PendingIntent",
+                "getActivity",
+                "(Landroid/content/Context;ILandroid/content/Intent;I)Landroid/app/PendingIntent;",
+                &[
+                    JValue::Object(&context),
+                    JValue::Int(0),
+                    JValue::Object(&intent),
+                    JValue::Int(PENDING_INTENT_FLAGS),
+                ],
+            )?
+            .l()?;
+        let service_name = JObject::from(env.new_string("alarm")?);
+        let alarm_manager = jni_utils::call_method_cached(
+            env,
+            &context,
+            "android/content/Context",
+            "getSystemService",
+            "(Ljava/lang/String;)Ljava/lang/Object;",
+            &[JValue::Object(&service_name)],
+        )?
+        .l()?;
+        let now_ms = env
+            .call_static_method("java/lang/System", "currentTimeMillis", "()J", &[])?
+            .j()?;
+        jni_utils::call_method_cached(
+            env,
+            &alarm_manager,
+            "android/app/AlarmManager",
+            "set",
+            "(IJLandroid/app/PendingIntent;)V",
+            &[
+                JValue::Int(ALARM_TYPE_RTC),
+                JValue::Long(now_ms + RESTART_DELAY_MS),
+                JValue::Object(&pending_intent),
+            ],
+        )?;
+        Ok(())
+    })
+}
+
+// Called from the fatal signal handler, which also covers panics that abort. Like the crash
+// report this is not async-signal-safe, the worst case is no relaunch.
+pub fn on_fatal_crash() {
+    if !RESTART_ON_CRASH.load(Ordering::Relaxed) {
+        return;
+    }
+    match schedule_restart() {
+        Ok(()) => log::error!("alxr-client: kiosk relaunch scheduled"),
+        Err(e) => log::error!("alxr-client: failed to schedule the kiosk relaunch: {e}"),
+    }
+}
@@ -1,39 +1,149 @@
 #![cfg(target_os = "android")]
+mod ambient_light_monitor;
+mod asset_extraction;
+mod battery_monitor;
+mod crash_reporter;
+mod imu_monitor;
+mod jni_utils;
+mod kiosk;
+mod ndk_sensors;
 mod permissions;
+mod proximity_monitor;
+mod thermal_monitor;
 mod wifi_manager;
 
+use ambient_light_monitor::{start_ambient_light_monitor, stop_ambient_light_monitor};
+use asset_extraction::start_asset_extraction;
+use battery_monitor::{start_battery_monitor, stop_battery_monitor};
+use imu_monitor::{start_imu_monitor, stop_imu_monitor};
+use jni::objects::{JObjectArray, JValue};
 use permissions::check_android_permissions;
-use std::time::Duration;
+use proximity_monitor::{start_proximity_monitor, stop_proximity_monitor, take_proximity_change};
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    time::{Duration, Instant},
+};
+use thermal_monitor::{start_thermal_monitor, stop_thermal_monitor};
 use version_compare::{Part, Version};
-use wifi_manager::{acquire_wifi_lock, release_wifi_lock};
+use wifi_manager::{
+    acquire_wifi_lock, is_wifi_lock_held, release_wifi_lock, start_wifi_monitor, stop_wifi_monitor,
+};
 
 use android_activity::{AndroidApp, MainEvent, PollEvent};
 use android_logger;
 
 use alxr_common::{
-    alxr_destroy, alxr_init, alxr_on_pause, alxr_on_resume, alxr_process_frame, battery_send,
-    init_connections, input_send, path_string_to_hash, request_idr, set_waiting_next_idr, shutdown,
+    advertise_tracking_server, alxr_destroy, alxr_init, alxr_on_pause, alxr_on_resume,
+    alxr_process_frame, battery_send, clear_poisoned, connection_state, init_connections,
+    input_send, is_exit_requested, is_poisoned, mark_startup_stage, path_string_to_hash,
+    prepare_connections, request_idr, set_binding_profile, set_controller_pose_time_offset,
+    set_launch_options, set_panic_hook, set_reference_space, set_waiting_next_idr, shutdown,
     time_sync_send, video_error_report_send, views_config_send, ALXRClientCtx, ALXRColorSpace,
-    ALXRDecoderType, ALXREyeTrackingType, ALXRFacialExpressionType, ALXRGraphicsApi,
-    ALXRPassthroughMode, ALXRSystemProperties, ALXRVersion, APP_CONFIG,
+    ALXRDecoderType, ALXRGraphicsApi, ALXRPassthroughMode, ALXRSystemProperties, ALXRVersion,
+    Capabilities, ConnectionState, DeviceVendor, Teardown, APP_CONFIG, DEFAULT_STAGE_TIMEOUT,
 };
 
-fn get_build_property<'a>(jvm: &'a jni::JavaVM, property_name: &str) -> String {
-    let mut env = jvm.attach_current_thread().unwrap();
-
-    let jdevice_name = env
-        .get_static_field("android/os/Build", &property_name, "Ljava/lang/String;")
-        .unwrap()
-        .l()
-        .unwrap();
-    let device_name_raw = env.get_string((&jdevice_name).into()).unwrap();
+fn get_build_property(property_name: &str) -> String {
+    jni_utils::with_env(|env| {
+        jni_utils::get_static_string_field(env, "android/os/Build", property_name)
+    })
+    .unwrap_or_else(|e| {
+        log::warn!("alxr-client: failed to read android.os.Build.{property_name}: {e}");
+        String::new()
+    })
+}
 
-    device_name_raw.to_string_lossy().as_ref().to_owned()
+// Launch intent extras as strings, e.g. `adb shell am start -n <package>/android.app.NativeActivity
+// -e verbose true -e server_address 192.168.1.2`.
+fn get_intent_extras() -> jni::errors::Result<HashMap<String, String>> {
+    jni_utils::with_env(|env| {
+        let mut extras_map = HashMap::new();
+        let activity = jni_utils::android_context();
+        let intent = jni_utils::call_method_cached(
+            env,
+            &activity,
+            "android/app/Activity",
+            "getIntent",
+            "()Landroid/content/Intent;",
+            &[],
+        )?
+        .l()?;
+        if intent.is_null() {
+            return Ok(extras_map);
+        }
+        let extras = jni_utils::call_method_cached(
+            env,
+            &intent,
+            "android/content/Intent",
+            "getExtras",
+            "()Landroid/os/Bundle;",
+            &[],
+        )?
+        .l()?;
+        if extras.is_null() {
+            return Ok(extras_map);
+        }
+        let keys = jni_utils::call_method_cached(
+            env,
+            &extras,
+            "android/os/BaseBundle",
+            "keySet",
+            "()Ljava/util/Set;",
+            &[],
+        )?
+        .l()?;
+        let keys = JObjectArray::from(
+            jni_utils::call_method_cached(
+                env,
+                &keys,
+                "java/util/Set",
+                "toArray",
+                "()[Ljava/lang/Object;",
+                &[],
+            )?
+            .l()?,
+        );
+        for idx in 0..env.get_array_length(&keys)? {
+            let key = env.get_object_array_element(&keys, idx)?;
+            let value = jni_utils::call_method_cached(
+                env,
+                &extras,
+                "android/os/BaseBundle",
+                "get",
+                "(Ljava/lang/String;)Ljava/lang/Object;",
+                &[JValue::Object(&key)],
+            )?
+            .l()?;
+            if value.is_null() {
+                continue;
+            }
+            let value = jni_utils::call_method_cached(
+                env,
+                &value,
+                "java/lang/Object",
+                "toString",
+                "()Ljava/lang/String;",
+                &[],
+            )?
+            .l()?;
+            let key = env
+                .get_string((&key).into())?
+                .to_string_lossy()
+                .into_owned();
+            let value = env
+                .get_string((&value).into())?
+                .to_string_lossy()
+                .into_owned();
+            extras_map.insert(key, value);
+        }
+        Ok(extras_map)
+    })
 }
 
-fn get_firmware_version<'a>(jvm: &'a jni::JavaVM) -> ALXRVersion {
-    fn get_version_helper<'a, 'b>(jvm: &'a jni::JavaVM, prop_name: &str) -> Option<[u32; 3]> {
-        let value_str = get_build_property(&jvm, &prop_name);
+fn get_firmware_version() -> ALXRVersion {
+    fn get_version_helper(prop_name: &str) -> Option<[u32; 3]> {
+        let value_str = get_build_property(&prop_name);
         match Version::from(&value_str) {
             Some(v) => {
                 let mut ret: [u32; 3] = [0, 0, 0];
@@ -49,8 +159,8 @@ fn get_firmware_version<'a>(jvm: &'a jni::JavaVM) -> ALXRVersion {
         }
     }
 
-    let version = get_version_helper(&jvm, "ID")
-        .unwrap_or_else(|| get_version_helper(&jvm, "DISPLAY").unwrap_or([0, 0, 0]));
+    let version = get_version_helper("ID")
+        .unwrap_or_else(|| get_version_helper("DISPLAY").unwrap_or([0, 0, 0]));
 
     ALXRVersion {
         major: version[0],
@@ -60,28 +170,27 @@ fn get_firmware_version<'a>(jvm: &'a jni::JavaVM) -> ALXRVersion {
 }
 
 #[allow(dead_code)]
-fn get_build_model<'a>(jvm: &'a jni::JavaVM) -> String {
-    get_build_property(&jvm, "MODEL")
+fn get_build_model() -> String {
+    get_build_property("MODEL")
 }
 
 #[allow(dead_code)]
-fn get_build_device<'a>(jvm: &'a jni::JavaVM) -> String {
-    get_build_property(&jvm, "DEVICE")
+fn get_build_device() -> String {
+    get_build_property("DEVICE")
 }
 
-#[allow(dead_code)]
-fn get_build_manufacturer<'a>(jvm: &'a jni::JavaVM) -> String {
-    get_build_property(&jvm, "MANUFACTURER")
+fn get_build_manufacturer() -> String {
+    get_build_property("MANUFACTURER")
 }
 
 #[allow(dead_code)]
-fn is_device<'a>(pname: &str, jvm: &'a jni::JavaVM) -> bool {
+fn is_device(pname: &str) -> bool {
     let key = pname.to_lowercase();
-    let model_name = get_build_model(&jvm).to_lowercase();
-    let device_name = get_build_device(&jvm).to_lowercase();
-    let man_name = get_build_manufacturer(&jvm).to_lowercase();
+    let model_name = get_build_model().to_lowercase();
+    let device_name = get_build_device().to_lowercase();
+    let man_name = get_build_manufacturer().to_lowercase();
     for dname in [model_name, device_name, man_name] {
-        if dname.contains(&key) {
+        if !dname.is_empty() && dname.contains(&key) {
             return true;
         }
     }
@@ -95,8 +204,29 @@ fn android_main(android_app: AndroidApp) {
     } else {
         log::LevelFilter::Info
     };
-    android_logger::init_once(android_logger::Config::default().with_max_level(log_level));
+    crash_reporter::init_logging(
+        android_logger::Config::default().with_max_level(log_level),
+        log_level,
+    );
+    mark_startup_stage("launch");
+    set_panic_hook();
+    // must happen before APP_CONFIG is first used.
+    match get_intent_extras() {
+        Ok(extras) => set_launch_options(extras),
+        Err(e) => log::warn!("alxr-client: failed to read launch intent extras: {e}"),
+    }
     log::info!("{:?}", *APP_CONFIG);
+    if let Some(crash_dir) = android_app
+        .external_data_path()
+        .map(|path| path.join("crashes"))
+    {
+        if APP_CONFIG.upload_crash_reports {
+            queue_crash_reports(crash_reporter::pending_reports(&crash_dir));
+        }
+        crash_reporter::install(&crash_dir, format!("{:?}", *APP_CONFIG));
+    }
+    kiosk::set_restart_on_crash(APP_CONFIG.kiosk);
+    prepare_connections();
     unsafe { run(&android_app).unwrap() };
     log::info!("successfully shutdown.");
 }
@@ -107,20 +237,44 @@ struct AppData {
     gained_focus: bool,
     window_inited: bool,
     sys_properties: Option<ALXRSystemProperties>,
+    // set while the headset is removed and the connections are stopped.
+    removed_since: Option<Instant>,
+    idr_on_streaming: bool,
 }
 
 impl AppData {
     fn pause(&mut self) {
         self.resumed = false;
-        if self.sys_properties.is_some() {
+        if self.sys_properties.is_some() && self.removed_since.is_none() {
             shutdown();
         }
+        self.removed_since = None;
         unsafe { alxr_on_pause() };
+        stop_proximity_monitor();
+        stop_ambient_light_monitor();
+        stop_imu_monitor();
+        stop_battery_monitor();
+        stop_thermal_monitor();
+        stop_wifi_monitor();
         release_wifi_lock();
     }
 
     fn resume(&mut self) {
         acquire_wifi_lock();
+        start_battery_monitor(Duration::from_secs(
+            APP_CONFIG.battery_poll_interval_s as u64,
+        ));
+        start_thermal_monitor(Duration::from_secs(
+            APP_CONFIG.thermal_poll_interval_s as u64,
+        ));
+        start_wifi_monitor(Duration::from_secs(APP_CONFIG.wifi_poll_interval_s as u64));
+        if !APP_CONFIG.no_proximity_sleep {
+            start_proximity_monitor();
+        }
+        if APP_CONFIG.aux_sensors {
+            start_ambient_light_monitor();
+        }
+        start_imu_monitor();
         unsafe { alxr_on_resume() };
         if let Some(sys_properties) = self.sys_properties {
             init_connections(&sys_properties);
@@ -128,6 +282,47 @@ impl AppData {
         self.resumed = true;
     }
 
+    fn headset_removed(&mut self) {
+        if !self.resumed || self.removed_since.is_some() {
+            return;
+        }
+        if self.sys_properties.is_some() {
+            log::info!("alxr-client: headset removed, stopping the connections.");
+            shutdown();
+            self.removed_since = Some(Instant::now());
+        }
+    }
+
+    fn headset_worn(&mut self) {
+        if self.removed_since.take().is_none() || !self.resumed {
+            return;
+        }
+        log::info!("alxr-client: headset worn, restarting the connections.");
+        acquire_wifi_lock();
+        init_connections(&self.sys_properties.unwrap());
+        // the decoder was idle, the first frames must not depend on stale references.
+        self.idr_on_streaming = true;
+    }
+
+    fn update_proximity(&mut self) {
+        match take_proximity_change() {
+            Some(true) => self.headset_worn(),
+            Some(false) => self.headset_removed(),
+            None => (),
+        }
+        if let Some(removed_since) = self.removed_since {
+            let grace = Duration::from_secs(APP_CONFIG.proximity_wifi_grace_s as u64);
+            if removed_since.elapsed() >= grace && is_wifi_lock_held() {
+                log::info!("alxr-client: headset still removed, releasing the wifi lock.");
+                release_wifi_lock();
+            }
+        }
+        if self.idr_on_streaming && connection_state() == ConnectionState::Streaming {
+            request_idr();
+            self.idr_on_streaming = false;
+        }
+    }
+
     fn handle_lifecycle_event(&mut self, android_app: &AndroidApp, event: &PollEvent) {
         match event {
             PollEvent::Main(main_event) => match main_event {
@@ -187,6 +382,7 @@ const NO_WAIT_TIME: Option<Duration> = Some(Duration::from_millis(0));
 #[inline(always)]
 unsafe fn run(android_app: &AndroidApp) -> Result<(), Box<dyn std::error::Error>> {
     let _lib = libloading::Library::new("libopenxr_loader.so")?;
+    let asset_extraction = start_asset_extraction(android_app);
 
     let native_activity = android_app.activity_as_ptr();
     let vm_ptr = android_app.vm_as_ptr();
@@ -194,7 +390,9 @@ unsafe fn run(android_app: &AndroidApp) -> Result<(), Box<dyn std::error::Error>
     let vm = jni::JavaVM::from_raw(vm_ptr.cast())?;
     let _env = vm.attach_current_thread()?;
 
-    check_android_permissions(native_activity as jni::sys::jobject, &vm)?;
+    if let Err(e) = check_android_permissions(native_activity as jni::sys::jobject) {
+        log::warn!("alxr-client: failed to check/request android permissions: {e}");
+    }
 
     let mut app_data = AppData {
         destroy_requested: false,
@@ -202,6 +400,8 @@ unsafe fn run(android_app: &AndroidApp) -> Result<(), Box<dyn std::error::Error>
         gained_focus: false,
         window_inited: false,
         sys_properties: None,
+        removed_since: None,
+        idr_on_streaming: false,
     };
     wait_until_window_init(&android_app, &mut app_data);
     if app_data.destroy_requested || android_app.native_window().is_none() {
@@ -209,23 +409,33 @@ unsafe fn run(android_app: &AndroidApp) -> Result<(), Box<dyn std::error::Error>
     }
     assert!(app_data.window_inited && android_app.native_window().is_some());
     log::debug!("alxr-client: is activity paused? {0} ", !app_data.resumed);
+    if APP_CONFIG.kiosk {
+        kiosk::start_lock_task();
+    }
 
-    let no_linearize_srgb = APP_CONFIG.no_linearize_srgb || is_device("Lynx", &vm);
+    let no_linearize_srgb = APP_CONFIG.no_linearize_srgb || is_device("Lynx");
     log::info!("alxr-client: Disable shader gamma/sRGB linearization? {no_linearize_srgb}");
 
-    let mut eye_tracking_type = APP_CONFIG.eye_tracking.unwrap_or(ALXREyeTrackingType::Auto);
-    // quest firmware version 71.0.0.178.498 has a crash bug in `xrSyncActions` when
-    // `XR_EXT_eye_gaze_interaction` extension is enabled.
-    match eye_tracking_type {
-        ALXREyeTrackingType::Auto | ALXREyeTrackingType::ExtEyeGazeInteraction => {
-            let build_id = get_build_property(&vm, "ID");
-            if build_id == "SQ3A.220605.009.A1" {
-                log::debug!("alxr-client: override eye-tracking type workaround enabled.");
-                eye_tracking_type = ALXREyeTrackingType::FBEyeTrackingSocial;
-            }
-        }
-        _ => {}
-    };
+    let firmware_version = get_firmware_version();
+    let vendor = DeviceVendor::from_manufacturer(&get_build_manufacturer());
+    let mut capabilities =
+        Capabilities::resolve(vendor, &firmware_version, &get_build_property("ID"));
+    let quirks = APP_CONFIG.load_quirks();
+    capabilities.apply_overrides(&quirks.capabilities);
+    set_controller_pose_time_offset(APP_CONFIG.controller_pose_time_offset(vendor, &quirks));
+    log::info!("alxr-client: vendor: {vendor:?}, capabilities: {capabilities:?}");
+    let gated_features = APP_CONFIG.gate_features(&capabilities);
+    set_reference_space(gated_features.reference_space);
+    let binding_profile =
+        APP_CONFIG.load_binding_profile(android_app.external_data_path().as_deref());
+    set_binding_profile(&binding_profile);
+
+    // must outlive alxr_init.
+    let internal_data_path = asset_extraction
+        .join()
+        .ok()
+        .flatten()
+        .and_then(|path| CString::new(path.to_string_lossy().as_bytes()).ok());
 
     let ctx = ALXRClientCtx {
         graphicsApi: APP_CONFIG.graphics_api.unwrap_or(ALXRGraphicsApi::Auto),
@@ -243,32 +453,34 @@ unsafe fn run(android_app: &AndroidApp) -> Result<(), Box<dyn std::error::Error>
         setWaitingNextIDR: Some(set_waiting_next_idr),
         requestIDR: Some(request_idr),
         disableLinearizeSrgb: no_linearize_srgb,
-        noSuggestedBindings: APP_CONFIG.no_bindings,
+        noSuggestedBindings: APP_CONFIG.no_bindings || binding_profile.no_suggested_bindings,
         noServerFramerateLock: APP_CONFIG.no_server_framerate_lock,
         noFrameSkip: APP_CONFIG.no_frameskip,
         disableLocalDimming: APP_CONFIG.disable_localdimming,
-        headlessSession: APP_CONFIG.headless_session,
-        noPassthrough: APP_CONFIG.no_passthrough,
+        headlessSession: APP_CONFIG.headless_session || APP_CONFIG.tracking_bridge,
+        noPassthrough: gated_features.no_passthrough,
         noFTServer: APP_CONFIG.no_tracking_server,
-        noHandTracking: APP_CONFIG.no_hand_tracking,
+        noHandTracking: gated_features.no_hand_tracking,
         faceTrackingDataSources: APP_CONFIG.get_face_tracking_data_source_flags(),
-        facialTracking: APP_CONFIG
-            .facial_tracking
-            .unwrap_or(ALXRFacialExpressionType::Auto),
-        eyeTracking: eye_tracking_type,
-        firmwareVersion: get_firmware_version(&vm),
+        facialTracking: gated_features.facial_tracking,
+        eyeTracking: gated_features.eye_tracking,
+        firmwareVersion: firmware_version,
         trackingServerPortNo: APP_CONFIG.tracking_server_port_no,
         simulateHeadless: APP_CONFIG.simulate_headless,
         passthroughMode: APP_CONFIG
             .passthrough_mode
             .unwrap_or(ALXRPassthroughMode::None),
-        internalDataPath: std::ptr::null(),
+        internalDataPath: internal_data_path
+            .as_ref()
+            .map_or(std::ptr::null(), |path| path.as_ptr()),
         noVisibilityMasks: APP_CONFIG.no_visibility_masks,
     };
     let mut sys_properties = ALXRSystemProperties::new();
     if !alxr_init(&ctx, &mut sys_properties) {
         return Ok(());
     }
+    mark_startup_stage("engine_initialized");
+    advertise_tracking_server(&sys_properties, &gated_features);
 
     let window = android_app.native_window().unwrap();
     log::info!(
@@ -290,16 +502,58 @@ unsafe fn run(android_app: &AndroidApp) -> Result<(), Box<dyn std::error::Error>
         android_app.poll_events(NO_WAIT_TIME, |event| {
             app_data.handle_lifecycle_event(&android_app, &event);
         });
+        app_data.update_proximity();
 
         let mut exit_render_loop = false;
         let mut request_restart = false;
         alxr_process_frame(&mut exit_render_loop, &mut request_restart);
-        if exit_render_loop {
+        if exit_render_loop || is_exit_requested() {
             break;
         }
+
+        if is_poisoned() {
+            log::warn!("alxr-client: client state poisoned by a panic, restarting connections.");
+            clear_poisoned();
+            if app_data.resumed && app_data.removed_since.is_none() {
+                shutdown();
+                init_connections(&app_data.sys_properties.unwrap());
+            }
+        }
     }
 
-    shutdown();
-    alxr_destroy();
+    // producers first, then the connections they feed. The wifi lock is a JNI global reference,
+    // released before the engine tears down the activity's OpenXR instance.
+    let mut teardown = Teardown::new("client");
+    teardown.stage(
+        "proximity monitor",
+        DEFAULT_STAGE_TIMEOUT,
+        stop_proximity_monitor,
+    );
+    teardown.stage(
+        "ambient light monitor",
+        DEFAULT_STAGE_TIMEOUT,
+        stop_ambient_light_monitor,
+    );
+    teardown.stage("imu monitor", DEFAULT_STAGE_TIMEOUT, stop_imu_monitor);
+    teardown.stage(
+        "battery monitor",
+        DEFAULT_STAGE_TIMEOUT,
+        stop_battery_monitor,
+    );
+    teardown.stage(
+        "thermal monitor",
+        DEFAULT_STAGE_TIMEOUT,
+        stop_thermal_monitor,
+    );
+    teardown.stage("wifi monitor", DEFAULT_STAGE_TIMEOUT, stop_wifi_monitor);
+    teardown.stage_on_current_thread("connections", shutdown);
+    teardown.stage("wifi lock", DEFAULT_STAGE_TIMEOUT, release_wifi_lock);
+    teardown.stage_on_current_thread("engine", || alxr_destroy());
+    let leaks = if is_wifi_lock_held() {
+        vec!["wifi lock"]
+    } else {
+        vec![]
+    };
+    teardown.finish(&leaks);
     Ok(())
 }
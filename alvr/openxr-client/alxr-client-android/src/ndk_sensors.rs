@@ -0,0 +1,125 @@
+#![cfg(target_os = "android")]
+// Polls a device sensor through the NDK sensor API on the calling thread, shared by the sensor
+// monitors.
+use std::{
+    ffi::{c_int, c_void},
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+pub const ASENSOR_TYPE_ACCELEROMETER: c_int = 1;
+pub const ASENSOR_TYPE_GYROSCOPE: c_int = 4;
+pub const ASENSOR_TYPE_LIGHT: c_int = 5;
+pub const ASENSOR_TYPE_PROXIMITY: c_int = 8;
+const ALOOPER_PREPARE_ALLOW_NON_CALLBACKS: c_int = 1;
+const LOOPER_ID_SENSOR: c_int = 1;
+const STOP_CHECK_INTERVAL_MS: c_int = 200;
+
+#[repr(C)]
+pub struct ASensorEvent {
+    version: i32,
+    sensor: i32,
+    sensor_type: i32,
+    reserved0: i32,
+    pub timestamp: i64,
+    pub data: [f32; 16],
+    flags: u32,
+    reserved1: [i32; 3],
+}
+
+#[link(name = "android")]
+extern "C" {
+    fn ASensorManager_getInstance() -> *mut c_void;
+    fn ASensorManager_getDefaultSensor(manager: *mut c_void, sensor_type: c_int) -> *const c_void;
+    fn ASensorManager_createEventQueue(
+        manager: *mut c_void,
+        looper: *mut c_void,
+        ident: c_int,
+        callback: *const c_void,
+        data: *mut c_void,
+    ) -> *mut c_void;
+    fn ASensorManager_destroyEventQueue(manager: *mut c_void, queue: *mut c_void) -> c_int;
+    fn ASensorEventQueue_enableSensor(queue: *mut c_void, sensor: *const c_void) -> c_int;
+    fn ASensorEventQueue_disableSensor(queue: *mut c_void, sensor: *const c_void) -> c_int;
+    fn ASensorEventQueue_setEventRate(
+        queue: *mut c_void,
+        sensor: *const c_void,
+        usec: i32,
+    ) -> c_int;
+    fn ASensorEventQueue_getEvents(
+        queue: *mut c_void,
+        events: *mut ASensorEvent,
+        count: usize,
+    ) -> isize;
+    fn ALooper_prepare(opts: c_int) -> *mut c_void;
+    fn ALooper_pollOnce(
+        timeout_ms: c_int,
+        out_fd: *mut c_int,
+        out_events: *mut c_int,
+        out_data: *mut *mut c_void,
+    ) -> c_int;
+}
+
+pub enum SensorError {
+    NoSensor,
+    EnableFailed,
+}
+
+// Calls on_event with every event until stop_requested is set. Without a sampling period the
+// sensor uses its default rate, which is slow for continuous sensors.
+pub fn poll_sensor(
+    sensor_type: c_int,
+    sampling_period: Option<Duration>,
+    stop_requested: &AtomicBool,
+    mut on_event: impl FnMut(&ASensorEvent),
+) -> Result<(), SensorError> {
+    unsafe {
+        let manager = ASensorManager_getInstance();
+        let sensor = ASensorManager_getDefaultSensor(manager, sensor_type);
+        if sensor.is_null() {
+            return Err(SensorError::NoSensor);
+        }
+        let looper = ALooper_prepare(ALOOPER_PREPARE_ALLOW_NON_CALLBACKS);
+        let queue = ASensorManager_createEventQueue(
+            manager,
+            looper,
+            LOOPER_ID_SENSOR,
+            ptr::null(),
+            ptr::null_mut(),
+        );
+        if queue.is_null() || ASensorEventQueue_enableSensor(queue, sensor) < 0 {
+            if !queue.is_null() {
+                ASensorManager_destroyEventQueue(manager, queue);
+            }
+            return Err(SensorError::EnableFailed);
+        }
+        if let Some(period) = sampling_period {
+            let period_us = period.as_micros().min(i32::MAX as u128) as i32;
+            if ASensorEventQueue_setEventRate(queue, sensor, period_us) < 0 {
+                log::warn!("ALXR: Failed to set the rate of sensor type {sensor_type}");
+            }
+        }
+
+        let mut events: [ASensorEvent; 8] = std::mem::zeroed();
+        while !stop_requested.load(Ordering::Relaxed) {
+            ALooper_pollOnce(
+                STOP_CHECK_INTERVAL_MS,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            );
+            loop {
+                let count = ASensorEventQueue_getEvents(queue, events.as_mut_ptr(), events.len());
+                if count <= 0 {
+                    break;
+                }
+                events[..count as usize].iter().for_each(&mut on_event);
+            }
+        }
+
+        ASensorEventQueue_disableSensor(queue, sensor);
+        ASensorManager_destroyEventQueue(manager, queue);
+    }
+    Ok(())
+}
@@ -1,4 +1,7 @@
 #![cfg(target_os = "android")]
+use crate::jni_utils::{
+    call_method_cached, clear_pending_exception, get_static_field_cached, with_env,
+};
 use jni;
 
 //
@@ -16,8 +19,12 @@ fn android_permission_name<'a>(
 ) -> jni::errors::Result<jni::objects::JValueOwned<'a>> {
     // nested class permission in class android.Manifest,
     // hence android 'slash' Manifest 'dollar' permission
-    let class_manifest_permission = jni_env.find_class("android/Manifest$permission")?;
-    jni_env.get_static_field(class_manifest_permission, perm_name, "Ljava/lang/String;")
+    get_static_field_cached(
+        jni_env,
+        "android/Manifest$permission",
+        perm_name,
+        "Ljava/lang/String;",
+    )
 }
 
 //
@@ -34,10 +41,13 @@ fn android_has_permission<'a>(
     perm_name: &str,
     jni_env: &mut jni::JNIEnv<'a>,
 ) -> jni::errors::Result<bool> {
-    let class_package_manager = jni_env.find_class("android/content/pm/PackageManager")?;
-    let permission_granted = jni_env
-        .get_static_field(class_package_manager, "PERMISSION_GRANTED", "I")?
-        .i()?;
+    let permission_granted = get_static_field_cached(
+        jni_env,
+        "android/content/pm/PackageManager",
+        "PERMISSION_GRANTED",
+        "I",
+    )?
+    .i()?;
 
     let maybe_custom_perm_name = if perm_name.contains('.') {
         Some(jni_env.new_string(&perm_name)?)
@@ -51,14 +61,15 @@ fn android_has_permission<'a>(
         maybe_custom_perm_name.unwrap().into()
     };
     let activity_obj = unsafe { jni::objects::JObject::from_raw(activity) };
-    let int_result = jni_env
-        .call_method(
-            activity_obj,
-            "checkSelfPermission",
-            "(Ljava/lang/String;)I",
-            &[(&ls_perm).into()],
-        )?
-        .i()?;
+    let int_result = call_method_cached(
+        jni_env,
+        &activity_obj,
+        "android/content/Context",
+        "checkSelfPermission",
+        "(Ljava/lang/String;)I",
+        &[(&ls_perm).into()],
+    )?
+    .i()?;
 
     Ok(int_result == permission_granted)
 }
@@ -103,8 +114,10 @@ fn android_request_permissions<'a>(
     }
 
     let activity_obj = unsafe { jni::objects::JObject::from_raw(activity) };
-    jni_env.call_method(
-        activity_obj,
+    call_method_cached(
+        jni_env,
+        &activity_obj,
+        "android/app/Activity",
         "requestPermissions",
         "([Ljava/lang/String;I)V",
         &[(&perm_array).into(), 0.into()],
@@ -112,25 +125,29 @@ fn android_request_permissions<'a>(
     return Ok(());
 }
 
-pub fn check_android_permissions<'a>(
-    activity: jni::sys::jobject,
-    jvm: &'a jni::JavaVM,
-) -> jni::errors::Result<()> {
-    let mut env = jvm.attach_current_thread()?;
-    let mut permission_names = vec![];
-    for perm_name in [
-        "RECORD_AUDIO",
-        "READ_EXTERNAL_STORAGE",
-        "com.oculus.permission.EYE_TRACKING",
-        "com.oculus.permission.FACE_TRACKING",
-        "com.magicleap.permission.EYE_TRACKING",
-        "com.picovr.permission.EYE_TRACKING",
-        // Re-enable when Pico runtime supports it.
-        //"com.picovr.permission.FACE_TRACKING",
-    ] {
-        if !android_has_permission(activity, &perm_name, &mut env)? {
-            permission_names.push(perm_name);
+pub fn check_android_permissions(activity: jni::sys::jobject) -> jni::errors::Result<()> {
+    with_env(|env| {
+        let mut permission_names = vec![];
+        for perm_name in [
+            "RECORD_AUDIO",
+            "READ_EXTERNAL_STORAGE",
+            "com.oculus.permission.EYE_TRACKING",
+            "com.oculus.permission.FACE_TRACKING",
+            "com.magicleap.permission.EYE_TRACKING",
+            "com.picovr.permission.EYE_TRACKING",
+            // Re-enable when Pico runtime supports it.
+            //"com.picovr.permission.FACE_TRACKING",
+        ] {
+            // Permissions unknown to older/OEM android builds are skipped instead of failing startup.
+            match android_has_permission(activity, &perm_name, env) {
+                Ok(false) => permission_names.push(perm_name),
+                Ok(true) => (),
+                Err(e) => {
+                    clear_pending_exception(env);
+                    log::warn!("alxr-client: failed to query permission {perm_name}: {e}");
+                }
+            }
         }
-    }
-    android_request_permissions(activity, &permission_names, &mut env)
+        android_request_permissions(activity, &permission_names, env)
+    })
 }
@@ -0,0 +1,92 @@
+#![cfg(target_os = "android")]
+// Reads the proximity sensor of the headset through the NDK sensor API, the main loop takes the
+// worn/removed changes with take_proximity_change().
+use crate::ndk_sensors::{self, SensorError, ASENSOR_TYPE_PROXIMITY};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU8, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+// Most proximity sensors only report near (0) or far (their maximum range, a few cm).
+const NEAR_DISTANCE_CM: f32 = 1.0;
+
+const NO_CHANGE: u8 = 0;
+const WORN: u8 = 1;
+const REMOVED: u8 = 2;
+
+struct ProximityMonitor {
+    stop_requested: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+lazy_static! {
+    static ref PROXIMITY_MONITOR: Mutex<Option<ProximityMonitor>> = Mutex::new(None);
+}
+
+// Only the latest change is kept, the main loop doesn't need the ones in between.
+static PROXIMITY_CHANGE: AtomicU8 = AtomicU8::new(NO_CHANGE);
+
+// Some(true) once the headset is put on, Some(false) once it is removed.
+pub fn take_proximity_change() -> Option<bool> {
+    match PROXIMITY_CHANGE.swap(NO_CHANGE, Ordering::Relaxed) {
+        WORN => Some(true),
+        REMOVED => Some(false),
+        _ => None,
+    }
+}
+
+fn monitor_loop(stop_requested: Arc<AtomicBool>) {
+    let mut worn = None;
+    let result = ndk_sensors::poll_sensor(ASENSOR_TYPE_PROXIMITY, None, &stop_requested, |event| {
+        let now_worn = event.data[0] < NEAR_DISTANCE_CM;
+        if worn != Some(now_worn) {
+            // the first reading only sets the initial state, the app resumes with it worn.
+            if worn.is_some() {
+                log::info!(
+                    "ALXR: Headset {}",
+                    if now_worn { "worn" } else { "removed" }
+                );
+                PROXIMITY_CHANGE.store(if now_worn { WORN } else { REMOVED }, Ordering::Relaxed);
+            }
+            worn = Some(now_worn);
+        }
+    });
+    match result {
+        Ok(()) => (),
+        Err(SensorError::NoSensor) => {
+            log::info!(
+                "ALXR: No proximity sensor, streaming continues while the headset is removed"
+            )
+        }
+        Err(SensorError::EnableFailed) => log::warn!("ALXR: Failed to enable the proximity sensor"),
+    }
+}
+
+pub fn start_proximity_monitor() {
+    let mut maybe_monitor = PROXIMITY_MONITOR.lock();
+    if maybe_monitor.is_some() {
+        return;
+    }
+    PROXIMITY_CHANGE.store(NO_CHANGE, Ordering::Relaxed);
+    let stop_requested = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let stop_requested = Arc::clone(&stop_requested);
+        thread::spawn(move || monitor_loop(stop_requested))
+    };
+    *maybe_monitor = Some(ProximityMonitor {
+        stop_requested,
+        thread,
+    });
+}
+
+pub fn stop_proximity_monitor() {
+    if let Some(monitor) = PROXIMITY_MONITOR.lock().take() {
+        monitor.stop_requested.store(true, Ordering::Relaxed);
+        monitor.thread.join().ok();
+    }
+}
@@ -0,0 +1,170 @@
+#![cfg(target_os = "android")]
+use crate::jni_utils::{android_context, call_method_cached, with_env};
+use alxr_common::{
+    subscribe_connection_state, thermal_send, ConnectionState, ThermalStatsPacket, ThermalStatus,
+};
+use jni::{objects::JObject, JNIEnv};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+const POWER_MANAGER_CLASS: &str = "android/os/PowerManager";
+
+// How far ahead the headroom is forecast, gives the server time to lower the bitrate.
+const HEADROOM_FORECAST_SECONDS: i32 = 10;
+
+// Smaller headroom changes are not worth a packet.
+const MIN_HEADROOM_CHANGE: f32 = 0.05;
+
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+struct ThermalMonitor {
+    stop_requested: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+lazy_static! {
+    static ref THERMAL_MONITOR: Mutex<Option<ThermalMonitor>> = Mutex::new(None);
+}
+
+fn thermal_status_from_android(status: i32) -> ThermalStatus {
+    match status {
+        1 => ThermalStatus::Light,
+        2 => ThermalStatus::Moderate,
+        3 => ThermalStatus::Severe,
+        4 => ThermalStatus::Critical,
+        5 => ThermalStatus::Emergency,
+        6 => ThermalStatus::Shutdown,
+        _ => ThermalStatus::None,
+    }
+}
+
+fn power_manager<'a>(env: &mut JNIEnv<'a>) -> jni::errors::Result<JObject<'a>> {
+    let power_service_str = env.new_string("power")?;
+    call_method_cached(
+        env,
+        &android_context(),
+        "android/content/Context",
+        "getSystemService",
+        "(Ljava/lang/String;)Ljava/lang/Object;",
+        &[(&power_service_str).into()],
+    )?
+    .l()
+}
+
+// getCurrentThermalStatus needs API 29 and getThermalHeadroom API 30.
+fn query_thermal_stats() -> jni::errors::Result<ThermalStatsPacket> {
+    let status = with_env(|env| {
+        let power_manager = power_manager(env)?;
+        call_method_cached(
+            env,
+            &power_manager,
+            POWER_MANAGER_CLASS,
+            "getCurrentThermalStatus",
+            "()I",
+            &[],
+        )?
+        .i()
+    })?;
+    // NaN when unsupported or when queried more than once a second.
+    let headroom = with_env(|env| {
+        let power_manager = power_manager(env)?;
+        call_method_cached(
+            env,
+            &power_manager,
+            POWER_MANAGER_CLASS,
+            "getThermalHeadroom",
+            "(I)F",
+            &[HEADROOM_FORECAST_SECONDS.into()],
+        )?
+        .f()
+    })
+    .ok()
+    .filter(|headroom| headroom.is_finite());
+    Ok(ThermalStatsPacket {
+        status: thermal_status_from_android(status),
+        headroom,
+    })
+}
+
+fn should_send(last_sent: Option<ThermalStatsPacket>, stats: ThermalStatsPacket) -> bool {
+    let last_sent = match last_sent {
+        Some(last_sent) => last_sent,
+        None => return true,
+    };
+    if last_sent.status != stats.status {
+        return true;
+    }
+    match (last_sent.headroom, stats.headroom) {
+        (Some(last_headroom), Some(headroom)) => {
+            (headroom - last_headroom).abs() >= MIN_HEADROOM_CHANGE
+        }
+        (last_headroom, headroom) => last_headroom.is_some() != headroom.is_some(),
+    }
+}
+
+fn monitor_loop(poll_interval: Duration, stop_requested: Arc<AtomicBool>) {
+    let mut connection_states = subscribe_connection_state();
+    let mut last_sent: Option<ThermalStatsPacket> = None;
+    let mut waited = poll_interval;
+    while !stop_requested.load(Ordering::Relaxed) {
+        // the server drops its thermal limits between connections.
+        while let Ok(state) = connection_states.try_recv() {
+            if state == ConnectionState::Streaming {
+                last_sent = None;
+                waited = poll_interval;
+            }
+        }
+
+        if waited >= poll_interval {
+            waited = Duration::ZERO;
+            match query_thermal_stats() {
+                Ok(stats) if should_send(last_sent, stats) => {
+                    if stats.status >= ThermalStatus::Severe {
+                        log::warn!("ALXR: Device is throttling: {stats:?}");
+                    }
+                    thermal_send(stats);
+                    last_sent = Some(stats);
+                }
+                Ok(_) => (),
+                Err(e) => {
+                    log::warn!("ALXR: Thermal status not available, stopping monitor: {e}");
+                    return;
+                }
+            }
+        }
+
+        thread::sleep(STOP_CHECK_INTERVAL);
+        waited += STOP_CHECK_INTERVAL;
+    }
+}
+
+pub fn start_thermal_monitor(poll_interval: Duration) {
+    let mut maybe_monitor = THERMAL_MONITOR.lock();
+    if maybe_monitor.is_some() || poll_interval.is_zero() {
+        return;
+    }
+    let stop_requested = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let stop_requested = Arc::clone(&stop_requested);
+        thread::spawn(move || monitor_loop(poll_interval, stop_requested))
+    };
+    *maybe_monitor = Some(ThermalMonitor {
+        stop_requested,
+        thread,
+    });
+}
+
+pub fn stop_thermal_monitor() {
+    if let Some(monitor) = THERMAL_MONITOR.lock().take() {
+        monitor.stop_requested.store(true, Ordering::Relaxed);
+        monitor.thread.join().ok();
+    }
+}
@@ -1,92 +1,300 @@
 #![cfg(target_os = "android")]
-use jni;
-use jni::objects::GlobalRef;
-use ndk_context;
+use crate::jni_utils::{android_context, call_method_cached, get_api_level, with_env};
+use alxr_common::{
+    is_tracking_server_discovery_enabled, wifi_stats_send, WifiLockMode, WifiStatsPacket,
+    APP_CONFIG,
+};
+use jni::{
+    objects::{GlobalRef, JString},
+    JNIEnv,
+};
 
 use lazy_static::lazy_static;
 use parking_lot::Mutex;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
 
 const WIFI_MODE_FULL_LOW_LATENCY: i32 = 4;
 const WIFI_MODE_FULL_HIGH_PERF: i32 = 3;
 
+const WIFI_LOCK_CLASS: &str = "android/net/wifi/WifiManager$WifiLock";
+const MULTICAST_LOCK_CLASS: &str = "android/net/wifi/WifiManager$MulticastLock";
+const WIFI_INFO_CLASS: &str = "android/net/wifi/WifiInfo";
+
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+struct WifiMonitor {
+    stop_requested: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
 lazy_static! {
     static ref WIFI_LOCK: Mutex<Option<GlobalRef>> = Mutex::new(None);
+    static ref MULTICAST_LOCK: Mutex<Option<GlobalRef>> = Mutex::new(None);
+    static ref WIFI_MONITOR: Mutex<Option<WifiMonitor>> = Mutex::new(None);
 }
 
-fn get_wifi_manager<'a>(env: &mut jni::JNIEnv<'a>) -> jni::objects::JObject<'a> {
-    let wifi_service_str = env.new_string("wifi").unwrap();
-
-    let ctx = ndk_context::android_context().context();
-    env.call_method(
-        unsafe { jni::objects::JObject::from_raw(ctx as jni::sys::jobject) },
+fn get_wifi_manager<'a>(
+    env: &mut jni::JNIEnv<'a>,
+) -> jni::errors::Result<jni::objects::JObject<'a>> {
+    let wifi_service_str = env.new_string("wifi")?;
+    call_method_cached(
+        env,
+        &android_context(),
+        "android/content/Context",
         "getSystemService",
         "(Ljava/lang/String;)Ljava/lang/Object;",
         &[(&wifi_service_str).into()],
-    )
-    .unwrap()
+    )?
     .l()
-    .unwrap()
 }
 
-fn get_api_level() -> i32 {
-    let vm_ptr = ndk_context::android_context().vm();
-    let vm = unsafe { jni::JavaVM::from_raw(vm_ptr.cast()).unwrap() };
-    let mut env = vm.attach_current_thread().unwrap();
-    env.get_static_field("android/os/Build$VERSION", "SDK_INT", "I")
-        .unwrap()
-        .i()
-        .unwrap()
+fn wifi_lock_mode() -> WifiLockMode {
+    APP_CONFIG.wifi_lock_mode.unwrap_or_else(|| {
+        if get_api_level().unwrap_or(0) >= 29 {
+            // Recommended for virtual reality since it disables WIFI scans
+            WifiLockMode::LowLatency
+        } else {
+            WifiLockMode::HighPerf
+        }
+    })
+}
+
+fn create_wifi_lock(env: &mut JNIEnv, wifi_mode: i32) -> jni::errors::Result<GlobalRef> {
+    let wifi_manager = get_wifi_manager(env)?;
+    let wifi_lock_jstring = env.new_string("alxr_wifi_lock")?;
+    let wifi_lock = call_method_cached(
+        env,
+        &wifi_manager,
+        "android/net/wifi/WifiManager",
+        "createWifiLock",
+        "(ILjava/lang/String;)Landroid/net/wifi/WifiManager$WifiLock;",
+        &[wifi_mode.into(), (&wifi_lock_jstring).into()],
+    )?
+    .l()?;
+    call_method_cached(env, &wifi_lock, WIFI_LOCK_CLASS, "acquire", "()V", &[])?;
+    env.new_global_ref(wifi_lock)
+}
+
+fn create_multicast_lock(env: &mut JNIEnv) -> jni::errors::Result<GlobalRef> {
+    let wifi_manager = get_wifi_manager(env)?;
+    let multicast_lock_jstring = env.new_string("alxr_multicast_lock")?;
+    let multicast_lock = call_method_cached(
+        env,
+        &wifi_manager,
+        "android/net/wifi/WifiManager",
+        "createMulticastLock",
+        "(Ljava/lang/String;)Landroid/net/wifi/WifiManager$MulticastLock;",
+        &[(&multicast_lock_jstring).into()],
+    )?
+    .l()?;
+    call_method_cached(
+        env,
+        &multicast_lock,
+        MULTICAST_LOCK_CLASS,
+        "acquire",
+        "()V",
+        &[],
+    )?;
+    env.new_global_ref(multicast_lock)
 }
 
-// This is needed to avoid wifi scans that disrupt streaming.
+fn release_lock(lock: GlobalRef, class: &str) -> jni::errors::Result<()> {
+    with_env(|env| call_method_cached(env, lock.as_obj(), class, "release", "()V", &[]).map(|_| ()))
+}
+
+// This is needed to avoid wifi scans that disrupt streaming. The multicast lock lets mDNS queries
+// for the tracking server through.
 pub fn acquire_wifi_lock() {
     let mut maybe_wifi_lock = WIFI_LOCK.lock();
 
-    if maybe_wifi_lock.is_none() {
-        log::info!("ALXR: Aquring Wifi Lock");
-        let vm_ptr = ndk_context::android_context().vm();
-        let vm = unsafe { jni::JavaVM::from_raw(vm_ptr.cast()).unwrap() };
-        let mut env = vm.attach_current_thread().unwrap();
+    let wifi_mode = match wifi_lock_mode() {
+        WifiLockMode::LowLatency => Some(WIFI_MODE_FULL_LOW_LATENCY),
+        WifiLockMode::HighPerf => Some(WIFI_MODE_FULL_HIGH_PERF),
+        WifiLockMode::Off => None,
+    };
+    if let (None, Some(wifi_mode)) = (&*maybe_wifi_lock, wifi_mode) {
+        log::info!("ALXR: Aquring Wifi Lock ({:?})", wifi_lock_mode());
 
-        let wifi_mode = if get_api_level() >= 29 {
-            // Recommended for virtual reality since it disables WIFI scans
-            WIFI_MODE_FULL_LOW_LATENCY
-        } else {
-            WIFI_MODE_FULL_HIGH_PERF
-        };
+        match with_env(|env| create_wifi_lock(env, wifi_mode)) {
+            Ok(wifi_lock) => {
+                *maybe_wifi_lock = Some(wifi_lock);
+                log::info!("ALXR: Wifi Lock Aquired");
+            }
+            Err(e) => log::warn!("ALXR: Failed to acquire Wifi Lock: {e}"),
+        }
+    }
 
-        let wifi_manager = get_wifi_manager(&mut env);
-        let wifi_lock_jstring = env.new_string("alxr_wifi_lock").unwrap();
-        let wifi_lock = env
-            .call_method(
-                wifi_manager,
-                "createWifiLock",
-                "(ILjava/lang/String;)Landroid/net/wifi/WifiManager$WifiLock;",
-                &[wifi_mode.into(), (&wifi_lock_jstring).into()],
-            )
-            .unwrap()
-            .l()
-            .unwrap();
-        env.call_method(&wifi_lock, "acquire", "()V", &[]).unwrap();
-
-        *maybe_wifi_lock = Some(env.new_global_ref(wifi_lock).unwrap());
-
-        log::info!("ALXR: Wifi Lock Aquired");
+    let mut maybe_multicast_lock = MULTICAST_LOCK.lock();
+    if maybe_multicast_lock.is_none() && is_tracking_server_discovery_enabled() {
+        match with_env(create_multicast_lock) {
+            Ok(multicast_lock) => {
+                *maybe_multicast_lock = Some(multicast_lock);
+                log::info!("ALXR: Multicast Lock Aquired");
+            }
+            Err(e) => log::warn!("ALXR: Failed to acquire Multicast Lock: {e}"),
+        }
     }
 }
 
+// The locks are busy while they are being released, which counts as still held.
+pub fn is_wifi_lock_held() -> bool {
+    [&*WIFI_LOCK, &*MULTICAST_LOCK]
+        .iter()
+        .any(|lock| lock.try_lock().map_or(true, |lock| lock.is_some()))
+}
+
 pub fn release_wifi_lock() {
     if let Some(wifi_lock) = WIFI_LOCK.lock().take() {
         log::info!("ALXR: Releasing Wifi Lock");
-
-        let vm_ptr = ndk_context::android_context().vm();
-        let vm = unsafe { jni::JavaVM::from_raw(vm_ptr.cast()).unwrap() };
-        let mut env = vm.attach_current_thread().unwrap();
-
-        env.call_method(wifi_lock.as_obj(), "release", "()V", &[])
-            .unwrap();
+        if let Err(e) = release_lock(wifi_lock, WIFI_LOCK_CLASS) {
+            log::warn!("ALXR: Failed to release Wifi Lock: {e}");
+        }
 
         // wifi_lock is dropped here
         log::info!("ALXR: Wifi Lock Released");
     }
+    if let Some(multicast_lock) = MULTICAST_LOCK.lock().take() {
+        if let Err(e) = release_lock(multicast_lock, MULTICAST_LOCK_CLASS) {
+            log::warn!("ALXR: Failed to release Multicast Lock: {e}");
+        }
+        log::info!("ALXR: Multicast Lock Released");
+    }
+}
+
+struct WifiSample {
+    rssi_dbm: i32,
+    link_speed_mbps: i32,
+    frequency_mhz: i32,
+    // Without the location permission android reports a fixed placeholder, roaming is then not
+    // detected.
+    bssid: Option<String>,
+}
+
+fn query_wifi_sample() -> jni::errors::Result<WifiSample> {
+    with_env(|env| {
+        let wifi_manager = get_wifi_manager(env)?;
+        let wifi_info = call_method_cached(
+            env,
+            &wifi_manager,
+            "android/net/wifi/WifiManager",
+            "getConnectionInfo",
+            "()Landroid/net/wifi/WifiInfo;",
+            &[],
+        )?
+        .l()?;
+        let mut get_int = |method: &str| -> jni::errors::Result<i32> {
+            call_method_cached(env, &wifi_info, WIFI_INFO_CLASS, method, "()I", &[])?.i()
+        };
+        let rssi_dbm = get_int("getRssi")?;
+        let link_speed_mbps = get_int("getLinkSpeed")?;
+        let frequency_mhz = get_int("getFrequency")?;
+        let bssid = call_method_cached(
+            env,
+            &wifi_info,
+            WIFI_INFO_CLASS,
+            "getBSSID",
+            "()Ljava/lang/String;",
+            &[],
+        )?
+        .l()?;
+        let bssid = if bssid.is_null() {
+            None
+        } else {
+            Some(env.get_string(&JString::from(bssid))?.into())
+        };
+        Ok(WifiSample {
+            rssi_dbm,
+            link_speed_mbps,
+            frequency_mhz,
+            bssid,
+        })
+    })
+}
+
+fn monitor_loop(poll_interval: Duration, stop_requested: Arc<AtomicBool>) {
+    let min_link_speed_mbps = APP_CONFIG.wifi_min_link_speed_mbps as i32;
+    let mut last_bssid: Option<String> = None;
+    let mut link_speed_low = false;
+    let mut waited = poll_interval;
+    while !stop_requested.load(Ordering::Relaxed) {
+        if waited >= poll_interval {
+            waited = Duration::ZERO;
+            let sample = match query_wifi_sample() {
+                Ok(sample) => sample,
+                Err(e) => {
+                    log::warn!("ALXR: Wifi info not available, stopping monitor: {e}");
+                    return;
+                }
+            };
+
+            // -1 when not connected to a wifi network.
+            if sample.link_speed_mbps > 0 {
+                let roamed = match (&last_bssid, &sample.bssid) {
+                    (Some(last_bssid), Some(bssid)) => last_bssid != bssid,
+                    _ => false,
+                };
+                if roamed {
+                    log::warn!(
+                        "ALXR: Roamed to access point {} ({} MHz, {} dBm)",
+                        sample.bssid.as_deref().unwrap_or_default(),
+                        sample.frequency_mhz,
+                        sample.rssi_dbm
+                    );
+                }
+                if sample.bssid.is_some() {
+                    last_bssid = sample.bssid.clone();
+                }
+
+                let now_low = sample.link_speed_mbps < min_link_speed_mbps;
+                if now_low && !link_speed_low {
+                    log::warn!(
+                        "ALXR: Wifi link speed dropped to {} Mbps ({} dBm)",
+                        sample.link_speed_mbps,
+                        sample.rssi_dbm
+                    );
+                }
+                link_speed_low = now_low;
+
+                wifi_stats_send(WifiStatsPacket {
+                    rssi_dbm: sample.rssi_dbm,
+                    link_speed_mbps: sample.link_speed_mbps as u32,
+                    frequency_mhz: sample.frequency_mhz.max(0) as u32,
+                    roamed,
+                });
+            }
+        }
+
+        thread::sleep(STOP_CHECK_INTERVAL);
+        waited += STOP_CHECK_INTERVAL;
+    }
+}
+
+pub fn start_wifi_monitor(poll_interval: Duration) {
+    let mut maybe_monitor = WIFI_MONITOR.lock();
+    if maybe_monitor.is_some() || poll_interval.is_zero() {
+        return;
+    }
+    let stop_requested = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let stop_requested = Arc::clone(&stop_requested);
+        thread::spawn(move || monitor_loop(poll_interval, stop_requested))
+    };
+    *maybe_monitor = Some(WifiMonitor {
+        stop_requested,
+        thread,
+    });
+}
+
+pub fn stop_wifi_monitor() {
+    if let Some(monitor) = WIFI_MONITOR.lock().take() {
+        monitor.stop_requested.store(true, Ordering::Relaxed);
+        monitor.thread.join().ok();
+    }
 }
@@ -1,11 +1,14 @@
 #![cfg_attr(target_vendor = "uwp", windows_subsystem = "windows")]
 
 use alxr_common::{
-    alxr_destroy, alxr_init, alxr_is_session_running, alxr_process_frame, battery_send,
-    init_connections, input_send, path_string_to_hash, request_idr, set_waiting_next_idr, shutdown,
-    time_sync_send, video_error_report_send, views_config_send, ALXRClientCtx, ALXRColorSpace,
-    ALXRDecoderType, ALXREyeTrackingType, ALXRFacialExpressionType, ALXRGraphicsApi,
-    ALXRPassthroughMode, ALXRSystemProperties, ALXRVersion, APP_CONFIG,
+    advertise_tracking_server, alxr_destroy, alxr_init, alxr_is_session_running,
+    alxr_process_frame, battery_send, clear_poisoned, init_connections, input_send,
+    is_exit_requested, is_poisoned, mark_startup_stage, path_string_to_hash, prepare_connections,
+    request_idr, set_binding_profile, set_controller_pose_time_offset, set_panic_hook,
+    set_reference_space, set_waiting_next_idr, shutdown, time_sync_send, video_error_report_send,
+    views_config_send, ALXRClientCtx, ALXRColorSpace, ALXRDecoderType, ALXRGraphicsApi,
+    ALXRPassthroughMode, ALXRSystemProperties, ALXRVersion, Capabilities, DeviceVendor, Options,
+    Teardown, APP_CONFIG,
 };
 use std::{thread, time};
 
@@ -37,9 +40,36 @@ const DEFAULT_GRAPHICS_API: ALXRGraphicsApi = ALXRGraphicsApi::Auto;
 
 #[cfg(not(target_os = "android"))]
 fn main() {
+    mark_startup_stage("launch");
+    set_panic_hook();
+    if APP_CONFIG.write_default_config {
+        match Options::write_default_config() {
+            Ok(path) => println!("Wrote default config to {}", path.display()),
+            Err(e) => eprintln!("Failed to write default config: {e}"),
+        }
+        return;
+    }
     println!("{:?}", *APP_CONFIG);
+    if !APP_CONFIG.no_alvr_server {
+        prepare_connections();
+    }
     let selected_api = APP_CONFIG.graphics_api.unwrap_or(DEFAULT_GRAPHICS_API);
     let selected_decoder = APP_CONFIG.decoder_type.unwrap_or(DEFAULT_DECODER_TYPE);
+    let firmware_version = ALXRVersion {
+        major: 0,
+        minor: 0,
+        patch: 0,
+    };
+    let mut capabilities = Capabilities::resolve(DeviceVendor::Unknown, &firmware_version, "");
+    let quirks = APP_CONFIG.load_quirks();
+    capabilities.apply_overrides(&quirks.capabilities);
+    set_controller_pose_time_offset(
+        APP_CONFIG.controller_pose_time_offset(DeviceVendor::Unknown, &quirks),
+    );
+    let gated_features = APP_CONFIG.gate_features(&capabilities);
+    set_reference_space(gated_features.reference_space);
+    let binding_profile = APP_CONFIG.load_binding_profile(None);
+    set_binding_profile(&binding_profile);
     unsafe {
         loop {
             let ctx = ALXRClientCtx {
@@ -56,24 +86,19 @@ fn main() {
                 displayColorSpace: APP_CONFIG.color_space.unwrap_or(ALXRColorSpace::Default),
                 verbose: APP_CONFIG.verbose,
                 disableLinearizeSrgb: APP_CONFIG.no_linearize_srgb,
-                noSuggestedBindings: APP_CONFIG.no_bindings,
+                noSuggestedBindings: APP_CONFIG.no_bindings
+                    || binding_profile.no_suggested_bindings,
                 noServerFramerateLock: false,
                 noFrameSkip: false,
                 disableLocalDimming: APP_CONFIG.disable_localdimming,
-                headlessSession: APP_CONFIG.headless_session,
-                noPassthrough: APP_CONFIG.no_passthrough,
+                headlessSession: APP_CONFIG.headless_session || APP_CONFIG.tracking_bridge,
+                noPassthrough: gated_features.no_passthrough,
                 noFTServer: APP_CONFIG.no_tracking_server,
-                noHandTracking: APP_CONFIG.no_hand_tracking,
+                noHandTracking: gated_features.no_hand_tracking,
                 faceTrackingDataSources: APP_CONFIG.get_face_tracking_data_source_flags(),
-                facialTracking: APP_CONFIG
-                    .facial_tracking
-                    .unwrap_or(ALXRFacialExpressionType::Auto),
-                eyeTracking: APP_CONFIG.eye_tracking.unwrap_or(ALXREyeTrackingType::Auto),
-                firmwareVersion: ALXRVersion {
-                    major: 0,
-                    minor: 0,
-                    patch: 0,
-                },
+                facialTracking: gated_features.facial_tracking,
+                eyeTracking: gated_features.eye_tracking,
+                firmwareVersion: firmware_version,
                 trackingServerPortNo: APP_CONFIG.tracking_server_port_no,
                 simulateHeadless: APP_CONFIG.simulate_headless,
                 passthroughMode: APP_CONFIG
@@ -86,6 +111,8 @@ fn main() {
             if !alxr_init(&ctx, &mut sys_properties) {
                 break;
             }
+            mark_startup_stage("engine_initialized");
+            advertise_tracking_server(&sys_properties, &gated_features);
             if !APP_CONFIG.no_alvr_server {
                 init_connections(&sys_properties);
             }
@@ -94,7 +121,12 @@ fn main() {
             loop {
                 let mut exit_render_loop = false;
                 alxr_process_frame(&mut exit_render_loop, &mut request_restart);
-                if exit_render_loop {
+                if exit_render_loop || is_exit_requested() {
+                    break;
+                }
+                if is_poisoned() {
+                    println!("client state poisoned by a panic, restarting.");
+                    request_restart = true;
                     break;
                 }
                 if !alxr_is_session_running() {
@@ -103,8 +135,11 @@ fn main() {
                 }
             }
 
-            shutdown();
-            alxr_destroy();
+            let mut teardown = Teardown::new("client");
+            teardown.stage_on_current_thread("connections", shutdown);
+            teardown.stage_on_current_thread("engine", || alxr_destroy());
+            teardown.finish(&[]);
+            clear_poisoned();
 
             if !request_restart {
                 break;
@@ -1,7 +1,12 @@
 #![cfg(target_os = "android")]
+use crate::{
+    latency_stats,
+    microphone::{samples_to_bytes, MonoResampler},
+    APP_CONFIG, AUDIO_SYNC_SENDER,
+};
 use alvr_common::prelude::*;
 use alvr_session::AudioConfig;
-use alvr_sockets::{StreamReceiver, StreamSender};
+use alvr_sockets::{AudioSyncPacket, StreamReceiver, StreamSender};
 use oboe::{
     AudioInputCallback, AudioInputStreamSafe, AudioOutputCallback, AudioOutputStreamSafe,
     AudioStream, AudioStreamBuilder, DataCallbackResult, InputPreset, Mono, PerformanceMode,
@@ -10,13 +15,14 @@ use oboe::{
 use parking_lot::Mutex;
 use std::{
     collections::VecDeque,
-    mem,
     sync::{mpsc as smpsc, Arc},
     thread,
+    time::{Duration, Instant},
 };
 use tokio::sync::mpsc as tmpsc;
 
 struct RecorderCallback {
+    resampler: MonoResampler,
     sender: tmpsc::UnboundedSender<Vec<u8>>,
 }
 
@@ -28,27 +34,31 @@ impl AudioInputCallback for RecorderCallback {
         _: &mut dyn AudioInputStreamSafe,
         frames: &[i16],
     ) -> DataCallbackResult {
-        let mut sample_buffer = Vec::with_capacity(frames.len() * mem::size_of::<i16>());
-
-        for frame in frames {
-            sample_buffer.extend(&frame.to_ne_bytes());
-        }
+        let mut samples = Vec::with_capacity(frames.len());
+        self.resampler.process(frames, &mut samples);
 
-        self.sender.send(sample_buffer).ok();
+        self.sender.send(samples_to_bytes(&samples)).ok();
 
         DataCallbackResult::Continue
     }
 }
 
-pub async fn record_audio_loop(sample_rate: u32, mut sender: StreamSender<()>) -> StrResult {
+// `capture_sample_rate` is the rate requested from the device, when it differs from `sample_rate`
+// the samples are resampled before being sent.
+pub async fn record_audio_loop(
+    capture_sample_rate: Option<u32>,
+    sample_rate: u32,
+    mut sender: StreamSender<()>,
+) -> StrResult {
     let (_shutdown_notifier, shutdown_receiver) = smpsc::channel::<()>();
     let (data_sender, mut data_receiver) = tmpsc::unbounded_channel();
+    let capture_sample_rate = capture_sample_rate.unwrap_or(sample_rate);
 
     thread::spawn(move || -> StrResult {
         let mut stream = trace_err!(AudioStreamBuilder::default()
             .set_shared()
             .set_performance_mode(PerformanceMode::LowLatency)
-            .set_sample_rate(sample_rate as _)
+            .set_sample_rate(capture_sample_rate as _)
             .set_sample_rate_conversion_quality(SampleRateConversionQuality::Fastest)
             .set_mono()
             .set_i16()
@@ -56,6 +66,7 @@ pub async fn record_audio_loop(sample_rate: u32, mut sender: StreamSender<()>) -
             .set_usage(Usage::VoiceCommunication)
             .set_input_preset(InputPreset::VoiceCommunication)
             .set_callback(RecorderCallback {
+                resampler: MonoResampler::new(capture_sample_rate, sample_rate),
                 sender: data_sender
             })
             .open_stream())?;
@@ -79,9 +90,168 @@ pub async fn record_audio_loop(sample_rate: u32, mut sender: StreamSender<()>) -
     Ok(())
 }
 
+// Offsets below this are left alone, corrections are audible.
+const AV_SYNC_TOLERANCE_US: f32 = 20_000.;
+const AV_SYNC_CORRECTION_INTERVAL: Duration = Duration::from_secs(1);
+const AV_SYNC_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+// Per batch weight of the offset average, about a second with 10ms batches.
+const AV_SYNC_SMOOTHING: f32 = 0.01;
+
+fn fade(batch: &mut [f32], fade_in: bool) {
+    let frames_count = batch.len() / 2;
+    for (f, frame) in batch.chunks_exact_mut(2).enumerate() {
+        let volume = f as f32 / frames_count as f32;
+        let volume = if fade_in { volume } else { 1. - volume };
+        frame[0] *= volume;
+        frame[1] *= volume;
+    }
+}
+
+// Audio packets carry no timestamps, the audio latency is estimated from the network latency
+// and the buffer level, the video one is the total latency reported by the server. Audio ahead
+// of the video is delayed by playing silence while the buffer fills up, audio behind is trimmed
+// from the front of the buffer.
+struct AvSync {
+    sample_rate: u32,
+    // The receive loop drops samples above this level, delays are limited by it.
+    max_buffer_frames_count: usize,
+    offset_us: Option<f32>,
+    silence_batches: usize,
+    fade_in: bool,
+    last_correction: Instant,
+    last_report: Instant,
+}
+
+impl AvSync {
+    fn new(sample_rate: u32, max_buffer_frames_count: usize) -> Self {
+        Self {
+            sample_rate,
+            max_buffer_frames_count,
+            offset_us: None,
+            silence_batches: 0,
+            fade_in: false,
+            last_correction: Instant::now(),
+            last_report: Instant::now(),
+        }
+    }
+
+    fn frames_to_us(&self, frames_count: usize) -> f32 {
+        frames_count as f32 * 1_000_000. / self.sample_rate as f32
+    }
+
+    fn us_to_frames(&self, us: f32) -> usize {
+        (us * self.sample_rate as f32 / 1_000_000.) as usize
+    }
+
+    fn update_offset(&mut self, buffered_frames_count: usize, batch_frames_count: usize) {
+        let (video_latency_us, transport_latency_us) = match latency_stats::server_latencies_us() {
+            Some(latencies) => latencies,
+            None => return,
+        };
+        let audio_latency_us = transport_latency_us as f32
+            + self.frames_to_us(buffered_frames_count + batch_frames_count);
+        let offset_us = video_latency_us as f32 - audio_latency_us;
+        let average_us = self.offset_us.map_or(offset_us, |average_us| {
+            average_us + (offset_us - average_us) * AV_SYNC_SMOOTHING
+        });
+        self.offset_us = Some(average_us);
+
+        if self.last_report.elapsed() >= AV_SYNC_REPORT_INTERVAL {
+            self.last_report = Instant::now();
+            if let Some(sender) = &*AUDIO_SYNC_SENDER.lock() {
+                sender
+                    .send(AudioSyncPacket {
+                        offset_us: average_us as i32,
+                        buffered_us: self.frames_to_us(buffered_frames_count) as u32,
+                    })
+                    .ok();
+            }
+        }
+    }
+
+    fn next_batch(
+        &mut self,
+        sample_buffer: &mut VecDeque<f32>,
+        batch_frames_count: usize,
+    ) -> Vec<f32> {
+        if self.silence_batches > 0 {
+            self.silence_batches -= 1;
+            return vec![0.; batch_frames_count * 2];
+        }
+
+        let buffered_frames_count = sample_buffer.len() / 2;
+        self.update_offset(buffered_frames_count, batch_frames_count);
+
+        let mut batch = alvr_audio::get_next_frame_batch(sample_buffer, 2, batch_frames_count);
+        if std::mem::take(&mut self.fade_in) {
+            fade(&mut batch, true);
+        }
+
+        let offset_us = match self.offset_us {
+            Some(offset_us) => offset_us,
+            None => return batch,
+        };
+        if offset_us.abs() < AV_SYNC_TOLERANCE_US
+            || self.last_correction.elapsed() < AV_SYNC_CORRECTION_INTERVAL
+            || buffered_frames_count < 2 * batch_frames_count
+        {
+            return batch;
+        }
+
+        let buffered_frames_count = sample_buffer.len() / 2;
+        if offset_us > 0. {
+            // one batch of margin, packets don't arrive in batch sized chunks.
+            let room_frames_count = self
+                .max_buffer_frames_count
+                .saturating_sub(buffered_frames_count + batch_frames_count);
+            self.silence_batches =
+                self.us_to_frames(offset_us).min(room_frames_count) / batch_frames_count;
+            if self.silence_batches == 0 {
+                return batch;
+            }
+            info!(
+                "Audio {:.1}ms ahead of video, delaying it by {:.1}ms",
+                offset_us / 1000.,
+                self.frames_to_us(self.silence_batches * batch_frames_count) / 1000.
+            );
+            fade(&mut batch, false);
+            self.fade_in = true;
+        } else {
+            // the cross-fade below consumes one more batch than it plays.
+            let trimmed_frames_count = self
+                .us_to_frames(-offset_us)
+                .saturating_sub(batch_frames_count)
+                .min(buffered_frames_count.saturating_sub(batch_frames_count));
+            if trimmed_frames_count == 0 {
+                return batch;
+            }
+            info!(
+                "Audio {:.1}ms behind video, trimming {:.1}ms",
+                -offset_us / 1000.,
+                self.frames_to_us(trimmed_frames_count + batch_frames_count) / 1000.
+            );
+            sample_buffer.drain(0..trimmed_frames_count * 2);
+            // cross-fade into the samples following the trimmed ones.
+            let mut next_batch =
+                alvr_audio::get_next_frame_batch(sample_buffer, 2, batch_frames_count);
+            fade(&mut batch, false);
+            fade(&mut next_batch, true);
+            for (sample, next) in batch.iter_mut().zip(next_batch) {
+                *sample += next;
+            }
+        }
+
+        // the average is restarted to only measure the effect of the correction.
+        self.offset_us = None;
+        self.last_correction = Instant::now();
+        batch
+    }
+}
+
 struct PlayerCallback {
     sample_buffer: Arc<Mutex<VecDeque<f32>>>,
     batch_frames_count: usize,
+    av_sync: Option<AvSync>,
 }
 
 impl AudioOutputCallback for PlayerCallback {
@@ -92,11 +262,13 @@ impl AudioOutputCallback for PlayerCallback {
         _: &mut dyn AudioOutputStreamSafe,
         out_frames: &mut [(f32, f32)],
     ) -> DataCallbackResult {
-        let samples = alvr_audio::get_next_frame_batch(
-            &mut *self.sample_buffer.lock(),
-            2,
-            self.batch_frames_count,
-        );
+        let mut sample_buffer = self.sample_buffer.lock();
+        let samples = match &mut self.av_sync {
+            Some(av_sync) => av_sync.next_batch(&mut sample_buffer, self.batch_frames_count),
+            None => {
+                alvr_audio::get_next_frame_batch(&mut sample_buffer, 2, self.batch_frames_count)
+            }
+        };
 
         for f in 0..out_frames.len() {
             out_frames[f] = (samples[f * 2], samples[f * 2 + 1]);
@@ -115,6 +287,12 @@ pub async fn play_audio_loop(
         sample_rate as usize * config.average_buffering_ms as usize / 1000;
 
     let sample_buffer = Arc::new(Mutex::new(VecDeque::new()));
+    let av_sync = (!APP_CONFIG.no_av_sync).then(|| {
+        AvSync::new(
+            sample_rate,
+            2 * average_buffer_frames_count + batch_frames_count,
+        )
+    });
 
     // store the stream in a thread (because !Send) and extract the playback handle
     let (_shutdown_notifier, shutdown_receiver) = smpsc::channel::<()>();
@@ -134,6 +312,7 @@ pub async fn play_audio_loop(
                 .set_callback(PlayerCallback {
                     sample_buffer,
                     batch_frames_count,
+                    av_sync,
                 })
                 .open_stream())?;
 
@@ -0,0 +1,53 @@
+// Readings of auxiliary sensors, like heart rate wearables or presence sensors, forwarded to the
+// server for fitness and biometrics apps. Readings come from the platform clients or the
+// companion app and are only sent with --aux-sensors, they can be personal data.
+use crate::APP_CONFIG;
+use alvr_common::prelude::*;
+use alvr_sockets::AuxSensorPacket;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+
+// Per sensor, readings in between are dropped.
+const MIN_SEND_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_SENSOR_NAME_LEN: usize = 64;
+const MAX_SENSOR_VALUES: usize = 16;
+
+lazy_static! {
+    pub(crate) static ref AUX_SENSOR_SENDER: Mutex<Option<mpsc::UnboundedSender<AuxSensorPacket>>> =
+        Mutex::new(None);
+    static ref LAST_SENT: Mutex<HashMap<String, Instant>> = Mutex::new(HashMap::new());
+}
+
+// Values are in the sensor's own units, e.g. beats per minute for "heart_rate". Readings taken
+// while not streaming are dropped.
+pub fn push_aux_sensor_reading(sensor: &str, values: &[f32]) {
+    if !APP_CONFIG.aux_sensors {
+        return;
+    }
+    if sensor.is_empty() || sensor.len() > MAX_SENSOR_NAME_LEN || values.len() > MAX_SENSOR_VALUES {
+        warn!("Invalid aux sensor reading from {sensor:?}");
+        return;
+    }
+    {
+        let mut last_sent = LAST_SENT.lock();
+        if let Some(time) = last_sent.get(sensor) {
+            if time.elapsed() < MIN_SEND_INTERVAL {
+                return;
+            }
+        }
+        last_sent.insert(sensor.to_owned(), Instant::now());
+    }
+    if let Some(sender) = &*AUX_SENSOR_SENDER.lock() {
+        sender
+            .send(AuxSensorPacket {
+                sensor: sensor.to_owned(),
+                values: values.to_vec(),
+            })
+            .ok();
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{
+    hand_gestures::{self, HandGesture},
+    Options,
+};
+use alvr_common::prelude::*;
+use alvr_sockets::LegacyController;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+// Looked up in the data directory when no profile is given on the command line.
+const DEFAULT_BINDING_PROFILE_FILENAME: &str = "binding_profile.json";
+
+// Controller inputs, values match the ALVR_INPUT enum of the server's packet_types.h.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControllerInput {
+    SystemClick = 0,
+    ApplicationMenuClick = 1,
+    GripClick = 2,
+    GripValue = 3,
+    GripTouch = 4,
+    AClick = 9,
+    ATouch = 10,
+    BClick = 11,
+    BTouch = 12,
+    XClick = 13,
+    XTouch = 14,
+    YClick = 15,
+    YTouch = 16,
+    JoystickClick = 27,
+    JoystickTouch = 30,
+    BackClick = 31,
+    TriggerClick = 34,
+    TriggerValue = 35,
+    TriggerTouch = 36,
+    TrackpadClick = 39,
+    TrackpadTouch = 40,
+    ThumbRestTouch = 41,
+}
+
+impl ControllerInput {
+    fn is_analog(self) -> bool {
+        matches!(
+            self,
+            ControllerInput::GripValue | ControllerInput::TriggerValue
+        )
+    }
+
+    pub(crate) fn button_flag(self) -> u64 {
+        1_u64 << (self as u64)
+    }
+}
+
+// Maps a physical input to the input it drives on the server, `null` unbinds it.
+type InputMap = HashMap<ControllerInput, Option<ControllerInput>>;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct BindingProfile {
+    pub no_suggested_bindings: bool,
+    pub both: InputMap,
+    pub left: InputMap,
+    pub right: InputMap,
+    // Inputs synthesized for each hand tracking gesture, see hand_gestures.
+    pub gestures: HashMap<HandGesture, Vec<ControllerInput>>,
+}
+
+impl BindingProfile {
+    pub fn load(path: &Path) -> StrResult<Self> {
+        let contents = trace_err!(fs::read_to_string(path))?;
+        trace_err!(serde_json::from_str(&contents))
+    }
+}
+
+#[derive(Default)]
+struct ControllerRemap {
+    // (physical button flag, remapped button flag)
+    buttons: Vec<(u64, Option<u64>)>,
+    grip_value: Option<Option<ControllerInput>>,
+    trigger_value: Option<Option<ControllerInput>>,
+}
+
+impl ControllerRemap {
+    fn new(both: &InputMap, hand: &InputMap) -> Self {
+        let mut remap = ControllerRemap::default();
+        // hand specific entries take precedence.
+        let mut merged = both.clone();
+        merged.extend(hand.iter());
+        for (&source, &target) in &merged {
+            if source.is_analog() {
+                let target = match target {
+                    Some(target) if !target.is_analog() => {
+                        warn!("Cannot bind analog input {source:?} to {target:?}, ignored");
+                        continue;
+                    }
+                    _ => target,
+                };
+                match source {
+                    ControllerInput::GripValue => remap.grip_value = Some(target),
+                    _ => remap.trigger_value = Some(target),
+                }
+            } else if let Some(target) = target.filter(|t| t.is_analog()) {
+                warn!("Cannot bind button {source:?} to analog input {target:?}, ignored");
+            } else {
+                remap.buttons.push((
+                    source.button_flag(),
+                    target.map(ControllerInput::button_flag),
+                ));
+            }
+        }
+        remap
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buttons.is_empty() && self.grip_value.is_none() && self.trigger_value.is_none()
+    }
+
+    fn apply(&self, controller: &mut LegacyController) {
+        if self.is_empty() {
+            return;
+        }
+        let physical_buttons = controller.buttons;
+        for &(source, _) in &self.buttons {
+            controller.buttons &= !source;
+        }
+        for &(source, target) in &self.buttons {
+            if let Some(target) = target.filter(|_| physical_buttons & source != 0) {
+                controller.buttons |= target;
+            }
+        }
+
+        let physical_values = [
+            (
+                ControllerInput::GripValue,
+                controller.grip_value,
+                self.grip_value,
+            ),
+            (
+                ControllerInput::TriggerValue,
+                controller.trigger_value,
+                self.trigger_value,
+            ),
+        ];
+        controller.grip_value = 0_f32;
+        controller.trigger_value = 0_f32;
+        for (source, value, remap) in physical_values {
+            let output = match remap.unwrap_or(Some(source)) {
+                Some(ControllerInput::GripValue) => &mut controller.grip_value,
+                Some(ControllerInput::TriggerValue) => &mut controller.trigger_value,
+                _ => continue,
+            };
+            *output = output.max(value);
+        }
+    }
+}
+
+lazy_static! {
+    static ref CONTROLLER_REMAPS: RwLock<[ControllerRemap; 2]> =
+        RwLock::new([ControllerRemap::default(), ControllerRemap::default()]);
+}
+
+pub fn set_binding_profile(profile: &BindingProfile) {
+    *CONTROLLER_REMAPS.write() = [
+        ControllerRemap::new(&profile.both, &profile.left),
+        ControllerRemap::new(&profile.both, &profile.right),
+    ];
+    hand_gestures::set_gesture_map(&profile.gestures);
+}
+
+pub(crate) fn remap_controllers(controllers: &mut [LegacyController; 2]) {
+    let remaps = CONTROLLER_REMAPS.read();
+    for (controller, remap) in controllers.iter_mut().zip(remaps.iter()) {
+        if !controller.is_hand {
+            remap.apply(controller);
+        }
+    }
+}
+
+impl Options {
+    // Relative profile paths are resolved against `data_dir` when given.
+    pub fn load_binding_profile(&self, data_dir: Option<&Path>) -> BindingProfile {
+        let resolve = |path: &Path| -> PathBuf {
+            match data_dir {
+                Some(dir) if path.is_relative() => dir.join(path),
+                _ => path.to_owned(),
+            }
+        };
+        let path = match &self.binding_profile {
+            Some(path) => resolve(path),
+            None => {
+                let default_path = resolve(Path::new(DEFAULT_BINDING_PROFILE_FILENAME));
+                if !default_path.exists() {
+                    return BindingProfile::default();
+                }
+                default_path
+            }
+        };
+        match BindingProfile::load(&path) {
+            Ok(profile) => {
+                info!("Loaded binding profile {}", path.display());
+                profile
+            }
+            Err(e) => {
+                warn!("Failed to load binding profile {}: {e}", path.display());
+                BindingProfile::default()
+            }
+        }
+    }
+}
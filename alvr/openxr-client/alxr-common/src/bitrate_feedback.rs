@@ -0,0 +1,121 @@
+use alvr_sockets::{BitrateRecommendationPacket, VideoFrameHeaderPacket, VideoPacketStats};
+use std::time::Duration;
+use tokio::time::Instant;
+
+// The base delay is the minimum over the current and the previous window, following clock drift
+// and route changes without forgetting it while a queue is building up.
+const BASE_DELAY_WINDOW: Duration = Duration::from_secs(5);
+const OVERUSE_QUEUE_DELAY_US: f32 = 15_000.;
+const UNDERUSE_QUEUE_DELAY_US: f32 = 5_000.;
+const OVERUSE_PACKET_LOSS: f32 = 0.02;
+// Per packet weight of the queue delay average.
+const QUEUE_DELAY_SMOOTHING: f32 = 0.05;
+const DECREASE_FACTOR: f32 = 0.85;
+const INCREASE_FACTOR: f32 = 1.05;
+// The limit is lifted once the server doesn't get close to it anymore.
+const LIFT_LIMIT_FACTOR: f32 = 1.5;
+const MIN_BITRATE_MBPS: u32 = 5;
+
+// Estimates the link capacity from the queuing delay of the video packets (their one way delay
+// above the lowest one seen recently) and the packet loss. The server caps its adaptive bitrate
+// to the recommendation.
+pub(crate) struct BitrateFeedback {
+    epoch: Instant,
+    base_delay_us: [Option<i64>; 2],
+    window_start: Instant,
+    queue_delay_us: f32,
+    bytes: usize,
+    interval_start: Instant,
+    last_packet_stats: VideoPacketStats,
+    bitrate_mbps: Option<u32>,
+}
+
+impl BitrateFeedback {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            epoch: now,
+            base_delay_us: [None; 2],
+            window_start: now,
+            queue_delay_us: 0.,
+            bytes: 0,
+            interval_start: now,
+            last_packet_stats: VideoPacketStats::default(),
+            bitrate_mbps: None,
+        }
+    }
+
+    pub fn report_packet(
+        &mut self,
+        header: &VideoFrameHeaderPacket,
+        payload_size: usize,
+        received: Instant,
+    ) {
+        self.bytes += payload_size;
+
+        // offset by the clock difference, which cancels out against the base delay.
+        let delay_us =
+            received.duration_since(self.epoch).as_micros() as i64 - header.sent_time as i64;
+        if self.window_start.elapsed() > BASE_DELAY_WINDOW {
+            self.base_delay_us = [self.base_delay_us[1], None];
+            self.window_start = Instant::now();
+        }
+        let window_min = self.base_delay_us[1].get_or_insert(delay_us);
+        *window_min = (*window_min).min(delay_us);
+        let base_delay_us = self.base_delay_us.iter().flatten().min().copied();
+
+        let queue_delay_us = (delay_us - base_delay_us.unwrap_or(delay_us)) as f32;
+        self.queue_delay_us += (queue_delay_us - self.queue_delay_us) * QUEUE_DELAY_SMOOTHING;
+    }
+
+    // Returns a packet when the recommendation changed. `packet_stats` are the totals of the
+    // reorder buffer.
+    pub fn take_recommendation(
+        &mut self,
+        packet_stats: VideoPacketStats,
+    ) -> Option<BitrateRecommendationPacket> {
+        let elapsed_s = self.interval_start.elapsed().as_secs_f32();
+        let received_mbps = std::mem::take(&mut self.bytes) as f32 * 8. / 1_000_000. / elapsed_s;
+        self.interval_start = Instant::now();
+
+        let received = packet_stats.received - self.last_packet_stats.received;
+        let lost = packet_stats
+            .lost
+            .saturating_sub(self.last_packet_stats.lost);
+        self.last_packet_stats = packet_stats;
+        let packet_loss = lost as f32 / (received + lost).max(1) as f32;
+
+        let bitrate_mbps = if self.queue_delay_us > OVERUSE_QUEUE_DELAY_US
+            || packet_loss > OVERUSE_PACKET_LOSS
+        {
+            if received == 0 {
+                // the stream is paused, nothing to measure.
+                return None;
+            }
+            let limit_mbps = (received_mbps * DECREASE_FACTOR) as u32;
+            // a recommendation that is already lower stays, the queue takes time to drain.
+            Some(
+                self.bitrate_mbps
+                    .map_or(limit_mbps, |bitrate_mbps| bitrate_mbps.min(limit_mbps))
+                    .max(MIN_BITRATE_MBPS),
+            )
+        } else if self.queue_delay_us < UNDERUSE_QUEUE_DELAY_US {
+            self.bitrate_mbps.and_then(|bitrate_mbps| {
+                let bitrate_mbps = (bitrate_mbps as f32 * INCREASE_FACTOR).ceil();
+                (bitrate_mbps < received_mbps * LIFT_LIMIT_FACTOR).then_some(bitrate_mbps as u32)
+            })
+        } else {
+            self.bitrate_mbps
+        };
+
+        if bitrate_mbps == self.bitrate_mbps {
+            return None;
+        }
+        self.bitrate_mbps = bitrate_mbps;
+        Some(BitrateRecommendationPacket {
+            bitrate_mbps,
+            queue_delay_us: self.queue_delay_us as u32,
+            packet_loss,
+        })
+    }
+}
@@ -0,0 +1,127 @@
+// Bundles what is needed to look into an issue in a single zip next to the config file: recent
+// logs, the effective options, headset info, the per frame timings and the crash reports.
+use crate::{config, connection_state, APP_CONFIG};
+use alvr_common::{prelude::*, ALVR_VERSION};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use zip::{write::FileOptions, ZipWriter};
+
+const BUG_REPORTS_DIRNAME: &str = "bug_reports";
+const CRASHES_DIRNAME: &str = "crashes";
+// Files the client keeps next to the config file. The config and servers.toml are left out, they
+// can hold secrets, options.txt has the effective options with them redacted.
+const DATA_FILENAMES: &[&str] = &["last_stream.json", "binding_profile.json"];
+const LOG_LINES: usize = 5000;
+// Repeated requests, like a held gesture, don't fill the storage.
+const MIN_BUG_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+// The perf log rotates at 64MB, only its tail is useful.
+const MAX_PERF_LOG_BYTES: usize = 4 * 1024 * 1024;
+
+lazy_static! {
+    static ref LAST_BUG_REPORT: Mutex<Option<Instant>> = Mutex::new(None);
+    static ref HEADSET_INFO: Mutex<String> = Mutex::new(String::new());
+}
+
+pub(crate) fn set_headset_info(info: String) {
+    *HEADSET_INFO.lock() = info;
+}
+
+#[cfg(target_os = "android")]
+pub(crate) fn read_process_logs(max_lines: usize) -> StrResult<Vec<String>> {
+    // apps are allowed to read their own process logs.
+    let output = trace_err!(std::process::Command::new("logcat")
+        .args(["-d", "-t", &max_lines.to_string()])
+        .arg(format!("--pid={}", std::process::id()))
+        .output())?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_owned)
+        .collect())
+}
+
+#[cfg(not(target_os = "android"))]
+pub(crate) fn read_process_logs(_max_lines: usize) -> StrResult<Vec<String>> {
+    fmt_e!("Log retrieval is only supported on android, desktop clients log to stdout")
+}
+
+fn system_info() -> String {
+    format!(
+        "ALXR {}\nOS: {} {}\nConnection state: {:?}\nHeadset: {}\n",
+        *ALVR_VERSION,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        connection_state::connection_state(),
+        HEADSET_INFO.lock()
+    )
+}
+
+fn file_tail(path: &Path, max_bytes: usize) -> StrResult<Vec<u8>> {
+    let contents = trace_err!(fs::read(path))?;
+    Ok(contents[contents.len().saturating_sub(max_bytes)..].to_vec())
+}
+
+fn write_bug_report(path: &Path, data_dir: &Path) -> StrResult {
+    let mut zip = ZipWriter::new(trace_err!(fs::File::create(path))?);
+    let mut add_file = |name: &str, contents: &[u8]| -> StrResult {
+        trace_err!(zip.start_file(name, FileOptions::default()))?;
+        trace_err!(zip.write_all(contents))
+    };
+
+    add_file("system.txt", system_info().as_bytes())?;
+    add_file("options.txt", format!("{:#?}\n", *APP_CONFIG).as_bytes())?;
+    let logs = read_process_logs(LOG_LINES).unwrap_or_else(|e| vec![e]);
+    add_file("logs.txt", logs.join("\n").as_bytes())?;
+    if let Some(perf_log) = &APP_CONFIG.perf_log {
+        match file_tail(&config::resolve_path(perf_log), MAX_PERF_LOG_BYTES) {
+            Ok(tail) => add_file("perf_log.csv", &tail)?,
+            Err(e) => debug!("Perf log not added to the bug report: {e}"),
+        }
+    }
+    for file_name in DATA_FILENAMES {
+        if let Ok(contents) = fs::read(data_dir.join(file_name)) {
+            add_file(file_name, &contents)?;
+        }
+    }
+    // crash reports are kept after they are uploaded.
+    if let Ok(entries) = fs::read_dir(data_dir.join(CRASHES_DIRNAME)) {
+        for entry in entries.flatten() {
+            if let Ok(contents) = fs::read(entry.path()) {
+                let name = format!("{CRASHES_DIRNAME}/{}", entry.file_name().to_string_lossy());
+                add_file(&name, &contents)?;
+            }
+        }
+    }
+
+    trace_err!(zip.finish()).map(|_| ())
+}
+
+// Returns the path of the new bundle. Fails if the last one is less than a minute old.
+pub fn create_bug_report() -> StrResult<PathBuf> {
+    let mut last_bug_report = LAST_BUG_REPORT.lock();
+    if let Some(last) = *last_bug_report {
+        if last.elapsed() < MIN_BUG_REPORT_INTERVAL {
+            return fmt_e!(
+                "A bug report was created {}s ago, try again later",
+                last.elapsed().as_secs()
+            );
+        }
+    }
+
+    let config_path = trace_none!(config::config_file_path())?;
+    let data_dir = trace_none!(config_path.parent())?;
+    let reports_dir = data_dir.join(BUG_REPORTS_DIRNAME);
+    trace_err!(fs::create_dir_all(&reports_dir))?;
+    let timestamp = trace_err!(SystemTime::now().duration_since(UNIX_EPOCH))?.as_secs();
+    let path = reports_dir.join(format!("alxr-bug-report-{timestamp}.zip"));
+    write_bug_report(&path, data_dir)?;
+
+    *last_bug_report = Some(Instant::now());
+    info!("Bug report written to {}", path.display());
+    Ok(path)
+}
@@ -0,0 +1,292 @@
+// Companion protocol, lets a PC-side helper configure the client and fetch logs (over adb port
+// forwarding, or Wi-Fi with --companion-listen-all) without shelling into the device.
+//
+// Requests and responses are newline delimited json objects, `PushApk` is followed by `size`
+// bytes of raw apk data. The first request must be a `Hello` with the --companion-token, the
+// connection is closed otherwise.
+use crate::{
+    bug_report,
+    config::{self, SecretString},
+    monocular::{self, Eye},
+    one_handed::{self, Hand},
+    session_limit, APP_CONFIG,
+};
+use alvr_common::{prelude::*, ALVR_VERSION};
+use alvr_sockets::SpectatorCameraPacket;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+};
+use tokio::{
+    fs::File,
+    io::{self, AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+const MAX_APK_SIZE_BYTES: u64 = 1024 * 1024 * 1024;
+const MAX_REQUEST_BYTES: u64 = 64 * 1024;
+const DEFAULT_LOG_LINES: usize = 1000;
+// Staged next to the config file, replaced by every push.
+const APK_FILENAME: &str = "alxr-update.apk";
+
+lazy_static! {
+    static ref SERVER_ADDRESS: Mutex<Option<Ipv4Addr>> = Mutex::new(None);
+}
+
+// Server address set by the companion, client handshakes are sent to it instead of broadcasted.
+pub(crate) fn server_address() -> Option<Ipv4Addr> {
+    *SERVER_ADDRESS.lock()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum CompanionRequest {
+    Hello {
+        token: SecretString,
+    },
+    SetServer {
+        address: Option<Ipv4Addr>,
+    },
+    // Answers the in-headset server picker, a missing name searches for any server.
+    SelectServer {
+        name: Option<String>,
+    },
+    // A missing camera removes it.
+    SetSpectatorCamera {
+        camera: Option<SpectatorCameraPacket>,
+    },
+    // A missing hand turns the one-handed mode off, see one_handed.rs.
+    SetOneHanded {
+        hand: Option<Hand>,
+    },
+    // Readings of wearables paired with the companion app, see aux_sensors.rs.
+    AuxSensorReading {
+        sensor: String,
+        values: Vec<f32>,
+    },
+    PushApk {
+        size: u64,
+    },
+    GetLogs {
+        max_lines: Option<usize>,
+    },
+    // Bundled into a zip in the data dir, see bug_report.rs.
+    CreateBugReport,
+    GetSessionTime,
+    // Restarts the --session-limit-min clock, see session_limit.rs.
+    ResetSessionTime,
+    // A missing eye streams both eyes again, see monocular.rs.
+    SetMonocularEye {
+        eye: Option<Eye>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+enum CompanionResponse {
+    Info {
+        version: String,
+        device_name: String,
+        hostname: String,
+        server_address: Option<Ipv4Addr>,
+    },
+    Ok,
+    ApkStored {
+        path: PathBuf,
+    },
+    Logs {
+        lines: Vec<String>,
+    },
+    BugReportStored {
+        path: PathBuf,
+    },
+    // The limits are None when disabled.
+    SessionTime {
+        elapsed_s: u64,
+        warning_s: Option<u64>,
+        limit_s: Option<u64>,
+        remaining_s: Option<u64>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Clone)]
+pub struct CompanionInfo {
+    pub device_name: String,
+    pub hostname: String,
+}
+
+// The apk is only staged, installing it is left to the helper (`pm install`) or the user, the
+// client cannot replace itself while running.
+async fn receive_apk<R: AsyncRead + Unpin>(reader: &mut R, size: u64) -> StrResult<PathBuf> {
+    if size > MAX_APK_SIZE_BYTES {
+        return fmt_e!("Apk size {size} is bigger than the {MAX_APK_SIZE_BYTES} bytes limit");
+    }
+    let config_path = trace_none!(config::config_file_path())?;
+    let path = trace_none!(config_path.parent())?.join(APK_FILENAME);
+    let mut file = trace_err!(File::create(&path).await)?;
+    let copied = trace_err!(io::copy(&mut io::AsyncReadExt::take(reader, size), &mut file).await)?;
+    if copied != size {
+        return fmt_e!("Apk transfer ended early, received {copied} of {size} bytes");
+    }
+    trace_err!(file.flush().await)?;
+    Ok(path)
+}
+
+async fn read_logs(max_lines: usize) -> StrResult<Vec<String>> {
+    let read = tokio::task::spawn_blocking(move || bug_report::read_process_logs(max_lines));
+    trace_err!(read.await)?
+}
+
+async fn handle_request(
+    request: CompanionRequest,
+    reader: &mut BufReader<io::ReadHalf<TcpStream>>,
+    info: &CompanionInfo,
+) -> CompanionResponse {
+    let result = match request {
+        CompanionRequest::Hello { .. } => Ok(CompanionResponse::Info {
+            version: ALVR_VERSION.to_string(),
+            device_name: info.device_name.clone(),
+            hostname: info.hostname.clone(),
+            server_address: server_address(),
+        }),
+        CompanionRequest::SetServer { address } => {
+            info!("Companion set server address to {address:?}");
+            *SERVER_ADDRESS.lock() = address;
+            Ok(CompanionResponse::Ok)
+        }
+        CompanionRequest::SelectServer { name } => {
+            crate::select_server_profile(name.as_deref()).map(|_| CompanionResponse::Ok)
+        }
+        CompanionRequest::SetSpectatorCamera { camera } => {
+            crate::set_spectator_camera(camera);
+            Ok(CompanionResponse::Ok)
+        }
+        CompanionRequest::SetOneHanded { hand } => {
+            one_handed::set_one_handed(hand);
+            Ok(CompanionResponse::Ok)
+        }
+        CompanionRequest::AuxSensorReading { sensor, values } => {
+            crate::push_aux_sensor_reading(&sensor, &values);
+            Ok(CompanionResponse::Ok)
+        }
+        CompanionRequest::PushApk { size } => receive_apk(reader, size)
+            .await
+            .map(|path| CompanionResponse::ApkStored { path }),
+        CompanionRequest::GetLogs { max_lines } => {
+            read_logs(max_lines.unwrap_or(DEFAULT_LOG_LINES))
+                .await
+                .map(|lines| CompanionResponse::Logs { lines })
+        }
+        CompanionRequest::CreateBugReport => {
+            trace_err!(tokio::task::spawn_blocking(bug_report::create_bug_report).await)
+                .and_then(|result| result)
+                .map(|path| CompanionResponse::BugReportStored { path })
+        }
+        CompanionRequest::GetSessionTime => {
+            let elapsed = session_limit::session_elapsed();
+            let limit = session_limit::session_limit();
+            Ok(CompanionResponse::SessionTime {
+                elapsed_s: elapsed.as_secs(),
+                warning_s: session_limit::session_warning().map(|warning| warning.as_secs()),
+                limit_s: limit.map(|limit| limit.as_secs()),
+                remaining_s: limit.map(|limit| limit.saturating_sub(elapsed).as_secs()),
+            })
+        }
+        CompanionRequest::ResetSessionTime => {
+            session_limit::reset_session_limit();
+            Ok(CompanionResponse::Ok)
+        }
+        CompanionRequest::SetMonocularEye { eye } => {
+            monocular::set_monocular_eye(eye);
+            Ok(CompanionResponse::Ok)
+        }
+    };
+    result.unwrap_or_else(|message| CompanionResponse::Error { message })
+}
+
+// Compared in constant time, the companion may be reachable from the network.
+fn token_matches(token: &str, expected: &str) -> bool {
+    token.len() == expected.len()
+        && token
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn serve_companion(stream: TcpStream, info: CompanionInfo, token: String) -> StrResult {
+    let (reader, mut writer) = io::split(stream);
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    let mut authenticated = false;
+    loop {
+        line.clear();
+        let read = trace_err!(
+            io::AsyncReadExt::take(&mut reader, MAX_REQUEST_BYTES)
+                .read_line(&mut line)
+                .await
+        )?;
+        if read == 0 {
+            return Ok(());
+        }
+        if read as u64 == MAX_REQUEST_BYTES && !line.ends_with('\n') {
+            return fmt_e!("Companion request longer than {MAX_REQUEST_BYTES} bytes");
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request = serde_json::from_str::<CompanionRequest>(&line);
+        if !authenticated {
+            match &request {
+                Ok(CompanionRequest::Hello { token: hello_token })
+                    if token_matches(&hello_token.0, &token) =>
+                {
+                    authenticated = true
+                }
+                _ => return fmt_e!("The first companion request must be a Hello with the token"),
+            }
+        }
+        let response = match request {
+            Ok(request) => handle_request(request, &mut reader, &info).await,
+            Err(e) => CompanionResponse::Error {
+                message: format!("Invalid request: {e}"),
+            },
+        };
+        let mut response = trace_err!(serde_json::to_vec(&response))?;
+        response.push(b'\n');
+        trace_err!(writer.write_all(&response).await)?;
+    }
+}
+
+pub async fn companion_server_loop(port: u16, info: CompanionInfo) -> StrResult {
+    let token = match &APP_CONFIG.companion_token {
+        Some(token) if !token.0.is_empty() => token.0.clone(),
+        _ => return fmt_e!("The companion server needs a --companion-token"),
+    };
+    let address = if APP_CONFIG.companion_listen_all {
+        Ipv4Addr::UNSPECIFIED
+    } else {
+        Ipv4Addr::LOCALHOST
+    };
+    let listener = trace_err!(TcpListener::bind(SocketAddr::from((address, port))).await)?;
+    info!("Companion server listening on {address}:{port}");
+    println!("Companion server listening on {address}:{port}");
+
+    loop {
+        let (stream, address) = trace_err!(listener.accept().await)?;
+        info!("Companion connected from {address}");
+        let info = info.clone();
+        let token = token.clone();
+        tokio::spawn(async move {
+            if let Err(e) = serve_companion(stream, info, token).await {
+                warn!("Companion connection error: {e}");
+            }
+        });
+    }
+}
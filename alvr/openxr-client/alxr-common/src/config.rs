@@ -0,0 +1,350 @@
+#![cfg_attr(target_vendor = "uwp", allow(dead_code))]
+use crate::{fleet_config, Options};
+use alvr_common::prelude::*;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize, Serializer};
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt::{self, Debug},
+    fs, iter,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use structopt::StructOpt;
+
+pub const CONFIG_FILENAME: &str = "alxr.toml";
+
+lazy_static! {
+    static ref FLEET_QUIRKS: Mutex<Option<toml::Value>> = Mutex::new(None);
+}
+
+// The [quirks] table of the fleet config, loaded with the options.
+pub(crate) fn fleet_quirks() -> Option<toml::Value> {
+    FLEET_QUIRKS.lock().clone()
+}
+
+// Config keys are the option field names, the same names used by the android system properties.
+pub(crate) struct ConfigFile {
+    entries: BTreeMap<String, toml::Value>,
+}
+
+impl ConfigFile {
+    pub fn load(path: &Path) -> StrResult<Self> {
+        let contents = trace_err!(fs::read_to_string(path))?;
+        let entries = trace_err!(toml::from_str(&contents))?;
+        Ok(Self { entries })
+    }
+
+    // A missing file is not an error, the config file is optional.
+    pub fn load_or_default(path: Option<&Path>) -> Self {
+        let empty = Self {
+            entries: BTreeMap::new(),
+        };
+        let path = match path {
+            Some(path) if path.exists() => path,
+            _ => return empty,
+        };
+        // runs before logging is set up.
+        match Self::load(path) {
+            Ok(mut config) => {
+                println!("ALXR config file: {}", path.display());
+                config.apply_fleet_config(path);
+                config
+            }
+            Err(e) => {
+                eprintln!("Failed to load config file {}: {e}", path.display());
+                empty
+            }
+        }
+    }
+
+    // Fleet entries override the local ones, see fleet_config.rs.
+    fn apply_fleet_config(&mut self, path: &Path) {
+        let url = self.entries.remove(fleet_config::URL_KEY);
+        let public_key = self.entries.remove(fleet_config::PUBLIC_KEY_KEY);
+        let (url, public_key) = match (url, public_key) {
+            (Some(toml::Value::String(url)), Some(toml::Value::String(public_key))) => {
+                (url, public_key)
+            }
+            (None, _) => return,
+            _ => {
+                eprintln!("The fleet config needs a URL and a public key, it is not used");
+                return;
+            }
+        };
+        let config_dir = path.parent().unwrap_or(Path::new("."));
+        if let Some(mut entries) = fleet_config::load(&url, &public_key, config_dir) {
+            *FLEET_QUIRKS.lock() = entries.remove(fleet_config::QUIRKS_KEY);
+            self.entries.extend(entries);
+        }
+    }
+
+    fn value_string(value: &toml::Value) -> Option<String> {
+        match value {
+            toml::Value::String(value) => Some(value.clone()),
+            toml::Value::Integer(value) => Some(value.to_string()),
+            toml::Value::Float(value) => Some(value.to_string()),
+            toml::Value::Boolean(value) => Some(value.to_string()),
+            toml::Value::Array(values) => Some(
+                values
+                    .iter()
+                    .filter_map(Self::value_string)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            _ => None,
+        }
+    }
+}
+
+// Options like keys, hidden from the logs and crash reports that print the options.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SecretString(pub String);
+
+impl Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("\"***\"")
+    }
+}
+
+impl FromStr for SecretString {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(Self(value.into()))
+    }
+}
+
+// Android wifi lock levels, WifiManager's WIFI_MODE_FULL_LOW_LATENCY and WIFI_MODE_FULL_HIGH_PERF.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum WifiLockMode {
+    LowLatency,
+    HighPerf,
+    Off,
+}
+
+impl FromStr for WifiLockMode {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim().to_lowercase().as_str() {
+            "lowlatency" => Ok(WifiLockMode::LowLatency),
+            "highperf" => Ok(WifiLockMode::HighPerf),
+            "off" => Ok(WifiLockMode::Off),
+            _ => Err(format!(
+                "unknown wifi lock mode {input}, expected LowLatency, HighPerf or Off"
+            )),
+        }
+    }
+}
+
+pub fn config_file_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(CONFIG_FILENAME))
+}
+
+impl Options {
+    pub fn write_default_config() -> StrResult<PathBuf> {
+        let path = trace_none!(config_file_path())?;
+        let defaults = Options::from_iter(iter::once("alxr-client"));
+        let contents = trace_err!(toml::to_string(&defaults))?;
+        if let Some(dir) = path.parent() {
+            trace_err!(fs::create_dir_all(dir))?;
+        }
+        trace_err!(fs::write(&path, contents))?;
+        Ok(path)
+    }
+}
+
+// Environment variable naming a client session. Each session has its own config directory, and
+// with it its own trusted servers, server profiles and last stream, so that clients for several
+// headsets can run side by side on one machine. Every session is a separate client process: the
+// engine and the client state are process global.
+#[cfg(not(any(target_os = "android", target_vendor = "uwp")))]
+pub const SESSION_ENV_VAR: &str = "ALXR_SESSION";
+
+#[cfg(not(any(target_os = "android", target_vendor = "uwp")))]
+fn config_dir() -> Option<PathBuf> {
+    let dir = dirs::config_dir()?.join("alxr");
+    match std::env::var(SESSION_ENV_VAR) {
+        Ok(session) if !session.is_empty() && !session.contains(['/', '\\']) => {
+            Some(dir.join("sessions").join(session))
+        }
+        _ => Some(dir),
+    }
+}
+
+// The app's external files dir, which is writable over adb without root.
+#[cfg(target_os = "android")]
+fn config_dir() -> Option<PathBuf> {
+    let cmdline = fs::read_to_string("/proc/self/cmdline").ok()?;
+    let package_name = cmdline.split(['\0', ':']).next()?;
+    if package_name.is_empty() {
+        return None;
+    }
+    Some(
+        Path::new("/sdcard/Android/data")
+            .join(package_name)
+            .join("files"),
+    )
+}
+
+#[cfg(target_vendor = "uwp")]
+fn config_dir() -> Option<PathBuf> {
+    None
+}
+
+// There is no useful working directory on android, relative paths go next to the config file.
+#[cfg(target_os = "android")]
+pub(crate) fn resolve_path(path: &Path) -> PathBuf {
+    match config_file_path().as_deref().and_then(Path::parent) {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_owned(),
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+pub(crate) fn resolve_path(path: &Path) -> PathBuf {
+    path.to_owned()
+}
+
+pub(crate) fn serialize_debug<T: Debug, S: Serializer>(
+    value: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    value
+        .as_ref()
+        .map(|value| format!("{value:?}"))
+        .serialize(serializer)
+}
+
+pub(crate) fn serialize_debug_vec<T: Debug, S: Serializer>(
+    values: &Option<Vec<T>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    values
+        .as_ref()
+        .map(|values| {
+            values
+                .iter()
+                .map(|value| format!("{value:?}"))
+                .collect::<Vec<_>>()
+        })
+        .serialize(serializer)
+}
+
+#[cfg(not(any(target_os = "android", target_vendor = "uwp")))]
+mod desktop {
+    use super::{config_file_path, ConfigFile};
+    use crate::Options;
+    use std::ffi::OsString;
+    use structopt::StructOpt;
+
+    // Options whose command line flag differs from the field name.
+    const RENAMED_FLAGS: &[(&str, &str)] = &[
+        ("graphics_api", "graphics"),
+        ("decoder_type", "decoder"),
+        ("headless_session", "headless"),
+        ("no_visibility_masks", "disable-visibility-masks"),
+    ];
+
+    impl ConfigFile {
+        fn to_args(&self) -> Vec<OsString> {
+            let mut args = vec![];
+            for (key, value) in &self.entries {
+                let flag = RENAMED_FLAGS
+                    .iter()
+                    .find(|(field, _)| field == key)
+                    .map_or_else(|| key.replace('_', "-"), |(_, flag)| flag.to_string());
+                let flag = OsString::from(format!("--{flag}"));
+                match value {
+                    toml::Value::Boolean(true) => args.push(flag),
+                    toml::Value::Boolean(false) => (),
+                    toml::Value::Array(values) => {
+                        args.push(flag);
+                        args.extend(values.iter().filter_map(Self::value_string).map(Into::into));
+                    }
+                    value => {
+                        if let Some(value) = Self::value_string(value) {
+                            args.push(flag);
+                            args.push(value.into());
+                        }
+                    }
+                }
+            }
+            args
+        }
+    }
+
+    impl Options {
+        // Precedence is command line > config file > default, config entries are inserted
+        // before the command line arguments and every option overrides itself.
+        pub fn from_args_and_config_file() -> Self {
+            let mut args = std::env::args_os();
+            let config_args = ConfigFile::load_or_default(config_file_path().as_deref()).to_args();
+            Options::from_iter(args.next().into_iter().chain(config_args).chain(args))
+        }
+    }
+}
+
+#[cfg(target_os = "android")]
+pub use android::set_launch_options;
+#[cfg(target_os = "android")]
+pub(crate) use android::PropertyLookup;
+
+#[cfg(target_os = "android")]
+mod android {
+    use super::{config_file_path, ConfigFile};
+    use android_system_properties::AndroidSystemProperties;
+    use lazy_static::lazy_static;
+    use parking_lot::Mutex;
+    use std::collections::HashMap;
+
+    const PROPERTY_PREFIX: &str = "debug.alxr.";
+
+    lazy_static! {
+        static ref LAUNCH_OPTIONS: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+    }
+
+    // Options given for a single launch (intent extras), keyed like the config file. Only
+    // effective when called before APP_CONFIG is first used.
+    pub fn set_launch_options(options: HashMap<String, String>) {
+        *LAUNCH_OPTIONS.lock() = options;
+    }
+
+    impl ConfigFile {
+        fn get(&self, key: &str) -> Option<String> {
+            self.entries.get(key).and_then(Self::value_string)
+        }
+    }
+
+    // Precedence is launch options > system properties > config file > built in defaults.
+    pub(crate) struct PropertyLookup {
+        launch_options: HashMap<String, String>,
+        sys_properties: AndroidSystemProperties,
+        config_file: ConfigFile,
+    }
+
+    impl PropertyLookup {
+        pub fn new() -> Self {
+            Self {
+                launch_options: LAUNCH_OPTIONS.lock().clone(),
+                sys_properties: AndroidSystemProperties::new(),
+                config_file: ConfigFile::load_or_default(config_file_path().as_deref()),
+            }
+        }
+
+        pub fn get(&self, property_name: &str) -> Option<String> {
+            let key = property_name
+                .strip_prefix(PROPERTY_PREFIX)
+                .unwrap_or(property_name);
+            self.launch_options
+                .get(key)
+                .cloned()
+                .or_else(|| self.sys_properties.get(property_name))
+                .or_else(|| self.config_file.get(key))
+        }
+    }
+}
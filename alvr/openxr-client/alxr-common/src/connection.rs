@@ -1,16 +1,43 @@
 use crate::{
+    aux_sensors::AUX_SENSOR_SENDER,
+    bitrate_feedback::BitrateFeedback,
+    companion, config,
+    connection_state::{connection_state, set_connection_state, ConnectionState},
     connection_utils::{self, ConnectionError},
-    ALXRTrackingSpace_StageRefSpace, TimeSync, VideoFrame, APP_CONFIG, BATTERY_SENDER,
-    INPUT_SENDER, TIME_SYNC_SENDER, VIDEO_ERROR_REPORT_SENDER, VIEWS_CONFIG_SENDER,
+    crash_reports, feature_gating,
+    fec_stats::FecStatsCollector,
+    foveation::FoveationState,
+    haptics::HapticsRouter,
+    imu_stream::{self, IMU_BATCH_INTERVAL, IMU_SENDER},
+    jitter_buffer::JitterBuffer,
+    latency_stats::{self, LatencyStatsCollector},
+    log_forwarding::{LogRateLimiter, LOG_SENDER},
+    mkv_writer::VideoCodec,
+    monocular, network_interface, pairing, perf_log, pose_offsets, pose_prediction,
+    preview::PreviewSource,
+    push_content,
+    server_profiles::{self, ServerSelection},
+    session_analytics,
+    spectator_camera::{self, SPECTATOR_CAMERA_SENDER},
+    startup_timing::{self, mark_startup_stage},
+    stream_resume, tracking_replay,
+    tracking_state::{self, TRACKING_STATE_SENDER},
+    video_recorder::{StreamFormat, VideoRecorder},
+    video_reorder::VideoReorderBuffer,
+    ALXRTrackingSpace_LocalRefSpace, ALXRTrackingSpace_StageRefSpace, TimeSync, VideoFrame,
+    APP_CONFIG, AUDIO_SYNC_SENDER, BATTERY_SENDER, INPUT_SENDER, THERMAL_SENDER, TIME_SYNC_SENDER,
+    VIDEO_ERROR_REPORT_SENDER, VIEWS_CONFIG_SENDER, WIFI_STATS_SENDER,
 };
 use alvr_common::{prelude::*, ALVR_NAME, ALVR_VERSION};
-use alvr_session::SessionDesc;
+use alvr_session::{CodecType, SessionDesc, SocketBufferSize};
 #[cfg(target_os = "android")]
 use alvr_sockets::AUDIO;
 use alvr_sockets::{
-    spawn_cancelable, ClientConfigPacket, ClientControlPacket, ClientHandshakePacket, Haptics,
-    HeadsetInfoPacket, PeerType, PrivateIdentity, ProtoControlSocket, ServerControlPacket,
-    ServerHandshakePacket, StreamSocketBuilder, VideoFrameHeaderPacket, HAPTICS, INPUT, VIDEO,
+    spawn_cancelable, ClientConfigPacket, ClientControlPacket, ClientHandshakePacket, Dscp,
+    Haptics, HeadsetInfoPacket, PairingCodePacket, PeerType, PrivateIdentity, ProtoControlSocket,
+    ReferenceSpace, ServerControlPacket, ServerHandshakePacket, StreamResumePacket,
+    StreamSocketBuilder, StreamViewMode, VideoFrameHeaderPacket, VideoStallPacket, HAPTICS, IMU,
+    INPUT, PAIRING_TIMEOUT, VIDEO,
 };
 
 use futures::future::BoxFuture;
@@ -23,6 +50,7 @@ use std::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
+    thread,
     time::Duration,
 };
 use tokio::{
@@ -32,6 +60,8 @@ use tokio::{
 
 #[cfg(target_os = "android")]
 use crate::audio;
+#[cfg(not(any(target_os = "android", target_vendor = "uwp")))]
+use crate::microphone;
 
 const INITIAL_MESSAGE: &str = "Searching for server...\n(open ALVR on your PC)";
 const NETWORK_UNREACHABLE_MESSAGE: &str = "Cannot connect to the internet";
@@ -51,6 +81,18 @@ const RETRY_CONNECT_MIN_INTERVAL: Duration = Duration::from_secs(1);
 const PLAYSPACE_SYNC_INTERVAL: Duration = Duration::from_millis(500);
 const NETWORK_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(1);
 const CLEANUP_PAUSE: Duration = Duration::from_millis(500);
+const VIDEO_REORDER_TIMEOUT: Duration = Duration::from_millis(10);
+// Smaller changes of the jitter buffer delay are not logged.
+const JITTER_DELAY_LOG_STEP: Duration = Duration::from_millis(5);
+const VIDEO_PACKET_STATS_INTERVAL: Duration = Duration::from_secs(1);
+const BITRATE_FEEDBACK_INTERVAL: Duration = Duration::from_millis(250);
+// Another IDR is requested after this long past the deadline even if no frame was seen lost.
+const IDR_REQUEST_FALLBACK: Duration = Duration::from_secs(3);
+// Longer gaps between video packets while streaming are reported as a stall.
+const VIDEO_STALL_TIMEOUT: Duration = Duration::from_millis(500);
+// Left for the render, tracking and network threads when the decoder thread count is automatic.
+const DECODER_RESERVED_CORES: u32 = 2;
+const MAX_AUTO_DECODER_THREADS: u32 = 6;
 
 // close stream on Drop (manual disconnection or execution canceling)
 struct StreamCloseGuard {
@@ -60,6 +102,7 @@ struct StreamCloseGuard {
 impl Drop for StreamCloseGuard {
     fn drop(&mut self) {
         self.is_connected.store(false, Ordering::Relaxed);
+        set_connection_state(ConnectionState::Reconnecting);
     }
 }
 
@@ -93,6 +136,20 @@ fn set_loading_message(
 }
 */
 
+// ffmpeg's automatic count uses every core, which on 8 core mobile SoCs starves the render and
+// tracking threads.
+fn decoder_thread_count() -> u32 {
+    if APP_CONFIG.decoder_thread_count > 0 {
+        return APP_CONFIG.decoder_thread_count;
+    }
+    let cores = thread::available_parallelism().map_or(1, |cores| cores.get() as u32);
+    let threads = cores
+        .saturating_sub(DECODER_RESERVED_CORES)
+        .clamp(1, MAX_AUTO_DECODER_THREADS);
+    info!("Software decoder uses {threads} threads on {cores} cores");
+    threads
+}
+
 async fn connection_pipeline(
     headset_info: &HeadsetInfoPacket,
     device_name: String,
@@ -114,8 +171,21 @@ async fn connection_pipeline(
 
     println!("host_name: {0}", handshake_packet.version);
 
+    let server_profile = match server_profiles::wait_for_selection().await {
+        ServerSelection::Profile(profile) => Some(profile),
+        ServerSelection::Discovery => None,
+    };
+
+    // keep reporting a reconnection while searching for the server that was lost.
+    if connection_state() != ConnectionState::Reconnecting {
+        set_connection_state(ConnectionState::Searching);
+    }
+
     let (mut proto_socket, server_ip) = tokio::select! {
-        res = connection_utils::announce_client_loop(handshake_packet) => {
+        res = connection_utils::announce_client_loop(
+            handshake_packet,
+            server_profile.as_ref(),
+        ) => {
             match res? {
                 ConnectionError::ServerMessage(message) => {
                     info!("Server response: {:?}", message);
@@ -127,11 +197,17 @@ async fn connection_pipeline(
                     };
                     //set_loading_message(&*java_vm, &*activity_ref, hostname, message_str)?;
                     println!("{0}", message_str);
+                    set_connection_state(ConnectionState::Error {
+                        cause: message_str.into(),
+                    });
                     return Ok(());
                 }
                 ConnectionError::NetworkUnreachable => {
                     info!("Network unreachable");
                     println!("Network unreachable");
+                    set_connection_state(ConnectionState::Error {
+                        cause: NETWORK_UNREACHABLE_MESSAGE.into(),
+                    });
                     //set_loading_message(
                     //     &*java_vm,
                     //     &*activity_ref,
@@ -155,15 +231,98 @@ async fn connection_pipeline(
         },
         pair = async {
             loop {
-                if let Ok(pair) = ProtoControlSocket::connect_to(PeerType::Server).await {
-                    break pair;
+                if let Ok((socket, server_ip)) =
+                    ProtoControlSocket::connect_to(PeerType::Server).await
+                {
+                    // the handshake was only sent to the server of the profile, unless the
+                    // companion redirected it.
+                    match &server_profile {
+                        Some(profile)
+                            if server_ip != profile.address
+                                && companion::server_address().is_none() =>
+                        {
+                            warn!("Ignoring connection from {server_ip}, expected {}", profile.name);
+                        }
+                        _ => break (socket, server_ip),
+                    }
                 }
 
                 time::sleep(CONTROL_CONNECT_RETRY_PAUSE).await;
             }
         } => pair
     };
+    set_connection_state(ConnectionState::Handshaking);
+
+    let encryption_key = server_profile
+        .as_ref()
+        .and_then(|profile| profile.encryption_key.as_ref())
+        .or(APP_CONFIG.encryption_key.as_ref())
+        .map(|key| key.0.as_str());
+    let pairing = APP_CONFIG.pairing || server_profile.as_ref().map_or(false, |p| p.pairing);
+    let trusted_servers = pairing.then(pairing::trusted_servers);
+    let pairing_requested = match proto_socket
+        .accept_encryption(
+            encryption_key,
+            APP_CONFIG.require_encryption,
+            trusted_servers.as_deref(),
+        )
+        .await
+    {
+        Ok(pairing_requested) => pairing_requested,
+        Err(e) => {
+            warn!("Encryption handshake failed: {e}");
+            println!("Encryption handshake failed: {e}");
+            set_connection_state(ConnectionState::Error { cause: e });
+            return Ok(());
+        }
+    };
+    let cipher = proto_socket.cipher();
+    if cipher.is_some() {
+        info!("Connection encrypted");
+    }
 
+    if pairing_requested {
+        // always set when pairing is requested.
+        let fingerprint = trace_none!(cipher.as_ref().and_then(|c| c.peer_fingerprint()))?;
+        let code = pairing::new_pairing_code();
+        info!("Unknown server {fingerprint}, pairing code: {code}");
+        println!("Enter the pairing code {code} in the server's dashboard");
+        set_connection_state(ConnectionState::Pairing { code: code.clone() });
+
+        let echoed_code =
+            match time::timeout(PAIRING_TIMEOUT, proto_socket.recv::<PairingCodePacket>()).await {
+                Ok(Ok(packet)) => packet.code,
+                Ok(Err(e)) => {
+                    info!("Server disconnected while pairing. Cause: {e}");
+                    set_connection_state(ConnectionState::Reconnecting);
+                    return Ok(());
+                }
+                Err(_) => {
+                    set_connection_state(ConnectionState::Error {
+                        cause: "Pairing timed out".into(),
+                    });
+                    return Ok(());
+                }
+            };
+        if echoed_code.trim() != code {
+            warn!("Wrong pairing code from server {fingerprint}");
+            set_connection_state(ConnectionState::Error {
+                cause: "Wrong pairing code".into(),
+            });
+            return Ok(());
+        }
+        if let Err(e) = pairing::trust_server(fingerprint) {
+            warn!("Paired server not saved, it will be paired again next time: {e}");
+        }
+        info!("Server {fingerprint} paired");
+        set_connection_state(ConnectionState::Handshaking);
+    }
+
+    let resume_offer = stream_resume::resume_offer(server_ip);
+    let headset_info = HeadsetInfoPacket {
+        resume: resume_offer.clone(),
+        ..headset_info.clone()
+    };
     trace_err!(proto_socket.send(&(headset_info, server_ip)).await)?;
     let config_packet = trace_err!(proto_socket.recv::<ClientConfigPacket>().await)?;
 
@@ -185,6 +344,7 @@ async fn connection_pipeline(
             info!("Server restarting");
             //set_loading_message(&*java_vm, &*activity_ref, hostname, SERVER_RESTART_MESSAGE)?;
             println!("{0}", SERVER_RESTART_MESSAGE);
+            set_connection_state(ConnectionState::Reconnecting);
             return Ok(());
         }
         Err(e) => {
@@ -198,27 +358,86 @@ async fn connection_pipeline(
             // )?;
             println!("{0}", SERVER_DISCONNECTED_MESSAGE);
             unsafe { crate::alxr_on_server_disconnect() };
+            set_connection_state(ConnectionState::Reconnecting);
             return Ok(());
         }
         _ => {
             info!("Unexpected packet");
             println!("Unexpected packet");
             //set_loading_message(&*java_vm, &*activity_ref, hostname, "Unexpected packet")?;
+            set_connection_state(ConnectionState::Error {
+                cause: "Unexpected packet".into(),
+            });
             return Ok(());
         }
     }
 
-    let settings = {
+    let mut settings = {
         let mut session_desc = SessionDesc::default();
         session_desc.merge_from_json(&trace_err!(json::from_str(&config_packet.session_desc))?)?;
         session_desc.to_settings()
     };
 
+    let stream = StreamResumePacket {
+        eye_resolution_width: config_packet.eye_resolution_width,
+        eye_resolution_height: config_packet.eye_resolution_height,
+        fps: config_packet.fps,
+        codec: settings.video.codec,
+    };
+    if resume_offer.as_ref() == Some(&stream) {
+        info!("Resuming with the previous stream parameters");
+    }
+    stream_resume::save(server_ip, stream);
+
+    // must reach the server before StreamReady, it connects the stream socket right after.
+    if let Some(transport) = APP_CONFIG.transport {
+        info!("Requesting the {transport:?} stream transport");
+        settings.connection.stream_protocol = transport.into();
+        control_sender
+            .lock()
+            .await
+            .send(&ClientControlPacket::StreamTransport(transport))
+            .await
+            .ok();
+    }
+    if let Some(dscp) = APP_CONFIG.stream_dscp {
+        info!("Requesting the {dscp:?} stream packet class");
+        control_sender
+            .lock()
+            .await
+            .send(&ClientControlPacket::StreamDscp(dscp))
+            .await
+            .ok();
+    }
+
+    // explicit sizes are kept by the interface tuning.
+    if let Some(size) = APP_CONFIG.socket_send_buffer_bytes {
+        settings.connection.client_send_buffer_bytes = SocketBufferSize::Custom(size);
+    }
+    if let Some(size) = APP_CONFIG.socket_recv_buffer_bytes {
+        settings.connection.client_recv_buffer_bytes = SocketBufferSize::Custom(size);
+    }
+
+    if let Some(interface) = network_interface::detect_network_interface() {
+        info!(
+            "Streaming over {} ({:?}, MTU {:?})",
+            interface.name, interface.kind, interface.mtu
+        );
+        network_interface::tune_socket_buffers(interface.kind, &mut settings.connection);
+        control_sender
+            .lock()
+            .await
+            .send(&ClientControlPacket::NetworkInterface(interface))
+            .await
+            .ok();
+    }
+
     let stream_socket_builder = StreamSocketBuilder::listen_for_server(
         settings.connection.stream_port,
         settings.connection.stream_protocol,
         settings.connection.client_send_buffer_bytes,
         settings.connection.client_recv_buffer_bytes,
+        APP_CONFIG.stream_dscp.unwrap_or(Dscp::Ef),
     )
     .await?;
 
@@ -241,10 +460,50 @@ async fn connection_pipeline(
     }
     println!("StreamReady");
 
+    // must reach the server before the first views config and input.
+    if APP_CONFIG.mono_stream {
+        info!("Requesting mono stream view mode");
+        control_sender
+            .lock()
+            .await
+            .send(&ClientControlPacket::StreamViewMode(StreamViewMode::Mono))
+            .await
+            .ok();
+    }
+
+    if let Some(fec_percentage) = APP_CONFIG.fec_percentage {
+        info!("Requesting {fec_percentage}% FEC");
+        control_sender
+            .lock()
+            .await
+            .send(&ClientControlPacket::FecPercentage(fec_percentage))
+            .await
+            .ok();
+    }
+
+    if let Some(velocity_frame) = APP_CONFIG.velocity_frame {
+        control_sender
+            .lock()
+            .await
+            .send(&ClientControlPacket::VelocityFrame(velocity_frame))
+            .await
+            .ok();
+    }
+
+    let reference_space = feature_gating::reference_space();
+    info!("Using the {reference_space:?} reference space");
+    control_sender
+        .lock()
+        .await
+        .send(&ClientControlPacket::ReferenceSpace(reference_space))
+        .await
+        .ok();
+
     let stream_socket = tokio::select! {
         res = stream_socket_builder.accept_from_server(
             server_ip,
             settings.connection.stream_port,
+            cipher,
         ) => res?,
         _ = time::sleep(Duration::from_secs(5)) => {
             println!("Timeout while setting up streams");
@@ -254,11 +513,27 @@ async fn connection_pipeline(
     let stream_socket = Arc::new(stream_socket);
     info!("Connected to server");
     println!("Connected to server");
+    mark_startup_stage("server_connected");
+
+    for (path, report) in crash_reports::take_pending_crash_reports() {
+        info!("Uploading crash report {}", report.file_name);
+        let res = control_sender
+            .lock()
+            .await
+            .send(&ClientControlPacket::CrashReport(report))
+            .await;
+        match res {
+            Ok(()) => crash_reports::mark_uploaded(&path),
+            Err(e) => warn!("Failed to upload crash report: {e}"),
+        }
+    }
 
     let is_connected = Arc::new(AtomicBool::new(true));
     let _stream_guard = StreamCloseGuard {
         is_connected: Arc::clone(&is_connected),
     };
+    session_analytics::set_session_server(server_ip);
+    set_connection_state(ConnectionState::Streaming);
 
     // trace_err!(trace_err!(java_vm.attach_current_thread())?.call_method(
     //     &*activity_ref,
@@ -272,6 +547,12 @@ async fn connection_pipeline(
     *VIEWS_CONFIG_SENDER.lock() = Some(views_config_sender);
     let (battery_sender, mut battery_receiver) = tmpsc::unbounded_channel();
     *BATTERY_SENDER.lock() = Some(battery_sender);
+    let (thermal_sender, mut thermal_receiver) = tmpsc::unbounded_channel();
+    *THERMAL_SENDER.lock() = Some(thermal_sender);
+    let (wifi_stats_sender, mut wifi_stats_receiver) = tmpsc::unbounded_channel();
+    *WIFI_STATS_SENDER.lock() = Some(wifi_stats_sender);
+    let (audio_sync_sender, mut audio_sync_receiver) = tmpsc::unbounded_channel();
+    *AUDIO_SYNC_SENDER.lock() = Some(audio_sync_sender);
 
     // assert!((config_packet.eye_resolution_width % headset_info.recommended_eye_width) == 0);
     // assert!((config_packet.eye_resolution_height % headset_info.recommended_eye_height) == 0);
@@ -286,63 +567,35 @@ async fn connection_pipeline(
         Switch::Disabled => false,
     };
 
+    let foveation = FoveationState::new(&settings.video.foveated_rendering);
+    let mut render_config = crate::ALXRRenderConfig {
+        eyeWidth: config_packet.eye_resolution_width,
+        eyeHeight: config_packet.eye_resolution_height,
+        refreshRate: config_packet.fps,
+        foveationCenterSizeX: 0_f32,
+        foveationCenterSizeY: 0_f32,
+        foveationCenterShiftX: 0_f32,
+        foveationCenterShiftY: 0_f32,
+        foveationEdgeRatioX: 0_f32,
+        foveationEdgeRatioY: 0_f32,
+        enableFoveation: false,
+    };
+    foveation.apply_to(&mut render_config);
+
     unsafe {
         crate::alxr_set_stream_config(crate::ALXRStreamConfig {
-            trackingSpaceType: ALXRTrackingSpace_StageRefSpace,
-            renderConfig: crate::ALXRRenderConfig {
-                eyeWidth: config_packet.eye_resolution_width,
-                eyeHeight: config_packet.eye_resolution_height,
-                refreshRate: config_packet.fps,
-
-                foveationCenterSizeX: if let Switch::Enabled(foveation_vars) =
-                    &settings.video.foveated_rendering
-                {
-                    foveation_vars.center_size_x
-                } else {
-                    3_f32 / 5_f32
-                },
-                foveationCenterSizeY: if let Switch::Enabled(foveation_vars) =
-                    &settings.video.foveated_rendering
-                {
-                    foveation_vars.center_size_y
-                } else {
-                    2_f32 / 5_f32
-                },
-                foveationCenterShiftX: if let Switch::Enabled(foveation_vars) =
-                    &settings.video.foveated_rendering
-                {
-                    foveation_vars.center_shift_x
-                } else {
-                    2_f32 / 5_f32
-                },
-                foveationCenterShiftY: if let Switch::Enabled(foveation_vars) =
-                    &settings.video.foveated_rendering
-                {
-                    foveation_vars.center_shift_y
-                } else {
-                    1_f32 / 10_f32
-                },
-                foveationEdgeRatioX: if let Switch::Enabled(foveation_vars) =
-                    &settings.video.foveated_rendering
-                {
-                    foveation_vars.edge_ratio_x
-                } else {
-                    2_f32
-                },
-                foveationEdgeRatioY: if let Switch::Enabled(foveation_vars) =
-                    &settings.video.foveated_rendering
-                {
-                    foveation_vars.edge_ratio_y
-                } else {
-                    2_f32
-                },
-                enableFoveation: matches!(settings.video.foveated_rendering, Switch::Enabled(_)),
+            trackingSpaceType: match reference_space {
+                ReferenceSpace::Stage => ALXRTrackingSpace_StageRefSpace,
+                ReferenceSpace::Local => ALXRTrackingSpace_LocalRefSpace,
             },
+            renderConfig: render_config,
             decoderConfig: crate::ALXRDecoderConfig {
                 codecType: settings.video.codec as crate::ALXRCodecType,
-                cpuThreadCount: APP_CONFIG.decoder_thread_count,
+                cpuThreadCount: decoder_thread_count(),
                 enableFEC: settings.connection.enable_fec,
-                realtimePriority: settings.video.client_request_realtime_decoder,
+                realtimePriority: APP_CONFIG
+                    .decoder_realtime_priority
+                    .unwrap_or(settings.video.client_request_realtime_decoder),
             },
             clientPrediction: tracking_clientside_prediction,
         });
@@ -368,7 +621,15 @@ async fn connection_pipeline(
         let mut socket_sender = stream_socket.request_stream(INPUT).await?;
         async move {
             let (data_sender, mut data_receiver) = tmpsc::unbounded_channel();
-            *INPUT_SENDER.lock() = Some(data_sender);
+            if let Some(path) = &APP_CONFIG.replay_tracking {
+                // the headset's own tracking is dropped while replaying.
+                tokio::spawn(tracking_replay::replay_loop(
+                    config::resolve_path(path),
+                    data_sender,
+                ));
+            } else {
+                *INPUT_SENDER.lock() = Some(data_sender);
+            }
             while let Some(input) = data_receiver.recv().await {
                 socket_sender
                     .send_buffer(socket_sender.new_buffer(&input, 0)?)
@@ -425,7 +686,9 @@ async fn connection_pipeline(
                 control_sender
                     .lock()
                     .await
-                    .send(&ClientControlPacket::ViewsConfig(config))
+                    .send(&ClientControlPacket::ViewsConfig(
+                        monocular::apply_monocular_views(config),
+                    ))
                     .await
                     .ok();
             }
@@ -450,66 +713,430 @@ async fn connection_pipeline(
         }
     };
 
-    let video_receive_loop = {
+    let thermal_send_loop = {
+        let control_sender = Arc::clone(&control_sender);
+        async move {
+            while let Some(packet) = thermal_receiver.recv().await {
+                control_sender
+                    .lock()
+                    .await
+                    .send(&ClientControlPacket::ThermalStats(packet))
+                    .await
+                    .ok();
+            }
+
+            Ok(())
+        }
+    };
+
+    let wifi_stats_send_loop = {
+        let control_sender = Arc::clone(&control_sender);
+        async move {
+            while let Some(packet) = wifi_stats_receiver.recv().await {
+                control_sender
+                    .lock()
+                    .await
+                    .send(&ClientControlPacket::WifiStats(packet))
+                    .await
+                    .ok();
+            }
+
+            Ok(())
+        }
+    };
+
+    let spectator_camera_send_loop = {
+        let control_sender = Arc::clone(&control_sender);
+        let (spectator_camera_sender, mut spectator_camera_receiver) = tmpsc::unbounded_channel();
+        if let Some(camera) = spectator_camera::spectator_camera() {
+            spectator_camera_sender.send(Some(camera)).ok();
+        }
+        *SPECTATOR_CAMERA_SENDER.lock() = Some(spectator_camera_sender);
+        async move {
+            while let Some(camera) = spectator_camera_receiver.recv().await {
+                control_sender
+                    .lock()
+                    .await
+                    .send(&ClientControlPacket::SpectatorCamera(camera))
+                    .await
+                    .ok();
+            }
+
+            Ok(())
+        }
+    };
+
+    let aux_sensor_send_loop = {
+        let control_sender = Arc::clone(&control_sender);
+        let (aux_sensor_sender, mut aux_sensor_receiver) = tmpsc::unbounded_channel();
+        *AUX_SENSOR_SENDER.lock() = Some(aux_sensor_sender);
+        async move {
+            while let Some(packet) = aux_sensor_receiver.recv().await {
+                control_sender
+                    .lock()
+                    .await
+                    .send(&ClientControlPacket::AuxSensor(packet))
+                    .await
+                    .ok();
+            }
+
+            Ok(())
+        }
+    };
+
+    let audio_sync_send_loop = {
+        let control_sender = Arc::clone(&control_sender);
+        async move {
+            while let Some(packet) = audio_sync_receiver.recv().await {
+                control_sender
+                    .lock()
+                    .await
+                    .send(&ClientControlPacket::AudioSync(packet))
+                    .await
+                    .ok();
+            }
+
+            Ok(())
+        }
+    };
+
+    let log_send_loop = {
+        let control_sender = Arc::clone(&control_sender);
+        let (log_sender, mut log_receiver) = tmpsc::unbounded_channel();
+        *LOG_SENDER.lock() = Some(log_sender);
+        async move {
+            let mut rate_limiter = LogRateLimiter::new();
+            while let Some(entry) = log_receiver.recv().await {
+                for entry in rate_limiter.admit(entry) {
+                    control_sender
+                        .lock()
+                        .await
+                        .send(&ClientControlPacket::Log(entry))
+                        .await
+                        .ok();
+                }
+            }
+
+            Ok(())
+        }
+    };
+
+    let tracking_state_loop: BoxFuture<_> = if !APP_CONFIG.no_tracking_state {
+        let control_sender = Arc::clone(&control_sender);
+        let (tracking_state_sender, mut tracking_state_receiver) = tmpsc::unbounded_channel();
+        tracking_state::reset_tracking_state();
+        *TRACKING_STATE_SENDER.lock() = Some(tracking_state_sender);
+        Box::pin(async move {
+            while let Some(packet) = tracking_state_receiver.recv().await {
+                control_sender
+                    .lock()
+                    .await
+                    .send(&ClientControlPacket::TrackingState(packet))
+                    .await
+                    .ok();
+            }
+
+            Ok(())
+        })
+    } else {
+        Box::pin(future::pending())
+    };
+
+    let imu_send_loop: BoxFuture<_> = if imu_stream::imu_stream_hz() > 0 {
+        let mut socket_sender = stream_socket.request_stream(IMU).await?;
+        let (imu_sender, mut imu_receiver) = tmpsc::unbounded_channel();
+        *IMU_SENDER.lock() = Some(imu_sender);
+        Box::pin(async move {
+            while let Some(sample) = imu_receiver.recv().await {
+                let mut samples = vec![sample];
+                while let Ok(sample) = imu_receiver.try_recv() {
+                    samples.push(sample);
+                }
+                socket_sender
+                    .send_buffer(socket_sender.new_buffer(&samples, 0)?)
+                    .await
+                    .ok();
+                time::sleep(IMU_BATCH_INTERVAL).await;
+            }
+
+            Ok(())
+        })
+    } else {
+        Box::pin(future::pending())
+    };
+
+    let video_receive_loop: BoxFuture<_> = if APP_CONFIG.tracking_bridge {
+        // without a subscriber the video packets are dropped on arrival.
+        Box::pin(future::pending())
+    } else {
         let mut receiver = stream_socket
             .subscribe_to_stream::<VideoFrameHeaderPacket>(VIDEO)
             .await?;
-        async move {
+        let control_sender = Arc::clone(&control_sender);
+        let stream_format = StreamFormat {
+            codec: match settings.video.codec {
+                CodecType::H264 => VideoCodec::H264,
+                CodecType::HEVC => VideoCodec::Hevc,
+            },
+            // both eyes side by side.
+            width: config_packet.eye_resolution_width * 2,
+            height: config_packet.eye_resolution_height,
+            ten_bit: settings.video.use_10bit_encoder,
+            fec: settings.connection.enable_fec,
+        };
+        let mut video_recorder = VideoRecorder::start(stream_format);
+        let mut preview_source = PreviewSource::start(stream_format);
+        Box::pin(async move {
             let mut idr_request_deadline = None;
+            let mut waiting_for_idr = !crate::IDR_PARSED.load(Ordering::Relaxed);
+            let mut reorder_buffer =
+                VideoReorderBuffer::new(APP_CONFIG.video_reorder_window as usize);
+            let mut ready_packets = vec![];
+            let mut jitter_buffer = (APP_CONFIG.jitter_buffer_ms > 0).then(|| {
+                JitterBuffer::new(Duration::from_millis(APP_CONFIG.jitter_buffer_ms as u64))
+            });
+            let mut jitter_delay = Duration::ZERO;
+            let mut latency_stats = LatencyStatsCollector::default();
+            let mut fec_stats = FecStatsCollector::default();
+            let mut last_stats = reorder_buffer.stats();
+            let mut stats_deadline = Instant::now() + VIDEO_PACKET_STATS_INTERVAL;
+            let mut bitrate_feedback = (!APP_CONFIG.no_bitrate_feedback).then(BitrateFeedback::new);
+            let mut bitrate_feedback_deadline = Instant::now() + BITRATE_FEEDBACK_INTERVAL;
+            let mut stalled_since: Option<Instant> = None;
             loop {
-                let packet = receiver.recv().await.unwrap();
-
-                // Send again IDR packet every 2s in case it is missed
-                // (due to dropped burst of packets at the start of the stream or otherwise).
-                if !crate::IDR_PARSED.load(Ordering::Relaxed) {
-                    if let Some(deadline) = idr_request_deadline {
-                        if deadline < Instant::now() {
-                            println!("IDR_PARSED sending IDR request");
-                            crate::IDR_REQUEST_NOTIFIER.notify_waiters();
-                            idr_request_deadline = None;
+                let next_release = jitter_buffer
+                    .as_ref()
+                    .and_then(JitterBuffer::next_release)
+                    .filter(|_| !reorder_buffer.has_pending());
+                // held packets are released even when no other packet arrives.
+                if let Some(next_release) = next_release {
+                    if let Ok(packet) = time::timeout_at(next_release, receiver.recv()).await {
+                        let packet = packet?;
+                        let counter = packet.header.packet_counter;
+                        reorder_buffer.push(counter, (Instant::now(), packet), &mut ready_packets);
+                    }
+                // a gap that is not filled in time is given up on, to not stall the decoder.
+                } else if reorder_buffer.has_pending() {
+                    match time::timeout(VIDEO_REORDER_TIMEOUT, receiver.recv()).await {
+                        Ok(packet) => {
+                            let packet = packet?;
+                            let counter = packet.header.packet_counter;
+                            reorder_buffer.push(
+                                counter,
+                                (Instant::now(), packet),
+                                &mut ready_packets,
+                            );
                         }
-                    } else {
-                        idr_request_deadline = Some(Instant::now() + Duration::from_secs(2));
+                        Err(_) => reorder_buffer.skip_gap(&mut ready_packets),
+                    }
+                } else if waiting_for_idr {
+                    let packet = receiver.recv().await?;
+                    let counter = packet.header.packet_counter;
+                    reorder_buffer.push(counter, (Instant::now(), packet), &mut ready_packets);
+                } else {
+                    // only detected and reported, what is displayed meanwhile is up to the engine.
+                    match time::timeout(VIDEO_STALL_TIMEOUT, receiver.recv()).await {
+                        Ok(packet) => {
+                            let packet = packet?;
+                            let counter = packet.header.packet_counter;
+                            reorder_buffer.push(
+                                counter,
+                                (Instant::now(), packet),
+                                &mut ready_packets,
+                            );
+                        }
+                        Err(_) => {
+                            if stalled_since.is_none() {
+                                warn!("Video stream stalled");
+                                stalled_since = Some(Instant::now() - VIDEO_STALL_TIMEOUT);
+                                set_connection_state(ConnectionState::Degraded {
+                                    reason: "Video stream stalled".into(),
+                                });
+                            }
+                            continue;
+                        }
+                    }
+                }
+
+                if let Some(jitter_buffer) = &mut jitter_buffer {
+                    for (received, packet) in ready_packets.drain(..) {
+                        jitter_buffer.push(packet.header.sent_time, received, (received, packet));
+                    }
+                    jitter_buffer.pop_ready(Instant::now(), &mut ready_packets);
+                    let delay = jitter_buffer.delay();
+                    if delay.max(jitter_delay) - delay.min(jitter_delay) >= JITTER_DELAY_LOG_STEP {
+                        jitter_delay = delay;
+                        info!("Video jitter buffer delay set to {jitter_delay:?}");
+                    }
+                }
+
+                if let Some(stall_start) = stalled_since.take() {
+                    let duration = stall_start.elapsed();
+                    info!("Video stream resumed after {duration:?}");
+                    if !waiting_for_idr {
+                        set_connection_state(ConnectionState::Streaming);
+                    }
+                    control_sender
+                        .lock()
+                        .await
+                        .send(&ClientControlPacket::VideoStall(VideoStallPacket {
+                            duration_ms: duration.as_millis() as u32,
+                        }))
+                        .await
+                        .ok();
+                }
+
+                if let Some(feedback) = &mut bitrate_feedback {
+                    if bitrate_feedback_deadline < Instant::now() {
+                        if let Some(recommendation) =
+                            feedback.take_recommendation(reorder_buffer.stats())
+                        {
+                            control_sender
+                                .lock()
+                                .await
+                                .send(&ClientControlPacket::BitrateRecommendation(recommendation))
+                                .await
+                                .ok();
+                        }
+                        bitrate_feedback_deadline = Instant::now() + BITRATE_FEEDBACK_INTERVAL;
                     }
                 }
 
-                let header = VideoFrame {
-                    type_: 9, // ALVR_PACKET_TYPE_VIDEO_FRAME
-                    packetCounter: packet.header.packet_counter,
-                    trackingFrameIndex: packet.header.tracking_frame_index,
-                    videoFrameIndex: packet.header.video_frame_index,
-                    sentTime: packet.header.sent_time,
-                    frameByteSize: packet.header.frame_byte_size,
-                    fecIndex: packet.header.fec_index,
-                    fecPercentage: packet.header.fec_percentage,
-                };
-                unsafe {
-                    crate::alxr_on_video_packet(
-                        &header,
-                        packet.buffer.as_ptr(),
-                        packet.buffer.len() as _,
-                    );
+                if stats_deadline < Instant::now() {
+                    let stats = reorder_buffer.stats();
+                    if stats != last_stats {
+                        control_sender
+                            .lock()
+                            .await
+                            .send(&ClientControlPacket::VideoPacketStats(stats))
+                            .await
+                            .ok();
+                        last_stats = stats;
+                    }
+                    if let Some(report) = fec_stats.take_report(stats) {
+                        control_sender
+                            .lock()
+                            .await
+                            .send(&ClientControlPacket::FecStats(report))
+                            .await
+                            .ok();
+                    }
+                    if let Some(report) = latency_stats.take_report() {
+                        control_sender
+                            .lock()
+                            .await
+                            .send(&ClientControlPacket::LatencyBreakdown(report))
+                            .await
+                            .ok();
+                    }
+                    stats_deadline = Instant::now() + VIDEO_PACKET_STATS_INTERVAL;
+                }
+
+                for (received, packet) in ready_packets.drain(..) {
+                    // frames cannot be decoded until the next IDR is received after a loss.
+                    if waiting_for_idr == crate::IDR_PARSED.load(Ordering::Relaxed) {
+                        waiting_for_idr = !waiting_for_idr;
+                        set_connection_state(if waiting_for_idr {
+                            ConnectionState::Degraded {
+                                reason: "Waiting for video keyframe".into(),
+                            }
+                        } else {
+                            ConnectionState::Streaming
+                        });
+                        if !waiting_for_idr {
+                            mark_startup_stage("first_frame");
+                            if let Some(stages) = startup_timing::take_startup_report() {
+                                control_sender
+                                    .lock()
+                                    .await
+                                    .send(&ClientControlPacket::StartupTimings(stages))
+                                    .await
+                                    .ok();
+                            }
+                        }
+                    }
+
+                    // Send again IDR packet every 2s in case it is missed
+                    // (due to dropped burst of packets at the start of the stream or otherwise).
+                    // The requested IDR is still on its way unless a frame was lost since,
+                    // requesting another one would only add a keyframe worth of bitrate.
+                    if !crate::IDR_PARSED.load(Ordering::Relaxed) {
+                        if let Some(deadline) = idr_request_deadline {
+                            if deadline < Instant::now()
+                                && (fec_stats.take_unrecoverable_frame()
+                                    || deadline + IDR_REQUEST_FALLBACK < Instant::now())
+                            {
+                                println!("IDR_PARSED sending IDR request");
+                                crate::IDR_REQUEST_NOTIFIER.notify_waiters();
+                                idr_request_deadline = None;
+                            }
+                        } else {
+                            fec_stats.take_unrecoverable_frame();
+                            idr_request_deadline = Some(Instant::now() + Duration::from_secs(2));
+                        }
+                    }
+
+                    let header = VideoFrame {
+                        type_: 9, // ALVR_PACKET_TYPE_VIDEO_FRAME
+                        packetCounter: packet.header.packet_counter,
+                        trackingFrameIndex: packet.header.tracking_frame_index,
+                        videoFrameIndex: packet.header.video_frame_index,
+                        sentTime: packet.header.sent_time,
+                        frameByteSize: packet.header.frame_byte_size,
+                        fecIndex: packet.header.fec_index,
+                        fecPercentage: packet.header.fec_percentage,
+                    };
+                    fec_stats.report_packet(&packet.header, packet.buffer.len());
+                    if let Some(feedback) = &mut bitrate_feedback {
+                        feedback.report_packet(&packet.header, packet.buffer.len(), received);
+                    }
+                    if let Some(recorder) = &mut video_recorder {
+                        recorder.push_packet(&packet.header, &packet.buffer);
+                    }
+                    if let Some(preview) = &mut preview_source {
+                        preview.push_packet(&packet.header, &packet.buffer);
+                    }
+
+                    let submit_start = Instant::now();
+                    unsafe {
+                        crate::alxr_on_video_packet(
+                            &header,
+                            packet.buffer.as_ptr(),
+                            packet.buffer.len() as _,
+                        );
+                    }
+                    if let Some(frame) = latency_stats.report_packet(
+                        &packet.header,
+                        received,
+                        submit_start,
+                        Instant::now(),
+                    ) {
+                        perf_log::log_frame(&frame);
+                    }
                 }
             }
-        }
+        })
     };
 
     let haptics_receive_loop = {
         let mut receiver = stream_socket
             .subscribe_to_stream::<Haptics>(HAPTICS)
             .await?;
+        let router = HapticsRouter::new(&APP_CONFIG);
         async move {
             loop {
                 let packet = receiver.recv().await?.header;
 
-                unsafe {
-                    crate::alxr_on_haptics_feedback(
-                        packet.path,
-                        packet.duration.as_secs_f32(),
-                        packet.frequency,
-                        packet.amplitude,
-                    )
-                };
+                if let Some(event) = router.route(&packet) {
+                    unsafe {
+                        crate::alxr_on_haptics_feedback(
+                            event.path,
+                            event.duration_s,
+                            event.frequency,
+                            event.amplitude,
+                        )
+                    };
+                }
             }
         }
     };
@@ -537,42 +1164,56 @@ async fn connection_pipeline(
         }
     };
 
-    let game_audio_loop: BoxFuture<_> = if let Switch::Enabled(_desc) = settings.audio.game_audio {
-        #[cfg(target_os = "android")]
-        if config_packet.game_audio_sample_rate < 8000 {
-            // The server is using a sample rate that won't work and will likely crash us
-            // We can't report errors clearly yet, so skip running audio so people who
-            // update their copy of ALXR don't suddenly start getting crashes.
-            println!("ALVR server chose an invalid audio sample rate. Disabling audio playback.");
+    let game_audio_loop: BoxFuture<_> = match settings.audio.game_audio {
+        Switch::Enabled(_desc) if !APP_CONFIG.tracking_bridge => {
+            #[cfg(target_os = "android")]
+            if config_packet.game_audio_sample_rate < 8000 {
+                // The server is using a sample rate that won't work and will likely crash us
+                // We can't report errors clearly yet, so skip running audio so people who
+                // update their copy of ALXR don't suddenly start getting crashes.
+                println!(
+                    "ALVR server chose an invalid audio sample rate. Disabling audio playback."
+                );
+                Box::pin(future::pending())
+            } else {
+                let game_audio_receiver = stream_socket.subscribe_to_stream(AUDIO).await?;
+                Box::pin(audio::play_audio_loop(
+                    config_packet.game_audio_sample_rate,
+                    _desc.config,
+                    game_audio_receiver,
+                ))
+            }
+            #[cfg(not(target_os = "android"))]
             Box::pin(future::pending())
-        } else {
-            let game_audio_receiver = stream_socket.subscribe_to_stream(AUDIO).await?;
-            Box::pin(audio::play_audio_loop(
-                config_packet.game_audio_sample_rate,
-                _desc.config,
-                game_audio_receiver,
-            ))
         }
-        #[cfg(not(target_os = "android"))]
-        Box::pin(future::pending())
-    } else {
-        Box::pin(future::pending())
+        _ => Box::pin(future::pending()),
     };
 
-    let microphone_loop: BoxFuture<_> = if let Switch::Enabled(_config) = settings.audio.microphone
-    {
-        #[cfg(target_os = "android")]
-        {
-            let microphone_sender = stream_socket.request_stream(AUDIO).await?;
-            Box::pin(audio::record_audio_loop(
-                _config.sample_rate,
-                microphone_sender,
-            ))
+    let microphone_loop: BoxFuture<_> = match settings.audio.microphone {
+        Switch::Enabled(_config) if !APP_CONFIG.no_microphone && !APP_CONFIG.tracking_bridge => {
+            #[cfg(target_os = "android")]
+            {
+                let microphone_sender = stream_socket.request_stream(AUDIO).await?;
+                Box::pin(audio::record_audio_loop(
+                    APP_CONFIG.mic_sample_rate,
+                    _config.sample_rate,
+                    microphone_sender,
+                ))
+            }
+            #[cfg(not(any(target_os = "android", target_vendor = "uwp")))]
+            {
+                let microphone_sender = stream_socket.request_stream(AUDIO).await?;
+                Box::pin(microphone::record_microphone_loop(
+                    APP_CONFIG.mic_device.clone(),
+                    APP_CONFIG.mic_sample_rate,
+                    _config.sample_rate,
+                    microphone_sender,
+                ))
+            }
+            #[cfg(target_vendor = "uwp")]
+            Box::pin(future::pending())
         }
-        #[cfg(not(target_os = "android"))]
-        Box::pin(future::pending())
-    } else {
-        Box::pin(future::pending())
+        _ => Box::pin(future::pending()),
     };
 
     let keepalive_sender_loop = {
@@ -628,6 +1269,10 @@ async fn connection_pipeline(
                                 break Ok(());
                             }
                             Ok(ServerControlPacket::TimeSync(data)) => {
+                                latency_stats::set_server_latencies(
+                                    data.server_total_latency,
+                                    data.average_transport_latency,
+                                );
                                 let time_sync = TimeSync {
                                     type_: 7, // ALVR_PACKET_TYPE_TIME_SYNC
                                     mode: data.mode,
@@ -653,6 +1298,22 @@ async fn connection_pipeline(
                                     crate::alxr_on_time_sync(&time_sync);
                                 }
                             },
+                            Ok(ServerControlPacket::Recenter) => {
+                                info!("Recenter requested by the server");
+                                crate::recenter();
+                            }
+                            Ok(ServerControlPacket::PushContent(content)) => {
+                                push_content::handle_push_content(content);
+                            }
+                            Ok(ServerControlPacket::PoseOffset(packet)) => {
+                                pose_offsets::set_pose_offset(packet);
+                            }
+                            Ok(ServerControlPacket::WorldOffset(offset)) => {
+                                crate::set_world_offset(offset);
+                            }
+                            Ok(ServerControlPacket::PosePrediction(prediction_ms)) => {
+                                pose_prediction::set_server_prediction(prediction_ms);
+                            }
                             Ok(_) => (),
                             Err(e) => {
                                 info!("Server disconnected. Cause: {}", e);
@@ -699,8 +1360,16 @@ async fn connection_pipeline(
         res = spawn_cancelable(video_error_report_send_loop) => res,
         res = spawn_cancelable(views_config_send_loop) => res,
         res = spawn_cancelable(battery_send_loop) => res,
+        res = spawn_cancelable(thermal_send_loop) => res,
+        res = spawn_cancelable(wifi_stats_send_loop) => res,
+        res = spawn_cancelable(spectator_camera_send_loop) => res,
+        res = spawn_cancelable(aux_sensor_send_loop) => res,
+        res = spawn_cancelable(audio_sync_send_loop) => res,
+        res = spawn_cancelable(log_send_loop) => res,
         res = spawn_cancelable(video_receive_loop) => res,
         res = spawn_cancelable(haptics_receive_loop) => res,
+        res = spawn_cancelable(tracking_state_loop) => res,
+        res = spawn_cancelable(imu_send_loop) => res,
 
         // keep these loops on the current task
         res = keepalive_sender_loop => res,
@@ -709,9 +1378,9 @@ async fn connection_pipeline(
 }
 
 pub async fn connection_lifecycle_loop(
-    headset_info: HeadsetInfoPacket,
+    headset_info: &HeadsetInfoPacket,
     device_name: &str,
-    private_identity: PrivateIdentity,
+    private_identity: &PrivateIdentity,
     // java_vm: Arc<JavaVM>,
     // activity_ref: Arc<GlobalRef>,
     // nal_class_ref: Arc<GlobalRef>,
@@ -729,9 +1398,9 @@ pub async fn connection_lifecycle_loop(
         tokio::join!(
             async {
                 let maybe_error = connection_pipeline(
-                    &headset_info,
+                    headset_info,
                     device_name.to_owned(),
-                    &private_identity,
+                    private_identity,
                     // Arc::clone(&java_vm),
                     // Arc::clone(&activity_ref),
                     // Arc::clone(&nal_class_ref),
@@ -739,6 +1408,7 @@ pub async fn connection_lifecycle_loop(
                 .await;
 
                 if let Err(e) = maybe_error {
+                    set_connection_state(ConnectionState::Error { cause: e.clone() });
                     let message =
                         format!("Connection error:\n{}\nCheck the PC for more details", e);
                     error!("{}", message);
@@ -0,0 +1,58 @@
+use alvr_common::prelude::*;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    // Several servers are known, one must be picked in the headset.
+    SelectingServer { profiles: Vec<String> },
+    Searching,
+    Handshaking,
+    // The code must be entered in the dashboard of the server before the connection goes on.
+    Pairing { code: String },
+    Streaming,
+    Degraded { reason: String },
+    Reconnecting,
+    Error { cause: String },
+    // Stopped by --session-limit-min until the companion resets it.
+    SessionLimitReached,
+}
+
+struct ConnectionStateStream {
+    current: ConnectionState,
+    subscribers: Vec<mpsc::UnboundedSender<ConnectionState>>,
+}
+
+lazy_static! {
+    static ref CONNECTION_STATE: Mutex<ConnectionStateStream> = Mutex::new(ConnectionStateStream {
+        current: ConnectionState::Searching,
+        subscribers: vec![],
+    });
+}
+
+pub fn connection_state() -> ConnectionState {
+    CONNECTION_STATE.lock().current.clone()
+}
+
+// The current state is sent first, followed by every change. Closed receivers are dropped
+// on the next change.
+pub fn subscribe_connection_state() -> mpsc::UnboundedReceiver<ConnectionState> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let mut stream = CONNECTION_STATE.lock();
+    sender.send(stream.current.clone()).ok();
+    stream.subscribers.push(sender);
+    receiver
+}
+
+pub(crate) fn set_connection_state(state: ConnectionState) {
+    let mut stream = CONNECTION_STATE.lock();
+    if stream.current == state {
+        return;
+    }
+    info!("Connection state: {state:?}");
+    stream
+        .subscribers
+        .retain(|subscriber| subscriber.send(state.clone()).is_ok());
+    stream.current = state;
+}
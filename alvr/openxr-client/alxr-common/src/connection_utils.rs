@@ -1,4 +1,4 @@
-use crate::APP_CONFIG;
+use crate::{companion, server_profiles::ServerProfile, APP_CONFIG};
 use alvr_common::prelude::*;
 use alvr_sockets::{
     ClientHandshakePacket, HandshakePacket, ServerHandshakePacket, CONTROL_PORT, LOCAL_IP,
@@ -16,6 +16,7 @@ pub enum ConnectionError {
 
 pub async fn announce_client_loop(
     handshake_packet: ClientHandshakePacket,
+    server_profile: Option<&ServerProfile>,
 ) -> StrResult<ConnectionError> {
     println!("announce_client_loop");
     println!("is localhost? {0}", APP_CONFIG.localhost);
@@ -33,11 +34,17 @@ pub async fn announce_client_loop(
     )))?;
 
     loop {
+        let server_address = companion::server_address()
+            .map(|address| (address, CONTROL_PORT))
+            .or_else(|| server_profile.map(|profile| (profile.address, profile.port)))
+            .or_else(|| {
+                APP_CONFIG
+                    .server_address
+                    .map(|address| (address, CONTROL_PORT))
+            })
+            .unwrap_or((Ipv4Addr::BROADCAST, CONTROL_PORT));
         let broadcast_result = handshake_socket
-            .send_to(
-                &client_handshake_packet,
-                (Ipv4Addr::BROADCAST, CONTROL_PORT),
-            )
+            .send_to(&client_handshake_packet, server_address)
             .await;
         if broadcast_result.is_err() {
             break Ok(ConnectionError::NetworkUnreachable);
@@ -0,0 +1,49 @@
+use alvr_common::prelude::*;
+use alvr_sockets::CrashReportPacket;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const UPLOADED_EXTENSION: &str = "uploaded";
+
+lazy_static! {
+    static ref PENDING_CRASH_REPORTS: Mutex<Vec<PathBuf>> = Mutex::new(vec![]);
+}
+
+// Reports are sent to the next server the client connects to.
+pub fn queue_crash_reports(paths: Vec<PathBuf>) {
+    PENDING_CRASH_REPORTS.lock().extend(paths);
+}
+
+pub(crate) fn take_pending_crash_reports() -> Vec<(PathBuf, CrashReportPacket)> {
+    PENDING_CRASH_REPORTS
+        .lock()
+        .drain(..)
+        .filter_map(|path| {
+            let contents = fs::read_to_string(&path).ok()?;
+            let file_name = path.file_name()?.to_string_lossy().into_owned();
+            Some((
+                path,
+                CrashReportPacket {
+                    file_name,
+                    contents,
+                },
+            ))
+        })
+        .collect()
+}
+
+// Uploaded reports are kept on the device but not sent again.
+pub(crate) fn mark_uploaded(path: &Path) {
+    let mut uploaded_path = path.as_os_str().to_owned();
+    uploaded_path.push(format!(".{UPLOADED_EXTENSION}"));
+    if let Err(e) = fs::rename(path, uploaded_path) {
+        warn!(
+            "Failed to mark crash report {} as uploaded: {e}",
+            path.display()
+        );
+    }
+}
@@ -0,0 +1,286 @@
+use crate::{config, ALXREyeTrackingType, ALXRFacialExpressionType, ALXRVersion, Options};
+use alvr_common::prelude::*;
+use alvr_sockets::ReferenceSpace;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use serde::Deserialize;
+use std::{fs, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceVendor {
+    Meta,
+    Pico,
+    HTC,
+    MagicLeap,
+    Unknown,
+}
+
+impl DeviceVendor {
+    pub fn from_manufacturer(manufacturer: &str) -> Self {
+        let name = manufacturer.to_lowercase();
+        if name.contains("oculus") || name.contains("meta") {
+            DeviceVendor::Meta
+        } else if name.contains("pico") || name.contains("bytedance") {
+            DeviceVendor::Pico
+        } else if name.contains("htc") {
+            DeviceVendor::HTC
+        } else if name.contains("magic leap") || name.contains("magicleap") {
+            DeviceVendor::MagicLeap
+        } else {
+            DeviceVendor::Unknown
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Capability {
+    EyeTracking,
+    EyeGazeInteraction,
+    FaceTracking,
+    HandTracking,
+    BodyTracking,
+    Passthrough,
+    StageSpace,
+}
+
+// A capability is only supported by the runtime for firmware versions in [min_version, max_version).
+struct FirmwareRule {
+    vendor: DeviceVendor,
+    capability: Capability,
+    min_version: [u32; 3],
+    max_version: Option<[u32; 3]>,
+}
+
+const FIRMWARE_RULES: &[FirmwareRule] = &[
+    FirmwareRule {
+        vendor: DeviceVendor::Meta,
+        capability: Capability::EyeTracking,
+        min_version: [46, 0, 0],
+        max_version: None,
+    },
+    FirmwareRule {
+        vendor: DeviceVendor::Meta,
+        capability: Capability::FaceTracking,
+        min_version: [46, 0, 0],
+        max_version: None,
+    },
+    FirmwareRule {
+        vendor: DeviceVendor::Meta,
+        capability: Capability::BodyTracking,
+        min_version: [60, 0, 0],
+        max_version: None,
+    },
+    FirmwareRule {
+        vendor: DeviceVendor::Pico,
+        capability: Capability::EyeTracking,
+        min_version: [5, 9, 0],
+        max_version: None,
+    },
+];
+
+// Android build ids with runtime bugs that are not covered by a firmware version range.
+// quest firmware version 71.0.0.178.498 has a crash bug in `xrSyncActions` when
+// `XR_EXT_eye_gaze_interaction` extension is enabled.
+const BROKEN_EYE_GAZE_INTERACTION_BUILD_IDS: &[&str] = &["SQ3A.220605.009.A1"];
+
+// Controller poses known to lag the HMD pose, in seconds. They are extrapolated by this much
+// before being sent.
+const CONTROLLER_POSE_TIME_OFFSETS: &[(DeviceVendor, f32)] = &[(DeviceVendor::Pico, 0.01)];
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct CapabilityOverrides {
+    pub eye_tracking: Option<bool>,
+    pub eye_gaze_interaction: Option<bool>,
+    pub face_tracking: Option<bool>,
+    pub hand_tracking: Option<bool>,
+    pub body_tracking: Option<bool>,
+    pub passthrough: Option<bool>,
+    pub stage_space: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct QuirksFile {
+    pub capabilities: CapabilityOverrides,
+    pub controller_pose_time_offset_ms: Option<f32>,
+}
+
+impl QuirksFile {
+    pub fn load(path: &Path) -> StrResult<Self> {
+        let contents = trace_err!(fs::read_to_string(path))?;
+        trace_err!(serde_json::from_str(&contents))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Capabilities {
+    pub eye_tracking: bool,
+    pub eye_gaze_interaction: bool,
+    pub face_tracking: bool,
+    pub hand_tracking: bool,
+    pub body_tracking: bool,
+    pub passthrough: bool,
+    // Some runtimes report a STAGE space with a wrong or missing floor.
+    pub stage_space: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            eye_tracking: true,
+            eye_gaze_interaction: true,
+            face_tracking: true,
+            hand_tracking: true,
+            body_tracking: true,
+            passthrough: true,
+            stage_space: true,
+        }
+    }
+}
+
+fn version_array(version: &ALXRVersion) -> [u32; 3] {
+    [version.major, version.minor, version.patch]
+}
+
+impl Capabilities {
+    // Unknown vendors and firmware versions (0.0.0) are never gated, the runtime is left to
+    // report what it supports.
+    pub fn resolve(vendor: DeviceVendor, firmware: &ALXRVersion, build_id: &str) -> Self {
+        let mut caps = Capabilities::default();
+        let version = version_array(firmware);
+        if version != [0, 0, 0] {
+            for rule in FIRMWARE_RULES.iter().filter(|r| r.vendor == vendor) {
+                let supported = version >= rule.min_version
+                    && rule.max_version.map_or(true, |max| version < max);
+                caps.set(rule.capability, supported);
+            }
+        }
+        if BROKEN_EYE_GAZE_INTERACTION_BUILD_IDS.contains(&build_id) {
+            caps.eye_gaze_interaction = false;
+        }
+        caps
+    }
+
+    fn set(&mut self, capability: Capability, enabled: bool) {
+        match capability {
+            Capability::EyeTracking => self.eye_tracking = enabled,
+            Capability::EyeGazeInteraction => self.eye_gaze_interaction = enabled,
+            Capability::FaceTracking => self.face_tracking = enabled,
+            Capability::HandTracking => self.hand_tracking = enabled,
+            Capability::BodyTracking => self.body_tracking = enabled,
+            Capability::Passthrough => self.passthrough = enabled,
+            Capability::StageSpace => self.stage_space = enabled,
+        }
+    }
+
+    // Quirk file entries take precedence over the built-in firmware rules.
+    pub fn apply_overrides(&mut self, overrides: &CapabilityOverrides) {
+        let fields = [
+            (Capability::EyeTracking, overrides.eye_tracking),
+            (
+                Capability::EyeGazeInteraction,
+                overrides.eye_gaze_interaction,
+            ),
+            (Capability::FaceTracking, overrides.face_tracking),
+            (Capability::HandTracking, overrides.hand_tracking),
+            (Capability::BodyTracking, overrides.body_tracking),
+            (Capability::Passthrough, overrides.passthrough),
+            (Capability::StageSpace, overrides.stage_space),
+        ];
+        for (capability, value) in fields {
+            if let Some(enabled) = value {
+                self.set(capability, enabled);
+            }
+        }
+    }
+}
+
+pub struct GatedFeatures {
+    pub eye_tracking: ALXREyeTrackingType,
+    pub facial_tracking: ALXRFacialExpressionType,
+    pub no_hand_tracking: bool,
+    pub no_passthrough: bool,
+    pub reference_space: ReferenceSpace,
+}
+
+lazy_static! {
+    static ref REFERENCE_SPACE: RwLock<ReferenceSpace> = RwLock::new(ReferenceSpace::Stage);
+}
+
+// Used for the next stream, reported to the server once streaming starts.
+pub fn set_reference_space(reference_space: ReferenceSpace) {
+    *REFERENCE_SPACE.write() = reference_space;
+}
+
+pub(crate) fn reference_space() -> ReferenceSpace {
+    *REFERENCE_SPACE.read()
+}
+
+impl Options {
+    pub fn load_quirks(&self) -> QuirksFile {
+        match &self.quirks_file {
+            Some(path) => QuirksFile::load(path).unwrap_or_else(|e| {
+                warn!("Failed to load quirks file {}: {e}", path.display());
+                QuirksFile::default()
+            }),
+            None => config::fleet_quirks()
+                .and_then(|quirks| {
+                    quirks
+                        .try_into::<QuirksFile>()
+                        .map_err(|e| warn!("Invalid fleet config quirks: {e}"))
+                        .ok()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    // Command line > quirk file > built-in vendor default.
+    pub fn controller_pose_time_offset(&self, vendor: DeviceVendor, quirks: &QuirksFile) -> f32 {
+        self.controller_pose_time_offset_ms
+            .or(quirks.controller_pose_time_offset_ms)
+            .map(|offset_ms| offset_ms / 1000_f32)
+            .unwrap_or_else(|| {
+                CONTROLLER_POSE_TIME_OFFSETS
+                    .iter()
+                    .find(|(v, _)| *v == vendor)
+                    .map_or(0_f32, |(_, offset_s)| *offset_s)
+            })
+    }
+
+    pub fn gate_features(&self, caps: &Capabilities) -> GatedFeatures {
+        let mut eye_tracking = self.eye_tracking.unwrap_or(ALXREyeTrackingType::Auto);
+        if !caps.eye_tracking {
+            eye_tracking = ALXREyeTrackingType::None;
+        } else if !caps.eye_gaze_interaction {
+            match eye_tracking {
+                ALXREyeTrackingType::Auto | ALXREyeTrackingType::ExtEyeGazeInteraction => {
+                    eye_tracking = ALXREyeTrackingType::FBEyeTrackingSocial;
+                }
+                _ => {}
+            }
+        }
+
+        let facial_tracking = if caps.face_tracking {
+            self.facial_tracking
+                .unwrap_or(ALXRFacialExpressionType::Auto)
+        } else {
+            ALXRFacialExpressionType::None
+        };
+
+        // An explicit choice is kept even if the quirks mark the stage space as broken.
+        let reference_space = self.reference_space.unwrap_or(if caps.stage_space {
+            ReferenceSpace::Stage
+        } else {
+            ReferenceSpace::Local
+        });
+
+        GatedFeatures {
+            eye_tracking,
+            facial_tracking,
+            no_hand_tracking: self.no_hand_tracking || !caps.hand_tracking,
+            no_passthrough: self.no_passthrough || !caps.passthrough,
+            reference_space,
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use alvr_sockets::{FecStatsPacket, VideoFrameHeaderPacket, VideoPacketStats};
+
+// ALVR_MAX_VIDEO_BUFFER_SIZE and ALVR_FEC_SHARDS_MAX of the server.
+const PACKET_PAYLOAD_SIZE: usize = 1400;
+const FEC_SHARDS_MAX: usize = 20;
+
+// Mirrors CalculateFECShardPackets() of the server.
+fn shard_packets(frame_byte_size: usize, fec_percentage: usize) -> usize {
+    let max_data_shards =
+        ((FEC_SHARDS_MAX - 2) * 100 + 99 + fec_percentage) / (100 + fec_percentage);
+    let min_block_size = (frame_byte_size + max_data_shards - 1) / max_data_shards;
+    ((min_block_size + PACKET_PAYLOAD_SIZE - 1) / PACKET_PAYLOAD_SIZE).max(1)
+}
+
+struct FrameFec {
+    video_frame_index: u64,
+    frame_byte_size: usize,
+    fec_percentage: usize,
+    received: Vec<bool>,
+    // Sent in one packet, the server has FEC disabled.
+    unfragmented: bool,
+}
+
+enum FrameOutcome {
+    Complete,
+    Recovered,
+    Unrecoverable,
+}
+
+impl FrameFec {
+    fn outcome(&self) -> FrameOutcome {
+        if self.unfragmented {
+            return FrameOutcome::Complete;
+        }
+        let data_packets = (self.frame_byte_size + PACKET_PAYLOAD_SIZE - 1) / PACKET_PAYLOAD_SIZE;
+        let is_received = |index: usize| self.received.get(index).copied().unwrap_or(false);
+        if (0..data_packets).all(is_received) {
+            return FrameOutcome::Complete;
+        }
+
+        // the decoder needs as many whole shards as there are data shards, the last data shard
+        // is padded with zeros instead of packets.
+        let shard_packets = shard_packets(self.frame_byte_size, self.fec_percentage);
+        let data_shards = (data_packets + shard_packets - 1) / shard_packets;
+        let parity_shards = (data_shards * self.fec_percentage + 99) / 100;
+        let whole_shards = (0..data_shards + parity_shards)
+            .filter(|shard| {
+                let start = shard * shard_packets;
+                let end = if *shard < data_shards {
+                    data_packets.min(start + shard_packets)
+                } else {
+                    start + shard_packets
+                };
+                (start..end).all(is_received)
+            })
+            .count();
+        if whole_shards >= data_shards {
+            FrameOutcome::Recovered
+        } else {
+            FrameOutcome::Unrecoverable
+        }
+    }
+}
+
+// Tells per frame whether packets were lost and if FEC can make up for them. The engine does the
+// actual reconstruction, this only mirrors the server's shard layout.
+#[derive(Default)]
+pub(crate) struct FecStatsCollector {
+    current_frame: Option<FrameFec>,
+    last_packet_stats: VideoPacketStats,
+    report: FecStatsPacket,
+    unrecoverable_frame: bool,
+}
+
+impl FecStatsCollector {
+    pub fn report_packet(&mut self, header: &VideoFrameHeaderPacket, payload_size: usize) {
+        if let Some(frame) = &mut self.current_frame {
+            if frame.video_frame_index == header.video_frame_index {
+                let index = header.fec_index as usize;
+                if frame.received.len() <= index {
+                    frame.received.resize(index + 1, false);
+                }
+                frame.received[index] = true;
+                return;
+            }
+        }
+
+        if let Some(frame) = self.current_frame.take() {
+            self.complete_frame(&frame);
+            // frames that had none of their packets arrive.
+            let skipped_frames = header
+                .video_frame_index
+                .saturating_sub(frame.video_frame_index + 1);
+            if skipped_frames > 0 {
+                self.report.frames += skipped_frames as u32;
+                self.report.frames_unrecoverable += skipped_frames as u32;
+                self.unrecoverable_frame = true;
+            }
+        }
+
+        let mut received = vec![false; header.fec_index as usize + 1];
+        received[header.fec_index as usize] = true;
+        self.current_frame = Some(FrameFec {
+            video_frame_index: header.video_frame_index,
+            frame_byte_size: header.frame_byte_size as usize,
+            fec_percentage: header.fec_percentage as usize,
+            received,
+            unfragmented: header.fec_index == 0 && payload_size == header.frame_byte_size as usize,
+        });
+    }
+
+    fn complete_frame(&mut self, frame: &FrameFec) {
+        self.report.frames += 1;
+        match frame.outcome() {
+            FrameOutcome::Complete => (),
+            FrameOutcome::Recovered => self.report.frames_recovered += 1,
+            FrameOutcome::Unrecoverable => {
+                self.report.frames_unrecoverable += 1;
+                self.unrecoverable_frame = true;
+            }
+        }
+    }
+
+    // Whether a frame was lost since the last call, a keyframe in it would have been lost too.
+    pub fn take_unrecoverable_frame(&mut self) -> bool {
+        std::mem::take(&mut self.unrecoverable_frame)
+    }
+
+    // `packet_stats` are the totals of the reorder buffer.
+    pub fn take_report(&mut self, packet_stats: VideoPacketStats) -> Option<FecStatsPacket> {
+        self.report.packets_received =
+            (packet_stats.received - self.last_packet_stats.received) as u32;
+        self.report.packets_lost = packet_stats
+            .lost
+            .saturating_sub(self.last_packet_stats.lost) as u32;
+        self.last_packet_stats = packet_stats;
+
+        let report = std::mem::take(&mut self.report);
+        (report.frames != 0).then_some(report)
+    }
+}
@@ -0,0 +1,46 @@
+use alvr_common::prelude::*;
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+// Set when a callback invoked from the engine panicked, the rust side state (senders, runtime)
+// can no longer be trusted and the client should tear down and restart.
+static POISONED: AtomicBool = AtomicBool::new(false);
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "Unavailable"
+    }
+}
+
+// Runs a callback invoked from C/C++ code, unwinding across the FFI boundary is undefined
+// behaviour (and aborts the process on newer toolchains) so any panic is caught here instead,
+// reported and the client is flagged for a restart.
+pub fn guard_callback<R: Default>(callback_name: &str, callback: impl FnOnce() -> R) -> R {
+    match panic::catch_unwind(AssertUnwindSafe(callback)) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = panic_message(payload.as_ref());
+            error!("FFI callback `{callback_name}` panicked: {message}");
+            eprintln!("FFI callback `{callback_name}` panicked: {message}");
+            if !POISONED.swap(true, Ordering::SeqCst) {
+                warn!("client state is poisoned, a restart has been requested.");
+            }
+            R::default()
+        }
+    }
+}
+
+pub fn is_poisoned() -> bool {
+    POISONED.load(Ordering::SeqCst)
+}
+
+pub fn clear_poisoned() {
+    POISONED.store(false, Ordering::SeqCst);
+}
@@ -0,0 +1,156 @@
+#![cfg_attr(target_vendor = "uwp", allow(dead_code))]
+// Central configuration for fleets of headsets. The local config file names an HTTPS URL and the
+// ed25519 public key of the fleet administrator:
+//
+//     fleet_config_url = "https://example.com/alxr/fleet.toml"
+//     fleet_config_public_key = "<hex encoded key>"
+//
+// The fleet file is signed, its hex encoded signature is fetched from the same URL with ".sig"
+// appended. Its entries override the local config file, and an optional [quirks] table is used
+// like a --quirks-file. The last verified copy is kept next to the config file and used while
+// the URL can't be reached.
+//
+// The fleet file must have an integer `fleet_config_version`, raised with every signed release.
+// A fetched file older than the cached copy is refused, an older signed file can't be replayed to
+// roll back the fleet config.
+use ed25519_dalek::{Signature, VerifyingKey};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+pub(crate) const URL_KEY: &str = "fleet_config_url";
+pub(crate) const PUBLIC_KEY_KEY: &str = "fleet_config_public_key";
+pub(crate) const QUIRKS_KEY: &str = "quirks";
+const VERSION_KEY: &str = "fleet_config_version";
+const CACHE_FILENAME: &str = "alxr.fleet.toml";
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_FLEET_CONFIG_BYTES: u64 = 1024 * 1024;
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.trim();
+    if !hex.is_ascii() || hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn verify(contents: &[u8], signature_hex: &str, public_key_hex: &str) -> Result<(), String> {
+    let public_key = decode_hex(public_key_hex)
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or("invalid public key")?;
+    let public_key = VerifyingKey::from_bytes(&public_key).map_err(|e| e.to_string())?;
+    let signature = decode_hex(signature_hex)
+        .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+        .ok_or("invalid signature")?;
+    public_key
+        .verify_strict(contents, &Signature::from_bytes(&signature))
+        .map_err(|e| e.to_string())
+}
+
+fn fetch(url: &str) -> Result<Vec<u8>, String> {
+    let response = ureq::get(url)
+        .timeout(FETCH_TIMEOUT)
+        .call()
+        .map_err(|e| e.to_string())?;
+    let mut contents = vec![];
+    response
+        .into_reader()
+        .take(MAX_FLEET_CONFIG_BYTES)
+        .read_to_end(&mut contents)
+        .map_err(|e| e.to_string())?;
+    Ok(contents)
+}
+
+fn fetch_verified(url: &str, public_key_hex: &str) -> Result<(Vec<u8>, String), String> {
+    if !url.starts_with("https://") {
+        return Err("the fleet config URL must use https".into());
+    }
+    let contents = fetch(url)?;
+    let signature = fetch(&format!("{url}.sig"))?;
+    let signature = String::from_utf8(signature).map_err(|e| e.to_string())?;
+    verify(&contents, &signature, public_key_hex)?;
+    Ok((contents, signature))
+}
+
+fn signature_path(cache_path: &Path) -> PathBuf {
+    let mut path = cache_path.as_os_str().to_owned();
+    path.push(".sig");
+    path.into()
+}
+
+// The cached copy is verified again, it is stored where it could be edited.
+fn load_cached(cache_path: &Path, public_key_hex: &str) -> Result<Vec<u8>, String> {
+    let contents = fs::read(cache_path).map_err(|e| e.to_string())?;
+    let signature = fs::read_to_string(signature_path(cache_path)).map_err(|e| e.to_string())?;
+    verify(&contents, &signature, public_key_hex)?;
+    Ok(contents)
+}
+
+// Returns the version and the entries without it.
+fn parse(contents: &[u8]) -> Result<(i64, BTreeMap<String, toml::Value>), String> {
+    let mut entries: BTreeMap<String, toml::Value> = std::str::from_utf8(contents)
+        .ok()
+        .and_then(|contents| toml::from_str(contents).ok())
+        .ok_or("not a valid TOML file")?;
+    match entries.remove(VERSION_KEY) {
+        Some(toml::Value::Integer(version)) => Ok((version, entries)),
+        _ => Err(format!("no integer {VERSION_KEY}")),
+    }
+}
+
+// Runs before logging is set up.
+pub(crate) fn load(
+    url: &str,
+    public_key_hex: &str,
+    config_dir: &Path,
+) -> Option<BTreeMap<String, toml::Value>> {
+    let cache_path = config_dir.join(CACHE_FILENAME);
+    let cached = load_cached(&cache_path, public_key_hex).and_then(|contents| parse(&contents));
+    let fetched = fetch_verified(url, public_key_hex).and_then(|(contents, signature)| {
+        let (version, entries) = parse(&contents)?;
+        match &cached {
+            Ok((cached_version, _)) if version < *cached_version => Err(format!(
+                "version {version} is older than the cached version {cached_version}"
+            )),
+            _ => Ok((contents, signature, version, entries)),
+        }
+    });
+    let mut entries = match fetched {
+        Ok((contents, signature, version, entries)) => {
+            println!("ALXR fleet config: {url}, version {version}");
+            if let Err(e) = fs::write(&cache_path, &contents)
+                .and_then(|_| fs::write(signature_path(&cache_path), signature))
+            {
+                eprintln!("Failed to cache the fleet config: {e}");
+            }
+            entries
+        }
+        Err(e) => {
+            eprintln!("Failed to fetch the fleet config from {url}: {e}");
+            match cached {
+                Ok((version, entries)) => {
+                    println!(
+                        "ALXR fleet config: {}, version {version}",
+                        cache_path.display()
+                    );
+                    entries
+                }
+                Err(e) => {
+                    eprintln!("No usable cached fleet config: {e}");
+                    return None;
+                }
+            }
+        }
+    };
+    // only the local config decides where the fleet config comes from.
+    entries.remove(URL_KEY);
+    entries.remove(PUBLIC_KEY_KEY);
+    Some(entries)
+}
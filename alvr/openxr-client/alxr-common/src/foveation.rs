@@ -0,0 +1,46 @@
+use crate::ALXRRenderConfig;
+use alvr_session::FoveatedRenderingDesc;
+use glam::Vec2;
+use settings_schema::Switch;
+
+// Values used when foveated rendering is disabled, these are never applied by the engine.
+const DEFAULT_CENTER_SIZE: Vec2 = Vec2::new(3_f32 / 5_f32, 2_f32 / 5_f32);
+const DEFAULT_CENTER_SHIFT: Vec2 = Vec2::new(2_f32 / 5_f32, 1_f32 / 10_f32);
+const DEFAULT_EDGE_RATIO: Vec2 = Vec2::new(2_f32, 2_f32);
+
+// The frames are foveated by the server, the client reconstructs them with the same params.
+pub struct FoveationState {
+    pub enabled: bool,
+    pub center_size: Vec2,
+    pub center_shift: Vec2,
+    pub edge_ratio: Vec2,
+}
+
+impl FoveationState {
+    pub fn new(settings: &Switch<FoveatedRenderingDesc>) -> Self {
+        match settings {
+            Switch::Enabled(desc) => Self {
+                enabled: true,
+                center_size: Vec2::new(desc.center_size_x, desc.center_size_y),
+                center_shift: Vec2::new(desc.center_shift_x, desc.center_shift_y),
+                edge_ratio: Vec2::new(desc.edge_ratio_x, desc.edge_ratio_y),
+            },
+            Switch::Disabled => Self {
+                enabled: false,
+                center_size: DEFAULT_CENTER_SIZE,
+                center_shift: DEFAULT_CENTER_SHIFT,
+                edge_ratio: DEFAULT_EDGE_RATIO,
+            },
+        }
+    }
+
+    pub fn apply_to(&self, render_config: &mut ALXRRenderConfig) {
+        render_config.enableFoveation = self.enabled;
+        render_config.foveationCenterSizeX = self.center_size.x;
+        render_config.foveationCenterSizeY = self.center_size.y;
+        render_config.foveationCenterShiftX = self.center_shift.x;
+        render_config.foveationCenterShiftY = self.center_shift.y;
+        render_config.foveationEdgeRatioX = self.edge_ratio.x;
+        render_config.foveationEdgeRatioY = self.edge_ratio.y;
+    }
+}
@@ -0,0 +1,154 @@
+// Controller-free navigation: gestures recognized from the hand skeleton are turned into the
+// controller inputs they are mapped to in the binding profile.
+//
+//     "gestures": { "pinch": ["trigger_click", "trigger_value"], "fist": [] }
+use crate::{bindings::ControllerInput, APP_CONFIG};
+use alvr_sockets::LegacyController;
+use glam::{Quat, Vec3};
+use lazy_static::lazy_static;
+use parking_lot::{Mutex, RwLock};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// Bones of the 19 bone hand skeleton, each rotation is relative to its parent.
+const THUMB_BONES: [usize; 2] = [4, 5];
+const FINGER_BONES: [[usize; 2]; 4] = [[7, 8], [10, 11], [13, 14], [17, 18]];
+const INDEX: usize = 0;
+const MIDDLE: usize = 1;
+
+// Flexion of the middle and distal joints, in degrees. Gestures turn on past the first value and
+// off below the second, so they don't flicker at the threshold.
+const PINCH_INDEX_CURL: (f32, f32) = (50., 35.);
+const PINCH_THUMB_CURL: f32 = 20.;
+const FIST_FINGER_CURL: (f32, f32) = (110., 80.);
+// Maximum angle between the palm normal and up for the palm to count as facing up.
+const PALM_UP_MAX_ANGLE: f32 = 35.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandGesture {
+    Pinch,
+    Fist,
+    // Replaces the pinch while the palm faces up.
+    PalmUpPinch,
+}
+
+pub(crate) type GestureMap = HashMap<HandGesture, Vec<ControllerInput>>;
+
+#[derive(Default, Clone, Copy)]
+struct HandState {
+    pinching: bool,
+    fist: bool,
+}
+
+lazy_static! {
+    static ref GESTURE_MAP: RwLock<GestureMap> = RwLock::new(default_gesture_map());
+    static ref HAND_STATES: Mutex<[HandState; 2]> = Mutex::new([HandState::default(); 2]);
+}
+
+fn default_gesture_map() -> GestureMap {
+    HashMap::from([
+        (
+            HandGesture::Pinch,
+            vec![ControllerInput::TriggerClick, ControllerInput::TriggerValue],
+        ),
+        (
+            HandGesture::Fist,
+            vec![ControllerInput::GripClick, ControllerInput::GripValue],
+        ),
+        (
+            HandGesture::PalmUpPinch,
+            vec![ControllerInput::ApplicationMenuClick],
+        ),
+    ])
+}
+
+// Entries of the profile replace the default ones, an empty list disables a gesture.
+pub(crate) fn set_gesture_map(gestures: &GestureMap) {
+    let mut map = default_gesture_map();
+    map.extend(
+        gestures
+            .iter()
+            .map(|(gesture, inputs)| (*gesture, inputs.clone())),
+    );
+    *GESTURE_MAP.write() = map;
+}
+
+fn curl_degrees(bone_rotations: &[Quat; 19], bones: &[usize]) -> f32 {
+    bones
+        .iter()
+        .fold(Quat::IDENTITY, |rotation, bone| {
+            rotation * bone_rotations[*bone]
+        })
+        .normalize()
+        .angle_between(Quat::IDENTITY)
+        .to_degrees()
+}
+
+fn with_hysteresis(active: bool, value: f32, (on, off): (f32, f32)) -> bool {
+    if active {
+        value > off
+    } else {
+        value > on
+    }
+}
+
+fn apply_input(controller: &mut LegacyController, input: ControllerInput) {
+    match input {
+        ControllerInput::TriggerValue => controller.trigger_value = 1.,
+        ControllerInput::GripValue => controller.grip_value = 1.,
+        input => controller.buttons |= input.button_flag(),
+    }
+}
+
+// `orientations` are the hand root orientations of the left and right hand. Controllers are left
+// untouched.
+pub(crate) fn apply_hand_gestures(
+    controllers: &mut [LegacyController; 2],
+    orientations: [Quat; 2],
+) {
+    if !APP_CONFIG.hand_gesture_buttons {
+        return;
+    }
+    let map = GESTURE_MAP.read();
+    let mut states = HAND_STATES.lock();
+    for ((controller, state), orientation) in controllers
+        .iter_mut()
+        .zip(states.iter_mut())
+        .zip(orientations)
+    {
+        if !controller.enabled || !controller.is_hand {
+            *state = HandState::default();
+            continue;
+        }
+        let bones = &controller.bone_rotations;
+        let finger_curls = FINGER_BONES.map(|finger| curl_degrees(bones, &finger));
+
+        state.fist = finger_curls
+            .iter()
+            .all(|curl| with_hysteresis(state.fist, *curl, FIST_FINGER_CURL));
+        state.pinching = !state.fist
+            && with_hysteresis(state.pinching, finger_curls[INDEX], PINCH_INDEX_CURL)
+            && finger_curls[MIDDLE] < FIST_FINGER_CURL.1
+            && curl_degrees(bones, &THUMB_BONES) > PINCH_THUMB_CURL;
+
+        // OpenXR hand joint convention, the palm faces -Y.
+        let palm_normal = orientation * Vec3::NEG_Y;
+        let palm_up = palm_normal.angle_between(Vec3::Y).to_degrees() < PALM_UP_MAX_ANGLE;
+
+        let gesture = if state.fist {
+            Some(HandGesture::Fist)
+        } else if state.pinching && palm_up {
+            Some(HandGesture::PalmUpPinch)
+        } else if state.pinching {
+            Some(HandGesture::Pinch)
+        } else {
+            None
+        };
+        if let Some(inputs) = gesture.and_then(|gesture| map.get(&gesture)) {
+            for input in inputs {
+                apply_input(controller, *input);
+            }
+        }
+    }
+}
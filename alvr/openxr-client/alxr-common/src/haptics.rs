@@ -0,0 +1,62 @@
+use crate::Options;
+use alvr_common::{LEFT_HAND_ID, RIGHT_HAND_ID};
+use alvr_sockets::Haptics;
+
+// Vibrations shorter than this are not felt on most controllers, the runtime picks its
+// minimum duration instead.
+const MIN_HAPTICS_DURATION_S: f32 = 0.001;
+
+pub struct HapticsEvent {
+    pub path: u64,
+    pub duration_s: f32,
+    pub frequency: f32,
+    pub amplitude: f32,
+}
+
+// Translates server haptic events to per-controller feedback with user amplitude scaling.
+pub struct HapticsRouter {
+    enabled: bool,
+    scale: f32,
+    left_scale: f32,
+    right_scale: f32,
+}
+
+impl HapticsRouter {
+    pub fn new(options: &Options) -> Self {
+        let scale = options.haptics_amplitude_scale.max(0_f32);
+        Self {
+            enabled: !options.no_haptics,
+            scale,
+            left_scale: scale * options.haptics_left_scale.unwrap_or(1_f32).max(0_f32),
+            right_scale: scale * options.haptics_right_scale.unwrap_or(1_f32).max(0_f32),
+        }
+    }
+
+    pub fn route(&self, packet: &Haptics) -> Option<HapticsEvent> {
+        if !self.enabled {
+            return None;
+        }
+        let scale = if packet.path == *LEFT_HAND_ID {
+            self.left_scale
+        } else if packet.path == *RIGHT_HAND_ID {
+            self.right_scale
+        } else {
+            self.scale
+        };
+        let amplitude = (packet.amplitude * scale).clamp(0_f32, 1_f32);
+        if amplitude <= 0_f32 {
+            return None;
+        }
+        let duration_s = packet.duration.as_secs_f32();
+        Some(HapticsEvent {
+            path: packet.path,
+            duration_s: if duration_s < MIN_HAPTICS_DURATION_S {
+                0_f32
+            } else {
+                duration_s
+            },
+            frequency: packet.frequency.max(0_f32),
+            amplitude,
+        })
+    }
+}
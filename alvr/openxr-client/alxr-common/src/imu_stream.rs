@@ -0,0 +1,49 @@
+// Raw IMU samples streamed to the server for research users doing their own sensor fusion. Off by
+// default, --imu-stream-hz enables it and caps the rate of each sensor. Samples are batched on
+// their own stream so they don't delay the tracking.
+use crate::APP_CONFIG;
+use alvr_sockets::ImuSample;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const MAX_IMU_STREAM_HZ: u32 = 1000;
+pub(crate) const IMU_BATCH_INTERVAL: Duration = Duration::from_millis(10);
+
+lazy_static! {
+    pub(crate) static ref IMU_SENDER: Mutex<Option<mpsc::UnboundedSender<ImuSample>>> =
+        Mutex::new(None);
+    // Timestamp of the last sample sent for each sensor.
+    static ref LAST_SAMPLE_NS: Mutex<[Option<i64>; 2]> = Mutex::new([None; 2]);
+}
+
+// The rate the platform should sample the IMU at, 0 when streaming is disabled.
+pub fn imu_stream_hz() -> u32 {
+    APP_CONFIG.imu_stream_hz.min(MAX_IMU_STREAM_HZ)
+}
+
+// Samples arriving faster than the configured rate are dropped, as well as the samples taken
+// while not streaming.
+pub fn push_imu_sample(sample: ImuSample) {
+    let rate_hz = imu_stream_hz();
+    if rate_hz == 0 {
+        return;
+    }
+    // sensors don't keep their rate exactly, a small margin avoids dropping every other sample.
+    let min_interval_ns = 900_000_000 / rate_hz as i64;
+    {
+        let mut last_sample_ns = LAST_SAMPLE_NS.lock();
+        let last = &mut last_sample_ns[sample.sensor as usize];
+        if let Some(last) = *last {
+            // a timestamp going back is a sensor restart, not a duplicate.
+            if (0..min_interval_ns).contains(&(sample.timestamp_ns - last)) {
+                return;
+            }
+        }
+        *last = Some(sample.timestamp_ns);
+    }
+    if let Some(sender) = &*IMU_SENDER.lock() {
+        sender.send(sample).ok();
+    }
+}
@@ -0,0 +1,184 @@
+use std::{collections::VecDeque, time::Duration};
+use tokio::time::Instant;
+
+// Same base delay tracking as the bitrate feedback, the minimum over the current and the previous
+// window.
+const BASE_DELAY_WINDOW: Duration = Duration::from_secs(5);
+const JITTER_HISTORY_LEN: usize = 1024;
+const ADAPT_INTERVAL: Duration = Duration::from_millis(500);
+// Share of the recent packets the delay should cover.
+const JITTER_PERCENTILE: f32 = 0.95;
+
+// Smooths out burst delivery of the video packets, as on Wi-Fi. A packet is released to the
+// decoder once it is `delay` older than it would be on the fastest path seen recently (its one
+// way delay above the lowest one), so packets delayed by less than that are decoded evenly
+// spaced. The delay follows the jitter of the recent packets, at most `max_delay`. Packets are
+// released in the order they are pushed.
+pub struct JitterBuffer<T> {
+    max_delay: Duration,
+    delay: Duration,
+    epoch: Instant,
+    base_delay_us: [Option<i64>; 2],
+    window_start: Instant,
+    jitter_history_us: VecDeque<u64>,
+    adapt_deadline: Instant,
+    pending: VecDeque<(Instant, T)>,
+}
+
+impl<T> JitterBuffer<T> {
+    pub fn new(max_delay: Duration) -> Self {
+        let now = Instant::now();
+        Self {
+            max_delay,
+            delay: Duration::ZERO,
+            epoch: now,
+            base_delay_us: [None; 2],
+            window_start: now,
+            jitter_history_us: VecDeque::new(),
+            adapt_deadline: now + ADAPT_INTERVAL,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    // When the oldest held packet is due.
+    pub fn next_release(&self) -> Option<Instant> {
+        self.pending.front().map(|(release, _)| *release)
+    }
+
+    // `sent_time` is the server timestamp of the packet in us.
+    pub fn push(&mut self, sent_time: u64, received: Instant, packet: T) {
+        // offset by the clock difference, which cancels out against the base delay.
+        let delay_us = received.duration_since(self.epoch).as_micros() as i64 - sent_time as i64;
+        if self.window_start.elapsed() > BASE_DELAY_WINDOW {
+            self.base_delay_us = [self.base_delay_us[1], None];
+            self.window_start = Instant::now();
+        }
+        let window_min = self.base_delay_us[1].get_or_insert(delay_us);
+        *window_min = (*window_min).min(delay_us);
+        let base_delay_us = self.base_delay_us.iter().flatten().min().copied();
+        let jitter = Duration::from_micros((delay_us - base_delay_us.unwrap_or(delay_us)) as u64);
+
+        self.jitter_history_us.push_back(jitter.as_micros() as u64);
+        if self.jitter_history_us.len() > JITTER_HISTORY_LEN {
+            self.jitter_history_us.pop_front();
+        }
+        // a burst later than the delay raises it at once, it goes down again slowly.
+        if jitter > self.delay {
+            self.delay = jitter.min(self.max_delay);
+        } else if self.adapt_deadline < Instant::now() {
+            self.adapt();
+        }
+
+        // after the delay went down, a packet can't be due before the ones held under the higher
+        // delay, they are released in order.
+        let release = received + self.delay.saturating_sub(jitter);
+        let release = match self.pending.back() {
+            Some((last_release, _)) => release.max(*last_release),
+            None => release,
+        };
+        self.pending.push_back((release, packet));
+    }
+
+    // Packets due by `now` are appended to `ready` in order.
+    pub fn pop_ready(&mut self, now: Instant, ready: &mut Vec<T>) {
+        while let Some((release, _)) = self.pending.front() {
+            if *release > now {
+                break;
+            }
+            if let Some((_, packet)) = self.pending.pop_front() {
+                ready.push(packet);
+            }
+        }
+    }
+
+    fn adapt(&mut self) {
+        let mut jitters = self.jitter_history_us.iter().copied().collect::<Vec<_>>();
+        jitters.sort_unstable();
+        let index = ((jitters.len() as f32 * JITTER_PERCENTILE) as usize).min(jitters.len() - 1);
+        self.delay = Duration::from_micros(jitters[index]).min(self.max_delay);
+        self.adapt_deadline = Instant::now() + ADAPT_INTERVAL;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time;
+
+    const PACKET_INTERVAL: Duration = Duration::from_millis(10);
+
+    // Pushes a packet sent `PACKET_INTERVAL` after the previous one, `late` later than on time.
+    fn push_late(buffer: &mut JitterBuffer<u32>, index: u32, late: Duration) {
+        let sent_time = (PACKET_INTERVAL * index).as_micros() as u64;
+        buffer.push(sent_time, Instant::now() + late, index);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_delay_rises_with_jitter() {
+        let mut buffer = JitterBuffer::new(Duration::from_millis(100));
+        push_late(&mut buffer, 0, Duration::ZERO);
+        assert_eq!(buffer.delay(), Duration::ZERO);
+
+        time::advance(PACKET_INTERVAL).await;
+        push_late(&mut buffer, 1, Duration::from_millis(30));
+        assert_eq!(buffer.delay(), Duration::from_millis(30));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_delay_max_delay() {
+        let mut buffer = JitterBuffer::new(Duration::from_millis(20));
+        push_late(&mut buffer, 0, Duration::ZERO);
+        time::advance(PACKET_INTERVAL).await;
+        push_late(&mut buffer, 1, Duration::from_millis(50));
+        assert_eq!(buffer.delay(), Duration::from_millis(20));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_delay_decays() {
+        let mut buffer = JitterBuffer::new(Duration::from_millis(100));
+        push_late(&mut buffer, 0, Duration::ZERO);
+        time::advance(PACKET_INTERVAL).await;
+        push_late(&mut buffer, 1, Duration::from_millis(30));
+
+        // the delay is kept until the next adaptation
+        for index in 2..40 {
+            time::advance(PACKET_INTERVAL).await;
+            push_late(&mut buffer, index, Duration::ZERO);
+        }
+        assert_eq!(buffer.delay(), Duration::from_millis(30));
+
+        for index in 40..60 {
+            time::advance(PACKET_INTERVAL).await;
+            push_late(&mut buffer, index, Duration::ZERO);
+        }
+        assert_eq!(buffer.delay(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_release_in_order_after_decay() {
+        let mut buffer = JitterBuffer::new(Duration::from_millis(400));
+        push_late(&mut buffer, 0, Duration::ZERO);
+        time::advance(PACKET_INTERVAL).await;
+        push_late(&mut buffer, 1, Duration::from_millis(300));
+        for index in 2..60 {
+            time::advance(PACKET_INTERVAL).await;
+            push_late(&mut buffer, index, Duration::ZERO);
+        }
+        assert_eq!(buffer.delay(), Duration::ZERO);
+
+        let releases = buffer
+            .pending
+            .iter()
+            .map(|(release, _)| *release)
+            .collect::<Vec<_>>();
+        assert!(releases.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        let mut ready = vec![];
+        buffer.pop_ready(Instant::now() + Duration::from_secs(1), &mut ready);
+        assert_eq!(ready, (0..60).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,144 @@
+use alvr_sockets::{LatencyBreakdownPacket, LatencyStat, VideoFrameHeaderPacket};
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+// Set from the engine's time sync reports, 0 until the decoder reports a latency.
+static DECODE_LATENCY_US: AtomicU32 = AtomicU32::new(0);
+
+pub(crate) fn set_decode_latency(latency_us: u32) {
+    DECODE_LATENCY_US.store(latency_us, Ordering::Relaxed);
+}
+
+pub(crate) fn decode_latency_us() -> u32 {
+    DECODE_LATENCY_US.load(Ordering::Relaxed)
+}
+
+// Set from the server's time sync packets, 0 until the server reports them.
+static VIDEO_LATENCY_US: AtomicU32 = AtomicU32::new(0);
+static TRANSPORT_LATENCY_US: AtomicU32 = AtomicU32::new(0);
+
+pub(crate) fn set_server_latencies(video_latency_us: u32, transport_latency_us: u32) {
+    VIDEO_LATENCY_US.store(video_latency_us, Ordering::Relaxed);
+    TRANSPORT_LATENCY_US.store(transport_latency_us, Ordering::Relaxed);
+}
+
+// End to end latency of the video and network latency of the packets sent by the server.
+pub(crate) fn server_latencies_us() -> Option<(u32, u32)> {
+    let video_latency_us = VIDEO_LATENCY_US.load(Ordering::Relaxed);
+    (video_latency_us != 0).then(|| {
+        (
+            video_latency_us,
+            TRANSPORT_LATENCY_US.load(Ordering::Relaxed),
+        )
+    })
+}
+
+#[derive(Default)]
+struct Accumulator {
+    total: Duration,
+    max: Duration,
+    count: u32,
+}
+
+impl Accumulator {
+    fn add(&mut self, value: Duration) {
+        self.total += value;
+        self.max = self.max.max(value);
+        self.count += 1;
+    }
+
+    fn take(&mut self) -> LatencyStat {
+        let acc = std::mem::take(self);
+        LatencyStat {
+            average_us: acc
+                .total
+                .checked_div(acc.count)
+                .unwrap_or_default()
+                .as_micros() as u32,
+            max_us: acc.max.as_micros() as u32,
+        }
+    }
+}
+
+pub(crate) struct FrameTiming {
+    pub video_frame_index: u64,
+    pub tracking_frame_index: u64,
+    pub frame_byte_size: u32,
+    pub packets: u32,
+    pub first_received: Instant,
+    pub last_submitted: Instant,
+    pub queue_wait_max: Duration,
+    pub decoder_submit_total: Duration,
+}
+
+// Collects per frame timings of the receive path, a frame is complete once a packet of the
+// next frame is submitted.
+#[derive(Default)]
+pub(crate) struct LatencyStatsCollector {
+    current_frame: Option<FrameTiming>,
+    frames: u32,
+    frame_receive: Accumulator,
+    queue_wait: Accumulator,
+    decoder_submit: Accumulator,
+}
+
+impl LatencyStatsCollector {
+    // Returns the previous frame when this packet starts a new one.
+    pub fn report_packet(
+        &mut self,
+        header: &VideoFrameHeaderPacket,
+        received: Instant,
+        submit_start: Instant,
+        submit_end: Instant,
+    ) -> Option<FrameTiming> {
+        let queue_wait = submit_start - received;
+        let decoder_submit = submit_end - submit_start;
+        self.queue_wait.add(queue_wait);
+        self.decoder_submit.add(decoder_submit);
+
+        if let Some(frame) = &mut self.current_frame {
+            if frame.video_frame_index == header.video_frame_index {
+                frame.packets += 1;
+                frame.first_received = frame.first_received.min(received);
+                frame.last_submitted = submit_end;
+                frame.queue_wait_max = frame.queue_wait_max.max(queue_wait);
+                frame.decoder_submit_total += decoder_submit;
+                return None;
+            }
+        }
+
+        let completed_frame = self.current_frame.replace(FrameTiming {
+            video_frame_index: header.video_frame_index,
+            tracking_frame_index: header.tracking_frame_index,
+            frame_byte_size: header.frame_byte_size,
+            packets: 1,
+            first_received: received,
+            last_submitted: submit_end,
+            queue_wait_max: queue_wait,
+            decoder_submit_total: decoder_submit,
+        });
+        if let Some(frame) = &completed_frame {
+            self.frame_receive
+                .add(frame.last_submitted - frame.first_received);
+            self.frames += 1;
+        }
+        completed_frame
+    }
+
+    pub fn take_report(&mut self) -> Option<LatencyBreakdownPacket> {
+        if self.frames == 0 {
+            return None;
+        }
+        let decode_us = decode_latency_us();
+        Some(LatencyBreakdownPacket {
+            frames: std::mem::take(&mut self.frames),
+            frame_receive: self.frame_receive.take(),
+            queue_wait: self.queue_wait.take(),
+            decoder_submit: self.decoder_submit.take(),
+            decode_us: (decode_us != 0).then_some(decode_us),
+        })
+    }
+}
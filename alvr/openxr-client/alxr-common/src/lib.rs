@@ -1,53 +1,142 @@
+mod aux_sensors;
+mod bindings;
+mod bitrate_feedback;
+mod bug_report;
+mod companion;
+mod config;
 mod connection;
+mod connection_state;
 mod connection_utils;
+mod crash_reports;
+mod feature_gating;
+mod fec_stats;
+mod ffi_guard;
+mod fleet_config;
+mod foveation;
+mod hand_gestures;
+mod haptics;
+mod imu_stream;
+mod jitter_buffer;
+mod latency_stats;
+mod log_forwarding;
+mod microphone;
+mod mkv_writer;
+mod monocular;
+mod network_interface;
+mod one_handed;
+mod pairing;
+mod perf_log;
+mod pose_offsets;
+mod pose_prediction;
+mod preview;
+mod push_content;
+mod recenter;
+mod server_profiles;
+mod session_analytics;
+mod session_limit;
+mod sleep_timer;
+mod spectator_camera;
+mod startup_timing;
+mod stream_resume;
+mod teardown;
+mod tracking_replay;
+mod tracking_server_discovery;
+mod tracking_state;
+mod video_recorder;
+mod video_reorder;
+mod world_offset;
 
 #[cfg(target_os = "android")]
 mod audio;
 
-use alvr_common::{prelude::*, ALVR_VERSION, HEAD_ID, LEFT_HAND_ID, RIGHT_HAND_ID};
+pub use alvr_common::set_panic_hook;
+pub use alvr_common::HEAD_ID;
+use alvr_common::{prelude::*, ALVR_VERSION, LEFT_HAND_ID, RIGHT_HAND_ID};
 use alvr_session::Fov;
 use alvr_sockets::{
-    BatteryPacket, HeadsetInfoPacket, HiddenAreaMesh, Input, LegacyController, LegacyInput,
-    MotionData, TimeSyncPacket, ViewsConfig,
+    AudioSyncPacket, BatteryPacket, Dscp, HeadsetInfoPacket, HiddenAreaMesh, Input,
+    LegacyController, LegacyInput, MotionData, PrivateIdentity, ReferenceSpace, StreamTransport,
+    TimeSyncPacket, VelocityFrame, ViewsConfig,
+};
+pub use alvr_sockets::{
+    ImuSample, ImuSensor, SpectatorCameraPacket, ThermalStatsPacket, ThermalStatus,
+    WifiStatsPacket, WorldOffsetPacket,
 };
 pub use alxr_engine_sys::*;
+pub use aux_sensors::push_aux_sensor_reading;
+pub use bindings::{set_binding_profile, BindingProfile};
+pub use bug_report::create_bug_report;
+#[cfg(target_os = "android")]
+pub use config::set_launch_options;
+pub use config::{config_file_path, SecretString, WifiLockMode, CONFIG_FILENAME};
+pub use connection_state::{connection_state, subscribe_connection_state, ConnectionState};
+pub use crash_reports::queue_crash_reports;
+pub use feature_gating::{set_reference_space, Capabilities, DeviceVendor, GatedFeatures};
+use ffi_guard::guard_callback;
+pub use ffi_guard::{clear_poisoned, is_poisoned};
+pub use imu_stream::{imu_stream_hz, push_imu_sample};
 use lazy_static::lazy_static;
 use local_ipaddress;
+pub use log_forwarding::forward_log;
 use parking_lot::Mutex;
+pub use pose_offsets::PoseOffset;
+pub use pose_prediction::set_controller_pose_time_offset;
+pub use recenter::recenter;
+use serde::Serialize;
+pub use server_profiles::{select_server_profile, server_profiles, ServerProfile, ServerSelection};
+pub use sleep_timer::is_exit_requested;
+pub use spectator_camera::set_spectator_camera;
+pub use startup_timing::mark_startup_stage;
 use std::ffi::CStr;
 use std::{
     slice,
     sync::atomic::{AtomicBool, Ordering},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+pub use teardown::{Teardown, DEFAULT_STAGE_TIMEOUT};
+use tokio::{
+    runtime::{self, Runtime},
+    sync::mpsc,
+    sync::Notify,
+};
+pub use tracking_server_discovery::{
+    advertise_tracking_server, is_tracking_server_discovery_enabled,
 };
-use tokio::{runtime::Runtime, sync::mpsc, sync::Notify};
+pub use world_offset::set_world_offset;
 //#[cfg(not(target_os = "android"))]
 use glam::{Quat, Vec2, Vec3};
 use structopt::StructOpt;
 
-#[cfg(target_os = "android")]
-use android_system_properties::AndroidSystemProperties;
-
 #[cfg(any(target_os = "android", target_vendor = "uwp"))]
 const ALXR_TRACKING_SERVER_PORT_NO: u16 = 49192;
 
-#[derive(Debug, StructOpt)]
-#[structopt(name = "alxr-client", about = "An OpenXR based ALVR client.")]
+// Also serialized as the default config file, see config.rs.
+#[derive(Debug, StructOpt, Serialize)]
+#[structopt(
+    name = "alxr-client",
+    about = "An OpenXR based ALVR client.",
+    setting = structopt::clap::AppSettings::AllArgsOverrideSelf
+)]
 pub struct Options {
     // short and long flags (-d, --debug) will be deduced from the field's name
     /// Enable this if the server and client are running on the same host-os.
     #[structopt(/*short,*/ long)]
     pub localhost: bool,
 
+    #[serde(serialize_with = "config::serialize_debug")]
     #[structopt(short = "g", long = "graphics", parse(from_str))]
     pub graphics_api: Option<ALXRGraphicsApi>,
 
+    #[serde(serialize_with = "config::serialize_debug")]
     #[structopt(short = "d", long = "decoder", parse(from_str))]
     pub decoder_type: Option<ALXRDecoderType>,
 
-    /// Number of threads to use for CPU based decoding.
-    #[structopt(long, default_value = "1")]
+    /// Number of threads to use for CPU based decoding, 0 picks one tuned to the CPU core count.
+    #[structopt(long, default_value = "0")]
     pub decoder_thread_count: u32,
 
+    #[serde(serialize_with = "config::serialize_debug")]
     #[structopt(long, parse(from_str))]
     pub color_space: Option<ALXRColorSpace>,
 
@@ -96,14 +185,17 @@ pub struct Options {
     pub no_hand_tracking: bool,
 
     /// Specifices which tracking sources to use for face-tracking, default is VisualSource only
+    #[serde(serialize_with = "config::serialize_debug_vec")]
     #[structopt(long, parse(from_str), default_value = "VisualSource")]
     pub face_tracking_data_sources: Option<Vec<ALXRFaceTrackingDataSource>>,
 
     /// Disable or Specify which type of facial tracking extension to use, default is auto detection in order of vendor specific to multi-vendor
+    #[serde(serialize_with = "config::serialize_debug")]
     #[structopt(long, parse(from_str))]
     pub facial_tracking: Option<ALXRFacialExpressionType>,
 
     /// Disable or specify which type of facial tracking extension to use, default is auto detection in order of vendor specific to multi-vendor
+    #[serde(serialize_with = "config::serialize_debug")]
     #[structopt(long, parse(from_str))]
     pub eye_tracking: Option<ALXREyeTrackingType>,
 
@@ -118,12 +210,346 @@ pub struct Options {
     pub simulate_headless: bool,
 
     /// Sets the initial passthrough mode, default is None (no passthrough blending)
+    #[serde(serialize_with = "config::serialize_debug")]
     #[structopt(long, parse(from_str))]
     pub passthrough_mode: Option<ALXRPassthroughMode>,
 
     /// Disables all usages of visibility masks
     #[structopt(/*short,*/ long = "disable-visibility-masks")]
     pub no_visibility_masks: bool,
+
+    /// Path to a json quirks file used to override firmware based feature gating.
+    #[structopt(long, parse(from_os_str))]
+    pub quirks_file: Option<std::path::PathBuf>,
+
+    /// Disables microphone capture even if it is enabled in the server settings.
+    #[structopt(long)]
+    pub no_microphone: bool,
+
+    /// Sample rate the microphone is captured at, resampled to the server's microphone sample rate
+    /// before sending. Defaults to the device's preferred sample rate.
+    #[structopt(long)]
+    pub mic_sample_rate: Option<u32>,
+
+    /// Name (or part of) of the microphone device to capture from, defaults to the system default input device.
+    /// Not supported on android.
+    #[structopt(long)]
+    pub mic_device: Option<String>,
+
+    /// Enables the companion protocol server on the given TCP port, used by PC-side helpers to
+    /// configure the client and fetch logs. Needs --companion-token, and listens on loopback for
+    /// adb port forwarding unless --companion-listen-all is set.
+    #[structopt(long)]
+    pub companion_port: Option<u16>,
+
+    /// Token the companion helper must send in its Hello before any other request.
+    #[structopt(long)]
+    pub companion_token: Option<config::SecretString>,
+
+    /// Listens for the companion on every network interface, for helpers on the same Wi-Fi.
+    #[structopt(/*short,*/ long)]
+    pub companion_listen_all: bool,
+
+    /// Requests a mono (single viewpoint) stream, for spectator devices like phones/tablets. Both
+    /// halves of the frame show the same view, it is still decoded and displayed as a stereo frame.
+    #[structopt(/*short,*/ long)]
+    pub mono_stream: bool,
+
+    /// Disables controller haptic feedback.
+    #[structopt(/*short,*/ long)]
+    pub no_haptics: bool,
+
+    /// Scales the amplitude of all haptic feedback, results are clamped to [0, 1].
+    #[structopt(long, default_value = "1.0")]
+    pub haptics_amplitude_scale: f32,
+
+    /// Additional amplitude scale for the left controller.
+    #[structopt(long)]
+    pub haptics_left_scale: Option<f32>,
+
+    /// Additional amplitude scale for the right controller.
+    #[structopt(long)]
+    pub haptics_right_scale: Option<f32>,
+
+    /// Json controller binding profile used to remap controller inputs, relative paths are resolved
+    /// against the app's data directory on android. Defaults to `binding_profile.json` if it exists.
+    #[structopt(long, parse(from_os_str))]
+    pub binding_profile: Option<std::path::PathBuf>,
+
+    /// Disables reporting of lost tracking to the server, last known poses are streamed instead.
+    #[structopt(/*short,*/ long)]
+    pub no_tracking_state: bool,
+
+    /// Writes a config file with the default options to the platform config directory and exits.
+    /// Options set in that file are used unless given on the command line.
+    #[serde(skip)]
+    #[structopt(/*short,*/ long)]
+    pub write_default_config: bool,
+
+    /// Frame of the forwarded device velocities, Stage (tracking space, default) or Local
+    /// (relative to the device orientation).
+    #[structopt(long, parse(from_str))]
+    pub velocity_frame: Option<VelocityFrame>,
+
+    /// Number of video packets held back while waiting for a reordered packet, 0 disables reordering.
+    #[structopt(long, default_value = "16")]
+    pub video_reorder_window: u32,
+
+    /// IPv4 address of the ALVR server, the client handshake is sent to it instead of broadcasted.
+    #[structopt(long)]
+    pub server_address: Option<std::net::Ipv4Addr>,
+
+    /// Seconds between headset battery queries on android, changes are sent to the server. 0 disables it.
+    #[structopt(long, default_value = "10")]
+    pub battery_poll_interval_s: u32,
+
+    /// Extrapolates controller poses by this many milliseconds before sending, overrides the
+    /// per device default and the quirks file.
+    #[structopt(long)]
+    pub controller_pose_time_offset_ms: Option<f32>,
+
+    /// Seconds between device thermal status queries on android, changes are sent to the server
+    /// so it can lower the bitrate before the SoC throttles. 0 disables it.
+    #[structopt(long, default_value = "5")]
+    pub thermal_poll_interval_s: u32,
+
+    /// Writes per frame video timings to this CSV file, rotated every 64MB. Relative paths are
+    /// relative to the config file directory on android.
+    #[structopt(long, parse(from_os_str))]
+    pub perf_log: Option<std::path::PathBuf>,
+
+    /// Sends crash reports saved on the device to the server after connecting.
+    #[structopt(long)]
+    pub upload_crash_reports: bool,
+
+    /// Base reference space, Stage or Local. Defaults to Stage unless the device quirks mark
+    /// it as broken, Local is the fallback.
+    #[structopt(long, parse(from_str))]
+    pub reference_space: Option<ReferenceSpace>,
+
+    /// Most verbose log level mirrored to the server while connected, Off disables forwarding.
+    #[serde(serialize_with = "config::serialize_debug")]
+    #[structopt(long, default_value = "Info")]
+    pub forward_log_level: Option<alvr_common::log::LevelFilter>,
+
+    /// Records the received video stream, before decoding, to Matroska files named
+    /// <path stem>_<unix ms>.mkv.
+    #[structopt(long, parse(from_os_str))]
+    pub record: Option<std::path::PathBuf>,
+
+    /// Seconds after which a recording moves on to a new file at the next keyframe, 0 records a
+    /// stream to a single file.
+    #[structopt(long, default_value = "300")]
+    pub record_segment_s: u32,
+
+    /// Stops advertising the tracking server over mDNS (_alxr-tracking._tcp).
+    #[structopt(/*short,*/ long)]
+    pub no_tracking_server_discovery: bool,
+
+    /// Recenters the forward direction when the menu button of either controller is held for
+    /// this many seconds, disabled by default.
+    #[structopt(long)]
+    pub recenter_hold_s: Option<f32>,
+
+    /// Stream transport, Udp, Tcp or Quic, overrides the streaming protocol of the server
+    /// settings. Quic needs client and server builds with the quic feature.
+    #[structopt(long, parse(from_str))]
+    pub transport: Option<StreamTransport>,
+
+    /// Disables delaying or trimming game audio to keep it in sync with the video (Android only).
+    #[structopt(/*short,*/ long)]
+    pub no_av_sync: bool,
+
+    /// Pins the share of FEC parity packets sent by the server, in percent of the video packets.
+    /// The server raises it on its own after repeated losses otherwise.
+    #[structopt(long)]
+    pub fec_percentage: Option<u16>,
+
+    /// Disables the bitrate recommendations sent to the server from the measured network queuing
+    /// delay and packet loss.
+    #[structopt(/*short,*/ long)]
+    pub no_bitrate_feedback: bool,
+
+    /// Serves a live preview of the stream over HTTP on the given TCP port, open
+    /// http://<headset ip>:<port>/ on a phone or browser to watch the headset view.
+    #[structopt(long)]
+    pub preview_port: Option<u16>,
+
+    /// Ignores URLs and text pushed from the server dashboard.
+    #[structopt(/*short,*/ long)]
+    pub no_push_content: bool,
+
+    /// Pre-shared key of the server's connection encryption, the server must use the same key.
+    #[structopt(long)]
+    pub encryption_key: Option<config::SecretString>,
+
+    /// Refuses to connect to servers without connection encryption.
+    #[structopt(/*short,*/ long)]
+    pub require_encryption: bool,
+
+    /// Only connects to servers paired with a code shown in the headset, the code is entered in
+    /// the server's dashboard the first time. Requires connection encryption.
+    #[structopt(/*short,*/ long)]
+    pub pairing: bool,
+
+    /// Name of the server from servers.toml (next to the config file) to connect to, skips the
+    /// in-headset server picker.
+    #[structopt(long)]
+    pub server_profile: Option<String>,
+
+    /// Doesn't offer the parameters of the last stream when reconnecting, the server always
+    /// derives new ones from the headset info.
+    #[structopt(/*short,*/ long)]
+    pub no_stream_resume: bool,
+
+    /// Keeps streaming on android while the headset is not worn, by default removing it stops the
+    /// connection and putting it back on reconnects.
+    #[structopt(/*short,*/ long)]
+    pub no_proximity_sleep: bool,
+
+    /// Seconds the wifi lock is kept after the headset is removed, so that putting it back on
+    /// shortly after reconnects at full speed.
+    #[structopt(long, default_value = "30")]
+    pub proximity_wifi_grace_s: u32,
+
+    /// Seconds between wifi signal, link speed and channel samples on android, they are sent to
+    /// the server statistics. 0 disables it.
+    #[structopt(long, default_value = "5")]
+    pub wifi_poll_interval_s: u32,
+
+    /// A warning is logged when the wifi link speed drops below this many Mbps. 0 disables it.
+    #[structopt(long, default_value = "200")]
+    pub wifi_min_link_speed_mbps: u32,
+
+    /// Places the spectator camera at the right controller when its grip and trigger are held
+    /// together for this many seconds, disabled by default.
+    #[structopt(long)]
+    pub spectator_camera_hold_s: Option<f32>,
+
+    /// Wifi lock held while streaming on android, LowLatency, HighPerf or Off. Defaults to
+    /// LowLatency from android 10, which also stops wifi scans, and HighPerf before.
+    #[structopt(long)]
+    pub wifi_lock_mode: Option<config::WifiLockMode>,
+
+    /// Turns hand tracking gestures (pinch, fist, palm up pinch) into controller inputs, mapped
+    /// in the `gestures` section of the binding profile.
+    #[structopt(/*short,*/ long)]
+    pub hand_gesture_buttons: bool,
+
+    /// Overrides the server's realtime decoder priority setting for the software decoder.
+    #[structopt(long)]
+    pub decoder_realtime_priority: Option<bool>,
+
+    /// Logs session start and end events as json lines, for fleet monitoring.
+    #[structopt(/*short,*/ long)]
+    pub session_events: bool,
+
+    /// URL the session start and end events are posted to as json, implies --session-events.
+    #[structopt(long)]
+    pub session_webhook: Option<config::SecretString>,
+
+    /// Locks the client down for unattended headsets: connects to the first server of servers.toml
+    /// without the picker, disables the companion and preview servers, and on android pins the app
+    /// and relaunches it after a crash.
+    #[structopt(/*short,*/ long)]
+    pub kiosk: bool,
+
+    /// Records the tracking sent to the server to a file, for --replay-tracking.
+    #[structopt(long, parse(from_os_str))]
+    pub record_tracking: Option<std::path::PathBuf>,
+
+    /// Sends the tracking recorded with --record-tracking, in a loop, instead of the headset's.
+    #[structopt(long, parse(from_os_str))]
+    pub replay_tracking: Option<std::path::PathBuf>,
+
+    /// Extra controller pose prediction in milliseconds, to cover the network latency on high
+    /// jitter links. Overrides the value set from the server dashboard.
+    #[structopt(long)]
+    pub prediction_ms: Option<f32>,
+
+    /// Forwards readings of auxiliary sensors, like heart rate wearables, to the server.
+    #[structopt(/*short,*/ long)]
+    pub aux_sensors: bool,
+
+    /// Pose offset of the left controller in its own frame, "x,y,z" in meters or
+    /// "x,y,z,qx,qy,qz,qw" with a rotation. Corrects a systematic controller misalignment.
+    #[structopt(long)]
+    pub left_controller_offset: Option<pose_offsets::PoseOffset>,
+
+    /// Pose offset of the right controller, see --left-controller-offset.
+    #[structopt(long)]
+    pub right_controller_offset: Option<pose_offsets::PoseOffset>,
+
+    /// Streams raw IMU samples to the server at up to this rate per sensor, for research use
+    /// (Android only). 0 disables it, the maximum is 1000.
+    #[structopt(long, default_value = "0")]
+    pub imu_stream_hz: u32,
+
+    /// Vibrates the controllers once this many minutes into the session, as a warning before
+    /// --session-limit-min. 0 disables it.
+    #[structopt(long, default_value = "0")]
+    pub session_warning_min: u32,
+
+    /// Stops streaming this many minutes after the session started, for demo stations and
+    /// parental control. The companion can reset it, 0 disables it.
+    #[structopt(long, default_value = "0")]
+    pub session_limit_min: u32,
+
+    /// Accessibility mode for one-handed players, Left or Right: the inputs and pose of that
+    /// controller are mirrored onto the other one. Holding its joystick and grip for 2s toggles it.
+    #[structopt(long)]
+    pub one_handed: Option<one_handed::Hand>,
+
+    /// Pose of the mirrored controller in the frame of the held one, "x,y,z" in meters or
+    /// "x,y,z,qx,qy,qz,qw". Defaults to 20cm to the side of the missing hand.
+    #[structopt(long)]
+    pub one_handed_offset: Option<pose_offsets::PoseOffset>,
+
+    /// Tracking bridge for devices used only as face/eye/body trackers: implies --headless, only
+    /// tracking is sent to the server and no video or audio is received, decoded or played.
+    /// Runs the connections on fewer threads.
+    #[structopt(/*short,*/ long)]
+    pub tracking_bridge: bool,
+
+    /// Keeps the session socket buffer sizes on every network interface, instead of the largest
+    /// buffers on wired and WiGig links.
+    #[structopt(/*short,*/ long)]
+    pub no_interface_tuning: bool,
+
+    /// For users with monocular vision, Left or Right: the view of that eye is shown on both
+    /// displays. The companion can change it while streaming.
+    #[structopt(long)]
+    pub monocular_eye: Option<monocular::Eye>,
+
+    /// Send buffer size of the stream socket (SO_SNDBUF) in bytes, overrides the server settings.
+    #[structopt(long)]
+    pub socket_send_buffer_bytes: Option<u32>,
+
+    /// Receive buffer size of the stream socket (SO_RCVBUF) in bytes, overrides the server
+    /// settings.
+    #[structopt(long)]
+    pub socket_recv_buffer_bytes: Option<u32>,
+
+    /// DSCP marking of the stream packets of the client and the server, Ef (default), Af41 or
+    /// BestEffort, for managed networks prioritizing traffic by class of service.
+    #[structopt(long)]
+    pub stream_dscp: Option<Dscp>,
+
+    /// Most latency in ms the video jitter buffer may add to smooth out bursty delivery, as on
+    /// Wi-Fi. The buffer adapts to the recent jitter within that bound, 0 decodes packets on
+    /// arrival.
+    #[structopt(long, default_value = "0")]
+    pub jitter_buffer_ms: u32,
+
+    /// Sleep timer, disconnects and exits the client this many minutes after it started. The
+    /// controllers vibrate a minute before, any controller input then restarts it. 0 disables it.
+    #[structopt(long, default_value = "0")]
+    pub sleep_timer_min: u32,
+
+    /// Disconnects and exits the client after this many minutes without controller input, with the
+    /// same warning as --sleep-timer-min. 0 disables it.
+    #[structopt(long, default_value = "0")]
+    pub sleep_inactivity_min: u32,
 }
 
 impl Options {
@@ -172,9 +598,80 @@ impl Options {
             simulate_headless: false,
             passthrough_mode: Some(ALXRPassthroughMode::None),
             no_visibility_masks: false,
+            quirks_file: None,
+            no_microphone: false,
+            mic_sample_rate: None,
+            mic_device: None,
+            companion_port: None,
+            companion_token: None,
+            companion_listen_all: false,
+            mono_stream: false,
+            no_haptics: false,
+            haptics_amplitude_scale: 1.0,
+            haptics_left_scale: None,
+            haptics_right_scale: None,
+            binding_profile: None,
+            no_tracking_state: false,
+            write_default_config: false,
+            velocity_frame: None,
+            video_reorder_window: 16,
+            server_address: None,
+            battery_poll_interval_s: 10,
+            controller_pose_time_offset_ms: None,
+            thermal_poll_interval_s: 5,
+            perf_log: None,
+            upload_crash_reports: false,
+            reference_space: None,
+            forward_log_level: Some(alvr_common::log::LevelFilter::Info),
+            record: None,
+            record_segment_s: 300,
+            no_tracking_server_discovery: false,
+            recenter_hold_s: None,
+            transport: None,
+            no_av_sync: false,
+            fec_percentage: None,
+            no_bitrate_feedback: false,
+            preview_port: None,
+            no_push_content: false,
+            encryption_key: None,
+            require_encryption: false,
+            pairing: false,
+            server_profile: None,
+            no_stream_resume: false,
+            no_proximity_sleep: false,
+            proximity_wifi_grace_s: 30,
+            wifi_poll_interval_s: 5,
+            wifi_min_link_speed_mbps: 200,
+            spectator_camera_hold_s: None,
+            wifi_lock_mode: None,
+            hand_gesture_buttons: false,
+            decoder_realtime_priority: None,
+            session_events: false,
+            session_webhook: None,
+            kiosk: false,
+            record_tracking: None,
+            replay_tracking: None,
+            prediction_ms: None,
+            aux_sensors: false,
+            left_controller_offset: None,
+            right_controller_offset: None,
+            imu_stream_hz: 0,
+            session_warning_min: 0,
+            session_limit_min: 0,
+            one_handed: None,
+            one_handed_offset: None,
+            tracking_bridge: false,
+            no_interface_tuning: false,
+            monocular_eye: None,
+            socket_send_buffer_bytes: None,
+            socket_recv_buffer_bytes: None,
+            stream_dscp: None,
+            jitter_buffer_ms: 0,
+            sleep_timer_min: 0,
+            sleep_inactivity_min: 0,
         };
 
-        let sys_properties = AndroidSystemProperties::new();
+        let sys_properties = config::PropertyLookup::new();
 
         let property_name = "debug.alxr.graphicsPlugin";
         if let Some(value) = sys_properties.get(&property_name) {
@@ -341,107 +838,954 @@ impl Options {
             );
         }
 
-        new_options
-    }
-}
+        let property_name = "debug.alxr.quirks_file";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.quirks_file = Some(std::path::PathBuf::from(value.as_str()));
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.quirks_file
+            );
+        }
 
-#[cfg(target_vendor = "uwp")]
-impl Options {
-    pub fn from_system_properties() -> Self {
-        let new_options = Options {
-            localhost: false,
-            verbose: cfg!(debug_assertions),
-            graphics_api: Some(ALXRGraphicsApi::D3D12),
-            decoder_type: Some(ALXRDecoderType::D311VA),
-            color_space: Some(ALXRColorSpace::Default),
-            decoder_thread_count: 0,
-            no_linearize_srgb: false,
-            no_alvr_server: false,
-            no_bindings: false,
-            no_server_framerate_lock: false,
-            no_frameskip: false,
-            disable_localdimming: false,
-            headless_session: false,
-            no_tracking_server: false,
-            no_passthrough: false,
-            no_hand_tracking: false,
-            face_tracking_data_sources: Some(vec![ALXRFaceTrackingDataSource::VisualSource]),
-            facial_tracking: Some(ALXRFacialExpressionType::Auto),
-            eye_tracking: Some(ALXREyeTrackingType::Auto),
-            tracking_server_port_no: ALXR_TRACKING_SERVER_PORT_NO,
-            simulate_headless: false,
-            passthrough_mode: Some(ALXRPassthroughMode::None),
-            no_visibility_masks: false,
-        };
-        new_options
-    }
-}
+        let property_name = "debug.alxr.no_microphone";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.no_microphone =
+                std::str::FromStr::from_str(value.as_str()).unwrap_or(new_options.no_microphone);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.no_microphone
+            );
+        }
 
-lazy_static! {
-    pub static ref RUNTIME: Mutex<Option<Runtime>> = Mutex::new(None);
-    static ref IDR_REQUEST_NOTIFIER: Notify = Notify::new();
-    static ref IDR_PARSED: AtomicBool = AtomicBool::new(false);
-    static ref INPUT_SENDER: Mutex<Option<mpsc::UnboundedSender<Input>>> = Mutex::new(None);
-    static ref VIEWS_CONFIG_SENDER: Mutex<Option<mpsc::UnboundedSender<ViewsConfig>>> =
-        Mutex::new(None);
-    static ref BATTERY_SENDER: Mutex<Option<mpsc::UnboundedSender<BatteryPacket>>> =
-        Mutex::new(None);
-    static ref TIME_SYNC_SENDER: Mutex<Option<mpsc::UnboundedSender<TimeSyncPacket>>> =
-        Mutex::new(None);
-    static ref VIDEO_ERROR_REPORT_SENDER: Mutex<Option<mpsc::UnboundedSender<()>>> =
-        Mutex::new(None);
-    pub static ref ON_PAUSE_NOTIFIER: Notify = Notify::new();
-}
+        let property_name = "debug.alxr.mic_sample_rate";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.mic_sample_rate = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.mic_sample_rate);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.mic_sample_rate
+            );
+        }
 
-#[cfg(all(not(target_os = "android"), not(target_vendor = "uwp")))]
-lazy_static! {
-    pub static ref APP_CONFIG: Options = Options::from_args();
-}
+        let property_name = "debug.alxr.companion_port";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.companion_port = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.companion_port);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.companion_port
+            );
+        }
 
-#[cfg(any(target_os = "android", target_vendor = "uwp"))]
-lazy_static! {
-    pub static ref APP_CONFIG: Options = Options::from_system_properties();
-}
+        let property_name = "debug.alxr.companion_token";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.companion_token = Some(config::SecretString(value.clone()));
+            // the token itself is not logged.
+            println!("ALXR System Property: {property_name} set");
+        }
 
-pub fn init_connections(sys_properties: &ALXRSystemProperties) {
-    alvr_common::show_err(|| -> StrResult {
-        println!("Init-connections started.");
+        let property_name = "debug.alxr.companion_listen_all";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.companion_listen_all = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.companion_listen_all);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.companion_listen_all
+            );
+        }
 
-        let device_name = sys_properties.system_name();
-        let available_refresh_rates = unsafe {
-            slice::from_raw_parts(
-                sys_properties.refreshRates,
-                sys_properties.refreshRatesCount as _,
-            )
-            .to_vec()
-        };
-        let preferred_refresh_rate = available_refresh_rates.last().cloned().unwrap_or(60_f32); //90.0;
+        let property_name = "debug.alxr.mono_stream";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.mono_stream =
+                std::str::FromStr::from_str(value.as_str()).unwrap_or(new_options.mono_stream);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.mono_stream
+            );
+        }
 
-        let headset_info = HeadsetInfoPacket {
-            recommended_eye_width: sys_properties.recommendedEyeWidth as _,
-            recommended_eye_height: sys_properties.recommendedEyeHeight as _,
-            available_refresh_rates,
-            preferred_refresh_rate,
-            reserved: format!("{}", *ALVR_VERSION),
-        };
+        let property_name = "debug.alxr.no_haptics";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.no_haptics =
+                std::str::FromStr::from_str(value.as_str()).unwrap_or(new_options.no_haptics);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.no_haptics
+            );
+        }
 
-        println!(
-            "recommended eye width: {0}, height: {1}",
-            headset_info.recommended_eye_width, headset_info.recommended_eye_height
-        );
+        let property_name = "debug.alxr.haptics_amplitude_scale";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.haptics_amplitude_scale = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.haptics_amplitude_scale);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.haptics_amplitude_scale
+            );
+        }
 
-        let ip_addr = if APP_CONFIG.localhost {
-            std::net::Ipv4Addr::LOCALHOST.to_string()
-        } else {
-            local_ipaddress::get().unwrap_or(alvr_sockets::LOCAL_IP.to_string())
-        };
-        let private_identity = alvr_sockets::create_identity(Some(ip_addr)).unwrap();
+        let property_name = "debug.alxr.haptics_left_scale";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.haptics_left_scale = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.haptics_left_scale);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.haptics_left_scale
+            );
+        }
 
-        let runtime = trace_err!(Runtime::new())?;
+        let property_name = "debug.alxr.haptics_right_scale";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.haptics_right_scale = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.haptics_right_scale);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.haptics_right_scale
+            );
+        }
+
+        let property_name = "debug.alxr.binding_profile";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.binding_profile = Some(std::path::PathBuf::from(value.as_str()));
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.binding_profile
+            );
+        }
+
+        let property_name = "debug.alxr.no_tracking_state";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.no_tracking_state = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.no_tracking_state);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.no_tracking_state
+            );
+        }
+
+        let property_name = "debug.alxr.velocity_frame";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.velocity_frame = Some(From::from(value.as_str()));
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.velocity_frame
+            );
+        }
+
+        let property_name = "debug.alxr.video_reorder_window";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.video_reorder_window = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.video_reorder_window);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.video_reorder_window
+            );
+        }
+
+        let property_name = "debug.alxr.server_address";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.server_address = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.server_address);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.server_address
+            );
+        }
+
+        let property_name = "debug.alxr.battery_poll_interval_s";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.battery_poll_interval_s = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.battery_poll_interval_s);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.battery_poll_interval_s
+            );
+        }
+
+        let property_name = "debug.alxr.controller_pose_time_offset_ms";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.controller_pose_time_offset_ms =
+                std::str::FromStr::from_str(value.as_str())
+                    .ok()
+                    .or(new_options.controller_pose_time_offset_ms);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.controller_pose_time_offset_ms
+            );
+        }
+
+        let property_name = "debug.alxr.thermal_poll_interval_s";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.thermal_poll_interval_s = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.thermal_poll_interval_s);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.thermal_poll_interval_s
+            );
+        }
+
+        let property_name = "debug.alxr.perf_log";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.perf_log = Some(From::from(value.as_str()));
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.perf_log
+            );
+        }
+
+        let property_name = "debug.alxr.upload_crash_reports";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.upload_crash_reports = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.upload_crash_reports);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.upload_crash_reports
+            );
+        }
+
+        let property_name = "debug.alxr.reference_space";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.reference_space = Some(From::from(value.as_str()));
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.reference_space
+            );
+        }
+
+        let property_name = "debug.alxr.forward_log_level";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.forward_log_level = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.forward_log_level);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.forward_log_level
+            );
+        }
+
+        let property_name = "debug.alxr.record";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.record = Some(From::from(value.as_str()));
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.record
+            );
+        }
+
+        let property_name = "debug.alxr.record_segment_s";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.record_segment_s =
+                std::str::FromStr::from_str(value.as_str()).unwrap_or(new_options.record_segment_s);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.record_segment_s
+            );
+        }
+
+        let property_name = "debug.alxr.no_tracking_server_discovery";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.no_tracking_server_discovery = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.no_tracking_server_discovery);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.no_tracking_server_discovery
+            );
+        }
+
+        let property_name = "debug.alxr.recenter_hold_s";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.recenter_hold_s = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.recenter_hold_s);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.recenter_hold_s
+            );
+        }
+
+        let property_name = "debug.alxr.transport";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.transport = Some(From::from(value.as_str()));
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.transport
+            );
+        }
+
+        let property_name = "debug.alxr.no_av_sync";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.no_av_sync =
+                std::str::FromStr::from_str(value.as_str()).unwrap_or(new_options.no_av_sync);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.no_av_sync
+            );
+        }
+
+        let property_name = "debug.alxr.fec_percentage";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.fec_percentage = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.fec_percentage);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.fec_percentage
+            );
+        }
+
+        let property_name = "debug.alxr.no_bitrate_feedback";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.no_bitrate_feedback = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.no_bitrate_feedback);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.no_bitrate_feedback
+            );
+        }
+
+        let property_name = "debug.alxr.preview_port";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.preview_port = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.preview_port);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.preview_port
+            );
+        }
+
+        let property_name = "debug.alxr.no_push_content";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.no_push_content =
+                std::str::FromStr::from_str(value.as_str()).unwrap_or(new_options.no_push_content);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.no_push_content
+            );
+        }
+
+        let property_name = "debug.alxr.encryption_key";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.encryption_key = Some(config::SecretString(value.clone()));
+            // the key itself is not logged.
+            println!("ALXR System Property: {property_name} set");
+        }
+
+        let property_name = "debug.alxr.require_encryption";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.require_encryption = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.require_encryption);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.require_encryption
+            );
+        }
+
+        let property_name = "debug.alxr.pairing";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.pairing =
+                std::str::FromStr::from_str(value.as_str()).unwrap_or(new_options.pairing);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.pairing
+            );
+        }
+
+        let property_name = "debug.alxr.server_profile";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.server_profile = Some(value.clone());
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.server_profile
+            );
+        }
+
+        let property_name = "debug.alxr.no_stream_resume";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.no_stream_resume =
+                std::str::FromStr::from_str(value.as_str()).unwrap_or(new_options.no_stream_resume);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.no_stream_resume
+            );
+        }
+
+        let property_name = "debug.alxr.no_proximity_sleep";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.no_proximity_sleep = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.no_proximity_sleep);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.no_proximity_sleep
+            );
+        }
+
+        let property_name = "debug.alxr.proximity_wifi_grace_s";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.proximity_wifi_grace_s = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.proximity_wifi_grace_s);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.proximity_wifi_grace_s
+            );
+        }
+
+        let property_name = "debug.alxr.wifi_poll_interval_s";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.wifi_poll_interval_s = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.wifi_poll_interval_s);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.wifi_poll_interval_s
+            );
+        }
+
+        let property_name = "debug.alxr.wifi_min_link_speed_mbps";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.wifi_min_link_speed_mbps = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.wifi_min_link_speed_mbps);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.wifi_min_link_speed_mbps
+            );
+        }
+
+        let property_name = "debug.alxr.spectator_camera_hold_s";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.spectator_camera_hold_s = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.spectator_camera_hold_s);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.spectator_camera_hold_s
+            );
+        }
+
+        let property_name = "debug.alxr.wifi_lock_mode";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.wifi_lock_mode = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.wifi_lock_mode);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.wifi_lock_mode
+            );
+        }
+
+        let property_name = "debug.alxr.hand_gesture_buttons";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.hand_gesture_buttons = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.hand_gesture_buttons);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.hand_gesture_buttons
+            );
+        }
+
+        let property_name = "debug.alxr.decoder_realtime_priority";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.decoder_realtime_priority = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.decoder_realtime_priority);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.decoder_realtime_priority
+            );
+        }
+
+        let property_name = "debug.alxr.session_events";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.session_events =
+                std::str::FromStr::from_str(value.as_str()).unwrap_or(new_options.session_events);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.session_events
+            );
+        }
+
+        let property_name = "debug.alxr.session_webhook";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.session_webhook = Some(config::SecretString(value.clone()));
+            // the url can carry a token, it is not logged.
+            println!("ALXR System Property: {property_name} set");
+        }
+
+        let property_name = "debug.alxr.kiosk";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.kiosk =
+                std::str::FromStr::from_str(value.as_str()).unwrap_or(new_options.kiosk);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.kiosk
+            );
+        }
+
+        let property_name = "debug.alxr.record_tracking";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.record_tracking = Some(From::from(value.as_str()));
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.record_tracking
+            );
+        }
+
+        let property_name = "debug.alxr.replay_tracking";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.replay_tracking = Some(From::from(value.as_str()));
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.replay_tracking
+            );
+        }
+
+        let property_name = "debug.alxr.prediction_ms";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.prediction_ms = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.prediction_ms);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.prediction_ms
+            );
+        }
+
+        let property_name = "debug.alxr.aux_sensors";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.aux_sensors =
+                std::str::FromStr::from_str(value.as_str()).unwrap_or(new_options.aux_sensors);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.aux_sensors
+            );
+        }
+
+        let property_name = "debug.alxr.left_controller_offset";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.left_controller_offset = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.left_controller_offset);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.left_controller_offset
+            );
+        }
+
+        let property_name = "debug.alxr.right_controller_offset";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.right_controller_offset = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.right_controller_offset);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.right_controller_offset
+            );
+        }
+
+        let property_name = "debug.alxr.imu_stream_hz";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.imu_stream_hz =
+                std::str::FromStr::from_str(value.as_str()).unwrap_or(new_options.imu_stream_hz);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.imu_stream_hz
+            );
+        }
+
+        let property_name = "debug.alxr.session_warning_min";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.session_warning_min = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.session_warning_min);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.session_warning_min
+            );
+        }
+
+        let property_name = "debug.alxr.session_limit_min";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.session_limit_min = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.session_limit_min);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.session_limit_min
+            );
+        }
+
+        let property_name = "debug.alxr.one_handed";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.one_handed = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.one_handed);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.one_handed
+            );
+        }
+
+        let property_name = "debug.alxr.one_handed_offset";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.one_handed_offset = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.one_handed_offset);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.one_handed_offset
+            );
+        }
+
+        let property_name = "debug.alxr.tracking_bridge";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.tracking_bridge =
+                std::str::FromStr::from_str(value.as_str()).unwrap_or(new_options.tracking_bridge);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.tracking_bridge
+            );
+        }
+
+        let property_name = "debug.alxr.no_interface_tuning";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.no_interface_tuning = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.no_interface_tuning);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.no_interface_tuning
+            );
+        }
+
+        let property_name = "debug.alxr.monocular_eye";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.monocular_eye = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.monocular_eye);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.monocular_eye
+            );
+        }
+
+        let property_name = "debug.alxr.socket_send_buffer_bytes";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.socket_send_buffer_bytes = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.socket_send_buffer_bytes);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.socket_send_buffer_bytes
+            );
+        }
+
+        let property_name = "debug.alxr.socket_recv_buffer_bytes";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.socket_recv_buffer_bytes = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.socket_recv_buffer_bytes);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.socket_recv_buffer_bytes
+            );
+        }
+
+        let property_name = "debug.alxr.stream_dscp";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.stream_dscp = std::str::FromStr::from_str(value.as_str())
+                .ok()
+                .or(new_options.stream_dscp);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {:?}",
+                new_options.stream_dscp
+            );
+        }
+
+        let property_name = "debug.alxr.jitter_buffer_ms";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.jitter_buffer_ms =
+                std::str::FromStr::from_str(value.as_str()).unwrap_or(new_options.jitter_buffer_ms);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.jitter_buffer_ms
+            );
+        }
+
+        let property_name = "debug.alxr.sleep_timer_min";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.sleep_timer_min =
+                std::str::FromStr::from_str(value.as_str()).unwrap_or(new_options.sleep_timer_min);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.sleep_timer_min
+            );
+        }
+
+        let property_name = "debug.alxr.sleep_inactivity_min";
+        if let Some(value) = sys_properties.get(&property_name) {
+            new_options.sleep_inactivity_min = std::str::FromStr::from_str(value.as_str())
+                .unwrap_or(new_options.sleep_inactivity_min);
+            println!(
+                "ALXR System Property: {property_name}, input: {value}, parsed-result: {}",
+                new_options.sleep_inactivity_min
+            );
+        }
+
+        new_options
+    }
+}
+
+#[cfg(target_vendor = "uwp")]
+impl Options {
+    pub fn from_system_properties() -> Self {
+        let new_options = Options {
+            localhost: false,
+            verbose: cfg!(debug_assertions),
+            graphics_api: Some(ALXRGraphicsApi::D3D12),
+            decoder_type: Some(ALXRDecoderType::D311VA),
+            color_space: Some(ALXRColorSpace::Default),
+            decoder_thread_count: 0,
+            no_linearize_srgb: false,
+            no_alvr_server: false,
+            no_bindings: false,
+            no_server_framerate_lock: false,
+            no_frameskip: false,
+            disable_localdimming: false,
+            headless_session: false,
+            no_tracking_server: false,
+            no_passthrough: false,
+            no_hand_tracking: false,
+            face_tracking_data_sources: Some(vec![ALXRFaceTrackingDataSource::VisualSource]),
+            facial_tracking: Some(ALXRFacialExpressionType::Auto),
+            eye_tracking: Some(ALXREyeTrackingType::Auto),
+            tracking_server_port_no: ALXR_TRACKING_SERVER_PORT_NO,
+            simulate_headless: false,
+            passthrough_mode: Some(ALXRPassthroughMode::None),
+            no_visibility_masks: false,
+            quirks_file: None,
+            no_microphone: false,
+            mic_sample_rate: None,
+            mic_device: None,
+            companion_port: None,
+            companion_token: None,
+            companion_listen_all: false,
+            mono_stream: false,
+            no_haptics: false,
+            haptics_amplitude_scale: 1.0,
+            haptics_left_scale: None,
+            haptics_right_scale: None,
+            binding_profile: None,
+            no_tracking_state: false,
+            write_default_config: false,
+            velocity_frame: None,
+            video_reorder_window: 16,
+            server_address: None,
+            battery_poll_interval_s: 10,
+            controller_pose_time_offset_ms: None,
+            thermal_poll_interval_s: 5,
+            perf_log: None,
+            upload_crash_reports: false,
+            reference_space: None,
+            forward_log_level: Some(alvr_common::log::LevelFilter::Info),
+            record: None,
+            record_segment_s: 300,
+            no_tracking_server_discovery: false,
+            recenter_hold_s: None,
+            transport: None,
+            no_av_sync: false,
+            fec_percentage: None,
+            no_bitrate_feedback: false,
+            preview_port: None,
+            no_push_content: false,
+            encryption_key: None,
+            require_encryption: false,
+            pairing: false,
+            server_profile: None,
+            no_stream_resume: false,
+            no_proximity_sleep: false,
+            proximity_wifi_grace_s: 30,
+            wifi_poll_interval_s: 5,
+            wifi_min_link_speed_mbps: 200,
+            spectator_camera_hold_s: None,
+            wifi_lock_mode: None,
+            hand_gesture_buttons: false,
+            decoder_realtime_priority: None,
+            session_events: false,
+            session_webhook: None,
+            kiosk: false,
+            record_tracking: None,
+            replay_tracking: None,
+            prediction_ms: None,
+            aux_sensors: false,
+            left_controller_offset: None,
+            right_controller_offset: None,
+            imu_stream_hz: 0,
+            session_warning_min: 0,
+            session_limit_min: 0,
+            one_handed: None,
+            one_handed_offset: None,
+            tracking_bridge: false,
+            no_interface_tuning: false,
+            monocular_eye: None,
+            socket_send_buffer_bytes: None,
+            socket_recv_buffer_bytes: None,
+            stream_dscp: None,
+            jitter_buffer_ms: 0,
+            sleep_timer_min: 0,
+            sleep_inactivity_min: 0,
+        };
+        new_options
+    }
+}
+
+lazy_static! {
+    pub static ref RUNTIME: Mutex<Option<Runtime>> = Mutex::new(None);
+    static ref IDR_REQUEST_NOTIFIER: Notify = Notify::new();
+    static ref IDR_PARSED: AtomicBool = AtomicBool::new(false);
+    static ref INPUT_SENDER: Mutex<Option<mpsc::UnboundedSender<Input>>> = Mutex::new(None);
+    static ref VIEWS_CONFIG_SENDER: Mutex<Option<mpsc::UnboundedSender<ViewsConfig>>> =
+        Mutex::new(None);
+    static ref BATTERY_SENDER: Mutex<Option<mpsc::UnboundedSender<BatteryPacket>>> =
+        Mutex::new(None);
+    static ref THERMAL_SENDER: Mutex<Option<mpsc::UnboundedSender<ThermalStatsPacket>>> =
+        Mutex::new(None);
+    static ref WIFI_STATS_SENDER: Mutex<Option<mpsc::UnboundedSender<WifiStatsPacket>>> =
+        Mutex::new(None);
+    static ref AUDIO_SYNC_SENDER: Mutex<Option<mpsc::UnboundedSender<AudioSyncPacket>>> =
+        Mutex::new(None);
+    static ref TIME_SYNC_SENDER: Mutex<Option<mpsc::UnboundedSender<TimeSyncPacket>>> =
+        Mutex::new(None);
+    static ref VIDEO_ERROR_REPORT_SENDER: Mutex<Option<mpsc::UnboundedSender<()>>> =
+        Mutex::new(None);
+    pub static ref ON_PAUSE_NOTIFIER: Notify = Notify::new();
+    static ref PREPARED_CONNECTION: Mutex<Option<JoinHandle<StrResult<(PrivateIdentity, Runtime)>>>> =
+        Mutex::new(None);
+}
+
+#[cfg(all(not(target_os = "android"), not(target_vendor = "uwp")))]
+lazy_static! {
+    pub static ref APP_CONFIG: Options = Options::from_args_and_config_file();
+}
+
+#[cfg(any(target_os = "android", target_vendor = "uwp"))]
+lazy_static! {
+    pub static ref APP_CONFIG: Options = Options::from_system_properties();
+}
+
+// The identity certificate and the async runtime don't depend on the engine.
+const TRACKING_BRIDGE_WORKER_THREADS: usize = 2;
+
+fn prepare_connection() -> StrResult<(PrivateIdentity, Runtime)> {
+    let ip_addr = if APP_CONFIG.localhost {
+        std::net::Ipv4Addr::LOCALHOST.to_string()
+    } else {
+        local_ipaddress::get().unwrap_or(alvr_sockets::LOCAL_IP.to_string())
+    };
+    let private_identity = alvr_sockets::create_identity(Some(ip_addr))?;
+    let runtime = if APP_CONFIG.tracking_bridge {
+        // only tracking goes through the connections, a couple of threads keep up with it.
+        trace_err!(runtime::Builder::new_multi_thread()
+            .worker_threads(TRACKING_BRIDGE_WORKER_THREADS)
+            .enable_all()
+            .build())?
+    } else {
+        trace_err!(Runtime::new())?
+    };
+    Ok((private_identity, runtime))
+}
+
+// Starts preparing the connection on a separate thread so it overlaps engine initialization,
+// the next `init_connections` call picks it up.
+pub fn prepare_connections() {
+    let mut prepared = PREPARED_CONNECTION.lock();
+    if prepared.is_none() {
+        *prepared = Some(thread::spawn(prepare_connection));
+    }
+}
+
+pub fn init_connections(sys_properties: &ALXRSystemProperties) {
+    alvr_common::show_err(|| -> StrResult {
+        println!("Init-connections started.");
+
+        let device_name = sys_properties.system_name();
+        let available_refresh_rates = unsafe {
+            slice::from_raw_parts(
+                sys_properties.refreshRates,
+                sys_properties.refreshRatesCount as _,
+            )
+            .to_vec()
+        };
+        let preferred_refresh_rate = available_refresh_rates.last().cloned().unwrap_or(60_f32); //90.0;
+
+        let headset_info = HeadsetInfoPacket {
+            recommended_eye_width: sys_properties.recommendedEyeWidth as _,
+            recommended_eye_height: sys_properties.recommendedEyeHeight as _,
+            available_refresh_rates,
+            preferred_refresh_rate,
+            reserved: format!("{}", *ALVR_VERSION),
+            resume: None,
+        };
+
+        println!(
+            "recommended eye width: {0}, height: {1}",
+            headset_info.recommended_eye_width, headset_info.recommended_eye_height
+        );
+        bug_report::set_headset_info(format!(
+            "{device_name}, eye resolution {}x{}, refresh rates {:?}",
+            headset_info.recommended_eye_width,
+            headset_info.recommended_eye_height,
+            headset_info.available_refresh_rates
+        ));
+
+        let prepared = PREPARED_CONNECTION
+            .lock()
+            .take()
+            .and_then(|handle| handle.join().ok());
+        let (private_identity, runtime) = prepared.unwrap_or_else(prepare_connection)?;
+
+        // kiosks can't be reconfigured or watched remotely.
+        if APP_CONFIG.kiosk
+            && (APP_CONFIG.companion_port.is_some() || APP_CONFIG.preview_port.is_some())
+        {
+            info!("Kiosk mode, the companion and preview servers are disabled");
+        }
+        if let Some(port) = APP_CONFIG.companion_port.filter(|_| !APP_CONFIG.kiosk) {
+            let info = companion::CompanionInfo {
+                device_name: device_name.clone(),
+                hostname: private_identity.hostname.clone(),
+            };
+            runtime.spawn(alvr_common::show_err_async(
+                companion::companion_server_loop(port, info),
+            ));
+        }
+        if let Some(port) = APP_CONFIG.preview_port.filter(|_| !APP_CONFIG.kiosk) {
+            runtime.spawn(alvr_common::show_err_async(preview::preview_server_loop(
+                port,
+            )));
+        }
+
+        runtime.spawn(session_analytics::session_analytics_loop(
+            device_name.clone(),
+            private_identity.hostname.clone(),
+        ));
+        runtime.spawn(sleep_timer::sleep_timer_loop());
 
         runtime.spawn(async move {
-            let connection_loop =
-                connection::connection_lifecycle_loop(headset_info, &device_name, private_identity);
+            let connection_loop = async {
+                // a reached session limit holds the connections back until it is reset.
+                loop {
+                    session_limit::wait_for_session_reset().await;
+                    tokio::select! {
+                        _ = connection::connection_lifecycle_loop(
+                            &headset_info,
+                            &device_name,
+                            &private_identity,
+                        ) => (),
+                        _ = session_limit::session_limit_loop() => (),
+                    };
+                }
+            };
             tokio::select! {
                 _ = connection_loop => (),
                 _ = ON_PAUSE_NOTIFIER.notified() => ()
@@ -450,19 +1794,87 @@ pub fn init_connections(sys_properties: &ALXRSystemProperties) {
 
         *RUNTIME.lock() = Some(runtime);
 
+        mark_startup_stage("connections_started");
         println!("Init-connections Finished");
 
         Ok(())
     }());
 }
 
+// Dropping the runtime waits for every blocking task, which could hang the app.
+const RUNTIME_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+fn is_held<T>(sender: &Mutex<Option<mpsc::UnboundedSender<T>>>) -> bool {
+    sender
+        .lock()
+        .as_ref()
+        .map_or(false, |sender| !sender.is_closed())
+}
+
+// What the connections still hold once the runtime is gone, an open sender means the task
+// receiving from it is still alive.
+fn connection_leaks() -> Vec<&'static str> {
+    [
+        ("runtime", RUNTIME.lock().is_some()),
+        (
+            "tracking server advertisement",
+            tracking_server_discovery::is_advertising(),
+        ),
+        ("input", is_held(&INPUT_SENDER)),
+        ("views config", is_held(&VIEWS_CONFIG_SENDER)),
+        ("battery", is_held(&BATTERY_SENDER)),
+        ("thermal", is_held(&THERMAL_SENDER)),
+        ("wifi stats", is_held(&WIFI_STATS_SENDER)),
+        (
+            "spectator camera",
+            is_held(&spectator_camera::SPECTATOR_CAMERA_SENDER),
+        ),
+        ("audio sync", is_held(&AUDIO_SYNC_SENDER)),
+        ("aux sensors", is_held(&aux_sensors::AUX_SENSOR_SENDER)),
+        ("imu", is_held(&imu_stream::IMU_SENDER)),
+        ("time sync", is_held(&TIME_SYNC_SENDER)),
+        ("video error report", is_held(&VIDEO_ERROR_REPORT_SENDER)),
+        ("log forwarding", is_held(&log_forwarding::LOG_SENDER)),
+        (
+            "tracking state",
+            is_held(&tracking_state::TRACKING_STATE_SENDER),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(name, held)| held.then_some(name))
+    .collect()
+}
+
 pub fn shutdown() {
+    let mut teardown = Teardown::new("connections");
+    teardown.stage(
+        "session analytics",
+        DEFAULT_STAGE_TIMEOUT,
+        session_analytics::end_session_on_pause,
+    );
+    teardown.stage(
+        "tracking server advertisement",
+        DEFAULT_STAGE_TIMEOUT,
+        tracking_server_discovery::stop_tracking_server_advertisement,
+    );
     ON_PAUSE_NOTIFIER.notify_waiters();
-    drop(RUNTIME.lock().take());
+    let runtime = RUNTIME.lock().take();
+    teardown.stage(
+        "runtime",
+        RUNTIME_SHUTDOWN_TIMEOUT + DEFAULT_STAGE_TIMEOUT,
+        move || {
+            if let Some(runtime) = runtime {
+                runtime.shutdown_timeout(RUNTIME_SHUTDOWN_TIMEOUT);
+            }
+        },
+    );
+    teardown.finish(&connection_leaks());
 }
 
 pub unsafe extern "C" fn path_string_to_hash(path: *const ::std::os::raw::c_char) -> u64 {
-    alvr_common::hash_string(CStr::from_ptr(path).to_str().unwrap())
+    guard_callback("path_string_to_hash", || {
+        alvr_common::hash_string(CStr::from_ptr(path).to_str().unwrap())
+    })
 }
 
 pub extern "C" fn input_send(data_ptr: *const TrackingInfo) {
@@ -476,131 +1888,166 @@ pub extern "C" fn input_send(data_ptr: *const TrackingInfo) {
         Vec3::new(vec.x, vec.y, vec.z)
     }
 
+    // runtimes may report NaN velocities for devices that are not tracked.
+    #[inline(always)]
+    fn from_tracking_velocity(vec: &TrackingVector3) -> Option<Vec3> {
+        Some(from_tracking_vector3(vec)).filter(|v| v.is_finite())
+    }
+
     #[inline(always)]
     fn from_tracking_vector2(vec: &TrackingVector2) -> Vec2 {
         Vec2::new(vec.x, vec.y)
     }
 
-    let data: &TrackingInfo = unsafe { &*data_ptr };
-    let input = Input {
-        target_timestamp: std::time::Duration::from_nanos(data.targetTimestampNs),
-        device_motions: vec![
-            (
-                *HEAD_ID,
-                MotionData {
-                    orientation: from_tracking_quat(&data.headPose.orientation),
-                    position: from_tracking_vector3(&data.headPose.position),
-                    linear_velocity: None,
-                    angular_velocity: None,
-                },
-            ),
-            (
-                *LEFT_HAND_ID,
-                MotionData {
-                    orientation: from_tracking_quat(if data.controller[0].isHand {
-                        &data.controller[0].boneRootPose.orientation
-                    } else {
-                        &data.controller[0].pose.orientation
-                    }),
-                    position: from_tracking_vector3(if data.controller[0].isHand {
-                        &data.controller[0].boneRootPose.position
-                    } else {
-                        &data.controller[0].pose.position
-                    }),
-                    linear_velocity: Some(from_tracking_vector3(
-                        &data.controller[0].linearVelocity,
-                    )),
-                    angular_velocity: Some(from_tracking_vector3(
-                        &data.controller[0].angularVelocity,
-                    )),
-                },
-            ),
-            (
-                *RIGHT_HAND_ID,
-                MotionData {
-                    orientation: from_tracking_quat(if data.controller[1].isHand {
-                        &data.controller[1].boneRootPose.orientation
-                    } else {
-                        &data.controller[1].pose.orientation
-                    }),
-                    position: from_tracking_vector3(if data.controller[1].isHand {
-                        &data.controller[1].boneRootPose.position
-                    } else {
-                        &data.controller[1].pose.position
-                    }),
-                    linear_velocity: Some(from_tracking_vector3(
-                        &data.controller[1].linearVelocity,
-                    )),
-                    angular_velocity: Some(from_tracking_vector3(
-                        &data.controller[1].angularVelocity,
-                    )),
-                },
-            ),
-        ],
-        // left_hand_tracking: None,
-        // right_hand_tracking: None,
-        // button_values: std::collections::HashMap::new(), // unused for now
-        legacy: LegacyInput {
-            mounted: data.mounted,
-            controllers: [
-                LegacyController {
-                    enabled: data.controller[0].enabled,
-                    is_hand: data.controller[0].isHand,
-                    buttons: data.controller[0].buttons,
-                    joystick_position: from_tracking_vector2(&data.controller[0].joystickPosition),
-                    trackpad_position: from_tracking_vector2(&data.controller[0].trackpadPosition),
-                    trigger_value: data.controller[0].triggerValue,
-                    grip_value: data.controller[0].gripValue,
-                    bone_rotations: {
-                        let bone_rotations = &data.controller[0].boneRotations;
-                        let mut array = [Quat::IDENTITY; 19];
-                        for i in 0..array.len() {
-                            array[i] = from_tracking_quat(&bone_rotations[i]);
-                        }
-                        array
-                    },
-                    bone_positions_base: {
-                        let bone_positions = &data.controller[0].bonePositionsBase;
-                        let mut array = [Vec3::ZERO; 19];
-                        for i in 0..array.len() {
-                            array[i] = from_tracking_vector3(&bone_positions[i]);
-                        }
-                        array
+    guard_callback("input_send", || {
+        let data: &TrackingInfo = unsafe { &*data_ptr };
+        let mut input = Input {
+            target_timestamp: std::time::Duration::from_nanos(data.targetTimestampNs),
+            device_motions: vec![
+                (
+                    *HEAD_ID,
+                    MotionData {
+                        orientation: from_tracking_quat(&data.headPose.orientation),
+                        position: from_tracking_vector3(&data.headPose.position),
+                        linear_velocity: None,
+                        angular_velocity: None,
                     },
-                    hand_finger_confience: data.controller[0].handFingerConfidences,
-                },
-                LegacyController {
-                    enabled: data.controller[1].enabled,
-                    is_hand: data.controller[1].isHand,
-                    buttons: data.controller[1].buttons,
-                    joystick_position: from_tracking_vector2(&data.controller[1].joystickPosition),
-                    trackpad_position: from_tracking_vector2(&data.controller[1].trackpadPosition),
-                    trigger_value: data.controller[1].triggerValue,
-                    grip_value: data.controller[1].gripValue,
-                    bone_rotations: {
-                        let bone_rotations = &data.controller[1].boneRotations;
-                        let mut array = [Quat::IDENTITY; 19];
-                        for i in 0..array.len() {
-                            array[i] = from_tracking_quat(&bone_rotations[i]);
-                        }
-                        array
+                ),
+                (
+                    *LEFT_HAND_ID,
+                    MotionData {
+                        orientation: from_tracking_quat(if data.controller[0].isHand {
+                            &data.controller[0].boneRootPose.orientation
+                        } else {
+                            &data.controller[0].pose.orientation
+                        }),
+                        position: from_tracking_vector3(if data.controller[0].isHand {
+                            &data.controller[0].boneRootPose.position
+                        } else {
+                            &data.controller[0].pose.position
+                        }),
+                        linear_velocity: from_tracking_velocity(&data.controller[0].linearVelocity),
+                        angular_velocity: from_tracking_velocity(
+                            &data.controller[0].angularVelocity,
+                        ),
                     },
-                    bone_positions_base: {
-                        let bone_positions = &data.controller[1].bonePositionsBase;
-                        let mut array = [Vec3::ZERO; 19];
-                        for i in 0..array.len() {
-                            array[i] = from_tracking_vector3(&bone_positions[i]);
-                        }
-                        array
+                ),
+                (
+                    *RIGHT_HAND_ID,
+                    MotionData {
+                        orientation: from_tracking_quat(if data.controller[1].isHand {
+                            &data.controller[1].boneRootPose.orientation
+                        } else {
+                            &data.controller[1].pose.orientation
+                        }),
+                        position: from_tracking_vector3(if data.controller[1].isHand {
+                            &data.controller[1].boneRootPose.position
+                        } else {
+                            &data.controller[1].pose.position
+                        }),
+                        linear_velocity: from_tracking_velocity(&data.controller[1].linearVelocity),
+                        angular_velocity: from_tracking_velocity(
+                            &data.controller[1].angularVelocity,
+                        ),
                     },
-                    hand_finger_confience: data.controller[1].handFingerConfidences,
-                },
+                ),
             ],
-        },
-    };
-    if let Some(sender) = &*INPUT_SENDER.lock() {
-        sender.send(input).ok();
-    }
+            // left_hand_tracking: None,
+            // right_hand_tracking: None,
+            // button_values: std::collections::HashMap::new(), // unused for now
+            legacy: LegacyInput {
+                mounted: data.mounted,
+                controllers: [
+                    LegacyController {
+                        enabled: data.controller[0].enabled,
+                        is_hand: data.controller[0].isHand,
+                        buttons: data.controller[0].buttons,
+                        joystick_position: from_tracking_vector2(
+                            &data.controller[0].joystickPosition,
+                        ),
+                        trackpad_position: from_tracking_vector2(
+                            &data.controller[0].trackpadPosition,
+                        ),
+                        trigger_value: data.controller[0].triggerValue,
+                        grip_value: data.controller[0].gripValue,
+                        bone_rotations: {
+                            let bone_rotations = &data.controller[0].boneRotations;
+                            let mut array = [Quat::IDENTITY; 19];
+                            for i in 0..array.len() {
+                                array[i] = from_tracking_quat(&bone_rotations[i]);
+                            }
+                            array
+                        },
+                        bone_positions_base: {
+                            let bone_positions = &data.controller[0].bonePositionsBase;
+                            let mut array = [Vec3::ZERO; 19];
+                            for i in 0..array.len() {
+                                array[i] = from_tracking_vector3(&bone_positions[i]);
+                            }
+                            array
+                        },
+                        hand_finger_confience: data.controller[0].handFingerConfidences,
+                    },
+                    LegacyController {
+                        enabled: data.controller[1].enabled,
+                        is_hand: data.controller[1].isHand,
+                        buttons: data.controller[1].buttons,
+                        joystick_position: from_tracking_vector2(
+                            &data.controller[1].joystickPosition,
+                        ),
+                        trackpad_position: from_tracking_vector2(
+                            &data.controller[1].trackpadPosition,
+                        ),
+                        trigger_value: data.controller[1].triggerValue,
+                        grip_value: data.controller[1].gripValue,
+                        bone_rotations: {
+                            let bone_rotations = &data.controller[1].boneRotations;
+                            let mut array = [Quat::IDENTITY; 19];
+                            for i in 0..array.len() {
+                                array[i] = from_tracking_quat(&bone_rotations[i]);
+                            }
+                            array
+                        },
+                        bone_positions_base: {
+                            let bone_positions = &data.controller[1].bonePositionsBase;
+                            let mut array = [Vec3::ZERO; 19];
+                            for i in 0..array.len() {
+                                array[i] = from_tracking_vector3(&bone_positions[i]);
+                            }
+                            array
+                        },
+                        hand_finger_confience: data.controller[1].handFingerConfidences,
+                    },
+                ],
+            },
+        };
+        tracking_state::update_tracking_state(&input);
+        sleep_timer::report_controller_activity(&input);
+        pose_offsets::apply_pose_offsets(&mut input);
+        one_handed::apply_one_handed(&mut input);
+        monocular::apply_monocular(&mut input);
+        pose_prediction::apply_pose_prediction(&mut input);
+        recenter::apply_recenter(&mut input);
+        world_offset::apply_world_offset(&mut input);
+        spectator_camera::apply_spectator_gesture(&input);
+        if let Some(velocity_frame) = APP_CONFIG.velocity_frame {
+            for (_, motion) in &mut input.device_motions {
+                motion.convert_velocity_frame(VelocityFrame::Stage, velocity_frame);
+            }
+        }
+        let hand_orientations = [
+            input.device_motions[1].1.orientation,
+            input.device_motions[2].1.orientation,
+        ];
+        hand_gestures::apply_hand_gestures(&mut input.legacy.controllers, hand_orientations);
+        bindings::remap_controllers(&mut input.legacy.controllers);
+        if let Some(path) = &APP_CONFIG.record_tracking {
+            tracking_replay::record_input(path, &input);
+        }
+        if let Some(sender) = &*INPUT_SENDER.lock() {
+            sender.send(input).ok();
+        }
+    })
 }
 
 #[inline(always)]
@@ -640,79 +2087,104 @@ fn make_hidden_area_meshes(view_config: &ALXRViewConfig) -> [HiddenAreaMesh; 2]
 }
 
 pub extern "C" fn views_config_send(view_config_ptr: *const ALXRViewConfig) {
-    let view_config: &ALXRViewConfig = unsafe { &*view_config_ptr };
-    let eye_info = &view_config.eyeInfo;
-    let fov = &view_config.eyeInfo.eyeFov;
-    if let Some(sender) = &*VIEWS_CONFIG_SENDER.lock() {
-        sender
-            .send(ViewsConfig {
-                ipd_m: eye_info.ipd,
-                fov: [
-                    Fov {
-                        left: fov[0].left,
-                        right: fov[0].right,
-                        top: fov[0].top,
-                        bottom: fov[0].bottom,
-                    },
-                    Fov {
-                        left: fov[1].left,
-                        right: fov[1].right,
-                        top: fov[1].top,
-                        bottom: fov[1].bottom,
-                    },
-                ],
-                hidden_area_meshes: make_hidden_area_meshes(&view_config),
-            })
-            .ok();
-    }
+    guard_callback("views_config_send", || {
+        let view_config: &ALXRViewConfig = unsafe { &*view_config_ptr };
+        let eye_info = &view_config.eyeInfo;
+        let fov = &view_config.eyeInfo.eyeFov;
+        if let Some(sender) = &*VIEWS_CONFIG_SENDER.lock() {
+            sender
+                .send(ViewsConfig {
+                    ipd_m: eye_info.ipd,
+                    fov: [
+                        Fov {
+                            left: fov[0].left,
+                            right: fov[0].right,
+                            top: fov[0].top,
+                            bottom: fov[0].bottom,
+                        },
+                        Fov {
+                            left: fov[1].left,
+                            right: fov[1].right,
+                            top: fov[1].top,
+                            bottom: fov[1].bottom,
+                        },
+                    ],
+                    hidden_area_meshes: make_hidden_area_meshes(&view_config),
+                })
+                .ok();
+        }
+    })
 }
 
 pub extern "C" fn battery_send(device_id: u64, gauge_value: f32, is_plugged: bool) {
-    if let Some(sender) = &*BATTERY_SENDER.lock() {
-        sender
-            .send(BatteryPacket {
-                device_id,
-                gauge_value,
-                is_plugged,
-            })
-            .ok();
+    guard_callback("battery_send", || {
+        if let Some(sender) = &*BATTERY_SENDER.lock() {
+            sender
+                .send(BatteryPacket {
+                    device_id,
+                    gauge_value,
+                    is_plugged,
+                })
+                .ok();
+        }
+    })
+}
+
+pub fn thermal_send(packet: ThermalStatsPacket) {
+    if let Some(sender) = &*THERMAL_SENDER.lock() {
+        sender.send(packet).ok();
     }
 }
 
-pub extern "C" fn time_sync_send(data_ptr: *const TimeSync) {
-    let data: &TimeSync = unsafe { &*data_ptr };
-    if let Some(sender) = &*TIME_SYNC_SENDER.lock() {
-        let time_sync = TimeSyncPacket {
-            mode: data.mode,
-            server_time: data.serverTime,
-            client_time: data.clientTime,
-            packets_lost_total: data.packetsLostTotal,
-            packets_lost_in_second: data.packetsLostInSecond,
-            average_send_latency: data.averageSendLatency,
-            average_transport_latency: data.averageTransportLatency,
-            average_decode_latency: data.averageDecodeLatency,
-            idle_time: data.idleTime,
-            fec_failure: data.fecFailure,
-            fec_failure_in_second: data.fecFailureInSecond,
-            fec_failure_total: data.fecFailureTotal,
-            fps: data.fps,
-            server_total_latency: data.serverTotalLatency,
-            tracking_recv_frame_index: data.trackingRecvFrameIndex,
-        };
-        sender.send(time_sync).ok();
+pub fn wifi_stats_send(packet: WifiStatsPacket) {
+    if let Some(sender) = &*WIFI_STATS_SENDER.lock() {
+        sender.send(packet).ok();
     }
 }
 
+pub extern "C" fn time_sync_send(data_ptr: *const TimeSync) {
+    guard_callback("time_sync_send", || {
+        let data: &TimeSync = unsafe { &*data_ptr };
+        latency_stats::set_decode_latency(data.averageDecodeLatency as u32);
+        if let Some(sender) = &*TIME_SYNC_SENDER.lock() {
+            let time_sync = TimeSyncPacket {
+                mode: data.mode,
+                server_time: data.serverTime,
+                client_time: data.clientTime,
+                packets_lost_total: data.packetsLostTotal,
+                packets_lost_in_second: data.packetsLostInSecond,
+                average_send_latency: data.averageSendLatency,
+                average_transport_latency: data.averageTransportLatency,
+                average_decode_latency: data.averageDecodeLatency,
+                idle_time: data.idleTime,
+                fec_failure: data.fecFailure,
+                fec_failure_in_second: data.fecFailureInSecond,
+                fec_failure_total: data.fecFailureTotal,
+                fps: data.fps,
+                server_total_latency: data.serverTotalLatency,
+                tracking_recv_frame_index: data.trackingRecvFrameIndex,
+            };
+            sender.send(time_sync).ok();
+        }
+    })
+}
+
 pub extern "C" fn video_error_report_send() {
-    if let Some(sender) = &*VIDEO_ERROR_REPORT_SENDER.lock() {
-        sender.send(()).ok();
-    }
+    guard_callback("video_error_report_send", || {
+        if let Some(sender) = &*VIDEO_ERROR_REPORT_SENDER.lock() {
+            sender.send(()).ok();
+        }
+    })
 }
 
 pub extern "C" fn set_waiting_next_idr(waiting: bool) {
-    IDR_PARSED.store(!waiting, Ordering::Relaxed);
+    guard_callback("set_waiting_next_idr", || {
+        IDR_PARSED.store(!waiting, Ordering::Relaxed);
+    })
 }
 
 pub extern "C" fn request_idr() {
-    IDR_REQUEST_NOTIFIER.notify_waiters();
+    guard_callback("request_idr", || {
+        IDR_REQUEST_NOTIFIER.notify_waiters();
+    })
 }
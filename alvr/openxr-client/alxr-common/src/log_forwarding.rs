@@ -0,0 +1,97 @@
+use crate::APP_CONFIG;
+use alvr_common::log::{Level, Record};
+use alvr_session::EventSeverity;
+use alvr_sockets::ClientLogEntry;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::{
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+// Entries past this rate are dropped so a log storm can't saturate the control socket.
+const MAX_ENTRIES_PER_SECOND: u32 = 100;
+
+lazy_static! {
+    pub(crate) static ref LOG_SENDER: Mutex<Option<mpsc::UnboundedSender<ClientLogEntry>>> =
+        Mutex::new(None);
+}
+
+fn timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis() as u64)
+}
+
+// Called by the platform logger for every record it outputs. Records are only forwarded
+// while connected to a server.
+pub fn forward_log(record: &Record) {
+    let sender = LOG_SENDER.lock();
+    let sender = match &*sender {
+        Some(sender) if !sender.is_closed() => sender,
+        _ => return,
+    };
+    if APP_CONFIG
+        .forward_log_level
+        .map_or(true, |max_level| record.level() > max_level)
+    {
+        return;
+    }
+    let severity = match record.level() {
+        Level::Error => EventSeverity::Error,
+        Level::Warn => EventSeverity::Warning,
+        Level::Info => EventSeverity::Info,
+        Level::Debug | Level::Trace => EventSeverity::Debug,
+    };
+    sender
+        .send(ClientLogEntry {
+            timestamp_ms: timestamp_ms(),
+            severity,
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        })
+        .ok();
+}
+
+// Lets through at most MAX_ENTRIES_PER_SECOND entries, the number of dropped entries is
+// reported once the window is over.
+pub(crate) struct LogRateLimiter {
+    window_start: Instant,
+    sent: u32,
+    dropped: u32,
+}
+
+impl LogRateLimiter {
+    pub fn new() -> Self {
+        Self {
+            window_start: Instant::now(),
+            sent: 0,
+            dropped: 0,
+        }
+    }
+
+    pub fn admit(&mut self, entry: ClientLogEntry) -> Vec<ClientLogEntry> {
+        let mut entries = vec![];
+        if self.window_start.elapsed() >= Duration::from_secs(1) {
+            if self.dropped > 0 {
+                entries.push(ClientLogEntry {
+                    timestamp_ms: timestamp_ms(),
+                    severity: EventSeverity::Warning,
+                    target: module_path!().to_string(),
+                    message: format!("{} log lines were not forwarded", self.dropped),
+                });
+            }
+            self.window_start = Instant::now();
+            self.sent = 0;
+            self.dropped = 0;
+        }
+        if self.sent < MAX_ENTRIES_PER_SECOND {
+            self.sent += 1;
+            entries.push(entry);
+        } else {
+            self.dropped += 1;
+        }
+        entries
+    }
+}
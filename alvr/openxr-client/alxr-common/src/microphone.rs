@@ -0,0 +1,181 @@
+#![cfg(not(target_vendor = "uwp"))]
+use alvr_common::prelude::*;
+
+// Linear resampler for mono i16 pcm, used when the microphone is captured at a different rate
+// than the one the server plays it back at.
+pub struct MonoResampler {
+    step: f64,
+    position: f64,
+    previous: i16,
+}
+
+impl MonoResampler {
+    pub fn new(source_sample_rate: u32, target_sample_rate: u32) -> Self {
+        Self {
+            step: source_sample_rate as f64 / target_sample_rate as f64,
+            position: 0_f64,
+            previous: 0,
+        }
+    }
+
+    pub fn process(&mut self, input: &[i16], output: &mut Vec<i16>) {
+        if self.step == 1_f64 {
+            output.extend_from_slice(input);
+            return;
+        }
+        // Sample positions are relative to the last sample of the previous batch (index 0).
+        let input_len = input.len() as f64;
+        while self.position < input_len {
+            let index = self.position.floor() as usize;
+            let fraction = self.position - index as f64;
+            let a = if index == 0 {
+                self.previous
+            } else {
+                input[index - 1]
+            } as f64;
+            let b = input[index] as f64;
+            output.push((a + (b - a) * fraction) as i16);
+            self.position += self.step;
+        }
+        self.position -= input_len;
+        if let Some(&last) = input.last() {
+            self.previous = last;
+        }
+    }
+}
+
+pub fn samples_to_bytes(samples: &[i16]) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(samples.len() * std::mem::size_of::<i16>());
+    for sample in samples {
+        buffer.extend(&sample.to_ne_bytes());
+    }
+    buffer
+}
+
+#[cfg(not(target_os = "android"))]
+mod desktop {
+    use super::{samples_to_bytes, MonoResampler};
+    use alvr_common::prelude::*;
+    use alvr_sockets::StreamSender;
+    use cpal::{
+        traits::{DeviceTrait, HostTrait, StreamTrait},
+        Device, Host, Sample, SampleFormat, SampleRate, Stream, StreamConfig,
+    };
+    use std::{sync::mpsc as smpsc, thread};
+    use tokio::sync::mpsc as tmpsc;
+
+    type DataSender = tmpsc::UnboundedSender<StrResult<Vec<i16>>>;
+
+    fn find_input_device(host: &Host, device_name: Option<&str>) -> StrResult<Device> {
+        match device_name {
+            Some(name_substring) => {
+                let name_substring = name_substring.to_lowercase();
+                trace_err!(host.input_devices())?
+                    .find(|d| {
+                        d.name()
+                            .map_or(false, |name| name.to_lowercase().contains(&name_substring))
+                    })
+                    .ok_or_else(|| {
+                        format!("Cannot find microphone which name contains \"{name_substring}\"")
+                    })
+            }
+            None => host
+                .default_input_device()
+                .ok_or_else(|| "No microphone found".to_owned()),
+        }
+    }
+
+    fn build_stream<T: Sample>(
+        device: &Device,
+        config: &StreamConfig,
+        mut resampler: MonoResampler,
+        data_sender: DataSender,
+    ) -> StrResult<Stream> {
+        let channels = config.channels as usize;
+        let error_sender = data_sender.clone();
+        trace_err!(device.build_input_stream(
+            config,
+            move |data: &[T], _| {
+                // only the first channel is kept, the server expects mono.
+                let mono: Vec<i16> = data
+                    .chunks_exact(channels)
+                    .map(|frame| frame[0].to_i16())
+                    .collect();
+                let mut samples = Vec::with_capacity(mono.len());
+                resampler.process(&mono, &mut samples);
+                data_sender.send(Ok(samples)).ok();
+            },
+            move |e| {
+                error_sender
+                    .send(fmt_e!("Error while recording microphone: {e}"))
+                    .ok();
+            },
+        ))
+    }
+
+    pub async fn record_microphone_loop(
+        device_name: Option<String>,
+        capture_sample_rate: Option<u32>,
+        target_sample_rate: u32,
+        mut sender: StreamSender<()>,
+    ) -> StrResult {
+        let (data_sender, mut data_receiver) = tmpsc::unbounded_channel::<StrResult<Vec<i16>>>();
+        let (_shutdown_notifier, shutdown_receiver) = smpsc::channel::<()>();
+
+        // the stream object must be destroyed on the same thread of creation.
+        thread::spawn(move || {
+            let res = (|| -> StrResult {
+                let host = cpal::default_host();
+                let device = find_input_device(&host, device_name.as_deref())?;
+                let supported_config = match capture_sample_rate {
+                    Some(sample_rate) => {
+                        trace_none!(trace_err!(device.supported_input_configs())?.find(|c| {
+                            c.min_sample_rate().0 <= sample_rate
+                                && c.max_sample_rate().0 >= sample_rate
+                        }))?
+                        .with_sample_rate(SampleRate(sample_rate))
+                    }
+                    None => trace_err!(device.default_input_config())?,
+                };
+                info!(
+                    "Recording microphone \"{}\" at {}Hz, sending at {target_sample_rate}Hz",
+                    device.name().unwrap_or_default(),
+                    supported_config.sample_rate().0
+                );
+
+                let config = supported_config.config();
+                let resampler = MonoResampler::new(config.sample_rate.0, target_sample_rate);
+                let stream = match supported_config.sample_format() {
+                    SampleFormat::I16 => {
+                        build_stream::<i16>(&device, &config, resampler, data_sender.clone())
+                    }
+                    SampleFormat::U16 => {
+                        build_stream::<u16>(&device, &config, resampler, data_sender.clone())
+                    }
+                    SampleFormat::F32 => {
+                        build_stream::<f32>(&device, &config, resampler, data_sender.clone())
+                    }
+                }?;
+                trace_err!(stream.play())?;
+
+                shutdown_receiver.recv().ok();
+                Ok(())
+            })();
+            if let Err(e) = res {
+                data_sender.send(Err(e)).ok();
+            }
+        });
+
+        while let Some(maybe_samples) = data_receiver.recv().await {
+            let data = samples_to_bytes(&maybe_samples?);
+            let mut buffer = sender.new_buffer(&(), data.len())?;
+            buffer.get_mut().extend(data);
+            sender.send_buffer(buffer).await.ok();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+pub use desktop::record_microphone_loop;
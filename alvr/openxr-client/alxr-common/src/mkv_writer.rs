@@ -0,0 +1,305 @@
+// Minimal Matroska muxer for a single H.264/HEVC video track. The segment and clusters are
+// written with an unknown size so nothing has to be seeked back to, a recording that is cut
+// short is still playable up to the last complete block.
+use std::io::{self, Write};
+
+const EBML: u32 = 0x1A45_DFA3;
+const EBML_VERSION: u32 = 0x4286;
+const EBML_READ_VERSION: u32 = 0x42F7;
+const EBML_MAX_ID_LENGTH: u32 = 0x42F2;
+const EBML_MAX_SIZE_LENGTH: u32 = 0x42F3;
+const DOC_TYPE: u32 = 0x4282;
+const DOC_TYPE_VERSION: u32 = 0x4287;
+const DOC_TYPE_READ_VERSION: u32 = 0x4285;
+const SEGMENT: u32 = 0x1853_8067;
+const INFO: u32 = 0x1549_A966;
+const TIMESTAMP_SCALE: u32 = 0x2A_D7B1;
+const MUXING_APP: u32 = 0x4D80;
+const WRITING_APP: u32 = 0x5741;
+const TRACKS: u32 = 0x1654_AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_NUMBER: u32 = 0xD7;
+const TRACK_UID: u32 = 0x73C5;
+const TRACK_TYPE: u32 = 0x83;
+const FLAG_LACING: u32 = 0x9C;
+const CODEC_ID: u32 = 0x86;
+const CODEC_PRIVATE: u32 = 0x63A2;
+const VIDEO: u32 = 0xE0;
+const PIXEL_WIDTH: u32 = 0xB0;
+const PIXEL_HEIGHT: u32 = 0xBA;
+const CLUSTER: u32 = 0x1F43_B675;
+const TIMESTAMP: u32 = 0xE7;
+const SIMPLE_BLOCK: u32 = 0xA3;
+
+const UNKNOWN_SIZE: [u8; 8] = [0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+const TRACK_TYPE_VIDEO: u64 = 1;
+const SIMPLE_BLOCK_KEYFRAME: u8 = 0x80;
+
+// Block timestamps are 16 bit offsets from the cluster timestamp, in ms.
+const MAX_CLUSTER_DURATION_MS: u64 = 30_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoCodec {
+    H264,
+    Hevc,
+}
+
+impl VideoCodec {
+    fn codec_id(self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "V_MPEG4/ISO/AVC",
+            VideoCodec::Hevc => "V_MPEGH/ISO/HEVC",
+        }
+    }
+
+    pub fn nal_type(self, nal: &[u8]) -> u8 {
+        match (self, nal.first()) {
+            (VideoCodec::H264, Some(header)) => header & 0x1F,
+            (VideoCodec::Hevc, Some(header)) => (header >> 1) & 0x3F,
+            (_, None) => 0,
+        }
+    }
+
+    pub fn is_keyframe_nal(self, nal: &[u8]) -> bool {
+        let nal_type = self.nal_type(nal);
+        match self {
+            VideoCodec::H264 => nal_type == 5,
+            // IDR_W_RADL, IDR_N_LP, CRA
+            VideoCodec::Hevc => (19..=21).contains(&nal_type),
+        }
+    }
+}
+
+// Splits an Annex B byte stream on its start codes.
+pub fn split_nals(data: &[u8]) -> Vec<&[u8]> {
+    let mut nals = vec![];
+    let mut start = None;
+    let mut index = 0;
+    while index + 3 <= data.len() {
+        if data[index] == 0 && data[index + 1] == 0 && data[index + 2] == 1 {
+            if let Some(start) = start {
+                // a 4 byte start code leaves a trailing zero on the previous nal.
+                let mut end = index;
+                while end > start && data[end - 1] == 0 {
+                    end -= 1;
+                }
+                nals.push(&data[start..end]);
+            }
+            index += 3;
+            start = Some(index);
+        } else {
+            index += 1;
+        }
+    }
+    if let Some(start) = start {
+        if start < data.len() {
+            nals.push(&data[start..]);
+        }
+    }
+    nals
+}
+
+// Removes the emulation prevention bytes (00 00 03) from the start of a nal.
+fn unescape(nal: &[u8], max_len: usize) -> Vec<u8> {
+    let mut rbsp = Vec::with_capacity(max_len);
+    let mut zeros = 0;
+    for &byte in nal {
+        if rbsp.len() == max_len {
+            break;
+        }
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        rbsp.push(byte);
+    }
+    rbsp
+}
+
+fn avc_decoder_config(sps: &[u8], pps: &[u8]) -> Option<Vec<u8>> {
+    if sps.len() < 4 {
+        return None;
+    }
+    let mut config = vec![1, sps[1], sps[2], sps[3], 0xFF, 0xE1];
+    config.extend_from_slice(&(sps.len() as u16).to_be_bytes());
+    config.extend_from_slice(sps);
+    config.push(1);
+    config.extend_from_slice(&(pps.len() as u16).to_be_bytes());
+    config.extend_from_slice(pps);
+    Some(config)
+}
+
+// The profile, tier and level are copied from the SPS, the chroma format is assumed to be 4:2:0
+// which is all the server encodes.
+fn hevc_decoder_config(vps: &[u8], sps: &[u8], pps: &[u8], ten_bit: bool) -> Option<Vec<u8>> {
+    // 2 byte nal header, 1 byte of ids, then the 12 byte general profile_tier_level.
+    let sps_start = unescape(sps, 15);
+    if sps_start.len() < 15 {
+        return None;
+    }
+    let profile_tier_level = &sps_start[3..15];
+    let bit_depth_minus_8 = if ten_bit { 2 } else { 0 };
+
+    let mut config = vec![1];
+    config.extend_from_slice(profile_tier_level);
+    config.extend_from_slice(&[
+        0xF0, // min_spatial_segmentation_idc
+        0x00,
+        0xFC, // parallelismType
+        0xFC | 1,
+        0xF8 | bit_depth_minus_8,
+        0xF8 | bit_depth_minus_8,
+        0x00, // avgFrameRate
+        0x00,
+        0x0F, // 1 temporal layer, temporal id nested, 4 byte nal lengths
+        3,
+    ]);
+    for (nal_type, nal) in [(32_u8, vps), (33, sps), (34, pps)] {
+        config.push(0x80 | nal_type);
+        config.extend_from_slice(&1_u16.to_be_bytes());
+        config.extend_from_slice(&(nal.len() as u16).to_be_bytes());
+        config.extend_from_slice(nal);
+    }
+    Some(config)
+}
+
+// Builds the CodecPrivate data from the parameter sets of a keyframe.
+pub fn decoder_config(codec: VideoCodec, nals: &[&[u8]], ten_bit: bool) -> Option<Vec<u8>> {
+    let find = |nal_type| {
+        nals.iter()
+            .find(|nal| codec.nal_type(nal) == nal_type)
+            .copied()
+    };
+    match codec {
+        VideoCodec::H264 => avc_decoder_config(find(7)?, find(8)?),
+        VideoCodec::Hevc => hevc_decoder_config(find(32)?, find(33)?, find(34)?, ten_bit),
+    }
+}
+
+fn write_id(out: &mut Vec<u8>, id: u32) {
+    let bytes = id.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&byte| byte == 0).count();
+    out.extend_from_slice(&bytes[skip..]);
+}
+
+fn write_size(out: &mut Vec<u8>, size: u64) {
+    // sizes are always written on 8 bytes, simpler and valid.
+    out.push(0x01);
+    out.extend_from_slice(&size.to_be_bytes()[1..]);
+}
+
+fn write_element(out: &mut Vec<u8>, id: u32, data: &[u8]) {
+    write_id(out, id);
+    write_size(out, data.len() as u64);
+    out.extend_from_slice(data);
+}
+
+fn write_uint(out: &mut Vec<u8>, id: u32, value: u64) {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&byte| byte == 0).count().min(7);
+    write_element(out, id, &bytes[skip..]);
+}
+
+fn write_master(out: &mut Vec<u8>, id: u32, build: impl FnOnce(&mut Vec<u8>)) {
+    let mut children = vec![];
+    build(&mut children);
+    write_element(out, id, &children);
+}
+
+pub struct MkvWriter<W: Write> {
+    out: W,
+    cluster_timestamp_ms: Option<u64>,
+}
+
+impl<W: Write> MkvWriter<W> {
+    pub fn new(
+        mut out: W,
+        codec: VideoCodec,
+        codec_private: &[u8],
+        width: u32,
+        height: u32,
+    ) -> io::Result<Self> {
+        let mut header = vec![];
+        write_master(&mut header, EBML, |ebml| {
+            write_uint(ebml, EBML_VERSION, 1);
+            write_uint(ebml, EBML_READ_VERSION, 1);
+            write_uint(ebml, EBML_MAX_ID_LENGTH, 4);
+            write_uint(ebml, EBML_MAX_SIZE_LENGTH, 8);
+            write_element(ebml, DOC_TYPE, b"matroska");
+            write_uint(ebml, DOC_TYPE_VERSION, 4);
+            write_uint(ebml, DOC_TYPE_READ_VERSION, 2);
+        });
+        write_id(&mut header, SEGMENT);
+        header.extend_from_slice(&UNKNOWN_SIZE);
+        write_master(&mut header, INFO, |info| {
+            write_uint(info, TIMESTAMP_SCALE, 1_000_000);
+            write_element(info, MUXING_APP, b"alxr");
+            write_element(info, WRITING_APP, b"alxr");
+        });
+        write_master(&mut header, TRACKS, |tracks| {
+            write_master(tracks, TRACK_ENTRY, |track| {
+                write_uint(track, TRACK_NUMBER, 1);
+                write_uint(track, TRACK_UID, 1);
+                write_uint(track, TRACK_TYPE, TRACK_TYPE_VIDEO);
+                write_uint(track, FLAG_LACING, 0);
+                write_element(track, CODEC_ID, codec.codec_id().as_bytes());
+                write_element(track, CODEC_PRIVATE, codec_private);
+                write_master(track, VIDEO, |video| {
+                    write_uint(video, PIXEL_WIDTH, width as u64);
+                    write_uint(video, PIXEL_HEIGHT, height as u64);
+                });
+            });
+        });
+        out.write_all(&header)?;
+        Ok(Self {
+            out,
+            cluster_timestamp_ms: None,
+        })
+    }
+
+    // Nals are written with 4 byte length prefixes, as declared in the codec private data.
+    pub fn write_frame(
+        &mut self,
+        timestamp_ms: u64,
+        nals: &[&[u8]],
+        keyframe: bool,
+    ) -> io::Result<()> {
+        let mut data = vec![];
+        let cluster_timestamp_ms = match self.cluster_timestamp_ms {
+            Some(cluster_timestamp_ms)
+                if !keyframe
+                    && timestamp_ms >= cluster_timestamp_ms
+                    && timestamp_ms - cluster_timestamp_ms < MAX_CLUSTER_DURATION_MS =>
+            {
+                cluster_timestamp_ms
+            }
+            _ => {
+                write_id(&mut data, CLUSTER);
+                data.extend_from_slice(&UNKNOWN_SIZE);
+                write_uint(&mut data, TIMESTAMP, timestamp_ms);
+                self.cluster_timestamp_ms = Some(timestamp_ms);
+                timestamp_ms
+            }
+        };
+
+        let mut block = vec![0x81]; // track 1
+        block.extend_from_slice(&((timestamp_ms - cluster_timestamp_ms) as i16).to_be_bytes());
+        block.push(if keyframe { SIMPLE_BLOCK_KEYFRAME } else { 0 });
+        for nal in nals {
+            block.extend_from_slice(&(nal.len() as u32).to_be_bytes());
+            block.extend_from_slice(nal);
+        }
+        write_element(&mut data, SIMPLE_BLOCK, &block);
+        self.out.write_all(&data)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    // For writers into memory, to take the data written so far.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+}
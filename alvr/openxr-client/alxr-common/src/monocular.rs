@@ -0,0 +1,100 @@
+// For users with monocular vision: the view of one eye is streamed to both displays, so the seeing
+// eye always gets the full image whatever display it looks through. The server renders both views
+// with that eye's fov from that eye's position. --monocular-eye turns it on, the companion can
+// change it live.
+use crate::{APP_CONFIG, VIEWS_CONFIG_SENDER};
+use alvr_common::{prelude::*, HEAD_ID};
+use alvr_sockets::{Input, ViewsConfig};
+use glam::Vec3;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+impl FromStr for Eye {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim().to_lowercase().as_str() {
+            "left" => Ok(Eye::Left),
+            "right" => Ok(Eye::Right),
+            _ => Err(format!("unknown eye {input}, expected Left or Right")),
+        }
+    }
+}
+
+impl Eye {
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+struct MonocularState {
+    eye: Option<Eye>,
+    // Last config reported by the runtime, resent when the eye changes.
+    runtime_config: Option<ViewsConfig>,
+}
+
+lazy_static! {
+    static ref MONOCULAR: Mutex<MonocularState> = Mutex::new(MonocularState {
+        eye: APP_CONFIG.monocular_eye,
+        runtime_config: None,
+    });
+}
+
+// None streams both eyes again.
+pub(crate) fn set_monocular_eye(eye: Option<Eye>) {
+    info!("Monocular view set to {eye:?}");
+    let runtime_config = {
+        let mut state = MONOCULAR.lock();
+        state.eye = eye;
+        state.runtime_config.clone()
+    };
+    if let (Some(config), Some(sender)) = (runtime_config, &*VIEWS_CONFIG_SENDER.lock()) {
+        sender.send(config).ok();
+    }
+}
+
+// Called with every config reported by the runtime, returns the one to send to the server.
+pub(crate) fn apply_monocular_views(config: ViewsConfig) -> ViewsConfig {
+    let mut state = MONOCULAR.lock();
+    state.runtime_config = Some(config.clone());
+    let eye = match state.eye {
+        Some(eye) => eye,
+        None => return config,
+    };
+    let fov = config.fov[eye.index()];
+    let mesh = &config.hidden_area_meshes[eye.index()];
+    ViewsConfig {
+        ipd_m: 0_f32,
+        fov: [fov, fov],
+        hidden_area_meshes: [mesh.clone(), mesh.clone()],
+    }
+}
+
+// With no ipd both views are rendered from the head position, moving the head to the eye keeps
+// the viewpoint of the seeing eye. Before the pose prediction like the other offsets.
+pub(crate) fn apply_monocular(input: &mut Input) {
+    let state = MONOCULAR.lock();
+    let (eye, ipd_m) = match (state.eye, &state.runtime_config) {
+        (Some(eye), Some(config)) => (eye, config.ipd_m),
+        _ => return,
+    };
+    let eye_offset = match eye {
+        Eye::Left => Vec3::NEG_X,
+        Eye::Right => Vec3::X,
+    } * (ipd_m / 2_f32);
+    if let Some((_, head)) = input
+        .device_motions
+        .iter_mut()
+        .find(|(id, _)| *id == *HEAD_ID)
+    {
+        head.position += head.orientation * eye_offset;
+    }
+}
@@ -0,0 +1,112 @@
+// Detects the interface the headset streams over (Wi-Fi, USB tethering, WiGig...), reports it to
+// the server and picks socket buffer sizes for it. Wired and 60GHz links have the headroom for
+// the largest buffers, Wi-Fi keeps the session settings since deep buffers there only add latency.
+// --no-interface-tuning keeps the session settings on every link.
+use crate::APP_CONFIG;
+use alvr_common::prelude::*;
+use alvr_session::{ConnectionDesc, SocketBufferSize};
+use alvr_sockets::{NetworkInterfaceKind, NetworkInterfacePacket};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sysfs {
+    use alvr_sockets::NetworkInterfaceKind;
+    use std::{fs, path::Path};
+
+    const WIGIG_DRIVER: &str = "wil6210";
+
+    fn read_trimmed(path: impl AsRef<Path>) -> Option<String> {
+        fs::read_to_string(path).ok().map(|s| s.trim().to_owned())
+    }
+
+    // The interface of the default route, lines of /proc/net/route are
+    // "Iface Destination Gateway ..." with the destination in hex.
+    fn default_route_interface() -> Option<String> {
+        let routes = fs::read_to_string("/proc/net/route").ok()?;
+        routes.lines().skip(1).find_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?;
+            (fields.next()? == "00000000").then(|| name.to_owned())
+        })
+    }
+
+    // Android hides the routing table from apps on recent versions.
+    fn first_up_interface() -> Option<String> {
+        let mut names = fs::read_dir("/sys/class/net")
+            .ok()?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name != "lo")
+            .collect::<Vec<_>>();
+        names.sort();
+        names.into_iter().find(|name| {
+            read_trimmed(format!("/sys/class/net/{name}/operstate")).as_deref() == Some("up")
+        })
+    }
+
+    pub fn active_interface() -> Option<String> {
+        default_route_interface().or_else(first_up_interface)
+    }
+
+    pub fn classify(name: &str) -> NetworkInterfaceKind {
+        let dir = Path::new("/sys/class/net").join(name);
+        let device = dir.join("device");
+        let driver = fs::read_link(device.join("driver"))
+            .ok()
+            .and_then(|link| Some(link.file_name()?.to_str()?.to_owned()));
+        if name.starts_with("wigig") || driver.as_deref() == Some(WIGIG_DRIVER) {
+            NetworkInterfaceKind::WiGig
+        } else if dir.join("wireless").exists() || dir.join("phy80211").exists() {
+            NetworkInterfaceKind::Wifi
+        } else if fs::canonicalize(&device)
+            .map_or(false, |path| path.to_string_lossy().contains("/usb"))
+        {
+            NetworkInterfaceKind::UsbEthernet
+        } else if device.exists() {
+            NetworkInterfaceKind::Ethernet
+        } else {
+            NetworkInterfaceKind::Unknown
+        }
+    }
+
+    pub fn mtu(name: &str) -> Option<u32> {
+        read_trimmed(format!("/sys/class/net/{name}/mtu"))?
+            .parse()
+            .ok()
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn detect_network_interface() -> Option<NetworkInterfacePacket> {
+    let name = sysfs::active_interface()?;
+    Some(NetworkInterfacePacket {
+        kind: sysfs::classify(&name),
+        mtu: sysfs::mtu(&name),
+        name,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn detect_network_interface() -> Option<NetworkInterfacePacket> {
+    None
+}
+
+// Only buffers left to the OS default are changed, explicit sizes are kept.
+pub(crate) fn tune_socket_buffers(kind: NetworkInterfaceKind, connection: &mut ConnectionDesc) {
+    if APP_CONFIG.no_interface_tuning {
+        return;
+    }
+    let buffer_size = match kind {
+        NetworkInterfaceKind::WiGig
+        | NetworkInterfaceKind::Ethernet
+        | NetworkInterfaceKind::UsbEthernet => SocketBufferSize::Maximum,
+        NetworkInterfaceKind::Wifi | NetworkInterfaceKind::Unknown => return,
+    };
+    for buffer in [
+        &mut connection.client_send_buffer_bytes,
+        &mut connection.client_recv_buffer_bytes,
+    ] {
+        if matches!(buffer, SocketBufferSize::Default) {
+            *buffer = buffer_size.clone();
+        }
+    }
+    info!("Socket buffers tuned for {kind:?}");
+}
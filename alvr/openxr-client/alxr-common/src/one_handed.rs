@@ -0,0 +1,166 @@
+// Accessibility mode for one-handed players: the inputs and pose of the held controller are
+// mirrored onto the other one, which follows it at a fixed offset, so two-controller games stay
+// playable. --one-handed turns it on, holding the joystick and grip of the held controller toggles
+// it and the companion can change the hand.
+use crate::{bindings::ControllerInput, pose_offsets::PoseOffset, APP_CONFIG};
+use alvr_common::{prelude::*, LEFT_HAND_ID, RIGHT_HAND_ID};
+use alvr_sockets::Input;
+use glam::{Quat, Vec3};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+const TOGGLE_HOLD: Duration = Duration::from_secs(2);
+// To the side of the missing hand, in the frame of the held controller.
+const DEFAULT_OFFSET_X: f32 = 0.2;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+impl FromStr for Hand {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim().to_lowercase().as_str() {
+            "left" => Ok(Hand::Left),
+            "right" => Ok(Hand::Right),
+            _ => Err(format!("unknown hand {input}, expected Left or Right")),
+        }
+    }
+}
+
+impl Hand {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn device_id(self) -> u64 {
+        match self {
+            Hand::Left => *LEFT_HAND_ID,
+            Hand::Right => *RIGHT_HAND_ID,
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            Hand::Left => Hand::Right,
+            Hand::Right => Hand::Left,
+        }
+    }
+}
+
+struct OneHandedState {
+    hand: Option<Hand>,
+    active: bool,
+    hold_start: Option<Instant>,
+    hold_handled: bool,
+}
+
+lazy_static! {
+    static ref ONE_HANDED: Mutex<OneHandedState> = Mutex::new(OneHandedState {
+        hand: APP_CONFIG.one_handed,
+        active: APP_CONFIG.one_handed.is_some(),
+        hold_start: None,
+        hold_handled: false,
+    });
+}
+
+// None turns the mode off.
+pub(crate) fn set_one_handed(hand: Option<Hand>) {
+    info!("One-handed mode set to {hand:?}");
+    let mut state = ONE_HANDED.lock();
+    state.hand = hand;
+    state.active = hand.is_some();
+}
+
+fn mirror_offset(hand: Hand) -> PoseOffset {
+    APP_CONFIG.one_handed_offset.unwrap_or_else(|| PoseOffset {
+        orientation: Quat::IDENTITY,
+        position: match hand {
+            Hand::Left => Vec3::X * DEFAULT_OFFSET_X,
+            Hand::Right => Vec3::NEG_X * DEFAULT_OFFSET_X,
+        },
+    })
+}
+
+// A/B of the right controller are X/Y on the left one.
+fn mirror_face_buttons(buttons: u64) -> u64 {
+    let right_face = [
+        ControllerInput::AClick,
+        ControllerInput::ATouch,
+        ControllerInput::BClick,
+        ControllerInput::BTouch,
+    ]
+    .into_iter()
+    .fold(0, |flags, input| flags | input.button_flag());
+    let shift = ControllerInput::XClick as u64 - ControllerInput::AClick as u64;
+    let left_face = right_face << shift;
+    (buttons & !(right_face | left_face))
+        | ((buttons & right_face) << shift)
+        | ((buttons & left_face) >> shift)
+}
+
+fn update_toggle(state: &mut OneHandedState, buttons: u64) {
+    let gesture_flags =
+        ControllerInput::JoystickClick.button_flag() | ControllerInput::GripClick.button_flag();
+    if buttons & gesture_flags != gesture_flags {
+        state.hold_start = None;
+        state.hold_handled = false;
+        return;
+    }
+    let hold_start = *state.hold_start.get_or_insert_with(Instant::now);
+    if state.hold_handled || hold_start.elapsed() < TOGGLE_HOLD {
+        return;
+    }
+    state.hold_handled = true;
+    state.active = !state.active;
+    info!(
+        "One-handed mode {}",
+        if state.active { "enabled" } else { "disabled" }
+    );
+}
+
+// After the pose offsets, the mirrored controller follows the corrected pose and is predicted like
+// the held one. Hand tracking is never mirrored.
+pub(crate) fn apply_one_handed(input: &mut Input) {
+    let mut state = ONE_HANDED.lock();
+    let hand = match state.hand {
+        Some(hand) => hand,
+        None => return,
+    };
+    let source = input.legacy.controllers[hand.index()].clone();
+    if !source.enabled || source.is_hand {
+        return;
+    }
+    update_toggle(&mut state, source.buttons);
+    if !state.active {
+        return;
+    }
+    let mut mirrored_motion = match input
+        .device_motions
+        .iter()
+        .find(|(id, _)| *id == hand.device_id())
+    {
+        Some((_, motion)) => motion.clone(),
+        None => return,
+    };
+    mirror_offset(hand).apply(&mut mirrored_motion);
+    let other = hand.other();
+    if let Some((_, motion)) = input
+        .device_motions
+        .iter_mut()
+        .find(|(id, _)| *id == other.device_id())
+    {
+        *motion = mirrored_motion;
+    }
+    let mirrored = &mut input.legacy.controllers[other.index()];
+    *mirrored = source;
+    mirrored.buttons = mirror_face_buttons(mirrored.buttons);
+}
@@ -0,0 +1,46 @@
+// Servers are trusted by the fingerprint of their static key. An unknown server must echo the
+// code shown in the headset, which the user types in its dashboard.
+use crate::config;
+use alvr_common::prelude::*;
+use rand::Rng;
+use std::{fs, path::PathBuf};
+
+const TRUSTED_SERVERS_FILENAME: &str = "trusted_servers.txt";
+
+fn trusted_servers_path() -> Option<PathBuf> {
+    config::config_file_path()?
+        .parent()
+        .map(|dir| dir.join(TRUSTED_SERVERS_FILENAME))
+}
+
+// One fingerprint per line, a missing file means no server is trusted yet.
+pub(crate) fn trusted_servers() -> Vec<String> {
+    trusted_servers_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|contents| {
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub(crate) fn trust_server(fingerprint: &str) -> StrResult {
+    let path = trace_none!(trusted_servers_path())?;
+    let mut servers = trusted_servers();
+    if servers.iter().any(|server| server == fingerprint) {
+        return Ok(());
+    }
+    servers.push(fingerprint.to_owned());
+    if let Some(dir) = path.parent() {
+        trace_err!(fs::create_dir_all(dir))?;
+    }
+    trace_err!(fs::write(&path, servers.join("\n") + "\n"))
+}
+
+pub(crate) fn new_pairing_code() -> String {
+    format!("{:06}", rand::thread_rng().gen_range(0..1_000_000))
+}
@@ -0,0 +1,138 @@
+use crate::{
+    config,
+    latency_stats::{self, FrameTiming},
+    APP_CONFIG,
+};
+use alvr_common::prelude::*;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const HEADER: &str = "arrival_unix_us,video_frame_index,tracking_frame_index,frame_bytes,packets,\
+                      receive_us,queue_wait_max_us,decoder_submit_us,decode_avg_us";
+
+// A full file is renamed to <path>.1, older files shift up to <path>.<MAX_ROTATED_FILES>.
+const MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+const MAX_ROTATED_FILES: u32 = 3;
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+struct PerfLogFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    written: u64,
+}
+
+impl PerfLogFile {
+    fn create(path: &Path) -> StrResult<Self> {
+        if let Some(dir) = path.parent() {
+            trace_err!(fs::create_dir_all(dir))?;
+        }
+        let mut writer = BufWriter::new(trace_err!(File::create(path))?);
+        trace_err!(writeln!(writer, "{HEADER}"))?;
+        Ok(Self {
+            path: path.to_owned(),
+            writer,
+            written: HEADER.len() as u64 + 1,
+        })
+    }
+
+    fn rotate(&mut self) -> StrResult {
+        trace_err!(self.writer.flush())?;
+        for index in (1..MAX_ROTATED_FILES).rev() {
+            let from = rotated_path(&self.path, index);
+            if from.exists() {
+                trace_err!(fs::rename(from, rotated_path(&self.path, index + 1)))?;
+            }
+        }
+        trace_err!(fs::rename(&self.path, rotated_path(&self.path, 1)))?;
+        *self = Self::create(&self.path.clone())?;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> StrResult {
+        if self.written + line.len() as u64 >= MAX_FILE_SIZE {
+            self.rotate()?;
+        }
+        trace_err!(writeln!(self.writer, "{line}"))?;
+        self.written += line.len() as u64 + 1;
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, index: u32) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(format!(".{index}"));
+    path.into()
+}
+
+// Lines are written on a separate thread to keep file IO off the video receive path.
+fn start(path: &Path) -> Option<mpsc::Sender<String>> {
+    let mut file = match PerfLogFile::create(path) {
+        Ok(file) => file,
+        Err(e) => {
+            warn!("Failed to create perf log {}: {e}", path.display());
+            return None;
+        }
+    };
+    info!("Writing per frame timings to {}", path.display());
+    let (sender, receiver) = mpsc::channel::<String>();
+    thread::spawn(move || loop {
+        match receiver.recv_timeout(FLUSH_INTERVAL) {
+            Ok(line) => {
+                if let Err(e) = file.write_line(&line) {
+                    warn!("Perf log stopped: {e}");
+                    return;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                file.writer.flush().ok();
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                file.writer.flush().ok();
+                return;
+            }
+        }
+    });
+    Some(sender)
+}
+
+lazy_static! {
+    static ref PERF_LOG_SENDER: Mutex<Option<mpsc::Sender<String>>> = Mutex::new(
+        APP_CONFIG
+            .perf_log
+            .as_deref()
+            .and_then(|path| start(&config::resolve_path(path)))
+    );
+}
+
+pub(crate) fn log_frame(frame: &FrameTiming) {
+    let sender = PERF_LOG_SENDER.lock();
+    let sender = match &*sender {
+        Some(sender) => sender,
+        None => return,
+    };
+    let arrival_unix_us = SystemTime::now()
+        .checked_sub(frame.first_received.elapsed())
+        .and_then(|arrival| arrival.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |arrival| arrival.as_micros());
+    let line = format!(
+        "{arrival_unix_us},{},{},{},{},{},{},{},{}",
+        frame.video_frame_index,
+        frame.tracking_frame_index,
+        frame.frame_byte_size,
+        frame.packets,
+        (frame.last_submitted - frame.first_received).as_micros(),
+        frame.queue_wait_max.as_micros(),
+        frame.decoder_submit_total.as_micros(),
+        latency_stats::decode_latency_us(),
+    );
+    sender.send(line).ok();
+}
@@ -0,0 +1,130 @@
+// Fixed pose offsets of the controllers, to correct a systematic misalignment between where the
+// runtime puts a controller and where games expect it, as on some Pico and Vive controllers.
+// Offsets come from the options and can be changed live by the server.
+use crate::APP_CONFIG;
+use alvr_common::{hash_string, prelude::*, LEFT_HAND_ID, RIGHT_HAND_ID};
+use alvr_sockets::{Input, MotionData, PoseOffsetPacket};
+use glam::{Quat, Vec3};
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use serde::{Serialize, Serializer};
+use std::{collections::HashMap, str::FromStr};
+
+// In the controller's own frame, the rotation is applied after the translation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PoseOffset {
+    pub orientation: Quat,
+    pub position: Vec3,
+}
+
+impl PoseOffset {
+    pub(crate) fn apply(&self, motion: &mut MotionData) {
+        let translation = motion.orientation * self.position;
+        motion.position += translation;
+        motion.orientation = (motion.orientation * self.orientation).normalize();
+        // the offset point moves faster than the controller origin while it rotates.
+        if let (Some(linear_velocity), Some(angular_velocity)) =
+            (&mut motion.linear_velocity, motion.angular_velocity)
+        {
+            *linear_velocity += angular_velocity.cross(translation);
+        }
+    }
+}
+
+// "x,y,z" in meters, or "x,y,z,qx,qy,qz,qw" with a rotation.
+impl FromStr for PoseOffset {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let values = input
+            .split(',')
+            .map(|value| value.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        let orientation = match values[..] {
+            [_, _, _] => Quat::IDENTITY,
+            [_, _, _, x, y, z, w] => Quat::from_xyzw(x, y, z, w),
+            _ => return Err(format!("expected 3 or 7 values, got {}", values.len())),
+        };
+        if !orientation.is_finite() || orientation.length() == 0_f32 {
+            return Err("invalid rotation".into());
+        }
+        Ok(Self {
+            orientation: orientation.normalize(),
+            position: Vec3::new(values[0], values[1], values[2]),
+        })
+    }
+}
+
+// Written to the default config file as a list, like it is read back.
+impl Serialize for PoseOffset {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let [x, y, z] = self.position.to_array();
+        let [qx, qy, qz, qw] = self.orientation.to_array();
+        [x, y, z, qx, qy, qz, qw].serialize(serializer)
+    }
+}
+
+lazy_static! {
+    static ref POSE_OFFSETS: RwLock<HashMap<u64, PoseOffset>> = RwLock::new(
+        [
+            (*LEFT_HAND_ID, APP_CONFIG.left_controller_offset),
+            (*RIGHT_HAND_ID, APP_CONFIG.right_controller_offset),
+        ]
+        .into_iter()
+        .filter_map(|(device_id, offset)| Some((device_id, offset?)))
+        .collect()
+    );
+}
+
+// The server's offset replaces the configured one until the client restarts.
+pub(crate) fn set_pose_offset(packet: PoseOffsetPacket) {
+    let device_id = hash_string(&packet.device_path);
+    if device_id != *LEFT_HAND_ID && device_id != *RIGHT_HAND_ID {
+        warn!(
+            "Pose offsets are only supported for controllers, not {}",
+            packet.device_path
+        );
+        return;
+    }
+    let offset = PoseOffset {
+        orientation: packet.orientation.normalize(),
+        position: packet.position,
+    };
+    if !offset.orientation.is_finite() || !offset.position.is_finite() {
+        warn!("Invalid pose offset for {}", packet.device_path);
+        return;
+    }
+    info!("Pose offset of {} set to {offset:?}", packet.device_path);
+    POSE_OFFSETS.write().insert(device_id, offset);
+}
+
+// Before the pose prediction, which extrapolates from the corrected pose. Hand tracking poses are
+// left alone.
+pub(crate) fn apply_pose_offsets(input: &mut Input) {
+    let offsets = POSE_OFFSETS.read();
+    if offsets.is_empty() {
+        return;
+    }
+    for (controller, device_id) in input
+        .legacy
+        .controllers
+        .iter()
+        .zip([*LEFT_HAND_ID, *RIGHT_HAND_ID])
+    {
+        if !controller.enabled || controller.is_hand {
+            continue;
+        }
+        let offset = match offsets.get(&device_id) {
+            Some(offset) => offset,
+            None => continue,
+        };
+        if let Some((_, motion)) = input
+            .device_motions
+            .iter_mut()
+            .find(|(id, _)| *id == device_id)
+        {
+            offset.apply(motion);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use crate::APP_CONFIG;
+use alvr_common::{prelude::*, LEFT_HAND_ID, RIGHT_HAND_ID};
+use alvr_sockets::{Input, MotionData};
+use glam::Quat;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+
+lazy_static! {
+    // seconds, positive values move controller poses forward in time.
+    static ref CONTROLLER_POSE_TIME_OFFSET: RwLock<f32> = RwLock::new(0_f32);
+    // milliseconds, set from the server dashboard while streaming.
+    static ref SERVER_PREDICTION_MS: RwLock<Option<f32>> = RwLock::new(None);
+}
+
+const MAX_PREDICTION_MS: f32 = 100_f32;
+
+pub fn set_controller_pose_time_offset(offset_s: f32) {
+    *CONTROLLER_POSE_TIME_OFFSET.write() = offset_s;
+}
+
+pub(crate) fn set_server_prediction(prediction_ms: Option<f32>) {
+    info!("Pose prediction (ms) set by the server: {prediction_ms:?}");
+    *SERVER_PREDICTION_MS.write() = prediction_ms;
+}
+
+// seconds, on top of the prediction to the display time done by the runtime.
+fn prediction_s() -> f32 {
+    APP_CONFIG
+        .prediction_ms
+        .or(*SERVER_PREDICTION_MS.read())
+        .map_or(0_f32, |prediction_ms| {
+            prediction_ms.clamp(0_f32, MAX_PREDICTION_MS) / 1000_f32
+        })
+}
+
+// Velocities must still be in the stage frame.
+fn extrapolate(motion: &mut MotionData, dt: f32) {
+    if let Some(linear_velocity) = motion.linear_velocity {
+        motion.position += linear_velocity * dt;
+    }
+    if let Some(angular_velocity) = motion.angular_velocity {
+        let angle = angular_velocity.length() * dt;
+        if angle != 0_f32 {
+            let delta = Quat::from_axis_angle(angular_velocity.normalize(), angle);
+            motion.orientation = (delta * motion.orientation).normalize();
+        }
+    }
+}
+
+// Hand tracking poses are left alone, the offsets are measured for controllers. The head pose is
+// not extrapolated either, the engine reprojects frames with its own record of it.
+pub(crate) fn apply_pose_prediction(input: &mut Input) {
+    let offset_s = *CONTROLLER_POSE_TIME_OFFSET.read() + prediction_s();
+    if offset_s == 0_f32 {
+        return;
+    }
+    for (controller, device_id) in input
+        .legacy
+        .controllers
+        .iter()
+        .zip([*LEFT_HAND_ID, *RIGHT_HAND_ID])
+    {
+        if !controller.enabled || controller.is_hand {
+            continue;
+        }
+        if let Some((_, motion)) = input
+            .device_motions
+            .iter_mut()
+            .find(|(id, _)| *id == device_id)
+        {
+            extrapolate(motion, offset_s);
+        }
+    }
+}
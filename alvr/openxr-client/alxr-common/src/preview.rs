@@ -0,0 +1,202 @@
+// Serves the received video stream over HTTP as a live Matroska stream, for watching the headset
+// view from a phone or a browser. The frames are forwarded as they arrive from the server, the
+// client doesn't decode them, so the preview has the resolution and bitrate of the stream.
+use crate::{
+    mkv_writer::{self, MkvWriter},
+    video_recorder::{FrameAssembler, StreamFormat},
+};
+use alvr_common::prelude::*;
+use alvr_sockets::VideoFrameHeaderPacket;
+use lazy_static::lazy_static;
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::broadcast::{self, error::RecvError},
+};
+
+// About a second of frames, slower viewers skip to the next keyframe.
+const PREVIEW_QUEUE_LEN: usize = 90;
+
+const INDEX_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head><meta name="viewport" content="width=device-width"><title>ALXR preview</title></head>
+<body style="margin:0;background:#000">
+<video src="/stream.mkv" autoplay muted playsinline style="width:100%;height:100vh"></video>
+</body>
+</html>
+"#;
+
+struct PreviewFrame {
+    format: StreamFormat,
+    timestamp_ns: u64,
+    data: Vec<u8>,
+}
+
+lazy_static! {
+    static ref PREVIEW_FRAMES: broadcast::Sender<Arc<PreviewFrame>> =
+        broadcast::channel(PREVIEW_QUEUE_LEN).0;
+}
+
+// Rebuilds frames for the preview viewers, nothing is done while none is connected.
+pub(crate) struct PreviewSource {
+    format: StreamFormat,
+    assembler: FrameAssembler,
+}
+
+impl PreviewSource {
+    // A new source is started for every stream, as the stream format may change.
+    pub fn start(format: StreamFormat) -> Option<Self> {
+        crate::APP_CONFIG.preview_port?;
+        Some(Self {
+            format,
+            assembler: FrameAssembler::new(format.fec),
+        })
+    }
+
+    pub fn push_packet(&mut self, header: &VideoFrameHeaderPacket, buffer: &[u8]) {
+        if PREVIEW_FRAMES.receiver_count() == 0 {
+            return;
+        }
+        if let Some(frame) = self.assembler.push(header, buffer) {
+            PREVIEW_FRAMES
+                .send(Arc::new(PreviewFrame {
+                    format: self.format,
+                    timestamp_ns: frame.timestamp_ns,
+                    data: frame.data,
+                }))
+                .ok();
+        }
+    }
+}
+
+struct PreviewWriter {
+    writer: MkvWriter<Vec<u8>>,
+    format: StreamFormat,
+    first_timestamp_ns: u64,
+}
+
+async fn send_stream(stream: &mut TcpStream) -> StrResult {
+    let mut frames = PREVIEW_FRAMES.subscribe();
+    // the viewer can only start decoding from a keyframe.
+    crate::IDR_REQUEST_NOTIFIER.notify_waiters();
+
+    trace_err!(
+        stream
+            .write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: video/x-matroska\r\n\
+                  Cache-Control: no-cache\r\nConnection: close\r\n\r\n",
+            )
+            .await
+    )?;
+
+    let mut preview: Option<PreviewWriter> = None;
+    let mut skip_to_keyframe = false;
+    loop {
+        let frame = match frames.recv().await {
+            Ok(frame) => frame,
+            Err(RecvError::Lagged(_)) => {
+                skip_to_keyframe = true;
+                continue;
+            }
+            Err(RecvError::Closed) => return Ok(()),
+        };
+        let codec = frame.format.codec;
+        let nals = mkv_writer::split_nals(&frame.data);
+        let keyframe = nals.iter().any(|nal| codec.is_keyframe_nal(nal));
+        if !keyframe && (skip_to_keyframe || preview.is_none()) {
+            continue;
+        }
+        skip_to_keyframe = false;
+
+        // a new stream format can't continue the same file, the viewer has to reconnect.
+        if let Some(current) = &preview {
+            if current.format.codec != codec
+                || current.format.width != frame.format.width
+                || current.format.height != frame.format.height
+            {
+                return Ok(());
+            }
+        }
+        if preview.is_none() {
+            let codec_private = match mkv_writer::decoder_config(codec, &nals, frame.format.ten_bit)
+            {
+                Some(codec_private) => codec_private,
+                None => continue,
+            };
+            preview = Some(PreviewWriter {
+                writer: trace_err!(MkvWriter::new(
+                    vec![],
+                    codec,
+                    &codec_private,
+                    frame.format.width,
+                    frame.format.height,
+                ))?,
+                format: frame.format,
+                first_timestamp_ns: frame.timestamp_ns,
+            });
+        }
+
+        if let Some(preview) = &mut preview {
+            let timestamp_ms = frame
+                .timestamp_ns
+                .saturating_sub(preview.first_timestamp_ns)
+                / 1_000_000;
+            trace_err!(preview.writer.write_frame(timestamp_ms, &nals, keyframe))?;
+            let data = std::mem::take(preview.writer.get_mut());
+            trace_err!(stream.write_all(&data).await)?;
+        }
+    }
+}
+
+async fn serve_preview(mut stream: TcpStream) -> StrResult {
+    let mut reader = BufReader::new(&mut stream);
+    let mut request_line = String::new();
+    trace_err!(reader.read_line(&mut request_line).await)?;
+    // the headers are not needed.
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if trace_err!(reader.read_line(&mut line).await)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    match path {
+        "/" => {
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\
+                 Connection: close\r\n\r\n{INDEX_PAGE}",
+                INDEX_PAGE.len()
+            );
+            trace_err!(stream.write_all(response.as_bytes()).await)
+        }
+        "/stream.mkv" => send_stream(&mut stream).await,
+        _ => trace_err!(
+            stream
+                .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")
+                .await
+        ),
+    }
+}
+
+pub async fn preview_server_loop(port: u16) -> StrResult {
+    let listener =
+        trace_err!(TcpListener::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port))).await)?;
+    info!("Preview server listening on port {port}");
+    println!("Preview server listening on port {port}");
+
+    loop {
+        let (stream, address) = trace_err!(listener.accept().await)?;
+        info!("Preview viewer connected from {address}");
+        tokio::spawn(async move {
+            if let Err(e) = serve_preview(stream).await {
+                debug!("Preview viewer {address} disconnected: {e}");
+            }
+        });
+    }
+}
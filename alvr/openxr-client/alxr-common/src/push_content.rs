@@ -0,0 +1,106 @@
+// URLs and text pushed from the dashboard. They are handed to the system tools, the client has
+// no access to the java side of the app on android.
+use alvr_common::prelude::*;
+use alvr_sockets::PushContentPacket;
+use std::{
+    process::{Command, Stdio},
+    thread,
+};
+
+// Other schemes could start arbitrary intents or local programs.
+fn is_web_url(url: &str) -> bool {
+    let url = url.trim().to_lowercase();
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+fn run(command: &mut Command) -> StrResult {
+    let status = trace_err!(command.stdout(Stdio::null()).stderr(Stdio::null()).status())?;
+    // explorer.exe returns 1 on success.
+    if status.success() || cfg!(windows) {
+        Ok(())
+    } else {
+        fmt_e!("{command:?} failed with {status}")
+    }
+}
+
+#[cfg(not(any(target_os = "android", target_vendor = "uwp")))]
+fn run_with_input(command: &mut Command, input: &str) -> StrResult {
+    use std::io::Write;
+
+    let mut child = trace_err!(command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        trace_err!(stdin.write_all(input.as_bytes()))?;
+    }
+    let status = trace_err!(child.wait())?;
+    if status.success() {
+        Ok(())
+    } else {
+        fmt_e!("{command:?} failed with {status}")
+    }
+}
+
+fn open_url(url: &str) -> StrResult {
+    if !is_web_url(url) {
+        return fmt_e!("Only http and https URLs can be opened");
+    }
+    if cfg!(target_os = "android") {
+        run(Command::new("am").args(["start", "-a", "android.intent.action.VIEW", "-d", url]))
+    } else if cfg!(target_vendor = "uwp") {
+        fmt_e!("Opening URLs is not supported")
+    } else if cfg!(windows) {
+        run(Command::new("explorer").arg(url))
+    } else if cfg!(target_os = "macos") {
+        run(Command::new("open").arg(url))
+    } else {
+        run(Command::new("xdg-open").arg(url))
+    }
+}
+
+#[cfg(any(target_os = "android", target_vendor = "uwp"))]
+fn copy_text(_: &str) -> StrResult {
+    fmt_e!("The clipboard is not available")
+}
+
+#[cfg(not(any(target_os = "android", target_vendor = "uwp")))]
+fn copy_text(text: &str) -> StrResult {
+    if cfg!(windows) {
+        run_with_input(&mut Command::new("clip"), text)
+    } else if cfg!(target_os = "macos") {
+        run_with_input(&mut Command::new("pbcopy"), text)
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        run_with_input(&mut Command::new("wl-copy"), text)
+    } else {
+        run_with_input(
+            Command::new("xclip").args(["-selection", "clipboard"]),
+            text,
+        )
+    }
+}
+
+// Runs on its own thread, the tools may take a while to return.
+pub(crate) fn handle_push_content(content: PushContentPacket) {
+    if crate::APP_CONFIG.no_push_content {
+        info!("Ignoring content pushed by the server");
+        return;
+    }
+    thread::spawn(move || {
+        let res = match &content {
+            PushContentPacket::Url(url) => {
+                info!("Opening URL pushed by the server: {url}");
+                open_url(url)
+            }
+            PushContentPacket::Text(text) => {
+                // the text may be a password or a code, it is not logged.
+                info!("Copying text pushed by the server ({} bytes)", text.len());
+                copy_text(text)
+            }
+        };
+        if let Err(e) = res {
+            warn!("Pushed content not handled: {e}");
+        }
+    });
+}
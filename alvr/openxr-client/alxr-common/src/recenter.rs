@@ -0,0 +1,99 @@
+use crate::APP_CONFIG;
+use alvr_common::{prelude::*, HEAD_ID};
+use alvr_sockets::Input;
+use glam::{Quat, Vec3};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::time::{Duration, Instant};
+
+// ALVR_INPUT_APPLICATION_MENU_CLICK, checked before the binding profile remaps the buttons.
+const MENU_CLICK_FLAG: u64 = 1 << 1;
+
+// Rotation about the vertical axis through `origin`, applied to every pose sent to the server.
+#[derive(Clone, Copy)]
+struct Correction {
+    rotation: Quat,
+    origin: Vec3,
+}
+
+#[derive(Default)]
+struct RecenterState {
+    last_head_pose: Option<(Quat, Vec3)>,
+    correction: Option<Correction>,
+    hold_start: Option<Instant>,
+    hold_handled: bool,
+}
+
+lazy_static! {
+    static ref RECENTER_STATE: Mutex<RecenterState> = Mutex::new(RecenterState::default());
+}
+
+// The correction is recomputed from the uncorrected head pose, recentering twice in a row
+// doesn't compound.
+fn recenter_state(state: &mut RecenterState) {
+    let (orientation, position) = match state.last_head_pose {
+        Some(pose) => pose,
+        None => {
+            warn!("Can't recenter before the first head pose");
+            return;
+        }
+    };
+    let forward = orientation * Vec3::NEG_Z;
+    if forward.x == 0_f32 && forward.z == 0_f32 {
+        // looking straight up or down, there is no forward direction to reset.
+        return;
+    }
+    let yaw = f32::atan2(-forward.x, -forward.z);
+    state.correction = Some(Correction {
+        rotation: Quat::from_rotation_y(-yaw),
+        origin: Vec3::new(position.x, 0_f32, position.z),
+    });
+    info!(
+        "Recentered, forward direction rotated by {:.1}°",
+        -yaw.to_degrees()
+    );
+}
+
+// Resets the forward direction sent to the server to where the headset is currently facing.
+pub fn recenter() {
+    recenter_state(&mut RECENTER_STATE.lock());
+}
+
+// Tracks the recenter gesture, then applies the current correction. Velocities must still be in
+// the stage frame.
+pub(crate) fn apply_recenter(input: &mut Input) {
+    let mut state = RECENTER_STATE.lock();
+    if let Some((_, head)) = input.device_motions.iter().find(|(id, _)| *id == *HEAD_ID) {
+        state.last_head_pose = Some((head.orientation, head.position));
+    }
+
+    if let Some(hold_s) = APP_CONFIG.recenter_hold_s.filter(|hold_s| *hold_s > 0_f32) {
+        let menu_held = input
+            .legacy
+            .controllers
+            .iter()
+            .any(|controller| controller.enabled && controller.buttons & MENU_CLICK_FLAG != 0);
+        if !menu_held {
+            state.hold_start = None;
+            state.hold_handled = false;
+        } else {
+            let hold_start = *state.hold_start.get_or_insert_with(Instant::now);
+            if !state.hold_handled && hold_start.elapsed() >= Duration::from_secs_f32(hold_s) {
+                state.hold_handled = true;
+                recenter_state(&mut state);
+            }
+        }
+    }
+
+    let correction = match state.correction {
+        Some(correction) => correction,
+        None => return,
+    };
+    for (_, motion) in &mut input.device_motions {
+        motion.orientation = (correction.rotation * motion.orientation).normalize();
+        motion.position =
+            correction.rotation * (motion.position - correction.origin) + correction.origin;
+        motion.linear_velocity = motion.linear_velocity.map(|v| correction.rotation * v);
+        motion.angular_velocity = motion.angular_velocity.map(|v| correction.rotation * v);
+    }
+}
@@ -0,0 +1,141 @@
+// Known servers, stored in servers.toml next to the config file. With several of them the user
+// picks one in the headset at startup, the engine lists them from the SelectingServer state and
+// calls select_server_profile().
+//
+//     [[server]]
+//     name = "Desk PC"
+//     address = "192.168.1.20"
+//     port = 9943                # optional, where the client handshake is sent
+//     encryption_key = "secret"  # optional, overrides --encryption-key
+//     pairing = true             # optional, same as --pairing
+use crate::{
+    config::{self, SecretString},
+    connection_state::{set_connection_state, ConnectionState},
+    APP_CONFIG,
+};
+use alvr_common::prelude::*;
+use alvr_sockets::CONTROL_PORT;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use std::{fs, net::Ipv4Addr, path::PathBuf};
+use tokio::sync::Notify;
+
+const SERVER_PROFILES_FILENAME: &str = "servers.toml";
+
+fn default_port() -> u16 {
+    CONTROL_PORT
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ServerProfile {
+    pub name: String,
+    pub address: Ipv4Addr,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub encryption_key: Option<SecretString>,
+    #[serde(default)]
+    pub pairing: bool,
+}
+
+#[derive(Deserialize, Default)]
+struct ServerProfilesFile {
+    #[serde(default)]
+    server: Vec<ServerProfile>,
+}
+
+#[derive(Clone, Debug)]
+pub enum ServerSelection {
+    // The client handshake is broadcasted, the first server to answer is used.
+    Discovery,
+    Profile(ServerProfile),
+}
+
+lazy_static! {
+    static ref SELECTION: Mutex<Option<ServerSelection>> = Mutex::new(None);
+    static ref SELECTION_NOTIFIER: Notify = Notify::new();
+}
+
+fn server_profiles_path() -> Option<PathBuf> {
+    config::config_file_path()?
+        .parent()
+        .map(|dir| dir.join(SERVER_PROFILES_FILENAME))
+}
+
+pub fn server_profiles() -> Vec<ServerProfile> {
+    let contents = match server_profiles_path().map(fs::read_to_string) {
+        Some(Ok(contents)) => contents,
+        _ => return vec![],
+    };
+    match toml::from_str::<ServerProfilesFile>(&contents) {
+        Ok(file) => file.server,
+        Err(e) => {
+            warn!("Invalid {SERVER_PROFILES_FILENAME}: {e}");
+            vec![]
+        }
+    }
+}
+
+// None searches for any server. Takes effect on the next connection attempt.
+pub fn select_server_profile(name: Option<&str>) -> StrResult {
+    let selection = match name {
+        Some(name) => match server_profiles()
+            .into_iter()
+            .find(|profile| profile.name == name)
+        {
+            Some(profile) => ServerSelection::Profile(profile),
+            None => return fmt_e!("Unknown server profile {name}"),
+        },
+        None => ServerSelection::Discovery,
+    };
+    info!("Selected server: {selection:?}");
+    *SELECTION.lock() = Some(selection);
+    SELECTION_NOTIFIER.notify_waiters();
+
+    Ok(())
+}
+
+// The picker is only needed with several profiles and no --server-profile, kiosks always use the
+// first one.
+fn initial_selection(profiles: &[ServerProfile]) -> Option<ServerSelection> {
+    if let Some(name) = &APP_CONFIG.server_profile {
+        match profiles.iter().find(|profile| &profile.name == name) {
+            Some(profile) => return Some(ServerSelection::Profile(profile.clone())),
+            None => warn!("Unknown server profile {name}"),
+        }
+    }
+    match profiles {
+        [] => Some(ServerSelection::Discovery),
+        [profile] => Some(ServerSelection::Profile(profile.clone())),
+        [profile, ..] if APP_CONFIG.kiosk => Some(ServerSelection::Profile(profile.clone())),
+        _ => None,
+    }
+}
+
+pub(crate) async fn wait_for_selection() -> ServerSelection {
+    if let Some(selection) = SELECTION.lock().clone() {
+        return selection;
+    }
+
+    let profiles = server_profiles();
+    if let Some(selection) = initial_selection(&profiles) {
+        *SELECTION.lock() = Some(selection.clone());
+        return selection;
+    }
+
+    let names = profiles
+        .into_iter()
+        .map(|profile| profile.name)
+        .collect::<Vec<_>>();
+    println!("Waiting for a server to be chosen: {}", names.join(", "));
+    set_connection_state(ConnectionState::SelectingServer { profiles: names });
+    loop {
+        // registered before checking, a selection made in between is not missed.
+        let notified = SELECTION_NOTIFIER.notified();
+        if let Some(selection) = SELECTION.lock().clone() {
+            return selection;
+        }
+        notified.await;
+    }
+}
@@ -0,0 +1,177 @@
+// Session start and end events for fleet monitoring, logged as json lines and optionally posted
+// to a webhook. A session starts when the stream starts and ends when it is lost, fails or the
+// app is paused.
+use crate::{
+    config,
+    connection_state::{subscribe_connection_state, ConnectionState},
+    APP_CONFIG,
+};
+use alvr_common::{prelude::*, ALVR_VERSION};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+    net::IpAddr,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(2);
+
+struct Session {
+    server: IpAddr,
+    start: Instant,
+}
+
+#[derive(Clone, Default)]
+struct ClientInfo {
+    device_name: String,
+    hostname: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case", tag = "event")]
+enum SessionEvent {
+    SessionStart {
+        server: IpAddr,
+    },
+    SessionEnd {
+        server: IpAddr,
+        duration_s: u64,
+        // None when the session ended without an error, like the app being paused.
+        error_category: Option<&'static str>,
+        error: Option<String>,
+    },
+}
+
+#[derive(Serialize)]
+struct SessionEventMessage {
+    #[serde(flatten)]
+    event: SessionEvent,
+    device_name: String,
+    hostname: String,
+    version: String,
+    timestamp: u64,
+}
+
+lazy_static! {
+    static ref SESSION: Mutex<Option<Session>> = Mutex::new(None);
+    static ref SESSION_SERVER: Mutex<Option<IpAddr>> = Mutex::new(None);
+    static ref CLIENT_INFO: Mutex<ClientInfo> = Mutex::new(ClientInfo::default());
+}
+
+fn is_enabled() -> bool {
+    APP_CONFIG.session_events || APP_CONFIG.session_webhook.is_some()
+}
+
+// The server of the stream about to start.
+pub(crate) fn set_session_server(server: IpAddr) {
+    *SESSION_SERVER.lock() = Some(server);
+}
+
+// Blocking, the webhook is posted with a short timeout.
+fn emit(event: SessionEvent) {
+    let client_info = CLIENT_INFO.lock().clone();
+    let message = SessionEventMessage {
+        event,
+        device_name: client_info.device_name,
+        hostname: client_info.hostname,
+        version: ALVR_VERSION.to_string(),
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs()),
+    };
+    let json = match serde_json::to_value(&message) {
+        Ok(json) => json,
+        Err(e) => {
+            warn!("Failed to serialize the session event: {e}");
+            return;
+        }
+    };
+    info!("Session event: {json}");
+    if let Some(config::SecretString(url)) = &APP_CONFIG.session_webhook {
+        // ureq errors contain the url, only their kind is logged.
+        match ureq::post(url).timeout(WEBHOOK_TIMEOUT).send_json(json) {
+            Ok(_) => (),
+            Err(ureq::Error::Status(code, _)) => {
+                warn!("The session webhook answered with status {code}")
+            }
+            Err(ureq::Error::Transport(e)) => warn!(
+                "Failed to post the session event to the webhook: {}",
+                e.kind()
+            ),
+        }
+    }
+}
+
+fn start_session() {
+    let server = match *SESSION_SERVER.lock() {
+        Some(server) => server,
+        None => return,
+    };
+    *SESSION.lock() = Some(Session {
+        server,
+        start: Instant::now(),
+    });
+    emit(SessionEvent::SessionStart { server });
+}
+
+fn end_session(error_category: Option<&'static str>, error: Option<String>) {
+    let session = match SESSION.lock().take() {
+        Some(session) => session,
+        None => return,
+    };
+    emit(SessionEvent::SessionEnd {
+        server: session.server,
+        duration_s: session.start.elapsed().as_secs(),
+        error_category,
+        error,
+    });
+}
+
+// Called on shutdown, before the runtime and the state subscription are gone.
+pub(crate) fn end_session_on_pause() {
+    if is_enabled() {
+        end_session(None, None);
+    }
+}
+
+fn error_category(state: &ConnectionState) -> (Option<&'static str>, Option<String>) {
+    match state {
+        ConnectionState::Reconnecting => (Some("connection_lost"), None),
+        ConnectionState::Error { cause } => (Some("error"), Some(cause.clone())),
+        ConnectionState::SessionLimitReached => (Some("session_limit"), None),
+        _ => (None, None),
+    }
+}
+
+pub(crate) async fn session_analytics_loop(device_name: String, hostname: String) {
+    if !is_enabled() {
+        return;
+    }
+    *CLIENT_INFO.lock() = ClientInfo {
+        device_name,
+        hostname,
+    };
+    let mut states = subscribe_connection_state();
+    while let Some(state) = states.recv().await {
+        let streaming = matches!(
+            state,
+            ConnectionState::Streaming | ConnectionState::Degraded { .. }
+        );
+        let in_session = SESSION.lock().is_some();
+        if streaming == in_session {
+            continue;
+        }
+        // posting the webhook blocks.
+        tokio::task::spawn_blocking(move || {
+            if streaming {
+                start_session();
+            } else {
+                let (category, error) = error_category(&state);
+                end_session(category, error);
+            }
+        })
+        .await
+        .ok();
+    }
+}
@@ -0,0 +1,103 @@
+// Time-boxed sessions for demo stations and parental control. The clock starts with the first
+// stream and keeps running across reconnects and headset removals. At --session-warning-min the
+// controllers vibrate, at --session-limit-min the connections stop until the companion resets
+// the clock or the app restarts.
+use crate::{
+    connection_state::{set_connection_state, subscribe_connection_state, ConnectionState},
+    APP_CONFIG,
+};
+use alvr_common::{prelude::*, LEFT_HAND_ID, RIGHT_HAND_ID};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    future,
+    time::{Duration, Instant},
+};
+use tokio::{sync::Notify, time};
+
+const WARNING_HAPTICS_DURATION_S: f32 = 1.0;
+
+lazy_static! {
+    static ref SESSION_START: Mutex<Option<Instant>> = Mutex::new(None);
+    static ref RESET_NOTIFIER: Notify = Notify::new();
+}
+
+fn minutes(value: u32) -> Option<Duration> {
+    (value > 0).then(|| Duration::from_secs(value as u64 * 60))
+}
+
+pub(crate) fn session_limit() -> Option<Duration> {
+    minutes(APP_CONFIG.session_limit_min)
+}
+
+// A warning at or after the limit is never given.
+pub(crate) fn session_warning() -> Option<Duration> {
+    minutes(APP_CONFIG.session_warning_min)
+        .filter(|warning| session_limit().map_or(true, |limit| *warning < limit))
+}
+
+// Zero until the first stream.
+pub(crate) fn session_elapsed() -> Duration {
+    SESSION_START
+        .lock()
+        .map_or(Duration::ZERO, |start| start.elapsed())
+}
+
+fn is_expired() -> bool {
+    session_limit().map_or(false, |limit| session_elapsed() >= limit)
+}
+
+// Restarts the clock, a stopped session connects again.
+pub(crate) fn reset_session_limit() {
+    info!("Session time limit reset");
+    *SESSION_START.lock() = None;
+    RESET_NOTIFIER.notify_waiters();
+}
+
+pub(crate) fn vibrate_controllers() {
+    for path in [*LEFT_HAND_ID, *RIGHT_HAND_ID] {
+        unsafe { crate::alxr_on_haptics_feedback(path, WARNING_HAPTICS_DURATION_S, 0_f32, 1_f32) };
+    }
+}
+
+// Returns at once, unless the limit was reached.
+pub(crate) async fn wait_for_session_reset() {
+    loop {
+        // created before the check so a reset in between isn't missed.
+        let reset = RESET_NOTIFIER.notified();
+        if !is_expired() {
+            return;
+        }
+        set_connection_state(ConnectionState::SessionLimitReached);
+        reset.await;
+    }
+}
+
+// Returns when the limit is reached, never without a limit.
+pub(crate) async fn session_limit_loop() {
+    let limit = match session_limit() {
+        Some(limit) => limit,
+        None => return future::pending().await,
+    };
+    if SESSION_START.lock().is_none() {
+        let mut states = subscribe_connection_state();
+        while let Some(state) = states.recv().await {
+            if state == ConnectionState::Streaming {
+                break;
+            }
+        }
+        SESSION_START.lock().get_or_insert_with(Instant::now);
+    }
+    // a warning already given before a reconnect isn't repeated.
+    if let Some(warning) = session_warning().filter(|warning| session_elapsed() < *warning) {
+        time::sleep(warning - session_elapsed()).await;
+        info!(
+            "Session time warning, {}s left",
+            (limit - warning).as_secs()
+        );
+        vibrate_controllers();
+    }
+    time::sleep(limit.saturating_sub(session_elapsed())).await;
+    info!("Session time limit reached, stopping the connections");
+    unsafe { crate::alxr_on_server_disconnect() };
+}
@@ -0,0 +1,146 @@
+// Sleep timer for users who fall asleep in media apps: --sleep-timer-min after the client started
+// or --sleep-inactivity-min without controller input, the controllers vibrate as a warning and a
+// minute later the client disconnects and exits. Any controller input during the warning restarts
+// both timers. A warning is always given, also when the headset was taken off past the deadline.
+use crate::{session_limit, APP_CONFIG};
+use alvr_common::prelude::*;
+use alvr_sockets::Input;
+use glam::Vec2;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    future,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+use tokio::time;
+
+const WARNING_BEFORE: Duration = Duration::from_secs(60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// Smaller changes of the triggers and joysticks are sensor noise, or a hand resting on them.
+const ANALOG_ACTIVITY_THRESHOLD: f32 = 0.1;
+
+#[derive(Clone, Copy, PartialEq)]
+struct ControllerControls {
+    buttons: u64,
+    trigger: f32,
+    grip: f32,
+    joystick: Vec2,
+    trackpad: Vec2,
+}
+
+impl ControllerControls {
+    fn moved_from(&self, other: &Self) -> bool {
+        self.buttons != other.buttons
+            || (self.trigger - other.trigger).abs() > ANALOG_ACTIVITY_THRESHOLD
+            || (self.grip - other.grip).abs() > ANALOG_ACTIVITY_THRESHOLD
+            || self.joystick.distance(other.joystick) > ANALOG_ACTIVITY_THRESHOLD
+            || self.trackpad.distance(other.trackpad) > ANALOG_ACTIVITY_THRESHOLD
+    }
+}
+
+struct SleepTimerState {
+    start: Instant,
+    last_activity: Instant,
+    last_controls: [Option<ControllerControls>; 2],
+    warned_at: Option<Instant>,
+}
+
+static EXIT_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref SLEEP_TIMER: Mutex<SleepTimerState> = Mutex::new(SleepTimerState {
+        start: Instant::now(),
+        last_activity: Instant::now(),
+        last_controls: [None; 2],
+        warned_at: None,
+    });
+}
+
+fn minutes(value: u32) -> Option<Duration> {
+    (value > 0).then(|| Duration::from_secs(value as u64 * 60))
+}
+
+fn is_enabled() -> bool {
+    APP_CONFIG.sleep_timer_min > 0 || APP_CONFIG.sleep_inactivity_min > 0
+}
+
+// Set once the sleep timer went off, the render loop should exit.
+pub fn is_exit_requested() -> bool {
+    EXIT_REQUESTED.load(Ordering::SeqCst)
+}
+
+fn deadline(state: &SleepTimerState) -> Option<Instant> {
+    let timer = minutes(APP_CONFIG.sleep_timer_min).map(|timer| state.start + timer);
+    let inactivity =
+        minutes(APP_CONFIG.sleep_inactivity_min).map(|inactivity| state.last_activity + inactivity);
+    timer.into_iter().chain(inactivity).min()
+}
+
+pub(crate) fn report_controller_activity(input: &Input) {
+    if !is_enabled() {
+        return;
+    }
+    let mut state = SLEEP_TIMER.lock();
+    let mut active = false;
+    for (controller, last_controls) in input
+        .legacy
+        .controllers
+        .iter()
+        .zip(state.last_controls.iter_mut())
+    {
+        if !controller.enabled {
+            continue;
+        }
+        let controls = ControllerControls {
+            buttons: controller.buttons,
+            trigger: controller.trigger_value,
+            grip: controller.grip_value,
+            joystick: controller.joystick_position,
+            trackpad: controller.trackpad_position,
+        };
+        if last_controls.map_or(true, |last_controls| controls.moved_from(&last_controls)) {
+            *last_controls = Some(controls);
+            active = true;
+        }
+    }
+    if !active {
+        return;
+    }
+    state.last_activity = Instant::now();
+    if state.warned_at.take().is_some() {
+        info!("Sleep timer restarted");
+        state.start = Instant::now();
+    }
+}
+
+// Returns when the timer went off, never without a timer.
+pub(crate) async fn sleep_timer_loop() {
+    if !is_enabled() {
+        return future::pending().await;
+    }
+    loop {
+        time::sleep(CHECK_INTERVAL).await;
+        let mut state = SLEEP_TIMER.lock();
+        match state.warned_at {
+            Some(warned_at) if warned_at.elapsed() >= WARNING_BEFORE => break,
+            Some(_) => (),
+            None => {
+                let remaining = deadline(&state).map_or(Duration::MAX, |deadline| {
+                    deadline.saturating_duration_since(Instant::now())
+                });
+                if remaining <= WARNING_BEFORE {
+                    info!(
+                        "Sleep timer warning, exiting in {}s",
+                        WARNING_BEFORE.as_secs()
+                    );
+                    state.warned_at = Some(Instant::now());
+                    session_limit::vibrate_controllers();
+                }
+            }
+        }
+    }
+    info!("Sleep timer went off, disconnecting and exiting");
+    EXIT_REQUESTED.store(true, Ordering::SeqCst);
+    unsafe { crate::alxr_on_server_disconnect() };
+}
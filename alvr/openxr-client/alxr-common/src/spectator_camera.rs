@@ -0,0 +1,82 @@
+// A virtual camera placed by the user, which servers with a spectator view render from. It is
+// placed at the right controller with a held gesture, or set by the companion app, and resent
+// on every connection.
+use crate::APP_CONFIG;
+use alvr_common::{prelude::*, RIGHT_HAND_ID};
+use alvr_sockets::{Input, SpectatorCameraPacket};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+// ALVR_INPUT_GRIP_CLICK and ALVR_INPUT_TRIGGER_CLICK, checked before the binding profile remaps
+// the buttons.
+const GRIP_CLICK_FLAG: u64 = 1 << 2;
+const TRIGGER_CLICK_FLAG: u64 = 1 << 34;
+
+const DEFAULT_FOV_DEGREES: f32 = 90.;
+
+#[derive(Default)]
+struct GestureState {
+    hold_start: Option<Instant>,
+    hold_handled: bool,
+}
+
+lazy_static! {
+    pub(crate) static ref SPECTATOR_CAMERA_SENDER: Mutex<Option<mpsc::UnboundedSender<Option<SpectatorCameraPacket>>>> =
+        Mutex::new(None);
+    static ref SPECTATOR_CAMERA: Mutex<Option<SpectatorCameraPacket>> = Mutex::new(None);
+    static ref GESTURE_STATE: Mutex<GestureState> = Mutex::new(GestureState::default());
+}
+
+// None removes the camera. Takes effect right away when connected.
+pub fn set_spectator_camera(camera: Option<SpectatorCameraPacket>) {
+    info!("Spectator camera set to {camera:?}");
+    *SPECTATOR_CAMERA.lock() = camera;
+    if let Some(sender) = &*SPECTATOR_CAMERA_SENDER.lock() {
+        sender.send(camera).ok();
+    }
+}
+
+// The camera placed before the connection, the server forgets it on disconnect.
+pub(crate) fn spectator_camera() -> Option<SpectatorCameraPacket> {
+    *SPECTATOR_CAMERA.lock()
+}
+
+// Places the camera at the right controller once grip and trigger are held together. Runs after
+// recentering, the camera is in the frame of the poses sent to the server.
+pub(crate) fn apply_spectator_gesture(input: &Input) {
+    let hold_s = match APP_CONFIG
+        .spectator_camera_hold_s
+        .filter(|hold_s| *hold_s > 0_f32)
+    {
+        Some(hold_s) => hold_s,
+        None => return,
+    };
+    let mut state = GESTURE_STATE.lock();
+    let controller = &input.legacy.controllers[1];
+    let gesture_flags = GRIP_CLICK_FLAG | TRIGGER_CLICK_FLAG;
+    if !controller.enabled || controller.buttons & gesture_flags != gesture_flags {
+        state.hold_start = None;
+        state.hold_handled = false;
+        return;
+    }
+    let hold_start = *state.hold_start.get_or_insert_with(Instant::now);
+    if state.hold_handled || hold_start.elapsed() < Duration::from_secs_f32(hold_s) {
+        return;
+    }
+    state.hold_handled = true;
+    match input
+        .device_motions
+        .iter()
+        .find(|(id, _)| *id == *RIGHT_HAND_ID)
+    {
+        Some((_, motion)) => set_spectator_camera(Some(SpectatorCameraPacket {
+            orientation: motion.orientation,
+            position: motion.position,
+            fov_degrees: spectator_camera()
+                .map_or(DEFAULT_FOV_DEGREES, |camera| camera.fov_degrees),
+        })),
+        None => warn!("Can't place the spectator camera without a right controller pose"),
+    }
+}
@@ -0,0 +1,46 @@
+use alvr_common::prelude::*;
+use alvr_sockets::StartupStage;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+lazy_static! {
+    static ref LAUNCH_INSTANT: Instant = Instant::now();
+    static ref STAGES: Mutex<Vec<StartupStage>> = Mutex::new(vec![]);
+}
+
+static REPORTED: AtomicBool = AtomicBool::new(false);
+
+// Only the first time a stage is reached counts, later reconnects and session restarts
+// are not part of the startup. The first stage marked is the time origin.
+pub fn mark_startup_stage(name: &str) {
+    let elapsed_ms = LAUNCH_INSTANT.elapsed().as_millis() as u32;
+    let mut stages = STAGES.lock();
+    if stages.iter().all(|stage| stage.name != name) {
+        stages.push(StartupStage {
+            name: name.into(),
+            elapsed_ms,
+        });
+    }
+}
+
+// Returns the stages once, after the first frame.
+pub(crate) fn take_startup_report() -> Option<Vec<StartupStage>> {
+    let stages = STAGES.lock().clone();
+    if stages.iter().all(|stage| stage.name != "first_frame")
+        || REPORTED.swap(true, Ordering::Relaxed)
+    {
+        return None;
+    }
+    let summary = stages
+        .iter()
+        .map(|stage| format!("{} {}ms", stage.name, stage.elapsed_ms))
+        .collect::<Vec<_>>()
+        .join(", ");
+    info!("Startup timings: {summary}");
+    println!("Startup timings: {summary}");
+    Some(stages)
+}
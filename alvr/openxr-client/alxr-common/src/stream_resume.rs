@@ -0,0 +1,65 @@
+// The parameters of the last stream are offered to the same server on reconnect. While its
+// settings are unchanged the server hands them back instead of deriving new ones from the headset
+// info, so a brief network drop does not restart SteamVR. They are saved next to the config file
+// to also survive a restart of the client.
+use crate::{config, APP_CONFIG};
+use alvr_common::prelude::*;
+use alvr_sockets::StreamResumePacket;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{fs, net::IpAddr, path::PathBuf};
+
+const LAST_STREAM_FILENAME: &str = "last_stream.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+struct LastStream {
+    server_ip: IpAddr,
+    stream: StreamResumePacket,
+}
+
+lazy_static! {
+    static ref LAST_STREAM: Mutex<Option<LastStream>> = Mutex::new(load_last_stream());
+}
+
+fn last_stream_path() -> Option<PathBuf> {
+    config::config_file_path()?
+        .parent()
+        .map(|dir| dir.join(LAST_STREAM_FILENAME))
+}
+
+fn load_last_stream() -> Option<LastStream> {
+    let contents = fs::read_to_string(last_stream_path()?).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn write_last_stream(last_stream: &LastStream) -> StrResult {
+    let path = trace_none!(last_stream_path())?;
+    if let Some(dir) = path.parent() {
+        trace_err!(fs::create_dir_all(dir))?;
+    }
+    trace_err!(fs::write(
+        &path,
+        trace_err!(serde_json::to_string(last_stream))?
+    ))
+}
+
+pub(crate) fn resume_offer(server_ip: IpAddr) -> Option<StreamResumePacket> {
+    if APP_CONFIG.no_stream_resume {
+        return None;
+    }
+    LAST_STREAM
+        .lock()
+        .as_ref()
+        .filter(|last| last.server_ip == server_ip)
+        .map(|last| last.stream.clone())
+}
+
+pub(crate) fn save(server_ip: IpAddr, stream: StreamResumePacket) {
+    let last_stream = LastStream { server_ip, stream };
+    // kept in memory anyway, it helps reconnecting without a config directory.
+    if let Err(e) = write_last_stream(&last_stream) {
+        debug!("Last stream parameters not saved: {e}");
+    }
+    *LAST_STREAM.lock() = Some(last_stream);
+}
@@ -0,0 +1,87 @@
+// Shutdown runs in named stages, in the order they are given. A stage that overruns its deadline
+// is logged and abandoned on its helper thread instead of hanging the app, and whatever is still
+// held at the end is reported as leaked.
+use alvr_common::prelude::*;
+use std::{
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const DEFAULT_STAGE_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct Teardown {
+    name: &'static str,
+    start: Instant,
+    abandoned: Vec<&'static str>,
+}
+
+impl Teardown {
+    pub fn new(name: &'static str) -> Self {
+        info!("Teardown of {name} started");
+        Self {
+            name,
+            start: Instant::now(),
+            abandoned: vec![],
+        }
+    }
+
+    // Runs the stage on a helper thread and stops waiting for it after `timeout`.
+    pub fn stage(
+        &mut self,
+        name: &'static str,
+        timeout: Duration,
+        stage: impl FnOnce() + Send + 'static,
+    ) {
+        let start = Instant::now();
+        let (done_sender, done_receiver) = mpsc::channel();
+        let spawned = thread::Builder::new()
+            .name(format!("teardown-{name}"))
+            .spawn(move || {
+                stage();
+                done_sender.send(()).ok();
+            });
+        if let Err(e) = spawned {
+            warn!("Teardown stage {name} not started: {e}");
+            self.abandoned.push(name);
+            return;
+        }
+        match done_receiver.recv_timeout(timeout) {
+            Ok(()) => debug!("Teardown stage {name} done in {:?}", start.elapsed()),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                warn!("Teardown stage {name} did not finish in {timeout:?}, abandoned");
+                self.abandoned.push(name);
+            }
+            // the stage panicked, the panic hook already reported it.
+            Err(mpsc::RecvTimeoutError::Disconnected) => self.abandoned.push(name),
+        }
+    }
+
+    // For stages bound to the calling thread, like the engine and its graphics context. They can
+    // only be timed.
+    pub fn stage_on_current_thread(&mut self, name: &'static str, stage: impl FnOnce()) {
+        let start = Instant::now();
+        stage();
+        let elapsed = start.elapsed();
+        if elapsed > DEFAULT_STAGE_TIMEOUT {
+            warn!("Teardown stage {name} took {elapsed:?}");
+        } else {
+            debug!("Teardown stage {name} done in {elapsed:?}");
+        }
+    }
+
+    // `leaks` names the resources still held after the stages ran.
+    pub fn finish(self, leaks: &[&str]) {
+        info!(
+            "Teardown of {} done in {:?}",
+            self.name,
+            self.start.elapsed()
+        );
+        if !self.abandoned.is_empty() || !leaks.is_empty() {
+            warn!(
+                "Teardown of {} leaked: abandoned stages {:?}, still held {:?}",
+                self.name, self.abandoned, leaks
+            );
+        }
+    }
+}
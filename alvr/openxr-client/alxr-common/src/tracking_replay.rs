@@ -0,0 +1,132 @@
+// Records the input packets sent to the server, with the time they were sent, and replays them
+// in place of the headset's own tracking. Replays make server side issues reproducible without a
+// headset, e.g. with --simulate-headless. Facial data is sent by the engine and not recorded.
+//
+// The file is the format version followed by bincode encoded records.
+use crate::config;
+use alvr_common::prelude::*;
+use alvr_sockets::Input;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+
+const RECORDING_VERSION: u32 = 1;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+// Time since the first record, and the input.
+type TrackingRecord = (Duration, Input);
+
+struct Recorder {
+    writer: BufWriter<File>,
+    start: Instant,
+    last_flush: Instant,
+}
+
+enum RecorderState {
+    Idle,
+    Recording(Recorder),
+    // not retried, the error was logged once.
+    Failed,
+}
+
+lazy_static! {
+    static ref RECORDER: Mutex<RecorderState> = Mutex::new(RecorderState::Idle);
+}
+
+fn create_recorder(path: &Path) -> StrResult<Recorder> {
+    let path = config::resolve_path(path);
+    let mut writer = BufWriter::new(trace_err!(File::create(path))?);
+    trace_err!(bincode::serialize_into(&mut writer, &RECORDING_VERSION))?;
+    info!("Recording tracking to {}", path.display());
+    Ok(Recorder {
+        writer,
+        start: Instant::now(),
+        last_flush: Instant::now(),
+    })
+}
+
+fn write_record(recorder: &mut Recorder, input: &Input) -> StrResult {
+    trace_err!(bincode::serialize_into(
+        &mut recorder.writer,
+        &(recorder.start.elapsed(), input)
+    ))?;
+    if recorder.last_flush.elapsed() > FLUSH_INTERVAL {
+        trace_err!(recorder.writer.flush())?;
+        recorder.last_flush = Instant::now();
+    }
+    Ok(())
+}
+
+// Called with every input packet before it is sent.
+pub(crate) fn record_input(path: &Path, input: &Input) {
+    let mut state = RECORDER.lock();
+    if let RecorderState::Idle = *state {
+        *state = match create_recorder(path) {
+            Ok(recorder) => RecorderState::Recording(recorder),
+            Err(e) => {
+                warn!("Tracking recording disabled: {e}");
+                RecorderState::Failed
+            }
+        };
+    }
+    if let RecorderState::Recording(recorder) = &mut *state {
+        if let Err(e) = write_record(recorder, input) {
+            warn!("Tracking recording stopped: {e}");
+            *state = RecorderState::Failed;
+        }
+    }
+}
+
+fn load_recording(path: &Path) -> StrResult<Vec<TrackingRecord>> {
+    let mut reader = BufReader::new(trace_err!(File::open(path))?);
+    let version: u32 = trace_err!(bincode::deserialize_from(&mut reader))?;
+    if version != RECORDING_VERSION {
+        return fmt_e!("Unsupported tracking recording version {version}");
+    }
+    let mut records = vec![];
+    loop {
+        match bincode::deserialize_from(&mut reader) {
+            Ok(record) => records.push(record),
+            Err(e) => match *e {
+                bincode::ErrorKind::Io(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                e => return fmt_e!("Invalid tracking recording: {e}"),
+            },
+        }
+    }
+    Ok(records)
+}
+
+// Sends the recorded inputs with their original timing, from the start again once the end is
+// reached. Returns once the connection stops receiving them.
+pub(crate) async fn replay_loop(path: PathBuf, sender: mpsc::UnboundedSender<Input>) {
+    let loaded = trace_err!(tokio::task::spawn_blocking(move || load_recording(&path)).await)
+        .and_then(|records| records);
+    let records = match loaded {
+        Ok(records) if !records.is_empty() => records,
+        Ok(_) => {
+            warn!("The tracking recording is empty");
+            return;
+        }
+        Err(e) => {
+            warn!("Failed to load the tracking recording: {e}");
+            return;
+        }
+    };
+    info!("Replaying {} tracking records", records.len());
+    loop {
+        let start = tokio::time::Instant::now();
+        for (time, input) in &records {
+            tokio::time::sleep_until(start + *time).await;
+            if sender.send(input.clone()).is_err() {
+                return;
+            }
+        }
+        debug!("Tracking replay restarted");
+    }
+}
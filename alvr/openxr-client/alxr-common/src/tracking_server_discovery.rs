@@ -0,0 +1,84 @@
+use crate::{feature_gating::GatedFeatures, ALXRSystemProperties, APP_CONFIG};
+use alvr_common::{prelude::*, ALVR_VERSION};
+use lazy_static::lazy_static;
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use parking_lot::Mutex;
+
+// Consumers browse for this type, the instance name is only for display.
+const SERVICE_TYPE: &str = "_alxr-tracking._tcp.local.";
+
+lazy_static! {
+    static ref DAEMON: Mutex<Option<ServiceDaemon>> = Mutex::new(None);
+}
+
+fn host_label(system_name: &str) -> String {
+    let label: String = system_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let label = label.trim_matches('-');
+    if label.is_empty() {
+        "alxr".into()
+    } else {
+        label.to_lowercase()
+    }
+}
+
+fn register(sys_properties: &ALXRSystemProperties, features: &GatedFeatures) -> StrResult {
+    let system_name = sys_properties.system_name();
+    let version = ALVR_VERSION.to_string();
+    let eye_tracking = format!("{:?}", features.eye_tracking);
+    let facial_tracking = format!("{:?}", features.facial_tracking);
+    // the engine's enabled tracking systems bit flags.
+    let tracking_systems = sys_properties.enabledTrackingSystemsFlags.to_string();
+    let properties = [
+        ("version", version.as_str()),
+        ("device", system_name.as_str()),
+        ("eye_tracking", eye_tracking.as_str()),
+        ("facial_tracking", facial_tracking.as_str()),
+        ("tracking_systems", tracking_systems.as_str()),
+    ];
+    let service = trace_err!(ServiceInfo::new(
+        SERVICE_TYPE,
+        &format!("ALXR {system_name}"),
+        &format!("{}.local.", host_label(&system_name)),
+        "",
+        APP_CONFIG.tracking_server_port_no,
+        &properties[..],
+    ))?
+    .enable_addr_auto();
+
+    let daemon = trace_err!(ServiceDaemon::new())?;
+    trace_err!(daemon.register(service))?;
+    if let Some(previous) = DAEMON.lock().replace(daemon) {
+        previous.shutdown().ok();
+    }
+    Ok(())
+}
+
+// mDNS answers queries sent to a multicast group, android drops those without a multicast lock.
+pub fn is_tracking_server_discovery_enabled() -> bool {
+    !APP_CONFIG.no_tracking_server && !APP_CONFIG.no_tracking_server_discovery
+}
+
+// Advertises the tracking server of the current engine instance over mDNS, replacing the
+// advertisement of a previous instance.
+pub fn advertise_tracking_server(sys_properties: &ALXRSystemProperties, features: &GatedFeatures) {
+    if !is_tracking_server_discovery_enabled() {
+        return;
+    }
+    match register(sys_properties, features) {
+        Ok(()) => info!("Advertising the tracking server as {SERVICE_TYPE}"),
+        Err(e) => warn!("Failed to advertise the tracking server: {e}"),
+    }
+}
+
+pub(crate) fn is_advertising() -> bool {
+    DAEMON.lock().is_some()
+}
+
+pub(crate) fn stop_tracking_server_advertisement() {
+    if let Some(daemon) = DAEMON.lock().take() {
+        daemon.shutdown().ok();
+    }
+}
@@ -0,0 +1,83 @@
+use alvr_common::{prelude::*, LEFT_HAND_ID, RIGHT_HAND_ID};
+use alvr_sockets::{Input, MotionData, TrackingStatePacket};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+
+// Runtimes keep returning the last known pose bit for bit once tracking is lost, a real
+// tracked pose always jitters slightly. ~1s at 90hz.
+const STALE_FRAME_COUNT: u32 = 90;
+
+const MAX_ORIENTATION_LENGTH_ERROR: f32 = 0.1;
+
+#[derive(Default)]
+struct DeviceTracking {
+    last_motion: Option<([f32; 4], [f32; 3])>,
+    unchanged_frames: u32,
+    lost: bool,
+}
+
+impl DeviceTracking {
+    fn update(&mut self, motion: &MotionData, enabled: bool) -> bool {
+        let orientation = motion.orientation.to_array();
+        let position = motion.position.to_array();
+        let is_valid_pose = orientation.iter().chain(&position).all(|v| v.is_finite())
+            && (motion.orientation.length() - 1_f32).abs() < MAX_ORIENTATION_LENGTH_ERROR;
+
+        if self.last_motion == Some((orientation, position)) {
+            self.unchanged_frames = self.unchanged_frames.saturating_add(1);
+        } else {
+            self.unchanged_frames = 0;
+        }
+        self.last_motion = Some((orientation, position));
+
+        enabled && is_valid_pose && self.unchanged_frames < STALE_FRAME_COUNT
+    }
+}
+
+lazy_static! {
+    pub(crate) static ref TRACKING_STATE_SENDER: Mutex<Option<mpsc::UnboundedSender<TrackingStatePacket>>> =
+        Mutex::new(None);
+    static ref DEVICES: Mutex<HashMap<u64, DeviceTracking>> = Mutex::new(HashMap::new());
+}
+
+// The server assumes every device is tracked when a connection starts.
+pub(crate) fn reset_tracking_state() {
+    DEVICES.lock().clear();
+}
+
+pub(crate) fn update_tracking_state(input: &Input) {
+    let sender = TRACKING_STATE_SENDER.lock();
+    let sender = match &*sender {
+        Some(sender) => sender,
+        None => return,
+    };
+    let mut devices = DEVICES.lock();
+    for (device_id, motion) in &input.device_motions {
+        let enabled = if *device_id == *LEFT_HAND_ID {
+            input.legacy.controllers[0].enabled
+        } else if *device_id == *RIGHT_HAND_ID {
+            input.legacy.controllers[1].enabled
+        } else {
+            true
+        };
+        let device = devices.entry(*device_id).or_default();
+        let tracked = device.update(motion, enabled);
+        if tracked == !device.lost {
+            continue;
+        }
+        device.lost = !tracked;
+        if tracked {
+            info!("Tracking resumed for device {device_id}");
+        } else {
+            warn!("Tracking lost for device {device_id}");
+        }
+        sender
+            .send(TrackingStatePacket {
+                device_id: *device_id,
+                tracked,
+            })
+            .ok();
+    }
+}
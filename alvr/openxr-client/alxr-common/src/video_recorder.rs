@@ -0,0 +1,230 @@
+use crate::{
+    config,
+    mkv_writer::{self, MkvWriter, VideoCodec},
+    APP_CONFIG,
+};
+use alvr_common::prelude::*;
+use alvr_sockets::VideoFrameHeaderPacket;
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+// Same as ALVR_MAX_VIDEO_BUFFER_SIZE on the server, the payload size of FEC packets.
+const FEC_PACKET_PAYLOAD_SIZE: usize = 1400;
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy)]
+pub(crate) struct StreamFormat {
+    pub codec: VideoCodec,
+    pub width: u32,
+    pub height: u32,
+    pub ten_bit: bool,
+    pub fec: bool,
+}
+
+pub(crate) struct EncodedFrame {
+    pub timestamp_ns: u64,
+    pub data: Vec<u8>,
+}
+
+struct PendingFrame {
+    video_frame_index: u64,
+    tracking_frame_index: u64,
+    data: Vec<u8>,
+    received_bytes: usize,
+}
+
+// Frames are rebuilt from their data packets, FEC parity packets are not used. A frame missing
+// a data packet is not recorded.
+pub(crate) struct FrameAssembler {
+    fec: bool,
+    pending: Option<PendingFrame>,
+}
+
+impl FrameAssembler {
+    pub fn new(fec: bool) -> Self {
+        Self { fec, pending: None }
+    }
+
+    pub fn push(&mut self, header: &VideoFrameHeaderPacket, buffer: &[u8]) -> Option<EncodedFrame> {
+        let frame_size = header.frame_byte_size as usize;
+        if !self.fec {
+            return (buffer.len() == frame_size).then(|| EncodedFrame {
+                timestamp_ns: header.tracking_frame_index,
+                data: buffer.to_vec(),
+            });
+        }
+
+        let offset = header.fec_index as usize * FEC_PACKET_PAYLOAD_SIZE;
+        if offset >= frame_size {
+            return None;
+        }
+        if self.pending.as_ref().map_or(true, |frame| {
+            frame.video_frame_index != header.video_frame_index
+        }) {
+            self.pending = Some(PendingFrame {
+                video_frame_index: header.video_frame_index,
+                tracking_frame_index: header.tracking_frame_index,
+                data: vec![0; frame_size],
+                received_bytes: 0,
+            });
+        }
+        let frame = self.pending.as_mut()?;
+        let len = buffer.len().min(frame_size - offset);
+        frame.data[offset..offset + len].copy_from_slice(&buffer[..len]);
+        frame.received_bytes += len;
+        if frame.received_bytes < frame_size {
+            return None;
+        }
+        self.pending.take().map(|frame| EncodedFrame {
+            timestamp_ns: frame.tracking_frame_index,
+            data: frame.data,
+        })
+    }
+}
+
+struct Segment {
+    writer: MkvWriter<BufWriter<File>>,
+    first_timestamp_ns: u64,
+    started: Instant,
+}
+
+// <dir>/<stem>_<unix ms>.mkv, the extension of the configured path is ignored.
+fn segment_path(base: &Path) -> PathBuf {
+    let stem = base
+        .file_stem()
+        .map_or_else(|| "alxr_recording".into(), |stem| stem.to_string_lossy());
+    let unix_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis());
+    base.with_file_name(format!("{stem}_{unix_ms}.mkv"))
+}
+
+struct Recorder {
+    base_path: PathBuf,
+    format: StreamFormat,
+    segment_duration: Option<Duration>,
+    segment: Option<Segment>,
+    idr_requested: bool,
+}
+
+impl Recorder {
+    fn start_segment(&self, nals: &[&[u8]], timestamp_ns: u64) -> StrResult<Option<Segment>> {
+        let codec_private =
+            match mkv_writer::decoder_config(self.format.codec, nals, self.format.ten_bit) {
+                Some(codec_private) => codec_private,
+                // keyframes without parameter sets can't start a file.
+                None => return Ok(None),
+            };
+        let path = segment_path(&self.base_path);
+        if let Some(dir) = path.parent() {
+            trace_err!(fs::create_dir_all(dir))?;
+        }
+        let file = BufWriter::new(trace_err!(File::create(&path))?);
+        let writer = trace_err!(MkvWriter::new(
+            file,
+            self.format.codec,
+            &codec_private,
+            self.format.width,
+            self.format.height,
+        ))?;
+        info!("Recording video to {}", path.display());
+        Ok(Some(Segment {
+            writer,
+            first_timestamp_ns: timestamp_ns,
+            started: Instant::now(),
+        }))
+    }
+
+    fn write_frame(&mut self, frame: &EncodedFrame) -> StrResult {
+        let nals = mkv_writer::split_nals(&frame.data);
+        let keyframe = nals
+            .iter()
+            .any(|nal| self.format.codec.is_keyframe_nal(nal));
+
+        // segments are cut on keyframes so every file decodes on its own, the server only sends
+        // keyframes on request.
+        let segment_due = match (&self.segment, self.segment_duration) {
+            (Some(segment), Some(duration)) => segment.started.elapsed() >= duration,
+            (None, _) => true,
+            _ => false,
+        };
+        if segment_due && keyframe {
+            if let Some(mut segment) = self.segment.take() {
+                trace_err!(segment.writer.flush())?;
+            }
+            self.segment = self.start_segment(&nals, frame.timestamp_ns)?;
+            self.idr_requested = false;
+        } else if segment_due && !self.idr_requested {
+            crate::IDR_REQUEST_NOTIFIER.notify_waiters();
+            self.idr_requested = true;
+        }
+
+        if let Some(segment) = &mut self.segment {
+            let timestamp_ms = frame
+                .timestamp_ns
+                .saturating_sub(segment.first_timestamp_ns)
+                / 1_000_000;
+            trace_err!(segment.writer.write_frame(timestamp_ms, &nals, keyframe))?;
+        }
+        Ok(())
+    }
+}
+
+// Muxing and file IO run on a separate thread, the video receive loop only rebuilds frames.
+pub(crate) struct VideoRecorder {
+    assembler: FrameAssembler,
+    sender: mpsc::Sender<EncodedFrame>,
+}
+
+impl VideoRecorder {
+    // A new recorder is started for every stream, as the stream format may change.
+    pub fn start(format: StreamFormat) -> Option<Self> {
+        let base_path = config::resolve_path(APP_CONFIG.record.as_deref()?);
+        let segment_duration = (APP_CONFIG.record_segment_s > 0)
+            .then(|| Duration::from_secs(APP_CONFIG.record_segment_s as u64));
+        let assembler = FrameAssembler::new(format.fec);
+        let mut recorder = Recorder {
+            base_path,
+            format,
+            segment_duration,
+            segment: None,
+            idr_requested: false,
+        };
+        let (sender, receiver) = mpsc::channel::<EncodedFrame>();
+        thread::spawn(move || loop {
+            match receiver.recv_timeout(FLUSH_INTERVAL) {
+                Ok(frame) => {
+                    if let Err(e) = recorder.write_frame(&frame) {
+                        warn!("Video recording stopped: {e}");
+                        return;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    if let Some(segment) = &mut recorder.segment {
+                        segment.writer.flush().ok();
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    if let Some(segment) = &mut recorder.segment {
+                        segment.writer.flush().ok();
+                    }
+                    return;
+                }
+            }
+        });
+        Some(Self { assembler, sender })
+    }
+
+    pub fn push_packet(&mut self, header: &VideoFrameHeaderPacket, buffer: &[u8]) {
+        if let Some(frame) = self.assembler.push(header, buffer) {
+            self.sender.send(frame).ok();
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use alvr_sockets::VideoPacketStats;
+use std::collections::{BTreeMap, VecDeque};
+
+// Counters of packets skipped over are kept this long to tell late packets from duplicates.
+const SKIPPED_HISTORY_LEN: usize = 1024;
+
+// Jumps further ahead than this are treated as a counter reset on the server.
+const RESYNC_DISTANCE: i32 = 1 << 16;
+
+// Restores the server send order of video packets using their packet counter. Packets after a
+// gap are held back until the gap is filled or more than `window` packets are pending, then
+// the gap is counted as lost. The engine keeps doing its own loss detection on the
+// delivered packets.
+pub struct VideoReorderBuffer<T> {
+    window: usize,
+    next_counter: Option<u32>,
+    pending: BTreeMap<u32, T>,
+    skipped: VecDeque<u32>,
+    stats: VideoPacketStats,
+}
+
+impl<T> VideoReorderBuffer<T> {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window,
+            next_counter: None,
+            pending: BTreeMap::new(),
+            skipped: VecDeque::new(),
+            stats: VideoPacketStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> VideoPacketStats {
+        self.stats
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    // Packets ready to be decoded are appended to `ready` in order.
+    pub fn push(&mut self, counter: u32, packet: T, ready: &mut Vec<T>) {
+        self.stats.received += 1;
+
+        let next_counter = match self.next_counter {
+            Some(next_counter) => next_counter,
+            None => {
+                self.deliver(counter, packet, ready);
+                return;
+            }
+        };
+
+        // packet counters wrap around.
+        let distance = counter.wrapping_sub(next_counter) as i32;
+        if distance < 0 {
+            if let Some(index) = self.skipped.iter().position(|&c| c == counter) {
+                self.skipped.remove(index);
+                self.stats.late += 1;
+                self.stats.lost = self.stats.lost.saturating_sub(1);
+            } else {
+                self.stats.duplicate += 1;
+            }
+        } else if distance == 0 {
+            if !self.pending.is_empty() {
+                self.stats.reordered += 1;
+            }
+            self.deliver(counter, packet, ready);
+            self.drain_in_order(ready);
+        } else if distance > RESYNC_DISTANCE {
+            ready.extend(std::mem::take(&mut self.pending).into_values());
+            self.skipped.clear();
+            self.deliver(counter, packet, ready);
+        } else if self.pending.contains_key(&counter) {
+            self.stats.duplicate += 1;
+        } else {
+            self.pending.insert(counter, packet);
+            if self.pending.len() > self.window {
+                self.skip_gap(ready);
+            }
+        }
+    }
+
+    // Gives up on the oldest gap, used when the window overflows or packets stop arriving.
+    pub fn skip_gap(&mut self, ready: &mut Vec<T>) {
+        let next_counter = match self.next_counter {
+            Some(next_counter) => next_counter,
+            None => return,
+        };
+        // not simply the smallest key, counters may have wrapped around.
+        let first_pending = match self
+            .pending
+            .keys()
+            .min_by_key(|&&counter| counter.wrapping_sub(next_counter))
+        {
+            Some(&first_pending) => first_pending,
+            None => return,
+        };
+        let mut counter = next_counter;
+        while counter != first_pending {
+            self.stats.lost += 1;
+            self.skipped.push_back(counter);
+            if self.skipped.len() > SKIPPED_HISTORY_LEN {
+                self.skipped.pop_front();
+            }
+            counter = counter.wrapping_add(1);
+        }
+        self.next_counter = Some(first_pending);
+        self.drain_in_order(ready);
+    }
+
+    fn deliver(&mut self, counter: u32, packet: T, ready: &mut Vec<T>) {
+        ready.push(packet);
+        self.next_counter = Some(counter.wrapping_add(1));
+    }
+
+    fn drain_in_order(&mut self, ready: &mut Vec<T>) {
+        while let Some(counter) = self.next_counter {
+            match self.pending.remove(&counter) {
+                Some(packet) => self.deliver(counter, packet, ready),
+                None => break,
+            }
+        }
+    }
+}
@@ -0,0 +1,42 @@
+// Moves the user in the game world by transforming every pose sent to the server, for playspace
+// mover tools on the server side. The offset is set by the server or through set_world_offset,
+// and is kept across reconnects until the client restarts.
+use alvr_common::prelude::*;
+use alvr_sockets::{Input, WorldOffsetPacket};
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+
+lazy_static! {
+    static ref WORLD_OFFSET: RwLock<Option<WorldOffsetPacket>> = RwLock::new(None);
+}
+
+// The rotation is about the origin of the stage, after the recentering. None removes the offset.
+pub fn set_world_offset(offset: Option<WorldOffsetPacket>) {
+    let offset = match offset {
+        Some(offset) if !offset.orientation.is_finite() || !offset.position.is_finite() => {
+            warn!("Ignoring an invalid world offset: {offset:?}");
+            return;
+        }
+        Some(offset) => Some(WorldOffsetPacket {
+            orientation: offset.orientation.normalize(),
+            position: offset.position,
+        }),
+        None => None,
+    };
+    debug!("World offset set to {offset:?}");
+    *WORLD_OFFSET.write() = offset;
+}
+
+// Velocities must still be in the stage frame.
+pub(crate) fn apply_world_offset(input: &mut Input) {
+    let offset = match *WORLD_OFFSET.read() {
+        Some(offset) => offset,
+        None => return,
+    };
+    for (_, motion) in &mut input.device_motions {
+        motion.orientation = (offset.orientation * motion.orientation).normalize();
+        motion.position = offset.orientation * motion.position + offset.position;
+        motion.linear_velocity = motion.linear_velocity.map(|v| offset.orientation * v);
+        motion.angular_velocity = motion.angular_velocity.map(|v| offset.orientation * v);
+    }
+}
@@ -57,8 +57,15 @@ fn cmake_option_from_feature(feature_name: &str) -> &'static str {
     cmake_option_from_bool(is_feature_enabled(&feature_name))
 }
 
-const FALVOR_FEATURE_NAMES: [&'static str; 2] = ["GENERIC_FLAVOR", "PICO_FLAVOR"];
-const GRADLE_FLAVOR_NAMES: [&'static str; 2] = ["Generic", "PicoMobileOXR"];
+const FALVOR_FEATURE_NAMES: [&'static str; 5] = [
+    "GENERIC_FLAVOR",
+    "PICO_FLAVOR",
+    "SPACES_FLAVOR",
+    "ML2_FLAVOR",
+    "YVR_FLAVOR",
+];
+const GRADLE_FLAVOR_NAMES: [&'static str; 5] =
+    ["Generic", "PicoMobileOXR", "Spaces", "MagicLeap2", "YVR"];
 
 fn get_product_flavour() -> &'static str {
     for i in 0..FALVOR_FEATURE_NAMES.len() {
@@ -82,8 +89,28 @@ const BUILD_CUDA_INTEROP_FEATURE: &'static str = "CARGO_FEATURE_CUDA_INTEROP";
 const ENABLE_OCULUS_EXT_HEADERS_FEATURE: &'static str = "CARGO_FEATURE_OCULUS_EXT_HEADERS";
 const DISABLE_DECODER_FEATURE: &'static str = "CARGO_FEATURE_NO_DECODER";
 const CMAKE_GEN_ENV_VAR: &'static str = "ALXR_CMAKE_GEN";
-
-const ENV_VAR_MONITOR_LIST: [&'static str; 2] = [CMAKE_GEN_ENV_VAR, BUNDLE_FFMPEG_INSTALL_DIR_VAR]; //, CMAKE_PREFIX_PATH_VAR];
+const YVR_LOADER_DIR_VAR: &'static str = "ALXR_YVR_LOADER_DIR";
+
+const ENV_VAR_MONITOR_LIST: [&'static str; 3] = [
+    CMAKE_GEN_ENV_VAR,
+    BUNDLE_FFMPEG_INSTALL_DIR_VAR,
+    YVR_LOADER_DIR_VAR,
+]; //, CMAKE_PREFIX_PATH_VAR];
+
+// The YVR loader can't be redistributed with the sources, it is taken from the vendor SDK as
+// $ALXR_YVR_LOADER_DIR/<android abi>/libopenxr_loader.so.
+fn yvr_loader_dir(target_triple: &Triple) -> PathBuf {
+    let loader_dir = env::var(YVR_LOADER_DIR_VAR).unwrap_or_else(|_| {
+        panic!("{YVR_LOADER_DIR_VAR} must be set to the YVR OpenXR loader directory")
+    });
+    let loader_dir = PathBuf::from(loader_dir).join(android_abi_name(target_triple).unwrap());
+    assert!(
+        loader_dir.join("libopenxr_loader.so").exists(),
+        "libopenxr_loader.so not found in {0}",
+        loader_dir.display()
+    );
+    loader_dir
+}
 
 fn main() {
     let target_triple = Triple::from_str(&env::var("TARGET").unwrap()).unwrap();
@@ -193,6 +220,19 @@ fn main() {
                     .define("BUILD_LOADER", "OFF")
                     .define("USE_PICO_MOBILE_LOADER", "ON");
             }
+            "YVR" => {
+                // the link search path also gets the vendor loader copied into the apk.
+                let loader_dir = yvr_loader_dir(&target_triple);
+                config
+                    .define("BUILD_LOADER", "OFF")
+                    .define("CMAKE_LIBRARY_PATH", &loader_dir);
+                println!(
+                    "cargo:rustc-link-search=native={0}",
+                    loader_dir.to_string_lossy()
+                );
+            }
+            // the Snapdragon Spaces and Magic Leap 2 runtimes are found by the Khronos loader, like
+            // the generic flavor.
             _ => {
                 config.define("BUILD_LOADER", "ON");
             }
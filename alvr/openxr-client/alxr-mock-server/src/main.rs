@@ -0,0 +1,317 @@
+// Plays the server side of the protocol against a real client: handshake, stream setup, dummy
+// video and receiving tracking. Meant for CI, the client is expected to run with a decoder-less
+// build so the dummy frames are only pushed through the network and packet handling paths.
+use alvr_common::{prelude::*, semver::Version, ALVR_NAME, ALVR_VERSION};
+use alvr_session::SessionDesc;
+use alvr_sockets::{
+    ClientConfigPacket, ClientControlPacket, Dscp, EncryptionMode, HandshakePacket,
+    HeadsetInfoPacket, Input, PairingCodePacket, PeerType, ProtoControlSocket, ServerControlPacket,
+    ServerHandshakePacket, StreamSocketBuilder, VideoFrameHeaderPacket, CONTROL_PORT, INPUT,
+    LOCAL_IP, MAX_HANDSHAKE_PACKET_SIZE_BYTES, VIDEO,
+};
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, SocketAddr},
+    process::ExitCode,
+    str::FromStr,
+    sync::Arc,
+};
+use structopt::StructOpt;
+use tokio::{
+    net::UdpSocket,
+    time::{self, Duration, Instant},
+};
+
+const CONTROL_CONNECT_RETRY_PAUSE: Duration = Duration::from_millis(500);
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(1);
+const STREAM_SETUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "alxr-mock-server",
+    about = "Plays the ALVR server side of the protocol"
+)]
+struct Options {
+    /// Connect to this client instead of waiting for its handshake broadcast.
+    #[structopt(long)]
+    client_ip: Option<IpAddr>,
+
+    /// How long to stream for once the client is connected.
+    #[structopt(long, default_value = "10")]
+    duration_s: u64,
+
+    /// Rate of the dummy video frames.
+    #[structopt(long, default_value = "72")]
+    fps: f32,
+
+    /// Size of the dummy video frames.
+    #[structopt(long, default_value = "1000")]
+    frame_size: usize,
+
+    /// Encrypts the connection, with this pre-shared key if not empty.
+    #[structopt(long)]
+    encryption_key: Option<String>,
+}
+
+#[derive(Default)]
+struct StreamStats {
+    video_frames_sent: u64,
+    input_packets: u64,
+    input_packets_lost: u64,
+    control_packets: BTreeMap<&'static str, u64>,
+}
+
+fn mbits_to_bytes(value: u64) -> u32 {
+    (value * 1024 * 1024 / 8) as u32
+}
+
+fn packet_name(packet: &ClientControlPacket) -> &'static str {
+    match packet {
+        ClientControlPacket::PlayspaceSync(_) => "PlayspaceSync",
+        ClientControlPacket::RequestIdr => "RequestIdr",
+        ClientControlPacket::KeepAlive => "KeepAlive",
+        ClientControlPacket::StreamReady => "StreamReady",
+        ClientControlPacket::ViewsConfig(_) => "ViewsConfig",
+        ClientControlPacket::Battery(_) => "Battery",
+        ClientControlPacket::VideoPacketStats(_) => "VideoPacketStats",
+        ClientControlPacket::ReferenceSpace(_) => "ReferenceSpace",
+        ClientControlPacket::Log(_) => "Log",
+        ClientControlPacket::StreamTransport(_) => "StreamTransport",
+        ClientControlPacket::StreamDscp(_) => "StreamDscp",
+        ClientControlPacket::AudioSync(_) => "AudioSync",
+        ClientControlPacket::FecStats(_) => "FecStats",
+        ClientControlPacket::FecPercentage(_) => "FecPercentage",
+        ClientControlPacket::BitrateRecommendation(_) => "BitrateRecommendation",
+        ClientControlPacket::WifiStats(_) => "WifiStats",
+        ClientControlPacket::SpectatorCamera(_) => "SpectatorCamera",
+        ClientControlPacket::VideoStall(_) => "VideoStall",
+        _ => "Other",
+    }
+}
+
+// Waits for a client handshake broadcast, incompatible clients are answered and rejected.
+async fn wait_for_client() -> StrResult<IpAddr> {
+    let socket = trace_err!(UdpSocket::bind((LOCAL_IP, CONTROL_PORT)).await)?;
+    let mut packet = [0_u8; MAX_HANDSHAKE_PACKET_SIZE_BYTES];
+    loop {
+        let (size, address) = trace_err!(socket.recv_from(&mut packet).await)?;
+        let handshake_packet = match bincode::deserialize(&packet[..size]) {
+            Ok(HandshakePacket::Client(handshake_packet)) => handshake_packet,
+            _ => continue,
+        };
+        if handshake_packet.alvr_name != ALVR_NAME {
+            continue;
+        }
+        if !alvr_common::is_version_compatible(&handshake_packet.version) {
+            let response = trace_err!(bincode::serialize(&HandshakePacket::Server(
+                ServerHandshakePacket::IncompatibleVersions
+            )))?;
+            trace_err!(socket.send_to(&response, address).await)?;
+            return fmt_e!(
+                "Client version {} is incompatible with {}",
+                handshake_packet.version,
+                *ALVR_VERSION
+            );
+        }
+        println!(
+            "Handshake from {} ({}, version {})",
+            address, handshake_packet.device_name, handshake_packet.version
+        );
+        return Ok(address.ip());
+    }
+}
+
+async fn run(options: Options) -> StrResult<StreamStats> {
+    let client_ip = match options.client_ip {
+        Some(client_ip) => client_ip,
+        None => wait_for_client().await?,
+    };
+
+    let (mut proto_socket, client_ip) = loop {
+        if let Ok(pair) = ProtoControlSocket::connect_to(PeerType::AnyClient(vec![client_ip])).await
+        {
+            break pair;
+        }
+        time::sleep(CONTROL_CONNECT_RETRY_PAUSE).await;
+    };
+
+    let encryption = match options.encryption_key {
+        Some(key) if key.is_empty() => EncryptionMode::KeyExchange,
+        Some(key) => EncryptionMode::PreSharedKey(key),
+        None => EncryptionMode::Disabled,
+    };
+    // a new identity each run, clients using pairing will ask for a code every time.
+    let static_key = alvr_sockets::generate_static_key()?;
+    if proto_socket
+        .offer_encryption(&encryption, &static_key)
+        .await?
+    {
+        println!("Enter the pairing code shown by the client:");
+        let mut code = String::new();
+        trace_err!(std::io::stdin().read_line(&mut code))?;
+        proto_socket
+            .send(&PairingCodePacket {
+                code: code.trim().to_owned(),
+            })
+            .await?;
+    }
+    let cipher = proto_socket.cipher();
+
+    let (headset_info, server_ip) =
+        trace_err!(proto_socket.recv::<(HeadsetInfoPacket, IpAddr)>().await)?;
+    println!(
+        "Headset info: {}x{} per eye, refresh rates {:?}",
+        headset_info.recommended_eye_width,
+        headset_info.recommended_eye_height,
+        headset_info.available_refresh_rates
+    );
+
+    // the dummy frames are sent whole, without parity packets.
+    let mut session = SessionDesc::default();
+    session.session_settings.connection.enable_fec = false;
+    let mut settings = session.to_settings();
+
+    let client_config = ClientConfigPacket {
+        session_desc: trace_err!(serde_json::to_string(&session))?,
+        dashboard_url: format!(
+            "http://{server_ip}:{}/",
+            settings.connection.web_server_port
+        ),
+        eye_resolution_width: headset_info.recommended_eye_width,
+        eye_resolution_height: headset_info.recommended_eye_height,
+        fps: options.fps,
+        game_audio_sample_rate: 0,
+        reserved: "".into(),
+        server_version: Version::from_str(&headset_info.reserved).ok(),
+    };
+    proto_socket.send(&client_config).await?;
+
+    let (mut control_sender, mut control_receiver) =
+        proto_socket.split::<ServerControlPacket, ClientControlPacket>();
+    control_sender
+        .send(&ServerControlPacket::StartStream)
+        .await?;
+
+    let mut stats = StreamStats::default();
+    let mut dscp = Dscp::Ef;
+    loop {
+        let packet = control_receiver.recv().await?;
+        *stats
+            .control_packets
+            .entry(packet_name(&packet))
+            .or_default() += 1;
+        match packet {
+            ClientControlPacket::StreamReady => break,
+            ClientControlPacket::StreamTransport(transport) => {
+                settings.connection.stream_protocol = transport.into();
+            }
+            ClientControlPacket::StreamDscp(requested) => dscp = requested,
+            _ => (),
+        }
+    }
+
+    let stream_socket = tokio::select! {
+        res = StreamSocketBuilder::connect_to_client(
+            client_ip,
+            settings.connection.stream_port,
+            settings.connection.stream_protocol,
+            mbits_to_bytes(settings.video.encode_bitrate_mbs),
+            settings.connection.server_send_buffer_bytes,
+            settings.connection.server_recv_buffer_bytes,
+            dscp,
+            cipher,
+        ) => res?,
+        _ = time::sleep(STREAM_SETUP_TIMEOUT) => {
+            return fmt_e!("Timeout while setting up streams");
+        }
+    };
+    let stream_socket = Arc::new(stream_socket);
+    println!(
+        "Streaming to {}",
+        SocketAddr::new(client_ip, settings.connection.stream_port)
+    );
+
+    let mut video_sender = stream_socket.request_stream(VIDEO).await?;
+    let mut input_receiver = stream_socket.subscribe_to_stream::<Input>(INPUT).await?;
+
+    let start = Instant::now();
+    let end = start + Duration::from_secs(options.duration_s);
+    let mut frame_interval = time::interval(Duration::from_secs_f32(1_f32 / options.fps));
+    let mut keepalive_interval = time::interval(KEEPALIVE_INTERVAL);
+    let frame_data = vec![0_u8; options.frame_size];
+
+    let receive_loop = stream_socket.receive_loop();
+    tokio::pin!(receive_loop);
+    loop {
+        tokio::select! {
+            res = &mut receive_loop => {
+                res?;
+                return fmt_e!("Stream socket closed");
+            }
+            _ = time::sleep_until(end) => break,
+            _ = frame_interval.tick() => {
+                let header = VideoFrameHeaderPacket {
+                    packet_counter: stats.video_frames_sent as u32,
+                    tracking_frame_index: start.elapsed().as_nanos() as u64,
+                    video_frame_index: stats.video_frames_sent,
+                    sent_time: start.elapsed().as_micros() as u64,
+                    frame_byte_size: frame_data.len() as u32,
+                    fec_index: 0,
+                    fec_percentage: 0,
+                };
+                let mut buffer = video_sender.new_buffer(&header, frame_data.len())?;
+                buffer.get_mut().extend_from_slice(&frame_data);
+                video_sender.send_buffer(buffer).await?;
+                stats.video_frames_sent += 1;
+            }
+            _ = keepalive_interval.tick() => {
+                control_sender.send(&ServerControlPacket::KeepAlive).await?;
+            }
+            packet = input_receiver.recv() => {
+                let packet = packet?;
+                stats.input_packets += 1;
+                if packet.had_packet_loss {
+                    stats.input_packets_lost += 1;
+                }
+            }
+            packet = control_receiver.recv() => {
+                let packet = packet?;
+                *stats.control_packets.entry(packet_name(&packet)).or_default() += 1;
+            }
+        }
+    }
+
+    control_sender
+        .send(&ServerControlPacket::Restarting)
+        .await
+        .ok();
+    Ok(stats)
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let options = Options::from_args();
+    let stats = match run(options).await {
+        Ok(stats) => stats,
+        Err(e) => {
+            eprintln!("Mock server failed: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    println!("Video frames sent: {}", stats.video_frames_sent);
+    println!(
+        "Input packets received: {} ({} after a loss)",
+        stats.input_packets, stats.input_packets_lost
+    );
+    for (name, count) in &stats.control_packets {
+        println!("Control packet {name}: {count}");
+    }
+
+    // a client that never sends tracking is broken even if the connection went through.
+    if stats.input_packets == 0 {
+        eprintln!("No input received from the client");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -252,7 +252,7 @@ float ClientConnection::GetPoseTimeOffset() {
 
 void ClientConnection::OnFecFailure() {
 	Debug("Listener::OnFecFailure()\n");
-	if (GetTimestampUs() - m_lastFecFailure < CONTINUOUS_FEC_FAILURE) {
+	if (!m_fecPercentagePinned && GetTimestampUs() - m_lastFecFailure < CONTINUOUS_FEC_FAILURE) {
 		if (m_fecPercentage < MAX_FEC_PERCENTAGE) {
 			m_fecPercentage += 5;
 		}
@@ -260,6 +260,11 @@ void ClientConnection::OnFecFailure() {
 	m_lastFecFailure = GetTimestampUs();
 }
 
+void ClientConnection::SetFecPercentage(int percentage) {
+	m_fecPercentage = percentage;
+	m_fecPercentagePinned = true;
+}
+
 std::shared_ptr<Statistics> ClientConnection::GetStatistics() {
 	return m_Statistics;
 }
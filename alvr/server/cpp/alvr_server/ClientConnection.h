@@ -22,6 +22,7 @@ public:
  	void ProcessTimeSync(TimeSync data);
 	float GetPoseTimeOffset();
 	void OnFecFailure();
+	void SetFecPercentage(int percentage);
 	std::shared_ptr<Statistics> GetStatistics();
 
 	std::shared_ptr<Statistics> m_Statistics;
@@ -37,6 +38,8 @@ public:
 	static const int INITIAL_FEC_PERCENTAGE = 5;
 	static const int MAX_FEC_PERCENTAGE = 10;
 	int m_fecPercentage = INITIAL_FEC_PERCENTAGE;
+	// Set by the client, FEC failures don't raise the percentage anymore.
+	bool m_fecPercentagePinned = false;
 
 	uint64_t mVideoFrameIndex = 1;
 
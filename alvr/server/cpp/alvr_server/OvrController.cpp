@@ -888,7 +888,11 @@ void OvrController::DebugRequest(const char * /*pchRequest*/,
         pchResponseBuffer[0] = 0;
 }
 
-vr::DriverPose_t OvrController::GetPose() { return m_pose; }
+vr::DriverPose_t OvrController::GetPose() {
+    auto pose = m_pose;
+    apply_tracking_state(pose);
+    return pose;
+}
 
 vr::VRInputComponentHandle_t OvrController::getHapticComponent() { return m_compHaptic; }
 
@@ -1390,7 +1394,7 @@ bool OvrController::onPoseUpdate(const TrackingInfo::Controller &c) {
             m_handles[ALVR_INPUT_FINGER_PINKY], rotPinky, 0.0);
 
         vr::VRServerDriverHost()->TrackedDevicePoseUpdated(
-            this->object_id, m_pose, sizeof(vr::DriverPose_t));
+            this->object_id, GetPose(), sizeof(vr::DriverPose_t));
     } else {
         switch (Settings::Instance().m_controllerMode) {
         case 3:
@@ -1721,7 +1725,7 @@ bool OvrController::onPoseUpdate(const TrackingInfo::Controller &c) {
         // vr::Prop_DeviceBatteryPercentage_Float, c.batteryPercentRemaining / 100.0f);
 
         vr::VRServerDriverHost()->TrackedDevicePoseUpdated(
-            this->object_id, m_pose, sizeof(vr::DriverPose_t));
+            this->object_id, GetPose(), sizeof(vr::DriverPose_t));
     }
 
     return false;
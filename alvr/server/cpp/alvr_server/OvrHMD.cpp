@@ -345,6 +345,7 @@ vr::DriverPose_t OvrHmd::GetPose() {
         pose.poseTimeOffset = m_Listener->GetPoseTimeOffset();
     }
 
+    apply_tracking_state(pose);
     return pose;
 }
 
@@ -25,6 +25,8 @@ public:
 		m_bitsSentInSecond = 0;
 		m_bitsSentInSecondPrev = 0;
 		m_bitrateSent = 0;
+		m_thermalBitrateScale = 1.0f;
+		m_clientBitrateLimit = 0;
 
 		m_framesInSecond = 0;
 		m_framesPrevious = 0;
@@ -110,6 +112,16 @@ public:
 		return m_sendLatency;
 	}
 
+	// Fraction of the maximum adaptive bitrate allowed by the client's thermal state.
+	void SetThermalBitrateScale(float scale) {
+		m_thermalBitrateScale = scale;
+	}
+
+	// Link capacity estimated by the client, 0 when it doesn't limit the bitrate.
+	void SetClientBitrateLimit(uint64_t bitrate) {
+		m_clientBitrateLimit = bitrate;
+	}
+
 	bool CheckBitrateUpdated() {
 		if (m_enableAdaptiveBitrate) {
 			uint64_t latencyUs = m_sendLatency; // using video stream transport latency
@@ -126,6 +138,11 @@ public:
 						m_bitrate += m_adaptiveBitrateUpRate; // increase bitrate if sent mbps is higher than set bitrate threshold (set bitrate * load threshold * valid framerate)
 				}
 			}
+			uint64_t thermalMaximum = (uint64_t)(m_adaptiveBitrateMaximum * m_thermalBitrateScale);
+			if (m_bitrate > thermalMaximum)
+				m_bitrate = thermalMaximum < 5 ? 5 : thermalMaximum; // client is close to throttling
+			if (m_clientBitrateLimit != 0 && m_bitrate > m_clientBitrateLimit)
+				m_bitrate = m_clientBitrateLimit < 5 ? 5 : m_clientBitrateLimit; // client sees the network queues grow
 			if (m_bitrateUpdated != m_bitrate) { // bitrate changed
 				m_bitrateUpdated = m_bitrate;
 				return true;
@@ -239,6 +256,8 @@ private:
 	uint64_t m_adaptiveBitrateThreshold = Settings::Instance().m_adaptiveBitrateThreshold;
 	
 	float m_adaptiveBitrateLightLoadThreshold = Settings::Instance().m_adaptiveBitrateLightLoadThreshold;
+	float m_thermalBitrateScale = 1.0f;
+	uint64_t m_clientBitrateLimit = 0;
 
 	time_t m_current;
 
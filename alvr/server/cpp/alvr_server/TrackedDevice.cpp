@@ -43,4 +43,11 @@ void TrackedDevice::set_prop(OpenvrProperty prop) {
     event_data.property.prop = key;
     vr::VRServerDriverHost()->VendorSpecificEvent(
         this->object_id, vr::VREvent_PropertyChanged, event_data, 0.);
-}
\ No newline at end of file
+}
+
+void TrackedDevice::apply_tracking_state(vr::DriverPose_t &pose) {
+    if (!this->tracked) {
+        pose.poseIsValid = false;
+        pose.result = vr::TrackingResult_Running_OutOfRange;
+    }
+}
@@ -10,7 +10,12 @@ class TrackedDevice {
     vr::TrackedDeviceIndex_t object_id = vr::k_unTrackedDeviceIndexInvalid;
     vr::PropertyContainerHandle_t prop_container = vr::k_ulInvalidPropertyContainer;
 
+    // Cleared while the client reports lost tracking for this device.
+    bool tracked = true;
+
     void set_prop(OpenvrProperty prop);
 
+    void apply_tracking_state(vr::DriverPose_t &pose);
+
     TrackedDevice(uint64_t device_path) : device_path(device_path) {}
 };
\ No newline at end of file
@@ -192,6 +192,11 @@ void InitializeStreaming() {
     // set correct client ip
     Settings::Instance().Load();
 
+    // a new client starts out tracked, it reports lost devices itself.
+    for (auto &device : g_driver_provider.tracked_devices) {
+        device.second->tracked = true;
+    }
+
     if (g_driver_provider.hmd) {
         g_driver_provider.hmd->StartStreaming();
     }
@@ -277,3 +282,29 @@ void SetBattery(unsigned long long top_level_path, float gauge_value, bool is_pl
         }
     }
 }
+
+void SetTracked(unsigned long long top_level_path, bool tracked) {
+    auto device_it = g_driver_provider.tracked_devices.find(top_level_path);
+
+    if (device_it != g_driver_provider.tracked_devices.end()) {
+        device_it->second->tracked = tracked;
+    }
+}
+
+void SetThermalBitrateScale(float scale) {
+    if (g_driver_provider.hmd && g_driver_provider.hmd->m_Listener) {
+        g_driver_provider.hmd->m_Listener->GetStatistics()->SetThermalBitrateScale(scale);
+    }
+}
+
+void SetClientBitrateLimit(unsigned long long bitrateMbs) {
+    if (g_driver_provider.hmd && g_driver_provider.hmd->m_Listener) {
+        g_driver_provider.hmd->m_Listener->GetStatistics()->SetClientBitrateLimit(bitrateMbs);
+    }
+}
+
+void SetFecPercentage(int percentage) {
+    if (g_driver_provider.hmd && g_driver_provider.hmd->m_Listener) {
+        g_driver_provider.hmd->m_Listener->SetFecPercentage(percentage);
+    }
+}
@@ -188,4 +188,8 @@ extern "C" void ShutdownSteamvr();
 
 extern "C" void SetOpenvrProperty(unsigned long long topLevelPath, OpenvrProperty prop);
 extern "C" void SetViewsConfig(const ViewsConfigData* config);
-extern "C" void SetBattery(unsigned long long topLevelPath, float gauge_value, bool is_plugged);
\ No newline at end of file
+extern "C" void SetBattery(unsigned long long topLevelPath, float gauge_value, bool is_plugged);
+extern "C" void SetTracked(unsigned long long topLevelPath, bool tracked);
+extern "C" void SetThermalBitrateScale(float scale);
+extern "C" void SetClientBitrateLimit(unsigned long long bitrateMbs);
+extern "C" void SetFecPercentage(int percentage);
\ No newline at end of file
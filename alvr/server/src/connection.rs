@@ -1,7 +1,10 @@
 use crate::{
     connection_utils, ClientListAction, EyeFov, TimeSync, TrackingInfo, TrackingInfo_Controller,
-    TrackingQuat, TrackingVector2, TrackingVector3, CLIENTS_UPDATED_NOTIFIER, HAPTICS_SENDER,
-    RESTART_NOTIFIER, SESSION_MANAGER, TIME_SYNC_SENDER, VIDEO_SENDER,
+    TrackingQuat, TrackingVector2, TrackingVector3, AUX_SENSORS, CLIENTS_UPDATED_NOTIFIER,
+    FILESYSTEM_LAYOUT, HAPTICS_SENDER, IMU_SAMPLES_SENDER, LAST_NEGOTIATED_STREAM,
+    PAIRING_CODE_SENDER, POSE_OFFSET_SENDER, POSE_PREDICTION_MS, POSE_PREDICTION_NOTIFIER,
+    PUSH_CONTENT_SENDER, RECENTER_NOTIFIER, RESTART_NOTIFIER, SESSION_MANAGER, SPECTATOR_CAMERA,
+    TIME_SYNC_SENDER, VIDEO_SENDER, WORLD_OFFSET_SENDER,
 };
 use alvr_audio::{AudioDevice, AudioDeviceType};
 use alvr_common::{
@@ -12,23 +15,31 @@ use alvr_common::{
     HEAD_ID, LEFT_HAND_ID, RIGHT_HAND_ID,
 };
 use alvr_session::{
-    CodecType, FrameSize, OpenvrConfig, OpenvrPropValue, OpenvrPropertyKey, ServerEvent,
+    BandwidthStatistics, ClientAudioSync, ClientFecStatistics, ClientLatencyBreakdown,
+    ClientWifiStatistics, CodecType, EventSeverity, FrameSize, OpenvrConfig, OpenvrPropValue,
+    OpenvrPropertyKey, ServerEvent,
 };
 use alvr_sockets::{
-    spawn_cancelable, ClientConfigPacket, ClientControlPacket, ControlSocketReceiver,
-    ControlSocketSender, HeadsetInfoPacket, Input, PeerType, ProtoControlSocket,
-    ServerControlPacket, StreamSocketBuilder, AUDIO, HAPTICS, INPUT, VIDEO,
+    count_channel_bytes, spawn_cancelable, take_bandwidth_usage, BandwidthChannel,
+    ClientConfigPacket, ClientControlPacket, ControlSocketReceiver, ControlSocketSender, Dscp,
+    EncryptionMode, HeadsetInfoPacket, ImuSample, Input, PairingCodePacket, PeerType,
+    ProtoControlSocket, ReferenceSpace, ServerControlPacket, SessionCipher, StreamResumePacket,
+    StreamSocketBuilder, StreamViewMode, ThermalStatsPacket, ThermalStatus, VelocityFrame, AUDIO,
+    HAPTICS, IMU, INPUT, PAIRING_TIMEOUT, VIDEO,
 };
 use futures::future::{BoxFuture, Either};
 use settings_schema::Switch;
 use std::{
-    future,
+    fs, future,
     net::IpAddr,
     process::Command,
     str::FromStr,
-    sync::{mpsc as smpsc, Arc},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc as smpsc, Arc,
+    },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tokio::{
     sync::{mpsc as tmpsc, Mutex},
@@ -39,6 +50,15 @@ const CONTROL_CONNECT_RETRY_PAUSE: Duration = Duration::from_millis(500);
 const RETRY_CONNECT_MIN_INTERVAL: Duration = Duration::from_secs(1);
 const NETWORK_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(1);
 const CLEANUP_PAUSE: Duration = Duration::from_millis(500);
+const BANDWIDTH_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+// ALVR_MAX_VIDEO_BUFFER_SIZE, payload size of the video packets.
+const VIDEO_PACKET_PAYLOAD_SIZE: u32 = 1400;
+// IPv4 and UDP headers, added to each video packet on the wire.
+const IP_UDP_HEADERS_SIZE: u32 = 20 + 8;
+// Keeps the shard count within ALVR_FEC_SHARDS_MAX.
+const MAX_FEC_PERCENTAGE: u16 = 100;
+// Bounds the readings kept for the dashboard, sensors are named by the client.
+const MAX_AUX_SENSORS: usize = 32;
 
 fn align32(value: f32) -> u32 {
     ((value / 32.).floor() * 32.) as u32
@@ -48,6 +68,36 @@ fn mbits_to_bytes(value: u64) -> u32 {
     (value * 1024 * 1024 / 8) as u32
 }
 
+// The adaptive bitrate maximum is lowered once the client's thermal headroom passes the soft
+// limit, down to the minimum scale when the client reaches the throttling threshold.
+const THERMAL_HEADROOM_SOFT_LIMIT: f32 = 0.8;
+const MIN_THERMAL_BITRATE_SCALE: f32 = 0.5;
+
+fn thermal_bitrate_scale(stats: &ThermalStatsPacket) -> f32 {
+    if stats.status >= ThermalStatus::Severe {
+        return MIN_THERMAL_BITRATE_SCALE;
+    }
+    match stats.headroom {
+        Some(headroom) if headroom > THERMAL_HEADROOM_SOFT_LIMIT => {
+            let t = ((headroom - THERMAL_HEADROOM_SOFT_LIMIT) / (1. - THERMAL_HEADROOM_SOFT_LIMIT))
+                .min(1.);
+            1. - t * (1. - MIN_THERMAL_BITRATE_SCALE)
+        }
+        _ => 1.,
+    }
+}
+
+// Channel numbers of the 2.4, 5 and 6 GHz bands, 0 if unknown.
+fn wifi_channel(frequency_mhz: u32) -> u32 {
+    match frequency_mhz {
+        2484 => 14,
+        2412..=2472 => (frequency_mhz - 2407) / 5,
+        5160..=5885 => (frequency_mhz - 5000) / 5,
+        5955..=7115 => (frequency_mhz - 5950) / 5,
+        _ => 0,
+    }
+}
+
 #[derive(Clone)]
 struct ClientId {
     hostname: String,
@@ -83,9 +133,64 @@ async fn client_discovery(auto_trust_clients: bool) -> StrResult<ClientId> {
     })
 }
 
+// Generated on first use. Clients that paired with the server remember its public key.
+fn load_or_create_static_key() -> StrResult<Vec<u8>> {
+    let path = FILESYSTEM_LAYOUT.server_key();
+    if let Ok(key) = fs::read(&path) {
+        if key.len() == 32 {
+            return Ok(key);
+        }
+        warn!("Invalid server key, generating a new one. Clients must be paired again");
+    }
+    let key = alvr_sockets::generate_static_key()?;
+    trace_err!(fs::write(&path, &key))?;
+
+    Ok(key)
+}
+
+// Waits for the code shown in the headset to be entered in the dashboard.
+async fn send_pairing_code(proto_socket: &mut ProtoControlSocket, client_ip: IpAddr) -> StrResult {
+    let (code_sender, mut code_receiver) = tmpsc::unbounded_channel();
+    *PAIRING_CODE_SENDER.lock() = Some(code_sender);
+    alvr_session::log_event(ServerEvent::PairingRequested(client_ip.to_string()));
+
+    let res = time::timeout(PAIRING_TIMEOUT, code_receiver.recv()).await;
+    *PAIRING_CODE_SENDER.lock() = None;
+
+    match res {
+        Ok(Some(code)) => proto_socket.send(&PairingCodePacket { code }).await,
+        _ => fmt_e!("Pairing with {client_ip} timed out"),
+    }
+}
+
+// The last stream parameters sent to a client and the settings they were derived from.
+#[derive(Clone)]
+pub struct NegotiatedStream {
+    settings_json: String,
+    stream: StreamResumePacket,
+    target_eye_width: u32,
+    target_eye_height: u32,
+}
+
+// A client reconnecting after a network drop offers the parameters it last streamed with. While
+// the settings are unchanged they are handed back as they were, a headset reporting a slightly
+// different resolution or refresh rates would otherwise restart SteamVR.
+fn resumed_stream(
+    headset_info: &HeadsetInfoPacket,
+    settings_json: &str,
+) -> Option<NegotiatedStream> {
+    let offer = headset_info.resume.as_ref()?;
+    LAST_NEGOTIATED_STREAM.lock().clone().filter(|last| {
+        last.stream == *offer
+            && last.settings_json == settings_json
+            && headset_info.available_refresh_rates.contains(&offer.fps)
+    })
+}
+
 struct ConnectionInfo {
     client_ip: IpAddr,
     version: Option<Version>,
+    cipher: Option<Arc<SessionCipher>>,
     control_sender: ControlSocketSender<ServerControlPacket>,
     control_receiver: ControlSocketReceiver<ClientControlPacket>,
 }
@@ -116,11 +221,27 @@ async fn client_handshake(
         time::sleep(CONTROL_CONNECT_RETRY_PAUSE).await;
     };
 
+    let settings = SESSION_MANAGER.lock().get().to_settings();
+
+    let encryption = match &settings.connection.encryption {
+        Switch::Enabled(desc) if desc.pre_shared_key.is_empty() => EncryptionMode::KeyExchange,
+        Switch::Enabled(desc) => EncryptionMode::PreSharedKey(desc.pre_shared_key.clone()),
+        Switch::Disabled => EncryptionMode::Disabled,
+    };
+    let static_key = load_or_create_static_key()?;
+    let pairing_requested = proto_socket
+        .offer_encryption(&encryption, &static_key)
+        .await
+        .map_err(|e| format!("Encryption handshake failed: {e}"))?;
+    if pairing_requested {
+        info!("Client {client_ip} requested pairing");
+        send_pairing_code(&mut proto_socket, client_ip).await?;
+    }
+    let cipher = proto_socket.cipher();
+
     let (headset_info, server_ip) =
         trace_err!(proto_socket.recv::<(HeadsetInfoPacket, IpAddr)>().await)?;
 
-    let settings = SESSION_MANAGER.lock().get().to_settings();
-
     let (eye_width, eye_height) = match settings.video.render_resolution {
         FrameSize::Scale(scale) => (
             headset_info.recommended_eye_width as f32 * scale,
@@ -161,6 +282,41 @@ async fn client_handshake(
         warn!("Chosen refresh rate not supported. Using {fps}Hz");
     }
 
+    let settings_json = trace_err!(serde_json::to_string(
+        &SESSION_MANAGER.lock().get().session_settings
+    ))?;
+    let (video_eye_width, video_eye_height, target_eye_width, target_eye_height, fps) =
+        match resumed_stream(&headset_info, &settings_json) {
+            Some(last) => {
+                info!("Resuming the previous stream parameters");
+                (
+                    last.stream.eye_resolution_width,
+                    last.stream.eye_resolution_height,
+                    last.target_eye_width,
+                    last.target_eye_height,
+                    last.stream.fps,
+                )
+            }
+            None => (
+                video_eye_width,
+                video_eye_height,
+                target_eye_width,
+                target_eye_height,
+                fps,
+            ),
+        };
+    *LAST_NEGOTIATED_STREAM.lock() = Some(NegotiatedStream {
+        settings_json,
+        stream: StreamResumePacket {
+            eye_resolution_width: video_eye_width,
+            eye_resolution_height: video_eye_height,
+            fps,
+            codec: settings.video.codec,
+        },
+        target_eye_width,
+        target_eye_height,
+    });
+
     let dashboard_url = format!(
         "http://{server_ip}:{}/",
         settings.connection.web_server_port
@@ -480,6 +636,7 @@ async fn client_handshake(
     Ok(ConnectionInfo {
         client_ip,
         version,
+        cipher,
         control_sender,
         control_receiver,
     })
@@ -568,6 +725,7 @@ async fn connection_pipeline() -> StrResult {
     let ConnectionInfo {
         client_ip,
         version: _,
+        cipher,
         control_sender,
         mut control_receiver,
     } = connection_info;
@@ -579,17 +737,32 @@ async fn connection_pipeline() -> StrResult {
         .send(&ServerControlPacket::StartStream)
         .await?;
 
-    match control_receiver.recv().await {
-        Ok(ClientControlPacket::StreamReady) => {}
-        Ok(_) => {
-            return fmt_e!("Got unexpected packet waiting for stream ack");
-        }
-        Err(e) => {
-            return fmt_e!("Error while waiting for stream ack: {e}");
+    let mut requested_transport = None;
+    let mut requested_dscp = None;
+    loop {
+        match control_receiver.recv().await {
+            Ok(ClientControlPacket::StreamReady) => break,
+            Ok(ClientControlPacket::StreamTransport(transport)) => {
+                info!("Client requested the {transport:?} stream transport");
+                requested_transport = Some(transport);
+            }
+            Ok(ClientControlPacket::StreamDscp(dscp)) => {
+                info!("Client requested the {dscp:?} stream packet class");
+                requested_dscp = Some(dscp);
+            }
+            Ok(_) => {
+                return fmt_e!("Got unexpected packet waiting for stream ack");
+            }
+            Err(e) => {
+                return fmt_e!("Error while waiting for stream ack: {e}");
+            }
         }
     }
 
-    let settings = SESSION_MANAGER.lock().get().to_settings();
+    let mut settings = SESSION_MANAGER.lock().get().to_settings();
+    if let Some(transport) = requested_transport {
+        settings.connection.stream_protocol = transport.into();
+    }
 
     let stream_socket = tokio::select! {
         res = StreamSocketBuilder::connect_to_client(
@@ -599,6 +772,8 @@ async fn connection_pipeline() -> StrResult {
             mbits_to_bytes(settings.video.encode_bitrate_mbs),
             settings.connection.server_send_buffer_bytes,
             settings.connection.server_recv_buffer_bytes,
+            requested_dscp.unwrap_or(Dscp::Ef),
+            cipher,
         ) => res?,
         _ = time::sleep(Duration::from_secs(5)) => {
             return fmt_e!("Timeout while setting up streams");
@@ -724,6 +899,10 @@ async fn connection_pipeline() -> StrResult {
             *VIDEO_SENDER.lock() = Some(data_sender);
 
             while let Some((header, data)) = data_receiver.recv().await {
+                // parity packets follow the data packets, see ClientConnection::SendVideo.
+                if header.fec_index * VIDEO_PACKET_PAYLOAD_SIZE >= header.frame_byte_size {
+                    count_channel_bytes(BandwidthChannel::VideoFec, data.len());
+                }
                 let mut buffer = socket_sender.new_buffer(&header, data.len())?;
                 buffer.get_mut().extend(data);
                 socket_sender.send_buffer(buffer).await.ok();
@@ -793,11 +972,22 @@ async fn connection_pipeline() -> StrResult {
         }
     }
 
+    // set by the client when it sends device velocities relative to the device orientation.
+    let local_velocities = Arc::new(AtomicBool::new(false));
+
     let input_receive_loop = {
         let mut receiver = stream_socket.subscribe_to_stream::<Input>(INPUT).await?;
+        let local_velocities = Arc::clone(&local_velocities);
         async move {
             loop {
-                let input = receiver.recv().await?.header;
+                let mut input = receiver.recv().await?.header;
+
+                // the driver expects stage velocities.
+                if local_velocities.load(Ordering::Relaxed) {
+                    for (_, motion) in &mut input.device_motions {
+                        motion.convert_velocity_frame(VelocityFrame::Local, VelocityFrame::Stage);
+                    }
+                }
 
                 let head_motion = &input
                     .device_motions
@@ -936,6 +1126,23 @@ async fn connection_pipeline() -> StrResult {
         }
     };
 
+    // Only clients with raw IMU streaming enabled send on this stream.
+    let imu_receive_loop = {
+        let mut receiver = stream_socket
+            .subscribe_to_stream::<Vec<ImuSample>>(IMU)
+            .await?;
+        async move {
+            loop {
+                let samples = receiver.recv().await?.header;
+                if IMU_SAMPLES_SENDER.receiver_count() > 0 {
+                    if let Ok(json) = serde_json::to_string(&samples) {
+                        IMU_SAMPLES_SENDER.send(json).ok();
+                    }
+                }
+            }
+        }
+    };
+
     let (playspace_sync_sender, playspace_sync_receiver) = smpsc::channel::<Vec2>();
 
     let is_tracking_ref_only = settings.headset.tracking_ref_only;
@@ -969,7 +1176,138 @@ async fn connection_pipeline() -> StrResult {
         }
     };
 
+    let bandwidth_loop = async {
+        // drop what was counted during the connection setup.
+        take_bandwidth_usage();
+        loop {
+            time::sleep(BANDWIDTH_REPORT_INTERVAL).await;
+            let usage = take_bandwidth_usage();
+            let to_mbps = |bytes_per_s: f32| bytes_per_s * 8. / 1_000_000.;
+            alvr_session::log_event(ServerEvent::BandwidthStatistics(BandwidthStatistics {
+                video_bandwidth: to_mbps(usage.video),
+                video_fec_bandwidth: to_mbps(usage.video_fec),
+                audio_bandwidth: to_mbps(usage.audio),
+                tracking_bandwidth: to_mbps(usage.tracking),
+                control_bandwidth: to_mbps(usage.control),
+                other_bandwidth: to_mbps(usage.haptics + usage.other),
+                total_bandwidth: to_mbps(usage.total()),
+            }));
+        }
+    };
+
+    let recenter_loop = {
+        let control_sender = Arc::clone(&control_sender);
+        async move {
+            loop {
+                RECENTER_NOTIFIER.notified().await;
+                info!("Requesting the client to recenter");
+                let res = control_sender
+                    .lock()
+                    .await
+                    .send(&ServerControlPacket::Recenter)
+                    .await;
+                if res.is_err() {
+                    // the keepalive loop reports the disconnection.
+                    break Ok(());
+                }
+            }
+        }
+    };
+
+    let pose_prediction_loop = {
+        let control_sender = Arc::clone(&control_sender);
+        async move {
+            loop {
+                // registered before the value is read so a change in between is not missed.
+                let notified = POSE_PREDICTION_NOTIFIER.notified();
+                let prediction_ms = *POSE_PREDICTION_MS.lock();
+                let res = control_sender
+                    .lock()
+                    .await
+                    .send(&ServerControlPacket::PosePrediction(prediction_ms))
+                    .await;
+                if res.is_err() {
+                    // the keepalive loop reports the disconnection.
+                    break Ok(());
+                }
+                notified.await;
+            }
+        }
+    };
+
+    let pose_offset_loop = {
+        let control_sender = Arc::clone(&control_sender);
+        async move {
+            let (offset_sender, mut offset_receiver) = tmpsc::unbounded_channel();
+            *POSE_OFFSET_SENDER.lock() = Some(offset_sender);
+
+            while let Some(offset) = offset_receiver.recv().await {
+                info!("Setting a client pose offset: {offset:?}");
+                let res = control_sender
+                    .lock()
+                    .await
+                    .send(&ServerControlPacket::PoseOffset(offset))
+                    .await;
+                if res.is_err() {
+                    // the keepalive loop reports the disconnection.
+                    break;
+                }
+            }
+
+            Ok(())
+        }
+    };
+
+    let world_offset_loop = {
+        let control_sender = Arc::clone(&control_sender);
+        async move {
+            let (offset_sender, mut offset_receiver) = tmpsc::unbounded_channel();
+            *WORLD_OFFSET_SENDER.lock() = Some(offset_sender);
+
+            while let Some(offset) = offset_receiver.recv().await {
+                // playspace movers update it every frame while dragging, not logged.
+                let res = control_sender
+                    .lock()
+                    .await
+                    .send(&ServerControlPacket::WorldOffset(offset))
+                    .await;
+                if res.is_err() {
+                    // the keepalive loop reports the disconnection.
+                    break;
+                }
+            }
+
+            Ok(())
+        }
+    };
+
+    let push_content_loop = {
+        let control_sender = Arc::clone(&control_sender);
+        async move {
+            let (content_sender, mut content_receiver) = tmpsc::unbounded_channel();
+            *PUSH_CONTENT_SENDER.lock() = Some(content_sender);
+
+            while let Some(content) = content_receiver.recv().await {
+                info!("Pushing content to the client: {content:?}");
+                let res = control_sender
+                    .lock()
+                    .await
+                    .send(&ServerControlPacket::PushContent(content))
+                    .await;
+                if res.is_err() {
+                    // the keepalive loop reports the disconnection.
+                    break;
+                }
+            }
+
+            Ok(())
+        }
+    };
+
+    let enable_fec = settings.connection.enable_fec;
     let control_loop = async move {
+        let mut view_mode = StreamViewMode::Stereo;
+        let mut mask_coverage = None;
         loop {
             match control_receiver.recv().await {
                 Ok(ClientControlPacket::PlayspaceSync(packet)) => {
@@ -1004,7 +1342,183 @@ async fn connection_pipeline() -> StrResult {
                 Ok(ClientControlPacket::VideoErrorReport) => unsafe {
                     crate::VideoErrorReportReceive()
                 },
+                Ok(ClientControlPacket::LatencyBreakdown(breakdown)) => {
+                    let us_to_ms = |us: u32| us as f32 / 1000.;
+                    alvr_session::log_event(ServerEvent::ClientLatencyBreakdown(
+                        ClientLatencyBreakdown {
+                            client_frame_receive: us_to_ms(breakdown.frame_receive.average_us),
+                            client_frame_receive_max: us_to_ms(breakdown.frame_receive.max_us),
+                            client_queue_wait: us_to_ms(breakdown.queue_wait.average_us),
+                            client_decoder_submit: us_to_ms(breakdown.decoder_submit.average_us),
+                            client_decode: us_to_ms(breakdown.decode_us.unwrap_or(0)),
+                        },
+                    ));
+                }
+                Ok(ClientControlPacket::AudioSync(sync)) => {
+                    alvr_session::log_event(ServerEvent::ClientAudioSync(ClientAudioSync {
+                        audio_video_offset: sync.offset_us as f32 / 1000.,
+                        audio_buffer: sync.buffered_us as f32 / 1000.,
+                    }));
+                }
+                Ok(ClientControlPacket::FecStats(stats)) => {
+                    let packets_sent = stats.packets_received + stats.packets_lost;
+                    alvr_session::log_event(ServerEvent::ClientFecStatistics(
+                        ClientFecStatistics {
+                            client_packet_loss: stats.packets_lost as f32 * 100.
+                                / packets_sent.max(1) as f32,
+                            client_fec_recovered_frames: stats.frames_recovered,
+                            client_unrecoverable_frames: stats.frames_unrecoverable,
+                        },
+                    ));
+                }
+                Ok(ClientControlPacket::WifiStats(stats)) => {
+                    if stats.roamed {
+                        warn!(
+                            "Client roamed to another access point ({} MHz)",
+                            stats.frequency_mhz
+                        );
+                    }
+                    alvr_session::log_event(ServerEvent::ClientWifiStatistics(
+                        ClientWifiStatistics {
+                            client_wifi_rssi: stats.rssi_dbm,
+                            client_wifi_link_speed: stats.link_speed_mbps,
+                            client_wifi_channel: wifi_channel(stats.frequency_mhz),
+                        },
+                    ));
+                }
+                Ok(ClientControlPacket::NetworkInterface(interface)) => {
+                    info!(
+                        "Client streams over {} ({:?})",
+                        interface.name, interface.kind
+                    );
+                    let packet_size = VIDEO_PACKET_PAYLOAD_SIZE
+                        + IP_UDP_HEADERS_SIZE
+                        + alvr_sockets::STREAM_PACKET_OVERHEAD as u32;
+                    if let Some(mtu) = interface.mtu.filter(|mtu| *mtu < packet_size) {
+                        warn!(
+                            "Client MTU {mtu} is below the {packet_size} byte video packets, \
+                            they will be fragmented"
+                        );
+                    }
+                }
+                Ok(ClientControlPacket::SpectatorCamera(camera)) => {
+                    info!("Client set the spectator camera to {camera:?}");
+                    *SPECTATOR_CAMERA.lock() = camera;
+                }
+                Ok(ClientControlPacket::AuxSensor(packet)) => {
+                    let mut sensors = AUX_SENSORS.lock();
+                    if sensors.len() < MAX_AUX_SENSORS || sensors.contains_key(&packet.sensor) {
+                        sensors.insert(packet.sensor, (packet.values, Instant::now()));
+                    }
+                }
+                Ok(ClientControlPacket::FecPercentage(percentage)) => {
+                    let percentage = percentage.min(MAX_FEC_PERCENTAGE);
+                    if enable_fec {
+                        info!("Client pinned FEC to {percentage}%");
+                        unsafe { crate::SetFecPercentage(percentage as _) };
+                    } else {
+                        warn!("Client requested {percentage}% FEC but FEC is disabled");
+                    }
+                }
+                Ok(ClientControlPacket::StartupTimings(stages)) => {
+                    let summary = stages
+                        .iter()
+                        .map(|stage| format!("{} {}ms", stage.name, stage.elapsed_ms))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    info!("Client startup timings: {summary}");
+                }
+                Ok(ClientControlPacket::CrashReport(report)) => {
+                    // only the file name is used, reports are always put in the log dir.
+                    let file_name = std::path::Path::new(&report.file_name)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| "crash.txt".into());
+                    let path = FILESYSTEM_LAYOUT
+                        .log_dir
+                        .join(format!("alxr_client_{file_name}"));
+                    match std::fs::write(&path, report.contents) {
+                        Ok(()) => warn!("Client crash report saved to {}", path.display()),
+                        Err(e) => error!("Failed to save client crash report: {e}"),
+                    }
+                }
+                Ok(ClientControlPacket::ReferenceSpace(reference_space)) => {
+                    info!("Client reference space: {reference_space:?}");
+                    if reference_space == ReferenceSpace::Local {
+                        warn!("The client uses a local reference space, the floor height is not tracked");
+                    }
+                }
+                Ok(ClientControlPacket::Log(entry)) => {
+                    let level = match entry.severity {
+                        EventSeverity::Error => log::Level::Error,
+                        EventSeverity::Warning => log::Level::Warn,
+                        EventSeverity::Info => log::Level::Info,
+                        EventSeverity::Debug => log::Level::Debug,
+                    };
+                    let time = chrono::TimeZone::timestamp_millis_opt(
+                        &chrono::Local,
+                        entry.timestamp_ms as i64,
+                    )
+                    .single()
+                    .map(|time| time.format("%H:%M:%S%.3f").to_string())
+                    .unwrap_or_default();
+                    // the logging backend would parse "#{" as the start of an event.
+                    let message = entry.message.replace("#{", "# {");
+                    log::log!(level, "Client {time} {}: {message}", entry.target);
+                }
+                Ok(ClientControlPacket::VideoPacketStats(stats)) => {
+                    debug!("Client video packets: {stats:?}");
+                }
+                Ok(ClientControlPacket::VideoStall(stall)) => {
+                    warn!("Client received no video for {}ms", stall.duration_ms);
+                }
+                Ok(ClientControlPacket::ThermalStats(stats)) => {
+                    let scale = thermal_bitrate_scale(&stats);
+                    if scale < 1. {
+                        warn!("Client is heating up ({stats:?}), bitrate limited to {scale:.2}x");
+                    } else {
+                        debug!("Client thermal stats: {stats:?}");
+                    }
+                    unsafe { crate::SetThermalBitrateScale(scale) };
+                }
+                Ok(ClientControlPacket::BitrateRecommendation(recommendation)) => {
+                    match recommendation.bitrate_mbps {
+                        Some(bitrate_mbps) => debug!(
+                            "Client recommends {bitrate_mbps} Mbps ({:.1}ms queuing, {:.1}% loss)",
+                            recommendation.queue_delay_us as f32 / 1000.,
+                            recommendation.packet_loss * 100.
+                        ),
+                        None => debug!("Client lifted its bitrate recommendation"),
+                    }
+                    // only the adaptive bitrate is capped, a fixed bitrate is left as set.
+                    unsafe {
+                        crate::SetClientBitrateLimit(recommendation.bitrate_mbps.unwrap_or(0) as _)
+                    };
+                }
+                Ok(ClientControlPacket::VelocityFrame(frame)) => {
+                    info!("Client sends velocities in the {frame:?} frame");
+                    local_velocities.store(frame == VelocityFrame::Local, Ordering::Relaxed);
+                }
+                Ok(ClientControlPacket::StreamViewMode(mode)) => {
+                    info!("Client requested {mode:?} stream view mode");
+                    view_mode = mode;
+                }
                 Ok(ClientControlPacket::ViewsConfig(config)) => unsafe {
+                    let config = if view_mode == StreamViewMode::Mono {
+                        config.to_mono()
+                    } else {
+                        config
+                    };
+                    // masks are resent whenever the runtime changes them, only report changes.
+                    let coverage = [0, 1]
+                        .map(|i| config.hidden_area_meshes[i].coverage(&config.fov[i]) * 100_f32);
+                    if mask_coverage != Some(coverage) {
+                        mask_coverage = Some(coverage);
+                        info!(
+                            "Visibility masks hide {:.1}% of the left view and {:.1}% of the right view",
+                            coverage[0], coverage[1]
+                        );
+                    }
                     let mut verts = [Vec::new(), Vec::new()];
                     let mut indxs = [Vec::new(), Vec::new()];
                     let empty_ham = crate::HiddenAreaMesh {
@@ -1054,6 +1568,9 @@ async fn connection_pipeline() -> StrResult {
                 Ok(ClientControlPacket::Battery(packet)) => unsafe {
                     crate::SetBattery(packet.device_id, packet.gauge_value, packet.is_plugged);
                 },
+                Ok(ClientControlPacket::TrackingState(packet)) => unsafe {
+                    crate::SetTracked(packet.device_id, packet.tracked);
+                },
                 Ok(_) => (),
                 Err(e) => {
                     alvr_session::log_event(ServerEvent::ClientDisconnected);
@@ -1084,9 +1601,16 @@ async fn connection_pipeline() -> StrResult {
         res = spawn_cancelable(time_sync_send_loop) => res,
         res = spawn_cancelable(haptics_send_loop) => res,
         res = spawn_cancelable(input_receive_loop) => res,
+        res = spawn_cancelable(imu_receive_loop) => res,
 
         // Leave these loops on the current task
         res = keepalive_loop => res,
+        res = recenter_loop => res,
+        res = push_content_loop => res,
+        res = pose_prediction_loop => res,
+        res = pose_offset_loop => res,
+        res = world_offset_loop => res,
+        _ = bandwidth_loop => Ok(()),
         res = control_loop => res,
 
         _ = RESTART_NOTIFIER.notified() => {
@@ -21,11 +21,14 @@ use alvr_filesystem::{self as afs, Layout};
 use alvr_session::{
     ClientConnectionDesc, OpenvrPropValue, OpenvrPropertyKey, ServerEvent, SessionManager,
 };
-use alvr_sockets::{Haptics, TimeSyncPacket, VideoFrameHeaderPacket};
+use alvr_sockets::{
+    Haptics, PoseOffsetPacket, PushContentPacket, SpectatorCameraPacket, TimeSyncPacket,
+    VideoFrameHeaderPacket, WorldOffsetPacket,
+};
 use graphics_info::GpuVendor;
 use parking_lot::Mutex;
 use std::{
-    collections::{hash_map::Entry, HashSet},
+    collections::{hash_map::Entry, HashMap, HashSet},
     ffi::{c_void, CStr, CString},
     net::IpAddr,
     os::raw::c_char,
@@ -35,7 +38,7 @@ use std::{
         Arc, Once,
     },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tokio::{
     runtime::Runtime,
@@ -57,9 +60,30 @@ lazy_static! {
         Mutex::new(None);
     static ref TIME_SYNC_SENDER: Mutex<Option<mpsc::UnboundedSender<TimeSyncPacket>>> =
         Mutex::new(None);
+    static ref PUSH_CONTENT_SENDER: Mutex<Option<mpsc::UnboundedSender<PushContentPacket>>> =
+        Mutex::new(None);
+    static ref POSE_OFFSET_SENDER: Mutex<Option<mpsc::UnboundedSender<PoseOffsetPacket>>> =
+        Mutex::new(None);
+    static ref WORLD_OFFSET_SENDER: Mutex<Option<mpsc::UnboundedSender<Option<WorldOffsetPacket>>>> =
+        Mutex::new(None);
+    static ref PAIRING_CODE_SENDER: Mutex<Option<mpsc::UnboundedSender<String>>> =
+        Mutex::new(None);
+    static ref SPECTATOR_CAMERA: Mutex<Option<SpectatorCameraPacket>> = Mutex::new(None);
+    // Latest reading of each auxiliary sensor of the client, with the time it was received.
+    static ref AUX_SENSORS: Mutex<HashMap<String, (Vec<f32>, Instant)>> =
+        Mutex::new(HashMap::new());
+    // Raw IMU samples of the client as json, for the /api/imu websocket.
+    static ref IMU_SAMPLES_SENDER: broadcast::Sender<String> =
+        broadcast::channel(web_server::WS_BROADCAST_CAPACITY).0;
+    // Extra controller pose prediction in milliseconds sent to clients, None for their default.
+    static ref POSE_PREDICTION_MS: Mutex<Option<f32>> = Mutex::new(None);
+    static ref LAST_NEGOTIATED_STREAM: Mutex<Option<connection::NegotiatedStream>> =
+        Mutex::new(None);
 
     static ref CLIENTS_UPDATED_NOTIFIER: Notify = Notify::new();
     static ref RESTART_NOTIFIER: Notify = Notify::new();
+    static ref RECENTER_NOTIFIER: Notify = Notify::new();
+    static ref POSE_PREDICTION_NOTIFIER: Notify = Notify::new();
     static ref SHUTDOWN_NOTIFIER: Notify = Notify::new();
 
     static ref FRAME_RENDER_VS_CSO: Vec<u8> =
@@ -152,6 +176,53 @@ pub fn notify_restart_driver() {
     alvr_commands::restart_steamvr(&FILESYSTEM_LAYOUT.launcher_exe()).ok();
 }
 
+// Only reaches a client that is currently streaming.
+pub fn notify_recenter_client() {
+    RECENTER_NOTIFIER.notify_waiters();
+}
+
+// Kept for the next connections, sent right away to a client that is streaming.
+pub fn set_client_pose_prediction(prediction_ms: Option<f32>) {
+    *POSE_PREDICTION_MS.lock() = prediction_ms;
+    POSE_PREDICTION_NOTIFIER.notify_waiters();
+}
+
+// Returns false if no client is streaming.
+pub fn push_content_to_client(content: PushContentPacket) -> bool {
+    if let Some(sender) = &*PUSH_CONTENT_SENDER.lock() {
+        sender.send(content).is_ok()
+    } else {
+        false
+    }
+}
+
+// Returns false if no client is streaming. The client forgets the offset when it restarts.
+pub fn set_client_pose_offset(offset: PoseOffsetPacket) -> bool {
+    if let Some(sender) = &*POSE_OFFSET_SENDER.lock() {
+        sender.send(offset).is_ok()
+    } else {
+        false
+    }
+}
+
+// Returns false if no client is streaming. None removes the offset.
+pub fn set_client_world_offset(offset: Option<WorldOffsetPacket>) -> bool {
+    if let Some(sender) = &*WORLD_OFFSET_SENDER.lock() {
+        sender.send(offset).is_ok()
+    } else {
+        false
+    }
+}
+
+// Returns false if no client is waiting to be paired.
+pub fn send_pairing_code(code: String) -> bool {
+    if let Some(sender) = &*PAIRING_CODE_SENDER.lock() {
+        sender.send(code).is_ok()
+    } else {
+        false
+    }
+}
+
 pub fn notify_application_update() {
     notify_shutdown_driver();
 
@@ -1,6 +1,10 @@
-use crate::{graphics_info, ClientListAction, FILESYSTEM_LAYOUT, SESSION_MANAGER};
+use crate::{
+    graphics_info, ClientListAction, AUX_SENSORS, FILESYSTEM_LAYOUT, IMU_SAMPLES_SENDER,
+    SESSION_MANAGER, SPECTATOR_CAMERA,
+};
 use alvr_common::{prelude::*, ALVR_VERSION};
 use alvr_session::ServerEvent;
+use alvr_sockets::{PoseOffsetPacket, PushContentPacket, WorldOffsetPacket};
 use bytes::Buf;
 use futures::SinkExt;
 use headers::HeaderMapExt;
@@ -10,12 +14,16 @@ use hyper::{
 };
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json as json;
-use std::{env::consts::OS, fs, io::Write, net::SocketAddr, path::PathBuf};
+use std::{
+    collections::HashMap, env::consts::OS, fs, io::Write, net::SocketAddr, path::PathBuf,
+    time::Duration,
+};
 use tokio::sync::broadcast::{self, error::RecvError};
 use tokio_tungstenite::{tungstenite::protocol, WebSocketStream};
 use tokio_util::codec::{BytesCodec, FramedRead};
 
 pub const WS_BROADCAST_CAPACITY: usize = 256;
+const AUX_SENSOR_MAX_AGE: Duration = Duration::from_secs(5);
 
 fn reply(code: StatusCode) -> StrResult<Response<Body>> {
     trace_err!(Response::builder().status(code).body(Body::empty()))
@@ -130,6 +138,7 @@ async fn http_api(
         }
         "/api/log" => text_websocket(request, log_sender).await?,
         "/api/events" => text_websocket(request, events_sender).await?,
+        "/api/imu" => text_websocket(request, IMU_SAMPLES_SENDER.clone()).await?,
         "/api/driver/register" => {
             if alvr_commands::driver_registration(
                 &[FILESYSTEM_LAYOUT.openvr_driver_root_dir.clone()],
@@ -208,6 +217,88 @@ async fn http_api(
                 reply(StatusCode::BAD_REQUEST)?
             }
         }
+        "/api/client/recenter" => {
+            crate::notify_recenter_client();
+            reply(StatusCode::OK)?
+        }
+        // Milliseconds, or null for the client default.
+        "/api/client/pose-prediction" => {
+            if let Ok(prediction_ms) = from_request_body::<Option<f32>>(request).await {
+                crate::set_client_pose_prediction(prediction_ms);
+                reply(StatusCode::OK)?
+            } else {
+                reply(StatusCode::BAD_REQUEST)?
+            }
+        }
+        "/api/client/pose-offset" => {
+            if let Ok(offset) = from_request_body::<PoseOffsetPacket>(request).await {
+                if crate::set_client_pose_offset(offset) {
+                    reply(StatusCode::OK)?
+                } else {
+                    reply(StatusCode::SERVICE_UNAVAILABLE)?
+                }
+            } else {
+                reply(StatusCode::BAD_REQUEST)?
+            }
+        }
+        // For playspace mover tools, null removes the offset.
+        "/api/client/world-offset" => {
+            if let Ok(offset) = from_request_body::<Option<WorldOffsetPacket>>(request).await {
+                if crate::set_client_world_offset(offset) {
+                    reply(StatusCode::OK)?
+                } else {
+                    reply(StatusCode::SERVICE_UNAVAILABLE)?
+                }
+            } else {
+                reply(StatusCode::BAD_REQUEST)?
+            }
+        }
+        "/api/client/open-url" => {
+            if let Ok(content) = from_request_body::<String>(request).await {
+                if crate::push_content_to_client(PushContentPacket::Url(content)) {
+                    reply(StatusCode::OK)?
+                } else {
+                    reply(StatusCode::SERVICE_UNAVAILABLE)?
+                }
+            } else {
+                reply(StatusCode::BAD_REQUEST)?
+            }
+        }
+        // Placed in the headset, for spectator view plugins and capture tools.
+        "/api/spectator-camera" => reply_json(&*SPECTATOR_CAMERA.lock())?,
+        // Latest values of each sensor, for fitness and biometrics apps.
+        "/api/aux-sensors" => {
+            let sensors = AUX_SENSORS
+                .lock()
+                .iter()
+                .filter(|(_, (_, received))| received.elapsed() < AUX_SENSOR_MAX_AGE)
+                .map(|(sensor, (values, _))| (sensor.clone(), values.clone()))
+                .collect::<HashMap<_, _>>();
+            reply_json(&sensors)?
+        }
+        "/api/client/pair" => {
+            if let Ok(code) = from_request_body::<String>(request).await {
+                if crate::send_pairing_code(code) {
+                    reply(StatusCode::OK)?
+                } else {
+                    reply(StatusCode::SERVICE_UNAVAILABLE)?
+                }
+            } else {
+                reply(StatusCode::BAD_REQUEST)?
+            }
+        }
+        "/api/client/push-text" => {
+            if let Ok(content) = from_request_body::<String>(request).await {
+                if crate::push_content_to_client(PushContentPacket::Text(content)) {
+                    reply(StatusCode::OK)?
+                } else {
+                    reply(StatusCode::SERVICE_UNAVAILABLE)?
+                }
+            } else {
+                reply(StatusCode::BAD_REQUEST)?
+            }
+        }
+        "/api/diagnostics/bandwidth" => reply_json(&alvr_sockets::last_bandwidth_usage())?,
         "/api/version" => Response::new(ALVR_VERSION.to_string().into()),
         "/api/open" => {
             if let Ok(url) = from_request_body::<String>(request).await {
@@ -34,6 +34,56 @@ pub struct Statistics {
     pub server_f_p_s: u32,
 }
 
+// Client video pipeline timings, in ms
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientLatencyBreakdown {
+    pub client_frame_receive: f32,
+    pub client_frame_receive_max: f32,
+    pub client_queue_wait: f32,
+    pub client_decoder_submit: f32,
+    pub client_decode: f32,
+}
+
+// Client game audio sync, in ms
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientAudioSync {
+    pub audio_video_offset: f32,
+    pub audio_buffer: f32,
+}
+
+// Client video losses over the last second
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientFecStatistics {
+    pub client_packet_loss: f32, // percent
+    pub client_fec_recovered_frames: u32,
+    pub client_unrecoverable_frames: u32,
+}
+
+// Client wifi link quality
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientWifiStatistics {
+    pub client_wifi_rssi: i32,       // dBm
+    pub client_wifi_link_speed: u32, // Mbps
+    pub client_wifi_channel: u32,
+}
+
+// Link usage per channel, in Mbps. Video excludes the FEC parity packets.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BandwidthStatistics {
+    pub video_bandwidth: f32,
+    pub video_fec_bandwidth: f32,
+    pub audio_bandwidth: f32,
+    pub tracking_bandwidth: f32,
+    pub control_bandwidth: f32,
+    pub other_bandwidth: f32,
+    pub total_bandwidth: f32,
+}
+
 // This struct is temporary, until we switch to the new event system
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Raw {
@@ -55,9 +105,16 @@ pub enum ServerEvent {
     ClientFoundWrongVersion(String),
     ClientConnected,
     ClientDisconnected,
+    // The client shows a code to be entered in the dashboard, data is the client IP.
+    PairingRequested(String),
     UpdateDownloadedBytesCount(usize),
     UpdateDownloadError,
     Statistics(Statistics),
+    ClientLatencyBreakdown(ClientLatencyBreakdown),
+    ClientAudioSync(ClientAudioSync),
+    BandwidthStatistics(BandwidthStatistics),
+    ClientFecStatistics(ClientFecStatistics),
+    ClientWifiStatistics(ClientWifiStatistics),
     ServerQuitting,
     Raw(Raw),
     EchoQuery(String),
@@ -130,7 +130,7 @@ pub struct ColorCorrectionDesc {
 // validation: "hevc" vs "hEVC".
 // This is caused by serde and settings-schema using different libraries for casing conversion
 // todo: don't use casing conversion also for all other structs and enums
-#[derive(SettingsSchema, Serialize, Deserialize, Debug, Copy, Clone)]
+#[derive(SettingsSchema, Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
 #[serde(tag = "type", content = "content")]
 #[repr(u8)]
 pub enum CodecType {
@@ -428,6 +428,9 @@ pub enum SocketProtocol {
     },
 
     Tcp,
+
+    #[schema(advanced)]
+    Quic,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
@@ -437,6 +440,13 @@ pub struct DiscoveryConfig {
     pub auto_trust_clients: bool,
 }
 
+#[derive(SettingsSchema, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionDesc {
+    // When empty, keys are exchanged without authenticating the client.
+    pub pre_shared_key: String,
+}
+
 #[derive(SettingsSchema, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase", tag = "type", content = "content")]
 pub enum SocketBufferSize {
@@ -481,6 +491,9 @@ pub struct ConnectionDesc {
 
     #[schema(advanced)]
     pub enable_fec: bool,
+
+    #[schema(advanced)]
+    pub encryption: Switch<EncryptionDesc>,
 }
 
 #[derive(SettingsSchema, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
@@ -757,6 +770,12 @@ pub fn session_settings_default() -> SettingsDefault {
             on_connect_script: "".into(),
             on_disconnect_script: "".into(),
             enable_fec: true,
+            encryption: SwitchDefault {
+                enabled: false,
+                content: EncryptionDescDefault {
+                    pre_shared_key: "".into(),
+                },
+            },
         },
         extra: ExtraDescDefault {
             theme: ThemeDefault {
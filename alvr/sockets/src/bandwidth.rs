@@ -0,0 +1,106 @@
+// Payload bytes sent and received by this process, per channel. UDP/IP and framing headers are
+// not counted. The server sees both directions of the link, its totals are the link usage.
+use super::{StreamId, AUDIO, HAPTICS, INPUT, VIDEO};
+use alvr_common::lazy_static;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BandwidthChannel {
+    Video,
+    // Parity packets, also counted as video.
+    VideoFec,
+    Audio,
+    Tracking,
+    Haptics,
+    Control,
+    Other,
+}
+
+impl From<StreamId> for BandwidthChannel {
+    fn from(stream_id: StreamId) -> Self {
+        match stream_id {
+            VIDEO => BandwidthChannel::Video,
+            AUDIO => BandwidthChannel::Audio,
+            INPUT => BandwidthChannel::Tracking,
+            HAPTICS => BandwidthChannel::Haptics,
+            _ => BandwidthChannel::Other,
+        }
+    }
+}
+
+const CHANNELS_COUNT: usize = 7;
+
+static CHANNEL_BYTES: [AtomicU64; CHANNELS_COUNT] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+
+lazy_static! {
+    static ref LAST_USAGE: Mutex<(Instant, Option<BandwidthUsage>)> =
+        Mutex::new((Instant::now(), None));
+}
+
+// In bytes per second, video excludes the FEC parity packets.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct BandwidthUsage {
+    pub video: f32,
+    pub video_fec: f32,
+    pub audio: f32,
+    pub tracking: f32,
+    pub haptics: f32,
+    pub control: f32,
+    pub other: f32,
+}
+
+impl BandwidthUsage {
+    pub fn total(&self) -> f32 {
+        self.video
+            + self.video_fec
+            + self.audio
+            + self.tracking
+            + self.haptics
+            + self.control
+            + self.other
+    }
+}
+
+pub fn count_channel_bytes(channel: BandwidthChannel, bytes_count: usize) {
+    CHANNEL_BYTES[channel as usize].fetch_add(bytes_count as u64, Ordering::Relaxed);
+}
+
+// Rates since the previous call, meant to be called periodically by a single loop.
+pub fn take_bandwidth_usage() -> BandwidthUsage {
+    let mut last_usage = LAST_USAGE.lock();
+    let elapsed_s = last_usage.0.elapsed().as_secs_f32().max(f32::EPSILON);
+    last_usage.0 = Instant::now();
+
+    let rate = |channel: BandwidthChannel| {
+        CHANNEL_BYTES[channel as usize].swap(0, Ordering::Relaxed) as f32 / elapsed_s
+    };
+    let video_fec = rate(BandwidthChannel::VideoFec);
+    let usage = BandwidthUsage {
+        video: (rate(BandwidthChannel::Video) - video_fec).max(0.),
+        video_fec,
+        audio: rate(BandwidthChannel::Audio),
+        tracking: rate(BandwidthChannel::Tracking),
+        haptics: rate(BandwidthChannel::Haptics),
+        control: rate(BandwidthChannel::Control),
+        other: rate(BandwidthChannel::Other),
+    };
+    last_usage.1 = Some(usage);
+    usage
+}
+
+pub fn last_bandwidth_usage() -> Option<BandwidthUsage> {
+    LAST_USAGE.lock().1
+}
@@ -1,36 +1,100 @@
-use super::{Ldc, CONTROL_PORT, LOCAL_IP};
+use super::{
+    count_channel_bytes, encryption, BandwidthChannel, CipherChannel, EncryptionAcceptPacket,
+    EncryptionMode, EncryptionOfferPacket, Ldc, SessionCipher, CONTROL_PORT, LOCAL_IP, TAG_SIZE,
+};
 use alvr_common::prelude::*;
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures::{
     stream::{SplitSink, SplitStream},
     SinkExt, StreamExt,
 };
 use serde::{de::DeserializeOwned, Serialize};
-use std::{marker::PhantomData, net::IpAddr};
+use std::{marker::PhantomData, net::IpAddr, sync::Arc};
 use tokio::net::{TcpListener, TcpStream};
 use tokio_util::codec::Framed;
 
+// TCP keeps the packets in order, the nonce counters are implicit.
+struct ControlCipher {
+    cipher: Arc<SessionCipher>,
+    counter: u64,
+}
+
+impl ControlCipher {
+    fn seal(&mut self, mut packet_bytes: Vec<u8>) -> StrResult<Vec<u8>> {
+        let tag = self
+            .cipher
+            .seal(CipherChannel::Control, self.counter, &[], &mut packet_bytes)?;
+        self.counter += 1;
+        packet_bytes.extend_from_slice(&tag);
+
+        Ok(packet_bytes)
+    }
+
+    fn open(&mut self, mut packet_bytes: BytesMut) -> StrResult<BytesMut> {
+        if packet_bytes.len() < TAG_SIZE {
+            return fmt_e!("Control packet too short");
+        }
+        let tag = packet_bytes.split_off(packet_bytes.len() - TAG_SIZE);
+        self.cipher.open(
+            CipherChannel::Control,
+            self.counter,
+            &[],
+            &mut packet_bytes,
+            &tag,
+        )?;
+        self.counter += 1;
+
+        Ok(packet_bytes)
+    }
+}
+
+fn serialize_packet<S: Serialize>(
+    packet: &S,
+    cipher: &mut Option<ControlCipher>,
+) -> StrResult<Bytes> {
+    let mut packet_bytes = trace_err!(bincode::serialize(packet))?;
+    if let Some(cipher) = cipher {
+        packet_bytes = cipher.seal(packet_bytes)?;
+    }
+    count_channel_bytes(BandwidthChannel::Control, packet_bytes.len());
+
+    Ok(packet_bytes.into())
+}
+
+fn deserialize_packet<R: DeserializeOwned>(
+    mut packet_bytes: BytesMut,
+    cipher: &mut Option<ControlCipher>,
+) -> StrResult<R> {
+    count_channel_bytes(BandwidthChannel::Control, packet_bytes.len());
+    if let Some(cipher) = cipher {
+        packet_bytes = cipher.open(packet_bytes)?;
+    }
+    trace_err!(bincode::deserialize(&packet_bytes))
+}
+
 pub struct ControlSocketSender<T> {
     inner: SplitSink<Framed<TcpStream, Ldc>, Bytes>,
+    cipher: Option<ControlCipher>,
     _phantom: PhantomData<T>,
 }
 
 impl<S: Serialize> ControlSocketSender<S> {
     pub async fn send(&mut self, packet: &S) -> StrResult {
-        let packet_bytes = trace_err!(bincode::serialize(packet))?;
-        trace_err!(self.inner.send(packet_bytes.into()).await)
+        let packet_bytes = serialize_packet(packet, &mut self.cipher)?;
+        trace_err!(self.inner.send(packet_bytes).await)
     }
 }
 
 pub struct ControlSocketReceiver<T> {
     inner: SplitStream<Framed<TcpStream, Ldc>>,
+    cipher: Option<ControlCipher>,
     _phantom: PhantomData<T>,
 }
 
 impl<R: DeserializeOwned> ControlSocketReceiver<R> {
     pub async fn recv(&mut self) -> StrResult<R> {
         let packet_bytes = trace_err!(trace_none!(self.inner.next().await)?)?;
-        trace_err!(bincode::deserialize(&packet_bytes))
+        deserialize_packet(packet_bytes, &mut self.cipher)
     }
 }
 
@@ -38,6 +102,9 @@ impl<R: DeserializeOwned> ControlSocketReceiver<R> {
 // the specified types can be exchanged
 pub struct ProtoControlSocket {
     inner: Framed<TcpStream, Ldc>,
+    cipher: Option<Arc<SessionCipher>>,
+    send_cipher: Option<ControlCipher>,
+    recv_cipher: Option<ControlCipher>,
 }
 
 pub enum PeerType {
@@ -66,17 +133,81 @@ impl ProtoControlSocket {
         let peer_ip = trace_err!(socket.peer_addr())?.ip();
         let socket = Framed::new(socket, Ldc::new());
 
-        Ok((Self { inner: socket }, peer_ip))
+        Ok((
+            Self {
+                inner: socket,
+                cipher: None,
+                send_cipher: None,
+                recv_cipher: None,
+            },
+            peer_ip,
+        ))
+    }
+
+    fn set_cipher(&mut self, cipher: SessionCipher) {
+        let cipher = Arc::new(cipher);
+        self.send_cipher = Some(ControlCipher {
+            cipher: Arc::clone(&cipher),
+            counter: 0,
+        });
+        self.recv_cipher = Some(ControlCipher {
+            cipher: Arc::clone(&cipher),
+            counter: 0,
+        });
+        self.cipher = Some(cipher);
+    }
+
+    // Server side, must be the first exchange on the socket. An offer is sent even if encryption
+    // is disabled, so the client knows it. Returns true if the client asks for a pairing code.
+    pub async fn offer_encryption(
+        &mut self,
+        mode: &EncryptionMode,
+        static_key: &[u8],
+    ) -> StrResult<bool> {
+        let (offer, handshake) = encryption::offer(mode, static_key)?;
+        self.send(&offer).await?;
+        if let Some(handshake) = handshake {
+            let accept = self.recv::<EncryptionAcceptPacket>().await?;
+            self.set_cipher(encryption::finish(handshake, &accept)?);
+            return Ok(accept.pairing_requested);
+        }
+
+        Ok(false)
+    }
+
+    // Client side. `pre_shared_key` must match the one of the server if it uses one. With
+    // `trusted_servers`, returns true if the server is unknown and must be paired.
+    pub async fn accept_encryption(
+        &mut self,
+        pre_shared_key: Option<&str>,
+        required: bool,
+        trusted_servers: Option<&[String]>,
+    ) -> StrResult<bool> {
+        let offer = self.recv::<EncryptionOfferPacket>().await?;
+        if let Some((accept, cipher)) =
+            encryption::accept(&offer, pre_shared_key, required, trusted_servers)?
+        {
+            self.send(&accept).await?;
+            self.set_cipher(cipher);
+            return Ok(accept.pairing_requested);
+        }
+
+        Ok(false)
+    }
+
+    // To encrypt the stream socket with the keys of this connection.
+    pub fn cipher(&self) -> Option<Arc<SessionCipher>> {
+        self.cipher.clone()
     }
 
     pub async fn send<S: Serialize>(&mut self, packet: &S) -> StrResult {
-        let packet_bytes = trace_err!(bincode::serialize(packet))?;
-        trace_err!(self.inner.send(packet_bytes.into()).await)
+        let packet_bytes = serialize_packet(packet, &mut self.send_cipher)?;
+        trace_err!(self.inner.send(packet_bytes).await)
     }
 
     pub async fn recv<R: DeserializeOwned>(&mut self) -> StrResult<R> {
         let packet_bytes = trace_err!(trace_none!(self.inner.next().await)?)?;
-        trace_err!(bincode::deserialize(&packet_bytes))
+        deserialize_packet(packet_bytes, &mut self.recv_cipher)
     }
 
     pub fn split<S: Serialize, R: DeserializeOwned>(
@@ -87,10 +218,12 @@ impl ProtoControlSocket {
         (
             ControlSocketSender {
                 inner: sender,
+                cipher: self.send_cipher,
                 _phantom: PhantomData,
             },
             ControlSocketReceiver {
                 inner: receiver,
+                cipher: self.recv_cipher,
                 _phantom: PhantomData,
             },
         )
@@ -0,0 +1,451 @@
+// Transport encryption of the control and stream sockets. Keys are agreed with a Noise handshake
+// on the control socket right after it connects, then every packet is sealed with
+// ChaCha20-Poly1305. The server proves a persistent static key, which clients can pin by pairing.
+// Without a pre-shared key or pairing nothing is authenticated: passive eavesdroppers are kept
+// out but not an active man in the middle.
+use crate::{EncryptionAcceptPacket, EncryptionOfferPacket};
+use alvr_common::prelude::*;
+use chacha20poly1305::{
+    aead::{AeadInPlace, KeyInit},
+    ChaCha20Poly1305, Key, Nonce, Tag,
+};
+use sha2::{Digest, Sha256};
+use snow::{Builder, HandshakeState};
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+// The server is the initiator, it sends its static key in the first message.
+const NOISE_PARAMS: &str = "Noise_IN_25519_ChaChaPoly_BLAKE2s";
+const NOISE_PSK_PARAMS: &str = "Noise_INpsk1_25519_ChaChaPoly_BLAKE2s";
+const NOISE_MAX_MESSAGE_SIZE: usize = 256;
+
+pub const TAG_SIZE: usize = 16;
+// Stream packets can be lost or reordered, they carry their nonce counter.
+pub const STREAM_PACKET_OVERHEAD: usize = 8 + TAG_SIZE;
+
+// Time left to the user to type the pairing code in the dashboard.
+pub const PAIRING_TIMEOUT: Duration = Duration::from_secs(120);
+
+// Counters already seen are remembered this far back, older stream packets are dropped.
+const REPLAY_WINDOW_SIZE: u64 = 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EncryptionMode {
+    Disabled,
+    KeyExchange,
+    PreSharedKey(String),
+}
+
+// Each direction has its own key, the nonces are a channel prefix followed by a counter.
+#[derive(Clone, Copy)]
+pub enum CipherChannel {
+    Control = 0,
+    Stream = 1,
+}
+
+fn nonce(channel: CipherChannel, counter: u64) -> Nonce {
+    let mut nonce = [0; 12];
+    nonce[..4].copy_from_slice(&(channel as u32).to_be_bytes());
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    nonce.into()
+}
+
+// Noise takes a 32 bytes key, passphrases of any length are hashed into one.
+fn pre_shared_key(passphrase: &str) -> [u8; 32] {
+    Sha256::digest(passphrase.as_bytes()).into()
+}
+
+fn noise_builder(psk: Option<&[u8; 32]>) -> StrResult<Builder<'_>> {
+    Ok(match psk {
+        Some(psk) => Builder::new(trace_err!(NOISE_PSK_PARAMS.parse())?).psk(1, psk),
+        None => Builder::new(trace_err!(NOISE_PARAMS.parse())?),
+    })
+}
+
+fn fingerprint(public_key: &[u8]) -> String {
+    Sha256::digest(public_key)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+// Private static key of a server, to be generated once and stored.
+pub fn generate_static_key() -> StrResult<Vec<u8>> {
+    let keypair = trace_err!(Builder::new(trace_err!(NOISE_PARAMS.parse())?).generate_keypair())?;
+    Ok(keypair.private)
+}
+
+pub struct SessionCipher {
+    send: ChaCha20Poly1305,
+    recv: ChaCha20Poly1305,
+    next_stream_counter: AtomicU64,
+    peer_fingerprint: Option<String>,
+}
+
+impl SessionCipher {
+    fn from_handshake(mut handshake: HandshakeState) -> Self {
+        let peer_fingerprint = handshake.get_remote_static().map(fingerprint);
+        let (initiator_key, responder_key) = handshake.dangerously_get_raw_split();
+        let (send_key, recv_key) = if handshake.is_initiator() {
+            (initiator_key, responder_key)
+        } else {
+            (responder_key, initiator_key)
+        };
+
+        Self {
+            send: ChaCha20Poly1305::new(Key::from_slice(&send_key)),
+            recv: ChaCha20Poly1305::new(Key::from_slice(&recv_key)),
+            next_stream_counter: AtomicU64::new(0),
+            peer_fingerprint,
+        }
+    }
+
+    // Hash of the static key of the server, only known by the client.
+    pub fn peer_fingerprint(&self) -> Option<&str> {
+        self.peer_fingerprint.as_deref()
+    }
+
+    pub fn next_stream_counter(&self) -> u64 {
+        self.next_stream_counter.fetch_add(1, Ordering::Relaxed)
+    }
+
+    // Encrypts `data` in place and returns the tag to send with it.
+    pub fn seal(
+        &self,
+        channel: CipherChannel,
+        counter: u64,
+        associated_data: &[u8],
+        data: &mut [u8],
+    ) -> StrResult<Tag> {
+        trace_err!(self.send.encrypt_in_place_detached(
+            &nonce(channel, counter),
+            associated_data,
+            data
+        ))
+    }
+
+    pub fn open(
+        &self,
+        channel: CipherChannel,
+        counter: u64,
+        associated_data: &[u8],
+        data: &mut [u8],
+        tag: &[u8],
+    ) -> StrResult {
+        if tag.len() != TAG_SIZE {
+            return fmt_e!("Invalid tag size");
+        }
+        self.recv
+            .decrypt_in_place_detached(
+                &nonce(channel, counter),
+                associated_data,
+                data,
+                Tag::from_slice(tag),
+            )
+            .map_err(|_| "Packet authentication failed".into())
+    }
+}
+
+// Server side, the handshake is None if encryption is disabled.
+pub(crate) fn offer(
+    mode: &EncryptionMode,
+    static_key: &[u8],
+) -> StrResult<(EncryptionOfferPacket, Option<HandshakeState>)> {
+    let psk = match mode {
+        EncryptionMode::Disabled => {
+            return Ok((
+                EncryptionOfferPacket {
+                    pre_shared_key: false,
+                    handshake: None,
+                },
+                None,
+            ))
+        }
+        EncryptionMode::KeyExchange => None,
+        EncryptionMode::PreSharedKey(passphrase) => Some(pre_shared_key(passphrase)),
+    };
+
+    let mut handshake = trace_err!(noise_builder(psk.as_ref())?
+        .local_private_key(static_key)
+        .build_initiator())?;
+    let mut message = vec![0; NOISE_MAX_MESSAGE_SIZE];
+    let len = trace_err!(handshake.write_message(&[], &mut message))?;
+    message.truncate(len);
+
+    Ok((
+        EncryptionOfferPacket {
+            pre_shared_key: psk.is_some(),
+            handshake: Some(message),
+        },
+        Some(handshake),
+    ))
+}
+
+// Client side. Fails if the server doesn't use the same kind of encryption. With
+// `trusted_servers`, pairing is requested from servers that are not in the list.
+pub(crate) fn accept(
+    offer: &EncryptionOfferPacket,
+    passphrase: Option<&str>,
+    required: bool,
+    trusted_servers: Option<&[String]>,
+) -> StrResult<Option<(EncryptionAcceptPacket, SessionCipher)>> {
+    let offer_message = match &offer.handshake {
+        Some(message) => message,
+        None if trusted_servers.is_some() => {
+            return fmt_e!("The server doesn't offer encryption, it is needed for pairing")
+        }
+        None if required || passphrase.is_some() => {
+            return fmt_e!("The server doesn't offer encryption")
+        }
+        None => return Ok(None),
+    };
+    let psk = match (offer.pre_shared_key, passphrase) {
+        (true, Some(passphrase)) => Some(pre_shared_key(passphrase)),
+        (false, None) => None,
+        (true, None) => return fmt_e!("The server requires a pre-shared key"),
+        // the key would not be checked, the server could be anyone.
+        (false, Some(_)) => return fmt_e!("The server doesn't use a pre-shared key"),
+    };
+
+    let mut handshake = trace_err!(noise_builder(psk.as_ref())?.build_responder())?;
+    let mut payload = vec![0; NOISE_MAX_MESSAGE_SIZE];
+    if handshake.read_message(offer_message, &mut payload).is_err() {
+        return fmt_e!("Encryption handshake failed, the pre-shared keys don't match");
+    }
+    let mut message = vec![0; NOISE_MAX_MESSAGE_SIZE];
+    let len = trace_err!(handshake.write_message(&[], &mut message))?;
+    message.truncate(len);
+
+    let cipher = SessionCipher::from_handshake(handshake);
+    let pairing_requested = match (trusted_servers, cipher.peer_fingerprint()) {
+        (Some(trusted_servers), Some(fingerprint)) => !trusted_servers
+            .iter()
+            .any(|trusted| trusted.as_str() == fingerprint),
+        (Some(_), None) => return fmt_e!("The server didn't send its key"),
+        (None, _) => false,
+    };
+
+    Ok(Some((
+        EncryptionAcceptPacket {
+            handshake: message,
+            pairing_requested,
+        },
+        cipher,
+    )))
+}
+
+pub(crate) fn finish(
+    mut handshake: HandshakeState,
+    accept: &EncryptionAcceptPacket,
+) -> StrResult<SessionCipher> {
+    let mut payload = vec![0; NOISE_MAX_MESSAGE_SIZE];
+    trace_err!(handshake.read_message(&accept.handshake, &mut payload))?;
+    if !handshake.is_handshake_finished() {
+        return fmt_e!("Encryption handshake not finished");
+    }
+
+    Ok(SessionCipher::from_handshake(handshake))
+}
+
+// Sliding window over the stream nonce counters, replayed packets are dropped.
+pub(crate) struct ReplayWindow {
+    highest: Option<u64>,
+    seen: [u64; (REPLAY_WINDOW_SIZE / 64) as usize],
+}
+
+impl ReplayWindow {
+    pub fn new() -> Self {
+        Self {
+            highest: None,
+            seen: [0; (REPLAY_WINDOW_SIZE / 64) as usize],
+        }
+    }
+
+    fn bit(counter: u64) -> (usize, u64) {
+        let index = counter % REPLAY_WINDOW_SIZE;
+        ((index / 64) as usize, 1 << (index % 64))
+    }
+
+    pub fn is_new(&self, counter: u64) -> bool {
+        match self.highest {
+            None => true,
+            Some(highest) if counter > highest => true,
+            Some(highest) if highest - counter >= REPLAY_WINDOW_SIZE => false,
+            Some(_) => {
+                let (word, mask) = Self::bit(counter);
+                self.seen[word] & mask == 0
+            }
+        }
+    }
+
+    // Only called for authenticated packets.
+    pub fn mark(&mut self, counter: u64) {
+        match self.highest {
+            Some(highest) if counter <= highest => (),
+            Some(highest) if counter - highest < REPLAY_WINDOW_SIZE => {
+                for skipped in highest + 1..counter {
+                    let (word, mask) = Self::bit(skipped);
+                    self.seen[word] &= !mask;
+                }
+                self.highest = Some(counter);
+            }
+            _ => {
+                self.seen = [0; (REPLAY_WINDOW_SIZE / 64) as usize];
+                self.highest = Some(counter);
+            }
+        }
+        let (word, mask) = Self::bit(counter);
+        self.seen[word] |= mask;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handshake(
+        mode: &EncryptionMode,
+        passphrase: Option<&str>,
+    ) -> StrResult<(SessionCipher, SessionCipher)> {
+        let static_key = generate_static_key()?;
+        let (offer_packet, server_handshake) = offer(mode, &static_key)?;
+        let (accept_packet, client_cipher) = accept(&offer_packet, passphrase, true, None)?
+            .ok_or_else(|| "no encryption offered".to_owned())?;
+        let server_cipher = finish(server_handshake.unwrap(), &accept_packet)?;
+        Ok((server_cipher, client_cipher))
+    }
+
+    fn assert_round_trip(sender: &SessionCipher, receiver: &SessionCipher) {
+        let mut data = b"video packet".to_vec();
+        let tag = sender
+            .seal(CipherChannel::Stream, 7, b"header", &mut data)
+            .unwrap();
+        assert_ne!(data, b"video packet");
+        receiver
+            .open(CipherChannel::Stream, 7, b"header", &mut data, &tag)
+            .unwrap();
+        assert_eq!(data, b"video packet");
+    }
+
+    #[test]
+    fn test_handshake_key_exchange() {
+        let (server, client) = handshake(&EncryptionMode::KeyExchange, None).unwrap();
+        assert_round_trip(&server, &client);
+        assert_round_trip(&client, &server);
+        assert!(client.peer_fingerprint().is_some());
+    }
+
+    #[test]
+    fn test_handshake_pre_shared_key() {
+        let mode = EncryptionMode::PreSharedKey("secret".into());
+        let (server, client) = handshake(&mode, Some("secret")).unwrap();
+        assert_round_trip(&server, &client);
+        assert_round_trip(&client, &server);
+    }
+
+    #[test]
+    fn test_handshake_pre_shared_key_mismatch() {
+        let mode = EncryptionMode::PreSharedKey("secret".into());
+        assert!(handshake(&mode, Some("other")).is_err());
+        assert!(handshake(&mode, None).is_err());
+        assert!(handshake(&EncryptionMode::KeyExchange, Some("secret")).is_err());
+    }
+
+    #[test]
+    fn test_handshake_disabled() {
+        let (offer_packet, server_handshake) =
+            offer(&EncryptionMode::Disabled, &generate_static_key().unwrap()).unwrap();
+        assert!(server_handshake.is_none());
+        assert!(accept(&offer_packet, None, false, None).unwrap().is_none());
+        assert!(accept(&offer_packet, None, true, None).is_err());
+    }
+
+    #[test]
+    fn test_open_rejects_tampering() {
+        let (server, client) = handshake(&EncryptionMode::KeyExchange, None).unwrap();
+        let mut data = b"control packet".to_vec();
+        let tag = server
+            .seal(CipherChannel::Control, 3, &[], &mut data)
+            .unwrap();
+
+        let mut bad_tag = tag.to_vec();
+        bad_tag[0] ^= 1;
+        assert!(client
+            .open(CipherChannel::Control, 3, &[], &mut data.clone(), &bad_tag)
+            .is_err());
+        let mut bad_data = data.clone();
+        bad_data[0] ^= 1;
+        assert!(client
+            .open(CipherChannel::Control, 3, &[], &mut bad_data, &tag)
+            .is_err());
+        assert!(client
+            .open(CipherChannel::Stream, 3, &[], &mut data.clone(), &tag)
+            .is_err());
+        assert!(client
+            .open(CipherChannel::Control, 4, &[], &mut data.clone(), &tag)
+            .is_err());
+        assert!(client
+            .open(CipherChannel::Control, 3, &[], &mut data.clone(), &tag[1..])
+            .is_err());
+        // each direction has its own key, the sender can't open its own packets.
+        assert!(server
+            .open(CipherChannel::Control, 3, &[], &mut data, &tag)
+            .is_err());
+    }
+
+    #[test]
+    fn test_nonce_channels() {
+        assert_ne!(
+            nonce(CipherChannel::Control, 1),
+            nonce(CipherChannel::Stream, 1)
+        );
+        assert_ne!(
+            nonce(CipherChannel::Stream, 1),
+            nonce(CipherChannel::Stream, 2)
+        );
+    }
+
+    #[test]
+    fn test_replay_window_duplicates() {
+        let mut window = ReplayWindow::new();
+        assert!(window.is_new(5));
+        window.mark(5);
+        assert!(!window.is_new(5));
+        // reordered packets from before the highest one are still accepted once.
+        assert!(window.is_new(3));
+        window.mark(3);
+        assert!(!window.is_new(3));
+        assert!(window.is_new(4));
+        assert!(window.is_new(6));
+    }
+
+    #[test]
+    fn test_replay_window_too_old() {
+        let mut window = ReplayWindow::new();
+        window.mark(2000);
+        assert!(!window.is_new(2000 - REPLAY_WINDOW_SIZE));
+        assert!(!window.is_new(0));
+        assert!(window.is_new(2000 - REPLAY_WINDOW_SIZE + 1));
+    }
+
+    #[test]
+    fn test_replay_window_forward_jump() {
+        let mut window = ReplayWindow::new();
+        for counter in 0..10 {
+            window.mark(counter);
+        }
+        window.mark(5000);
+        assert!(!window.is_new(5000));
+        assert!(!window.is_new(9));
+        // the bits of the skipped counters are cleared, they are not taken for duplicates.
+        assert!(window.is_new(4999));
+        assert!(window.is_new(5000 - REPLAY_WINDOW_SIZE + 1));
+
+        // a jump within the window keeps the counters seen before it.
+        window.mark(5100);
+        assert!(!window.is_new(5000));
+        assert!(window.is_new(5050));
+    }
+}
@@ -1,4 +1,6 @@
+mod bandwidth;
 mod control_socket;
+mod encryption;
 mod packets;
 mod stream_socket;
 
@@ -7,7 +9,9 @@ use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::net::{IpAddr, Ipv4Addr};
 
+pub use bandwidth::*;
 pub use control_socket::*;
+pub use encryption::*;
 pub use packets::*;
 pub use stream_socket::*;
 
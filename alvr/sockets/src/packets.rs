@@ -1,17 +1,18 @@
 use std::{collections::HashMap, time::Duration};
 
-use crate::StreamId;
+use crate::{Dscp, StreamId};
 use alvr_common::{
     glam::{Quat, Vec2, Vec3},
     semver::Version,
 };
-use alvr_session::Fov;
+use alvr_session::{CodecType, EventSeverity, Fov, SocketProtocol};
 use serde::{Deserialize, Serialize};
 
 pub const INPUT: StreamId = 0; // tracking and buttons
 pub const HAPTICS: StreamId = 1;
 pub const AUDIO: StreamId = 2;
 pub const VIDEO: StreamId = 3;
+pub const IMU: StreamId = 4; // raw samples, only requested by clients with the option enabled
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ClientHandshakePacket {
@@ -39,6 +40,29 @@ pub enum HandshakePacket {
     Server(ServerHandshakePacket),
 }
 
+// First packet on the control socket, sent in plain by the server. `handshake` is the first Noise
+// message, None if the server has encryption disabled.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EncryptionOfferPacket {
+    pub pre_shared_key: bool,
+    pub handshake: Option<Vec<u8>>,
+}
+
+// Reply of the client to an offer with a handshake, the packets after it are encrypted.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EncryptionAcceptPacket {
+    pub handshake: Vec<u8>,
+    // The server is not in the trusted list of the client, it must echo the code shown in the
+    // headset.
+    pub pairing_requested: bool,
+}
+
+// Sent encrypted by the server after the handshake, only if pairing was requested.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PairingCodePacket {
+    pub code: String,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct HeadsetInfoPacket {
     pub recommended_eye_width: u32,
@@ -49,6 +73,17 @@ pub struct HeadsetInfoPacket {
     // reserved field is used to add features in a minor release that otherwise would break the
     // packets schema
     pub reserved: String,
+
+    pub resume: Option<StreamResumePacket>,
+}
+
+// Stream parameters the client last streamed with from this server, offered again on reconnect.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StreamResumePacket {
+    pub eye_resolution_width: u32,
+    pub eye_resolution_height: u32,
+    pub fps: f32,
+    pub codec: CodecType,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -71,6 +106,37 @@ pub enum ServerControlPacket {
     TimeSync(TimeSyncPacket), // legacy
     Reserved(String),
     ReservedBuffer(Vec<u8>),
+    Recenter,
+    PushContent(PushContentPacket),
+    // Extra controller pose prediction in milliseconds, None for the client default.
+    PosePrediction(Option<f32>),
+    PoseOffset(PoseOffsetPacket),
+    // None removes the offset.
+    WorldOffset(Option<WorldOffsetPacket>),
+}
+
+// Transform applied to every pose the client sends, to move the user in the game world.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct WorldOffsetPacket {
+    pub orientation: Quat,
+    pub position: Vec3,
+}
+
+// Pose offset of a device in its own frame, e.g. "/user/hand/left". The identity removes it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PoseOffsetPacket {
+    pub device_path: String,
+    pub orientation: Quat,
+    pub position: Vec3,
+}
+
+// Content pushed from the dashboard to the headset.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum PushContentPacket {
+    // Opened in the system browser.
+    Url(String),
+    // Copied to the clipboard.
+    Text(String),
 }
 
 // VisibilityMask following OpenXR conventions,
@@ -85,6 +151,29 @@ pub struct HiddenAreaMesh {
     pub indices: Vec<u32>,
 }
 
+impl HiddenAreaMesh {
+    // Fraction of the view hidden by the mask, from 0 to 1.
+    pub fn coverage(&self, fov: &Fov) -> f32 {
+        let view_area =
+            (fov.right.tan() - fov.left.tan()).abs() * (fov.top.tan() - fov.bottom.tan()).abs();
+        if view_area == 0_f32 {
+            return 0_f32;
+        }
+        let mut hidden_area = 0_f32;
+        for triangle in self.indices.chunks_exact(3) {
+            let vertex = |index: u32| self.vertices.get(index as usize).copied();
+            if let (Some(a), Some(b), Some(c)) = (
+                vertex(triangle[0]),
+                vertex(triangle[1]),
+                vertex(triangle[2]),
+            ) {
+                hidden_area += (b - a).perp_dot(c - a).abs() / 2_f32;
+            }
+        }
+        (hidden_area / view_area).min(1_f32)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ViewsConfig {
     // Note: the head-to-eye transform is always a translation along the x axis
@@ -93,6 +182,44 @@ pub struct ViewsConfig {
     pub hidden_area_meshes: [HiddenAreaMesh; 2],
 }
 
+impl ViewsConfig {
+    // A single viewpoint covering the fov of both eyes, the server renders both views from the
+    // head position. Hidden area meshes no longer match the widened fov and are dropped.
+    pub fn to_mono(&self) -> ViewsConfig {
+        fn widest(a: f32, b: f32) -> f32 {
+            if a.abs() >= b.abs() {
+                a
+            } else {
+                b
+            }
+        }
+        let [left_eye, right_eye] = &self.fov;
+        let fov = Fov {
+            left: widest(left_eye.left, right_eye.left),
+            right: widest(left_eye.right, right_eye.right),
+            top: widest(left_eye.top, right_eye.top),
+            bottom: widest(left_eye.bottom, right_eye.bottom),
+        };
+        let empty_mesh = HiddenAreaMesh {
+            vertices: vec![],
+            indices: vec![],
+        };
+        ViewsConfig {
+            ipd_m: 0_f32,
+            fov: [fov, fov],
+            hidden_area_meshes: [empty_mesh.clone(), empty_mesh],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamViewMode {
+    Stereo,
+    // Used by spectator devices (phones/tablets), must be sent before the first ViewsConfig. Only
+    // the views change, the frame keeps its stereo layout.
+    Mono,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct BatteryPacket {
     pub device_id: u64,
@@ -100,6 +227,177 @@ pub struct BatteryPacket {
     pub is_plugged: bool,
 }
 
+// Video packet counters of the client receive path, totals since the stream started.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VideoPacketStats {
+    pub received: u64,
+    // Arrived after a later packet, but in time to be put back in order.
+    pub reordered: u64,
+    pub duplicate: u64,
+    // Arrived after the client gave up waiting for them, these are dropped.
+    pub late: u64,
+    // Never arrived.
+    pub lost: u64,
+}
+
+// Sent when video packets arrive again after a gap while streaming.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VideoStallPacket {
+    pub duration_ms: u32,
+}
+
+// Client video frame counters over the last report interval.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FecStatsPacket {
+    pub packets_received: u32,
+    pub packets_lost: u32,
+    pub frames: u32,
+    // Had packets missing that the parity packets make up for.
+    pub frames_recovered: u32,
+    // Can't be decoded, including frames that had no packet arrive at all.
+    pub frames_unrecoverable: u32,
+}
+
+// Client estimate of what the link can carry. None lifts a previous limit.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct BitrateRecommendationPacket {
+    pub bitrate_mbps: Option<u32>,
+    pub queue_delay_us: u32,
+    pub packet_loss: f32, // range [0, 1]
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LatencyStat {
+    pub average_us: u32,
+    pub max_us: u32,
+}
+
+// Client video pipeline timings over the last reporting interval.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LatencyBreakdownPacket {
+    pub frames: u32,
+    // From the first packet of a frame arriving to its last packet reaching the decoder.
+    pub frame_receive: LatencyStat,
+    // Time packets are held back to be put in order.
+    pub queue_wait: LatencyStat,
+    // Time spent handing packets to the decoder.
+    pub decoder_submit: LatencyStat,
+    // Average reported by the decoder, None until it reports one.
+    pub decode_us: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StartupStage {
+    pub name: String,
+    // Since the client process started.
+    pub elapsed_ms: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CrashReportPacket {
+    pub file_name: String,
+    pub contents: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClientLogEntry {
+    pub timestamp_ms: u64, // since the unix epoch, client clock
+    pub severity: EventSeverity,
+    pub target: String,
+    pub message: String,
+}
+
+// Same levels as android's PowerManager thermal status.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ThermalStatus {
+    None,
+    Light,
+    Moderate,
+    Severe,
+    Critical,
+    Emergency,
+    Shutdown,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ThermalStatsPacket {
+    pub status: ThermalStatus,
+    // Forecast fraction of the throttling threshold, the SoC throttles from 1.0. None when the
+    // device can't tell.
+    pub headroom: Option<f32>,
+}
+
+// Virtual camera placed by the user for spectator views, in the same frame as the poses.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct SpectatorCameraPacket {
+    pub orientation: Quat,
+    pub position: Vec3,
+    pub fov_degrees: f32, // horizontal
+}
+
+// Reading of an auxiliary sensor, like a heart rate wearable. Sensors are named by the client,
+// e.g. "heart_rate", values are in the sensor's own units.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuxSensorPacket {
+    pub sensor: String,
+    pub values: Vec<f32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImuSensor {
+    Accelerometer, // m/s², including gravity
+    Gyroscope,     // rad/s
+}
+
+// Uncorrected sample of the headset's IMU, in the sensor's own frame.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ImuSample {
+    pub sensor: ImuSensor,
+    pub timestamp_ns: i64, // sensor clock, only meaningful between samples
+    pub values: [f32; 3],
+}
+
+// Sampled on android from the connection info of the wifi manager.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WifiStatsPacket {
+    pub rssi_dbm: i32,
+    pub link_speed_mbps: u32,
+    pub frequency_mhz: u32,
+    // The headset moved to another access point since the last sample.
+    pub roamed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetworkInterfaceKind {
+    Unknown,
+    Ethernet,
+    UsbEthernet,
+    Wifi,
+    WiGig, // 60GHz
+}
+
+// The interface the client streams over, sent before StreamReady.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct NetworkInterfacePacket {
+    pub kind: NetworkInterfaceKind,
+    pub name: String,
+    pub mtu: Option<u32>,
+}
+
+// Estimated game audio lead over the video, positive when the audio plays first.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AudioSyncPacket {
+    pub offset_us: i32,
+    pub buffered_us: u32,
+}
+
+// Sent when the client runtime stops (or resumes) reporting a valid pose for a device.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrackingStatePacket {
+    pub device_id: u64,
+    pub tracked: bool,
+}
+
 #[derive(Serialize, Deserialize)]
 pub enum ClientControlPacket {
     PlayspaceSync(Vec2),
@@ -112,6 +410,32 @@ pub enum ClientControlPacket {
     VideoErrorReport,         // legacy
     Reserved(String),
     ReservedBuffer(Vec<u8>),
+    StreamViewMode(StreamViewMode),
+    TrackingState(TrackingStatePacket),
+    // Must be sent before the first input, stage is assumed otherwise.
+    VelocityFrame(VelocityFrame),
+    VideoPacketStats(VideoPacketStats),
+    VideoStall(VideoStallPacket),
+    ThermalStats(ThermalStatsPacket),
+    LatencyBreakdown(LatencyBreakdownPacket),
+    // Sent once per client process, after the first frame is decoded.
+    StartupTimings(Vec<StartupStage>),
+    CrashReport(CrashReportPacket),
+    ReferenceSpace(ReferenceSpace),
+    Log(ClientLogEntry),
+    StreamTransport(StreamTransport),
+    AudioSync(AudioSyncPacket),
+    FecStats(FecStatsPacket),
+    // Pins the share of parity packets, in percent of the data packets.
+    FecPercentage(u16),
+    BitrateRecommendation(BitrateRecommendationPacket),
+    WifiStats(WifiStatsPacket),
+    // None when the user removes the camera.
+    SpectatorCamera(Option<SpectatorCameraPacket>),
+    AuxSensor(AuxSensorPacket),
+    NetworkInterface(NetworkInterfacePacket),
+    // Class of service the server marks its stream packets with, sent before StreamReady.
+    StreamDscp(Dscp),
 }
 
 // legacy video packet
@@ -152,6 +476,70 @@ pub enum ButtonValue {
     Scalar(f32),
 }
 
+// Frame the MotionData velocities are expressed in. Stage velocities share the tracking space
+// of the pose, local velocities are relative to the device orientation. Linear velocities are
+// in m/s, angular velocities in rad/s.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VelocityFrame {
+    Stage,
+    Local,
+}
+
+impl From<&str> for VelocityFrame {
+    fn from(input: &str) -> Self {
+        match input.trim() {
+            "Local" => VelocityFrame::Local,
+            _ => VelocityFrame::Stage,
+        }
+    }
+}
+
+// Base OpenXR reference space of the client poses. Local space has its origin at the initial
+// head position, the floor height is unknown to the client.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReferenceSpace {
+    Stage,
+    Local,
+}
+
+impl From<&str> for ReferenceSpace {
+    fn from(input: &str) -> Self {
+        match input.trim() {
+            "Local" => ReferenceSpace::Local,
+            _ => ReferenceSpace::Stage,
+        }
+    }
+}
+
+// Stream transport requested by the client, overrides the protocol of the server settings for
+// this connection. QUIC needs both sides to be built with the quic feature.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamTransport {
+    Udp,
+    Tcp,
+    Quic,
+}
+
+impl From<&str> for StreamTransport {
+    fn from(input: &str) -> Self {
+        match input.trim().to_lowercase().as_str() {
+            "tcp" => StreamTransport::Tcp,
+            "quic" => StreamTransport::Quic,
+            _ => StreamTransport::Udp,
+        }
+    }
+}
+
+impl From<StreamTransport> for SocketProtocol {
+    fn from(transport: StreamTransport) -> Self {
+        match transport {
+            StreamTransport::Udp => SocketProtocol::Udp,
+            StreamTransport::Tcp => SocketProtocol::Tcp,
+            StreamTransport::Quic => SocketProtocol::Quic,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct MotionData {
     pub orientation: Quat,
@@ -160,13 +548,25 @@ pub struct MotionData {
     pub angular_velocity: Option<Vec3>,
 }
 
+impl MotionData {
+    pub fn convert_velocity_frame(&mut self, from: VelocityFrame, to: VelocityFrame) {
+        let rotation = match (from, to) {
+            (VelocityFrame::Stage, VelocityFrame::Local) => self.orientation.inverse(),
+            (VelocityFrame::Local, VelocityFrame::Stage) => self.orientation,
+            _ => return,
+        };
+        self.linear_velocity = self.linear_velocity.map(|v| rotation * v);
+        self.angular_velocity = self.angular_velocity.map(|v| rotation * v);
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct HandTrackingInput {
     pub target_ray_motion: MotionData,
     pub skeleton_motion: Vec<MotionData>,
 }
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct LegacyController {
     pub bone_rotations: [Quat; 19],
     pub bone_positions_base: [Vec3; 19],
@@ -180,13 +580,13 @@ pub struct LegacyController {
     pub is_hand: bool,
 }
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 pub struct LegacyInput {
     pub controllers: [LegacyController; 2],
     pub mounted: u8,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Input {
     pub legacy: LegacyInput,
     pub device_motions: Vec<(u64, MotionData)>,
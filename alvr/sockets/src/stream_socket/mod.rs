@@ -4,20 +4,29 @@
 // StreamSender and StreamReceiver endpoints allow for convenient conversion of the header to/from
 // bytes while still handling the additional byte buffer with zero copies and extra allocations.
 
+#[cfg(feature = "quic")]
+mod quic;
 mod tcp;
 mod throttled_udp;
 mod udp;
 
+use crate::{
+    count_channel_bytes, encryption::ReplayWindow, CipherChannel, SessionCipher,
+    STREAM_PACKET_OVERHEAD,
+};
 use alvr_common::prelude::*;
 use alvr_session::{SocketBufferSize, SocketProtocol};
 use bytes::{Buf, BufMut, BytesMut};
 use futures::SinkExt;
-use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "quic")]
+use quic::{QuicStreamReceiveSocket, QuicStreamSendSocket};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
     collections::HashMap,
     marker::PhantomData,
     net::IpAddr,
     ops::{Deref, DerefMut},
+    str::FromStr,
     sync::Arc,
 };
 use tcp::{TcpStreamReceiveSocket, TcpStreamSendSocket};
@@ -29,6 +38,42 @@ use udp::{UdpStreamReceiveSocket, UdpStreamSendSocket};
 // todo: when const_generics reaches stable, convert this to an enum
 pub type StreamId = u16;
 
+// Class of service of the stream packets, for networks that prioritize traffic by DSCP. Expedited
+// Forwarding unless the client asks for another class, for its own packets and the server's.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dscp {
+    BestEffort,
+    Af41,
+    Ef,
+}
+
+impl FromStr for Dscp {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim().to_lowercase().as_str() {
+            "besteffort" | "none" => Ok(Dscp::BestEffort),
+            "af41" => Ok(Dscp::Af41),
+            "ef" => Ok(Dscp::Ef),
+            _ => Err(format!(
+                "unknown DSCP class {input}, expected Ef, Af41 or BestEffort"
+            )),
+        }
+    }
+}
+
+impl Dscp {
+    // The DSCP is the upper 6 bits of the TOS byte.
+    fn tos(self) -> u32 {
+        let dscp = match self {
+            Dscp::BestEffort => 0,
+            Dscp::Af41 => 34,
+            Dscp::Ef => 46,
+        };
+        dscp << 2
+    }
+}
+
 pub fn set_socket_buffers(
     socket: &socket2::Socket,
     send_buffer_bytes: SocketBufferSize,
@@ -86,12 +131,16 @@ enum StreamSendSocket {
     Udp(UdpStreamSendSocket),
     ThrottledUdp(ThrottledUdpStreamSendSocket),
     Tcp(TcpStreamSendSocket),
+    #[cfg(feature = "quic")]
+    Quic(QuicStreamSendSocket),
 }
 
 enum StreamReceiveSocket {
     Udp(UdpStreamReceiveSocket),
     ThrottledUdp(ThrottledUdpStreamReceiveSocket),
     Tcp(TcpStreamReceiveSocket),
+    #[cfg(feature = "quic")]
+    Quic(QuicStreamReceiveSocket),
 }
 
 pub struct SendBufferLock<'a> {
@@ -141,6 +190,7 @@ impl<T> SenderBuffer<T> {
 pub struct StreamSender<T> {
     stream_id: StreamId,
     socket: StreamSendSocket,
+    cipher: Option<Arc<SessionCipher>>,
     // if the packet index overflows the worst that happens is a false positive packet loss
     next_packet_index: u32,
     _phantom: PhantomData<T>,
@@ -153,6 +203,16 @@ impl<T> StreamSender<T> {
         buffer.inner[2..6].copy_from_slice(&self.next_packet_index.to_be_bytes());
         self.next_packet_index += 1;
 
+        // everything but the stream ID is encrypted, the stream ID is authenticated.
+        if let Some(cipher) = &self.cipher {
+            let counter = cipher.next_stream_counter();
+            let (stream_id, payload) = buffer.inner.split_at_mut(2);
+            let tag = cipher.seal(CipherChannel::Stream, counter, stream_id, payload)?;
+            buffer.inner.put_u64(counter);
+            buffer.inner.extend_from_slice(&tag);
+        }
+        count_channel_bytes(self.stream_id.into(), buffer.inner.len());
+
         match &self.socket {
             StreamSendSocket::Udp(socket) => trace_err!(
                 socket
@@ -168,6 +228,8 @@ impl<T> StreamSender<T> {
             StreamSendSocket::ThrottledUdp(socket) => {
                 trace_err!(socket.send(buffer.inner.freeze()).await)
             }
+            #[cfg(feature = "quic")]
+            StreamSendSocket::Quic(socket) => socket.send(buffer.inner.freeze()).await,
         }
     }
 }
@@ -219,14 +281,48 @@ pub struct ReceivedPacket<T> {
 pub struct StreamReceiver<T> {
     stream_id: StreamId,
     receiver: StreamReceiverType,
+    cipher: Option<(Arc<SessionCipher>, ReplayWindow)>,
     next_packet_index: u32,
     _phantom: PhantomData<T>,
 }
 
 impl<T: DeserializeOwned> StreamReceiver<T> {
     pub async fn recv(&mut self) -> StrResult<ReceivedPacket<T>> {
-        let mut bytes = match &mut self.receiver {
-            StreamReceiverType::Queue(receiver) => trace_none!(receiver.recv().await)?,
+        let mut bytes = loop {
+            let mut bytes = match &mut self.receiver {
+                StreamReceiverType::Queue(receiver) => trace_none!(receiver.recv().await)?,
+            };
+            // the stream ID was already consumed by the receive loop.
+            count_channel_bytes(self.stream_id.into(), 2 + bytes.len());
+
+            if let Some((cipher, replay_window)) = &mut self.cipher {
+                // forged, corrupted and replayed packets are dropped like lost ones.
+                if bytes.len() < STREAM_PACKET_OVERHEAD {
+                    continue;
+                }
+                let mut trailer = bytes.split_off(bytes.len() - STREAM_PACKET_OVERHEAD);
+                let counter = trailer.get_u64();
+                if !replay_window.is_new(counter)
+                    || cipher
+                        .open(
+                            CipherChannel::Stream,
+                            counter,
+                            &self.stream_id.to_be_bytes(),
+                            &mut bytes,
+                            &trailer,
+                        )
+                        .is_err()
+                {
+                    debug!(
+                        "Dropped an unauthenticated packet on stream {}",
+                        self.stream_id
+                    );
+                    continue;
+                }
+                replay_window.mark(counter);
+            }
+
+            break bytes;
         };
 
         let packet_index = bytes.get_u32();
@@ -250,6 +346,8 @@ pub enum StreamSocketBuilder {
     Tcp(net::TcpListener),
     Udp(net::UdpSocket),
     ThrottledUdp(net::UdpSocket),
+    #[cfg(feature = "quic")]
+    Quic(quinn::Endpoint),
 }
 
 impl StreamSocketBuilder {
@@ -258,21 +356,34 @@ impl StreamSocketBuilder {
         stream_socket_config: SocketProtocol,
         send_buffer_bytes: SocketBufferSize,
         recv_buffer_bytes: SocketBufferSize,
+        dscp: Dscp,
     ) -> StrResult<Self> {
         Ok(match stream_socket_config {
             SocketProtocol::Udp => StreamSocketBuilder::Udp(
-                udp::bind(port, send_buffer_bytes, recv_buffer_bytes).await?,
+                udp::bind(port, send_buffer_bytes, recv_buffer_bytes, dscp).await?,
             ),
             SocketProtocol::Tcp => StreamSocketBuilder::Tcp(
-                tcp::bind(port, send_buffer_bytes, recv_buffer_bytes).await?,
+                tcp::bind(port, send_buffer_bytes, recv_buffer_bytes, dscp).await?,
             ),
             SocketProtocol::ThrottledUdp { .. } => StreamSocketBuilder::ThrottledUdp(
-                udp::bind(port, send_buffer_bytes, recv_buffer_bytes).await?,
+                udp::bind(port, send_buffer_bytes, recv_buffer_bytes, dscp).await?,
             ),
+            #[cfg(feature = "quic")]
+            SocketProtocol::Quic => StreamSocketBuilder::Quic(quic::listen(
+                udp::bind(port, send_buffer_bytes, recv_buffer_bytes, dscp).await?,
+            )?),
+            #[cfg(not(feature = "quic"))]
+            SocketProtocol::Quic => return fmt_e!("Built without QUIC support"),
         })
     }
 
-    pub async fn accept_from_server(self, server_ip: IpAddr, port: u16) -> StrResult<StreamSocket> {
+    // `cipher` comes from the control socket of the same connection.
+    pub async fn accept_from_server(
+        self,
+        server_ip: IpAddr,
+        port: u16,
+        cipher: Option<Arc<SessionCipher>>,
+    ) -> StrResult<StreamSocket> {
         let (send_socket, receive_socket) = match self {
             StreamSocketBuilder::Udp(socket) => {
                 let (send_socket, receive_socket) = udp::connect(socket, server_ip, port).await?;
@@ -297,15 +408,26 @@ impl StreamSocketBuilder {
                     StreamReceiveSocket::ThrottledUdp(receive_socket),
                 )
             }
+            #[cfg(feature = "quic")]
+            StreamSocketBuilder::Quic(endpoint) => {
+                let (send_socket, receive_socket) =
+                    quic::accept_from_server(endpoint, server_ip).await?;
+                (
+                    StreamSendSocket::Quic(send_socket),
+                    StreamReceiveSocket::Quic(receive_socket),
+                )
+            }
         };
 
         Ok(StreamSocket {
             send_socket,
             receive_socket: Arc::new(Mutex::new(Some(receive_socket))),
             packet_queues: Arc::new(Mutex::new(HashMap::new())),
+            cipher,
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn connect_to_client(
         client_ip: IpAddr,
         port: u16,
@@ -313,10 +435,12 @@ impl StreamSocketBuilder {
         video_byterate: u32,
         send_buffer_bytes: SocketBufferSize,
         recv_buffer_bytes: SocketBufferSize,
+        dscp: Dscp,
+        cipher: Option<Arc<SessionCipher>>,
     ) -> StrResult<StreamSocket> {
         let (send_socket, receive_socket) = match protocol {
             SocketProtocol::Udp => {
-                let socket = udp::bind(port, send_buffer_bytes, recv_buffer_bytes).await?;
+                let socket = udp::bind(port, send_buffer_bytes, recv_buffer_bytes, dscp).await?;
                 let (send_socket, receive_socket) = udp::connect(socket, client_ip, port).await?;
                 (
                     StreamSendSocket::Udp(send_socket),
@@ -324,16 +448,21 @@ impl StreamSocketBuilder {
                 )
             }
             SocketProtocol::Tcp => {
-                let (send_socket, receive_socket) =
-                    tcp::connect_to_client(client_ip, port, send_buffer_bytes, recv_buffer_bytes)
-                        .await?;
+                let (send_socket, receive_socket) = tcp::connect_to_client(
+                    client_ip,
+                    port,
+                    send_buffer_bytes,
+                    recv_buffer_bytes,
+                    dscp,
+                )
+                .await?;
                 (
                     StreamSendSocket::Tcp(send_socket),
                     StreamReceiveSocket::Tcp(receive_socket),
                 )
             }
             SocketProtocol::ThrottledUdp { bitrate_multiplier } => {
-                let socket = udp::bind(port, send_buffer_bytes, recv_buffer_bytes).await?;
+                let socket = udp::bind(port, send_buffer_bytes, recv_buffer_bytes, dscp).await?;
 
                 let (send_socket, receive_socket) = throttled_udp::connect_to_client(
                     socket,
@@ -348,12 +477,25 @@ impl StreamSocketBuilder {
                     StreamReceiveSocket::ThrottledUdp(receive_socket),
                 )
             }
+            #[cfg(feature = "quic")]
+            SocketProtocol::Quic => {
+                let socket = udp::bind(port, send_buffer_bytes, recv_buffer_bytes, dscp).await?;
+                let (send_socket, receive_socket) =
+                    quic::connect_to_client(socket, client_ip, port).await?;
+                (
+                    StreamSendSocket::Quic(send_socket),
+                    StreamReceiveSocket::Quic(receive_socket),
+                )
+            }
+            #[cfg(not(feature = "quic"))]
+            SocketProtocol::Quic => return fmt_e!("Built without QUIC support"),
         };
 
         Ok(StreamSocket {
             send_socket,
             receive_socket: Arc::new(Mutex::new(Some(receive_socket))),
             packet_queues: Arc::new(Mutex::new(HashMap::new())),
+            cipher,
         })
     }
 }
@@ -362,6 +504,7 @@ pub struct StreamSocket {
     send_socket: StreamSendSocket,
     receive_socket: Arc<Mutex<Option<StreamReceiveSocket>>>,
     packet_queues: Arc<Mutex<HashMap<StreamId, mpsc::UnboundedSender<BytesMut>>>>,
+    cipher: Option<Arc<SessionCipher>>,
 }
 
 impl StreamSocket {
@@ -369,6 +512,7 @@ impl StreamSocket {
         Ok(StreamSender {
             stream_id,
             socket: self.send_socket.clone(),
+            cipher: self.cipher.clone(),
             next_packet_index: 0,
             _phantom: PhantomData,
         })
@@ -384,6 +528,10 @@ impl StreamSocket {
         Ok(StreamReceiver {
             stream_id,
             receiver: StreamReceiverType::Queue(dequeuer),
+            cipher: self
+                .cipher
+                .as_ref()
+                .map(|cipher| (Arc::clone(cipher), ReplayWindow::new())),
             next_packet_index: 0,
             _phantom: PhantomData,
         })
@@ -400,6 +548,10 @@ impl StreamSocket {
             StreamReceiveSocket::ThrottledUdp(socket) => {
                 throttled_udp::receive_loop(socket, Arc::clone(&self.packet_queues)).await
             }
+            #[cfg(feature = "quic")]
+            StreamReceiveSocket::Quic(socket) => {
+                quic::receive_loop(socket, Arc::clone(&self.packet_queues)).await
+            }
         }
     }
 }
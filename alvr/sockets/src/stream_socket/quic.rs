@@ -0,0 +1,227 @@
+// The client is the QUIC server and the server the QUIC client, like the other stream sockets.
+// Packets that fit in a datagram are sent unreliably, like UDP, larger ones each get their own
+// unidirectional stream so a lost packet doesn't hold back the following ones.
+use super::StreamId;
+use alvr_common::prelude::*;
+use bytes::{Buf, Bytes, BytesMut};
+use quinn::{
+    congestion::BbrConfig, ClientConfig, Connection, Endpoint, EndpointConfig, ServerConfig,
+    TokioRuntime, TransportConfig, VarInt,
+};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    net::UdpSocket,
+    sync::{mpsc, Mutex},
+};
+
+// The certificate is generated for every connection and never verified, see SkipVerification.
+const SERVER_NAME: &str = "alxr.client";
+const ALPN_PROTOCOL: &[u8] = b"alxr-stream";
+
+const MAX_PACKET_SIZE: usize = 16 * 1024 * 1024;
+const MAX_CONCURRENT_UNI_STREAMS: u32 = 1024;
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(1);
+const IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+// Local network round trips, the default of 333ms makes the first packets wait on loss
+// detection for too long.
+const INITIAL_RTT: Duration = Duration::from_millis(10);
+
+// The endpoint is kept alive for as long as one of the two sockets is.
+#[derive(Clone)]
+pub struct QuicStreamSendSocket {
+    connection: Connection,
+    _endpoint: Endpoint,
+}
+
+pub struct QuicStreamReceiveSocket {
+    connection: Connection,
+    _endpoint: Endpoint,
+}
+
+fn split(
+    connection: Connection,
+    endpoint: Endpoint,
+) -> (QuicStreamSendSocket, QuicStreamReceiveSocket) {
+    (
+        QuicStreamSendSocket {
+            connection: connection.clone(),
+            _endpoint: endpoint.clone(),
+        },
+        QuicStreamReceiveSocket {
+            connection,
+            _endpoint: endpoint,
+        },
+    )
+}
+
+impl QuicStreamSendSocket {
+    pub async fn send(&self, packet: Bytes) -> StrResult {
+        if self
+            .connection
+            .max_datagram_size()
+            .map_or(false, |max_size| packet.len() <= max_size)
+        {
+            return trace_err!(self.connection.send_datagram(packet));
+        }
+
+        let mut stream = trace_err!(self.connection.open_uni().await)?;
+        trace_err!(stream.write_all(&packet).await)?;
+        // finishing waits for the peer acknowledgement, the next packet must not wait on it.
+        tokio::spawn(async move { stream.finish().await.ok() });
+        Ok(())
+    }
+}
+
+// BBR keeps the queues short, loss based controllers fill the buffers of the access point
+// before backing off which shows up as latency spikes.
+fn transport_config() -> Arc<TransportConfig> {
+    let mut config = TransportConfig::default();
+    config
+        .congestion_controller_factory(Arc::new(BbrConfig::default()))
+        .max_concurrent_uni_streams(VarInt::from_u32(MAX_CONCURRENT_UNI_STREAMS))
+        .max_concurrent_bidi_streams(VarInt::from_u32(0))
+        .keep_alive_interval(Some(KEEP_ALIVE_INTERVAL))
+        .initial_rtt(INITIAL_RTT);
+    if let Ok(idle_timeout) = IDLE_TIMEOUT.try_into() {
+        config.max_idle_timeout(Some(idle_timeout));
+    }
+    Arc::new(config)
+}
+
+// The stream sockets are not authenticated over UDP and TCP either, the peer address is checked
+// against the one of the control connection instead.
+struct SkipVerification;
+
+impl rustls::client::ServerCertVerifier for SkipVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        _server_name: &rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::ServerCertVerified::assertion())
+    }
+}
+
+fn server_config() -> StrResult<ServerConfig> {
+    let certificate = trace_err!(rcgen::generate_simple_self_signed([SERVER_NAME.into()]))?;
+    let certificate_der = trace_err!(certificate.serialize_der())?;
+    let key_der = certificate.serialize_private_key_der();
+
+    let mut crypto = trace_err!(rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![rustls::Certificate(certificate_der)],
+            rustls::PrivateKey(key_der)
+        ))?;
+    crypto.alpn_protocols = vec![ALPN_PROTOCOL.to_vec()];
+
+    let mut config = ServerConfig::with_crypto(Arc::new(crypto));
+    config.transport_config(transport_config());
+    Ok(config)
+}
+
+fn client_config() -> ClientConfig {
+    let mut crypto = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(SkipVerification))
+        .with_no_client_auth();
+    crypto.alpn_protocols = vec![ALPN_PROTOCOL.to_vec()];
+
+    let mut config = ClientConfig::new(Arc::new(crypto));
+    config.transport_config(transport_config());
+    config
+}
+
+// Takes over a socket from udp::bind(), to keep its buffer sizes and DSCP marking.
+pub fn listen(socket: UdpSocket) -> StrResult<Endpoint> {
+    trace_err!(Endpoint::new(
+        EndpointConfig::default(),
+        Some(server_config()?),
+        trace_err!(socket.into_std())?,
+        Arc::new(TokioRuntime),
+    ))
+}
+
+pub async fn accept_from_server(
+    endpoint: Endpoint,
+    server_ip: IpAddr,
+) -> StrResult<(QuicStreamSendSocket, QuicStreamReceiveSocket)> {
+    let connecting = trace_none!(endpoint.accept().await)?;
+    let connection = trace_err!(connecting.await)?;
+
+    let server_address = connection.remote_address();
+    if server_address.ip() != server_ip {
+        return fmt_e!("Connected to wrong server: {server_address} != {server_ip}");
+    }
+
+    Ok(split(connection, endpoint))
+}
+
+pub async fn connect_to_client(
+    socket: UdpSocket,
+    client_ip: IpAddr,
+    port: u16,
+) -> StrResult<(QuicStreamSendSocket, QuicStreamReceiveSocket)> {
+    let mut endpoint = trace_err!(Endpoint::new(
+        EndpointConfig::default(),
+        None,
+        trace_err!(socket.into_std())?,
+        Arc::new(TokioRuntime),
+    ))?;
+    endpoint.set_default_client_config(client_config());
+
+    let connecting = trace_err!(endpoint.connect(SocketAddr::new(client_ip, port), SERVER_NAME))?;
+    let connection = trace_err!(connecting.await)?;
+
+    Ok(split(connection, endpoint))
+}
+
+async fn enqueue_packet(
+    mut packet: BytesMut,
+    packet_enqueuers: &Mutex<HashMap<StreamId, mpsc::UnboundedSender<BytesMut>>>,
+) -> StrResult {
+    if packet.len() < 2 {
+        return Ok(());
+    }
+    let stream_id = packet.get_u16();
+    if let Some(enqueuer) = packet_enqueuers.lock().await.get_mut(&stream_id) {
+        trace_err!(enqueuer.send(packet))?;
+    }
+    Ok(())
+}
+
+pub async fn receive_loop(
+    socket: QuicStreamReceiveSocket,
+    packet_enqueuers: Arc<Mutex<HashMap<StreamId, mpsc::UnboundedSender<BytesMut>>>>,
+) -> StrResult {
+    loop {
+        tokio::select! {
+            datagram = socket.connection.read_datagram() => {
+                let datagram = trace_err!(datagram)?;
+                enqueue_packet(BytesMut::from(&datagram[..]), &packet_enqueuers).await?;
+            }
+            stream = socket.connection.accept_uni() => {
+                let mut stream = trace_err!(stream)?;
+                let packet_enqueuers = Arc::clone(&packet_enqueuers);
+                // streams are read concurrently, a stalled one doesn't block the others.
+                tokio::spawn(async move {
+                    if let Ok(packet) = stream.read_to_end(MAX_PACKET_SIZE).await {
+                        enqueue_packet(BytesMut::from(&packet[..]), &packet_enqueuers)
+                            .await
+                            .ok();
+                    }
+                });
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::StreamId;
+use super::{Dscp, StreamId};
 use crate::{Ldc, LOCAL_IP};
 use alvr_common::prelude::*;
 use alvr_session::SocketBufferSize;
@@ -17,12 +17,11 @@ use tokio_util::codec::Framed;
 pub type TcpStreamSendSocket = Arc<Mutex<SplitSink<Framed<TcpStream, Ldc>, Bytes>>>;
 pub type TcpStreamReceiveSocket = SplitStream<Framed<TcpStream, Ldc>>;
 
-const IPTOS_DSCP_EF: u32 = 0xb8;
-
 pub async fn bind(
     port: u16,
     send_buffer_bytes: SocketBufferSize,
     recv_buffer_bytes: SocketBufferSize,
+    dscp: Dscp,
 ) -> StrResult<TcpListener> {
     let socket = TcpListener::bind((LOCAL_IP, port)).await.map_err(err!())?;
     let socket = socket2::Socket::from(socket.into_std().map_err(err!())?);
@@ -30,7 +29,7 @@ pub async fn bind(
     super::set_socket_buffers(&socket, send_buffer_bytes, recv_buffer_bytes).ok();
 
     socket.set_nodelay(true).ok();
-    socket.set_tos(IPTOS_DSCP_EF).ok();
+    socket.set_tos(dscp.tos()).ok();
 
     TcpListener::from_std(socket.into()).map_err(err!())
 }
@@ -57,6 +56,7 @@ pub async fn connect_to_client(
     port: u16,
     send_buffer_bytes: SocketBufferSize,
     recv_buffer_bytes: SocketBufferSize,
+    dscp: Dscp,
 ) -> StrResult<(TcpStreamSendSocket, TcpStreamReceiveSocket)> {
     let socket = TcpStream::connect((client_ip, port))
         .await
@@ -66,7 +66,7 @@ pub async fn connect_to_client(
     super::set_socket_buffers(&socket, send_buffer_bytes, recv_buffer_bytes).ok();
 
     socket.set_nodelay(true).ok();
-    socket.set_tos(IPTOS_DSCP_EF).ok();
+    socket.set_tos(dscp.tos()).ok();
 
     let socket = TcpStream::from_std(socket.into()).map_err(err!())?;
     let socket = Framed::new(socket, Ldc::new());
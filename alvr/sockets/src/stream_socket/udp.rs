@@ -1,4 +1,4 @@
-use super::StreamId;
+use super::{Dscp, StreamId};
 use crate::{Ldc, LOCAL_IP};
 use alvr_common::prelude::*;
 use alvr_session::SocketBufferSize;
@@ -18,8 +18,6 @@ use tokio::{
 };
 use tokio_util::udp::UdpFramed;
 
-const IPTOS_DSCP_EF: u32 = 0xb8;
-
 #[allow(clippy::type_complexity)]
 #[derive(Clone)]
 pub struct UdpStreamSendSocket {
@@ -40,13 +38,14 @@ pub async fn bind(
     port: u16,
     send_buffer_bytes: SocketBufferSize,
     recv_buffer_bytes: SocketBufferSize,
+    dscp: Dscp,
 ) -> StrResult<UdpSocket> {
     let socket = UdpSocket::bind((LOCAL_IP, port)).await.map_err(err!())?;
     let socket = socket2::Socket::from(socket.into_std().map_err(err!())?);
 
     super::set_socket_buffers(&socket, send_buffer_bytes, recv_buffer_bytes).ok();
 
-    socket.set_tos(IPTOS_DSCP_EF).ok();
+    socket.set_tos(dscp.tos()).ok();
 
     UdpSocket::from_std(socket.into()).map_err(err!())
 }
@@ -35,6 +35,9 @@ SUBCOMMANDS:
     build-alxr-android  Build OpenXR based client (android platforms only), then copy binaries to build folder
     build-alxr-quest    Build OpenXR based client for Oculus Quest (same as `build-alxr-android --target aarch64-linux-android`), then copy binaries to build folder
     build-alxr-pico     Build OpenXR based client for Pico 4/Neo 3 PUI >= 5.2.x (same as `build-alxr-android --pico`), then copy binaries to build folder
+    build-alxr-spaces   Build OpenXR based client for Snapdragon Spaces AR glasses (same as `build-alxr-android --spaces`), then copy binaries to build folder
+    build-alxr-ml2      Build OpenXR based client for Magic Leap 2 (same as `build-alxr-android --ml2`), then copy binaries to build folder
+    build-alxr-yvr      Build OpenXR based client for YVR/Play For Dream (same as `build-alxr-android --yvr`), needs ALXR_YVR_LOADER_DIR, then copy binaries to build folder
     build-ffmpeg-linux  Build FFmpeg with VAAPI, NvEnc and Vulkan support. Only for CI
     publish-server      Build server in release mode, make portable version and installer
     publish-client      Build client for all headsets
@@ -55,7 +58,10 @@ FLAGS:
     --oculus-go         Oculus Go build. Used only for build-client subcommand
     --generic           Generic Android build (cross-vendor openxr loader). Used only for build-alxr-android subcommand
     --pico-neo          Pico Neo 3 build. Used only for build-alxr-android subcommand
-    --all-flavors       Build all android variants (Generic,Quest,Pico, etc), Used only for build-alxr-android subcommand
+    --spaces            Snapdragon Spaces AR glasses build. Used only for build-alxr-android subcommand
+    --ml2               Magic Leap 2 build. Used only for build-alxr-android subcommand
+    --yvr               YVR/Play For Dream build, needs ALXR_YVR_LOADER_DIR. Used only for build-alxr-android subcommand
+    --all-flavors       Build all android variants (Generic,Quest,Pico,Spaces,ML2, etc, YVR when ALXR_YVR_LOADER_DIR is set), Used only for build-alxr-android subcommand
     --target <ABI>      Build only for specific android CPU arch, options: aarch64-linux-android, armv7-linux-androideabi, x86_64-linux-android, i686-linux-android. Used only for build-alxr-android subcommand.
     --bundle-ffmpeg     Bundle ffmpeg libraries. Only used for build-server subcommand on Linux
     --no-nvidia         Additional flag to use with `build-server` or `build-alxr-client`. Disables nVidia/CUDA support.
@@ -908,7 +914,17 @@ fn install_alxr_depends() {
 #[derive(Clone, Copy, Debug)]
 pub enum AndroidFlavor {
     Generic,
-    Pico, // PUI >= 5.2.x
+    Pico,   // PUI >= 5.2.x
+    Spaces, // Snapdragon Spaces AR glasses
+    ML2,    // Magic Leap 2
+    YVR,    // YVR/Play For Dream, vendor loader
+}
+
+impl AndroidFlavor {
+    // The YVR loader comes from the vendor SDK, --all-flavors skips it unless it is set up.
+    fn in_all_flavors(self) -> bool {
+        !matches!(self, AndroidFlavor::YVR) || env::var("ALXR_YVR_LOADER_DIR").is_ok()
+    }
 }
 
 pub fn build_alxr_android(
@@ -939,6 +955,9 @@ pub fn build_alxr_android(
 
     let client_dir = match client_flavor {
         AndroidFlavor::Pico => "pico",
+        AndroidFlavor::Spaces => "spaces",
+        AndroidFlavor::ML2 => "ml2",
+        AndroidFlavor::YVR => "yvr",
         _ => "",
     };
     // cargo-apk has an issue where it will search the entire "target" build directory for "output" files that contain
@@ -1042,6 +1061,9 @@ fn main() {
         let for_oculus_go = args.contains("--oculus-go");
         let for_generic = args.contains("--generic");
         let for_pico = args.contains("--pico");
+        let for_spaces = args.contains("--spaces");
+        let for_ml2 = args.contains("--ml2");
+        let for_yvr = args.contains("--yvr");
         let for_all_flavors = args.contains("--all-flavors");
         let oculus_ext = args.contains("--oculus-ext");
         //
@@ -1165,11 +1187,14 @@ fn main() {
                     let flavours = vec![
                         (for_generic, AndroidFlavor::Generic, abi_target.clone()),
                         (for_pico, AndroidFlavor::Pico, Option::None),
+                        (for_spaces, AndroidFlavor::Spaces, Option::None),
+                        (for_ml2, AndroidFlavor::ML2, Option::None),
+                        (for_yvr, AndroidFlavor::YVR, Option::None),
                     ];
 
-                    for (_, flavour, maybe_abi) in
-                        flavours.iter().filter(|(f, _, _)| for_all_flavors || *f)
-                    {
+                    for (_, flavour, maybe_abi) in flavours.iter().filter(|(f, flavour, _)| {
+                        *f || (for_all_flavors && flavour.in_all_flavors())
+                    }) {
                         build_alxr_android(root.clone(), *flavour, maybe_abi.clone(), build_flags);
                     }
                     if !for_all_flavors && flavours.iter().all(|(flag, _, _)| !flag) {
@@ -1204,6 +1229,48 @@ fn main() {
                         ..Default::default()
                     },
                 ),
+                "build-alxr-spaces" => build_alxr_android(
+                    root,
+                    AndroidFlavor::Spaces,
+                    Option::None,
+                    AlxBuildFlags {
+                        is_release: is_release,
+                        reproducible: reproducible,
+                        no_nvidia: true,
+                        bundle_ffmpeg: false,
+                        fetch_crates: fetch,
+                        no_decoder: no_decoder,
+                        ..Default::default()
+                    },
+                ),
+                "build-alxr-ml2" => build_alxr_android(
+                    root,
+                    AndroidFlavor::ML2,
+                    Option::None,
+                    AlxBuildFlags {
+                        is_release: is_release,
+                        reproducible: reproducible,
+                        no_nvidia: true,
+                        bundle_ffmpeg: false,
+                        fetch_crates: fetch,
+                        no_decoder: no_decoder,
+                        ..Default::default()
+                    },
+                ),
+                "build-alxr-yvr" => build_alxr_android(
+                    root,
+                    AndroidFlavor::YVR,
+                    Option::None,
+                    AlxBuildFlags {
+                        is_release: is_release,
+                        reproducible: reproducible,
+                        no_nvidia: true,
+                        bundle_ffmpeg: false,
+                        fetch_crates: fetch,
+                        no_decoder: no_decoder,
+                        ..Default::default()
+                    },
+                ),
                 "build-ffmpeg-linux" => {
                     dependencies::build_ffmpeg_linux(true);
                 }
@@ -172,6 +172,9 @@ pub fn bump_alxr_version(maybe_version: Option<String>, is_nightly: bool) {
         "alxr-client",
         "alxr-client-android",
         "alxr-client-android/pico",
+        "alxr-client-android/spaces",
+        "alxr-client-android/ml2",
+        "alxr-client-android/yvr",
     ]
     .into_iter()
     .map(|d| base_dir.join(&d).to_str().unwrap().to_owned())